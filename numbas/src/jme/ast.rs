@@ -1,4 +1,6 @@
 use crate::jme::builtin_functions::BuiltinFunctions;
+use crate::jme::types::JMEType;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -100,6 +102,12 @@ pub struct Ident {
 }
 
 impl Ident {
+    pub fn name(&self) -> &str {
+        &self.name[..]
+    }
+    pub fn annotations(&self) -> &[String] {
+        &self.annotations[..]
+    }
     pub fn is_builtin_funtion(&self) -> bool {
         BuiltinFunctions::get(&self.name[..]).is_some()
     }
@@ -188,8 +196,8 @@ pub enum Expr {
     Prefix(PrefixOperator, Box<Expr>),
     /// Matches a faculty expression
     Faculty(Box<Expr>),
-    /// Matches an indexation expression
-    Indexation(Box<Expr>),
+    /// Matches an indexation expression, e.g. `x[1]`
+    Indexation(Box<Expr>, Box<Expr>),
     /// Matches a cast expression
     Cast(Box<Expr>, Box<Expr>),
     /// Matches a sequence expression
@@ -202,6 +210,113 @@ pub enum ExprValidationError {
     UnknownVariable(Ident),
 }
 
+impl std::fmt::Display for ExprValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExprValidationError::UnknownFunction(ident) => write!(f, "unknown function {}", ident),
+            ExprValidationError::UnknownVariable(ident) => write!(f, "unknown variable {}", ident),
+        }
+    }
+}
+
+/// The builtin constants of Numbas
+const BUILTIN_CONSTANTS: [&str; 8] = ["e", "pi", "π", "i", "infinity", "infty", "∞", "nan"];
+
+/// The names that are defined when a marking script is evaluated
+const MARKING_SCRIPT_NAMES: [&str; 9] = [
+    "studentanswer",
+    "settings",
+    "marks",
+    "parttype",
+    "partchecked",
+    "gaps",
+    "steps",
+    "path",
+    "question",
+];
+
+/// The notes that the base marking script of each part type defines
+const BASE_MARKING_NOTES: [&str; 2] = ["mark", "interpreted_answer"];
+
+/// The names that can be used in an expression, next to the builtin functions
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationScope {
+    /// The lowercase names of the variables
+    variables: BTreeSet<String>,
+    /// The lowercase names of the (non builtin) functions
+    functions: BTreeSet<String>,
+    /// The enabled builtin constants
+    constants: BTreeSet<String>,
+    /// The known types of the variables, by lowercase name
+    variable_types: BTreeMap<String, JMEType>,
+    /// The known output types of the (non builtin) functions, by lowercase name
+    function_types: BTreeMap<String, JMEType>,
+}
+
+impl Default for ValidationScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ValidationScope {
+    /// Create a scope without variables and functions, where all builtin constants are enabled
+    pub fn new() -> Self {
+        Self {
+            variables: BTreeSet::new(),
+            functions: BTreeSet::new(),
+            constants: BUILTIN_CONSTANTS.iter().map(|c| c.to_string()).collect(),
+            variable_types: BTreeMap::new(),
+            function_types: BTreeMap::new(),
+        }
+    }
+    pub fn add_variable(&mut self, name: &str) {
+        self.variables.insert(name.to_lowercase());
+    }
+    pub fn add_function(&mut self, name: &str) {
+        self.functions.insert(name.to_lowercase());
+    }
+    pub fn disable_constant(&mut self, name: &str) {
+        self.constants.remove(name);
+    }
+    /// Add the names that are available in marking scripts, like `studentAnswer` and the
+    /// `interpreted_answer` note of the base marking script
+    pub fn add_marking_script_names(&mut self) {
+        for name in MARKING_SCRIPT_NAMES.iter().chain(BASE_MARKING_NOTES.iter()) {
+            self.add_variable(name);
+        }
+    }
+    pub fn has_variable(&self, name: &str) -> bool {
+        self.variables.contains(&name.to_lowercase())
+    }
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains(&name.to_lowercase())
+    }
+    pub fn has_constant(&self, name: &str) -> bool {
+        self.constants.contains(name)
+    }
+    pub fn set_variable_type(&mut self, name: &str, t: JMEType) {
+        self.variable_types.insert(name.to_lowercase(), t);
+    }
+    /// The type of the variable, `JMEType::Any` if it is not known
+    pub fn variable_type(&self, name: &str) -> JMEType {
+        self.variable_types
+            .get(&name.to_lowercase())
+            .copied()
+            .unwrap_or(JMEType::Any)
+    }
+    pub fn set_function_type(&mut self, name: &str, t: JMEType) {
+        self.function_types.insert(name.to_lowercase(), t);
+    }
+    /// The output type of the function, `JMEType::Any` if it is not known
+    pub fn function_type(&self, name: &str) -> JMEType {
+        self.function_types
+            .get(&name.to_lowercase())
+            .copied()
+            .unwrap_or(JMEType::Any)
+    }
+}
+
 impl Expr {
     pub fn validate(&self) -> Vec<ExprValidationError> {
         match self {
@@ -219,7 +334,7 @@ impl Expr {
                 .into_iter()
                 .chain(e2.validate().into_iter())
                 .collect(),
-            Expr::Ident(_) => vec![], // TODO: check if part of variable list
+            Expr::Ident(_) => vec![], // Variables can only be checked with a scope, see validate_in_scope
             Expr::Constant(_) => vec![],
             Expr::Relation(_, e1, e2) => e1
                 .validate()
@@ -251,7 +366,11 @@ impl Expr {
             }
             Expr::Prefix(_, e1) => e1.validate(),
             Expr::Faculty(e1) => e1.validate(),
-            Expr::Indexation(e1) => e1.validate(),
+            Expr::Indexation(e1, e2) => e1
+                .validate()
+                .into_iter()
+                .chain(e2.validate().into_iter())
+                .collect(),
             Expr::Cast(e1, e2) => e1
                 .validate()
                 .into_iter()
@@ -264,6 +383,179 @@ impl Expr {
                 .collect(),
         }
     }
+
+    /// Validate this expression, using the given scope to check the used variables and functions.
+    ///
+    /// Names that are bound by functions like `map` and `let` are known within those functions.
+    pub fn validate_in_scope(&self, scope: &ValidationScope) -> Vec<ExprValidationError> {
+        let mut errors: Vec<_> = self
+            .validate()
+            .into_iter()
+            .filter(|e| match e {
+                ExprValidationError::UnknownFunction(ident) => !scope.has_function(ident.name()),
+                ExprValidationError::UnknownVariable(_) => true,
+            })
+            .collect();
+        let mut variables = Vec::new();
+        self.collect_free_idents(&BTreeSet::new(), &mut variables);
+        let mut constants = Vec::new();
+        self.collect_constants(&mut constants);
+        let unknown_variables = variables
+            .into_iter()
+            .filter(|ident| !scope.has_variable(ident.name()));
+        // A disabled constant can still be used as a variable
+        let unknown_constants = constants
+            .into_iter()
+            .filter(|ident| !scope.has_constant(ident.name()) && !scope.has_variable(ident.name()));
+        let mut reported = BTreeSet::new();
+        for ident in unknown_variables.chain(unknown_constants) {
+            if reported.insert(ident.name().to_lowercase()) {
+                errors.push(ExprValidationError::UnknownVariable(ident.clone()));
+            }
+        }
+        errors
+    }
+
+    /// The direct subexpressions of this expression
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Str(_)
+            | Expr::Int(_)
+            | Expr::Float(_, _)
+            | Expr::Bool(_)
+            | Expr::Ident(_)
+            | Expr::Constant(_) => vec![],
+            Expr::Range(_, e1, e2)
+            | Expr::Arithmetic(_, e1, e2)
+            | Expr::Relation(_, e1, e2)
+            | Expr::Logic(_, e1, e2)
+            | Expr::Indexation(e1, e2)
+            | Expr::Cast(e1, e2)
+            | Expr::Sequence(e1, e2) => vec![e1, e2],
+            Expr::List(es) | Expr::FunctionApplication(_, es) => es.iter().collect(),
+            Expr::Dictionary(es) => es.iter().flat_map(|(k, v)| vec![k, v]).collect(),
+            Expr::Prefix(_, e) | Expr::Faculty(e) => vec![e],
+        }
+    }
+
+    /// The (lowercase) names of the variables that are used in this expression.
+    ///
+    /// Names that are bound by functions like `map` and `let` are not included.
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        let mut variables = Vec::new();
+        self.collect_free_idents(&BTreeSet::new(), &mut variables);
+        variables
+            .into_iter()
+            .map(|ident| ident.name().to_lowercase())
+            .collect()
+    }
+
+    fn collect_free_idents<'a>(&'a self, bound: &BTreeSet<String>, variables: &mut Vec<&'a Ident>) {
+        let with_names = |names: &[&Expr]| {
+            let mut bound = bound.clone();
+            for name in names.iter() {
+                bound.extend(name.bound_names());
+            }
+            bound
+        };
+        match self {
+            Expr::Ident(ident) => {
+                if !bound.contains(&ident.name().to_lowercase()) {
+                    variables.push(ident);
+                }
+            }
+            Expr::Dictionary(es) => {
+                // Names used as keys are no variables
+                for (k, v) in es.iter() {
+                    if !matches!(k, Expr::Ident(_)) {
+                        k.collect_free_idents(bound, variables);
+                    }
+                    v.collect_free_idents(bound, variables);
+                }
+            }
+            Expr::Indexation(e, index) => {
+                e.collect_free_idents(bound, variables);
+                index.collect_free_idents(bound, variables);
+            }
+            Expr::FunctionApplication(ident, args) => {
+                match (&ident.name().to_lowercase()[..], &args[..]) {
+                    ("map" | "filter", [expr, names, list]) => {
+                        list.collect_free_idents(bound, variables);
+                        expr.collect_free_idents(&with_names(&[names]), variables);
+                    }
+                    ("take", [n, expr, names, list]) => {
+                        n.collect_free_idents(bound, variables);
+                        list.collect_free_idents(bound, variables);
+                        expr.collect_free_idents(&with_names(&[names]), variables);
+                    }
+                    ("foldl", [expr, accumulator, item, initial, list]) => {
+                        initial.collect_free_idents(bound, variables);
+                        list.collect_free_idents(bound, variables);
+                        expr.collect_free_idents(&with_names(&[accumulator, item]), variables);
+                    }
+                    ("iterate", [expr, names, initial, times]) => {
+                        initial.collect_free_idents(bound, variables);
+                        times.collect_free_idents(bound, variables);
+                        expr.collect_free_idents(&with_names(&[names]), variables);
+                    }
+                    ("iterate_until", [expr, names, initial, condition, rest @ ..]) => {
+                        initial.collect_free_idents(bound, variables);
+                        for e in rest.iter() {
+                            e.collect_free_idents(bound, variables);
+                        }
+                        let bound = with_names(&[names]);
+                        expr.collect_free_idents(&bound, variables);
+                        condition.collect_free_idents(&bound, variables);
+                    }
+                    ("try", [expr, name, fallback]) => {
+                        expr.collect_free_idents(bound, variables);
+                        fallback.collect_free_idents(&with_names(&[name]), variables);
+                    }
+                    ("let", [definitions @ .., body]) if definitions.len() % 2 == 0 => {
+                        let mut bound = bound.clone();
+                        for definition in definitions.chunks(2) {
+                            definition[1].collect_free_idents(&bound, variables);
+                            bound.extend(definition[0].bound_names());
+                        }
+                        body.collect_free_idents(&bound, variables);
+                    }
+                    ("isset", _) => (),
+                    _ => {
+                        for arg in args.iter() {
+                            arg.collect_free_idents(bound, variables);
+                        }
+                    }
+                }
+            }
+            e => {
+                for child in e.children() {
+                    child.collect_free_idents(bound, variables);
+                }
+            }
+        }
+    }
+
+    fn collect_constants<'a>(&'a self, constants: &mut Vec<&'a Ident>) {
+        match self {
+            Expr::Constant(ident) => constants.push(ident),
+            e => {
+                for child in e.children() {
+                    child.collect_constants(constants);
+                }
+            }
+        }
+    }
+
+    /// The names that are bound when this expression is used as the name argument of
+    /// functions like `map`: a name or a list of names.
+    pub(crate) fn bound_names(&self) -> Vec<String> {
+        match self {
+            Expr::Ident(ident) => vec![ident.name().to_lowercase()],
+            Expr::Str(s) => vec![s.to_lowercase()],
+            Expr::List(es) => es.iter().flat_map(|e| e.bound_names()).collect(),
+            _ => vec![],
+        }
+    }
 }
 
 #[cfg(test)]
@@ -280,28 +572,101 @@ mod test {
     use serde::{Deserialize, Serialize};
     use std::fmt::Write;
 
+    #[test]
+    fn free_variables() {
+        let expr = consume_one_expression(
+            parse_as_jme("map(x + a, x, 1..b) + let(c, d, c + e) + [f: g][h] + isset(k)").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            expr.free_variables(),
+            vec!["a", "b", "d", "g", "h"]
+                .into_iter()
+                .map(|s| s.to_owned())
+                .collect()
+        );
+    }
+
+    #[test]
+    fn validate_in_scope() {
+        let expr = consume_one_expression(
+            parse_as_jme("map(x + a, x, 1..B) + f(c) + let(d, 1, d + e) + pi + g(y)").unwrap(),
+        )
+        .unwrap();
+        let mut scope = super::ValidationScope::new();
+        scope.add_variable("a");
+        scope.add_variable("b");
+        scope.add_function("f");
+        scope.disable_constant("e");
+        assert_eq!(
+            expr.validate_in_scope(&scope),
+            vec![
+                UnknownFunction(Ident {
+                    name: "g".to_owned(),
+                    annotations: vec![],
+                }),
+                UnknownVariable(Ident {
+                    name: "c".to_owned(),
+                    annotations: vec![],
+                }),
+                UnknownVariable(Ident {
+                    name: "y".to_owned(),
+                    annotations: vec![],
+                }),
+                UnknownVariable(Ident {
+                    name: "e".to_owned(),
+                    annotations: vec![],
+                }),
+            ]
+        );
+        scope.add_variable("c");
+        scope.add_variable("e");
+        scope.add_variable("y");
+        scope.add_function("g");
+        assert_eq!(expr.validate_in_scope(&scope), vec![]);
+    }
+
+    #[test]
+    fn marking_script_names() {
+        let expr = consume_one_expression(
+            parse_as_jme("interpreted_answer + mark + len(studentAnswer) + partType + note")
+                .unwrap(),
+        )
+        .unwrap();
+        let mut scope = super::ValidationScope::new();
+        scope.add_marking_script_names();
+        assert_eq!(
+            expr.validate_in_scope(&scope),
+            vec![UnknownVariable(Ident {
+                name: "note".to_owned(),
+                annotations: vec![],
+            })]
+        );
+    }
+
     #[test]
     fn ast() {
+        // `and` binds tighter than `or`
         let expected = Logic(
-            And,
+            Or,
             Box::new(Logic(
-                Or,
-                Box::new(Logic(
-                    And,
-                    Box::new(Relation(
-                        GreaterThan,
-                        Box::new(Arithmetic(
-                            Multiply,
-                            Box::new(Ident(Ident {
-                                name: "a".to_string(),
-                                annotations: vec![],
-                            })),
-                            Box::new(Int(7)),
-                        )),
-                        Box::new(Int(5)),
+                And,
+                Box::new(Relation(
+                    GreaterThan,
+                    Box::new(Arithmetic(
+                        Multiply,
+                        Box::new(Ident(Ident {
+                            name: "a".to_string(),
+                            annotations: vec![],
+                        })),
+                        Box::new(Int(7)),
                     )),
-                    Box::new(Bool(true)),
+                    Box::new(Int(5)),
                 )),
+                Box::new(Bool(true)),
+            )),
+            Box::new(Logic(
+                And,
                 Box::new(Relation(
                     LessThan,
                     Box::new(Arithmetic(
@@ -311,8 +676,8 @@ mod test {
                     )),
                     Box::new(Arithmetic(Divide, Box::new(Int(6)), Box::new(Int(10)))),
                 )),
+                Box::new(Bool(false)),
             )),
-            Box::new(Bool(false)),
         );
 
         for input in &[
@@ -13,10 +13,11 @@ pub enum BuiltinFunctions {
     Rational,
     /// Convert n to an integer, rounding to the nearest integer.
     Int,
-    #[serde(alias = "len")]
-    #[serde(alias = "length")]
     /// Absolute value, or modulus.
-    Abs, // TODO: maybe split these?
+    Abs,
+    #[serde(alias = "length")]
+    /// Length of a string, list, set, range or dictionary.
+    Len,
     /// Argument of a complex number.
     Arg,
     /// Real part of a complex number.
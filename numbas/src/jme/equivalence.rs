@@ -0,0 +1,297 @@
+//! Check whether two jme expressions are numerically equivalent, the way Numbas marks jme parts.
+//!
+//! The free variables of the expressions get random values and both expressions are evaluated
+//! for these values. The expressions are equivalent if the results are close enough for almost
+//! all sets of values.
+
+use crate::jme::ast::Expr;
+use crate::jme::eval::{
+    precround, siground, values_equal, EvaluationError, Evaluator, Scope, Value,
+};
+use crate::question::part::jme::{JMEAccuracy, JMECheckingType, JMEValueGenerator};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EquivalenceError {
+    /// The value generator of the variable is not a valid jme expression
+    InvalidValueGenerator(String),
+    /// The value generator of the variable can't be evaluated
+    ValueGenerator(String, EvaluationError),
+    /// The expected expression can't be evaluated
+    Expected(EvaluationError),
+    /// The range to pick the values of the variables from is empty or not finite
+    InvalidRange(f64, f64),
+}
+
+impl Display for EquivalenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InvalidValueGenerator(name) => {
+                write!(
+                    f,
+                    "The value generator of {} is not a valid expression",
+                    name
+                )
+            }
+            Self::ValueGenerator(name, e) => {
+                write!(
+                    f,
+                    "The value generator of {} can't be evaluated: {}",
+                    name, e
+                )
+            }
+            Self::Expected(e) => write!(f, "The expected answer can't be evaluated: {}", e),
+            Self::InvalidRange(start, end) => write!(
+                f,
+                "The range {}..{} to pick the values of the variables from is invalid",
+                start, end
+            ),
+        }
+    }
+}
+
+/// Check whether the student's expression is equivalent to the expected expression.
+///
+/// The free variables (the names that are not defined in the scope) are sampled
+/// `vset_range_points` times, with their value generator or uniformly from the `vset_range`.
+/// The expressions are equivalent if the comparison fails less than `failure_rate` times.
+/// If the student's expression can't be evaluated, the comparison fails.
+pub fn expressions_equivalent(
+    student: &Expr,
+    expected: &Expr,
+    accuracy: &JMEAccuracy,
+    value_generators: &[JMEValueGenerator],
+    evaluator: &mut Evaluator,
+    scope: &Scope,
+) -> Result<bool, EquivalenceError> {
+    let generators = value_generators
+        .iter()
+        .map(|g| {
+            g.value
+                .ast()
+                .map(|e| (g.name.to_lowercase(), e.clone()))
+                .ok_or_else(|| EquivalenceError::InvalidValueGenerator(g.name.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let [start, end] = accuracy.vset_range;
+    let (start, end) = (start.0, end.0);
+    if !(start.is_finite() && end.is_finite() && start <= end) {
+        return Err(EquivalenceError::InvalidRange(start, end));
+    }
+    let names: BTreeSet<String> = student
+        .free_variables()
+        .into_iter()
+        .chain(expected.free_variables())
+        .filter(|name| scope.variable(name).is_none())
+        .collect();
+
+    let mut failures = 0.0;
+    for _ in 0..accuracy.vset_range_points.0.max(1) {
+        let mut point = scope.child();
+        for name in names.iter() {
+            let value = match generators.iter().find(|(n, _)| n == name) {
+                Some((_, generator)) => evaluator
+                    .evaluate(generator, &point)
+                    .map_err(|e| EquivalenceError::ValueGenerator(name.clone(), e))?,
+                None => Value::Number(
+                    evaluator
+                        .random_number(start, end)
+                        .map_err(|_| EquivalenceError::InvalidRange(start, end))?,
+                ),
+            };
+            point.set_variable(name, value);
+        }
+        let expected_value = evaluator
+            .evaluate(expected, &point)
+            .map_err(EquivalenceError::Expected)?;
+        let close = match evaluator.evaluate(student, &point) {
+            Ok(student_value) => {
+                values_close(&student_value, &expected_value, &accuracy.checking_type)
+            }
+            Err(_) => false,
+        };
+        if !close {
+            failures += 1.0;
+        }
+    }
+    Ok(failures < accuracy.failure_rate)
+}
+
+/// Whether the values are equal, numbers (also in lists, vectors and matrices) only need to be
+/// equal within the accuracy of the checking type.
+pub fn values_close(a: &Value, b: &Value, checking_type: &JMECheckingType) -> bool {
+    let all_close = |a: &[f64], b: &[f64]| {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b.iter())
+                .all(|(a, b)| numbers_close(*a, *b, checking_type))
+    };
+    match (a, b) {
+        (Value::List(a), Value::List(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| values_close(a, b, checking_type))
+        }
+        (Value::Vector(a), Value::Vector(b)) => all_close(a, b),
+        (Value::Matrix(a), Value::Matrix(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| all_close(a, b))
+        }
+        (Value::Complex(re1, im1), Value::Complex(re2, im2)) => {
+            numbers_close(*re1, *re2, checking_type) && numbers_close(*im1, *im2, checking_type)
+        }
+        (Value::Complex(re, im), b) | (b, Value::Complex(re, im)) => match b.as_f64() {
+            Some(x) => {
+                numbers_close(*re, x, checking_type) && numbers_close(*im, 0.0, checking_type)
+            }
+            None => false,
+        },
+        _ => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => numbers_close(a, b, checking_type),
+            _ => values_equal(a, b),
+        },
+    }
+}
+
+/// Whether the number `a` is close enough to the expected number `b`
+pub fn numbers_close(a: f64, b: f64, checking_type: &JMECheckingType) -> bool {
+    if a == b {
+        return true;
+    }
+    if !a.is_finite() || !b.is_finite() {
+        return false;
+    }
+    match checking_type {
+        JMECheckingType::RelativeDifference(d) => {
+            let difference = (a - b).abs();
+            if b != 0.0 {
+                difference / b.abs() < d.checking_accuracy.0
+            } else {
+                difference < d.checking_accuracy.0
+            }
+        }
+        JMECheckingType::AbsoluteDifference(d) => (a - b).abs() < d.checking_accuracy.0,
+        JMECheckingType::DecimalPlaces(d) => {
+            let places = d.checking_accuracy as isize;
+            precround(a, places) == precround(b, places)
+        }
+        JMECheckingType::SignificantFigures(d) => {
+            let figures = d.checking_accuracy as isize;
+            siground(a, figures) == siground(b, figures)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jme::JMEString;
+    use std::convert::TryFrom;
+
+    fn equivalent(student: &str, expected: &str, accuracy: serde_json::Value) -> bool {
+        check_equivalence(student, expected, serde_json::from_value(accuracy).unwrap()).unwrap()
+    }
+
+    fn check_equivalence(
+        student: &str,
+        expected: &str,
+        accuracy: JMEAccuracy,
+    ) -> Result<bool, EquivalenceError> {
+        let parse = |s: &str| {
+            JMEString::try_from(s.to_string())
+                .unwrap()
+                .ast()
+                .cloned()
+                .unwrap()
+        };
+        let generators: Vec<JMEValueGenerator> =
+            serde_json::from_value(serde_json::json!([{"name": "n", "value": "random(1..5)"}]))
+                .unwrap();
+        let mut scope = Scope::new();
+        scope.set_variable("a", Value::Integer(2));
+        expressions_equivalent(
+            &parse(student),
+            &parse(expected),
+            &accuracy,
+            &generators,
+            &mut Evaluator::new(3),
+            &scope,
+        )
+    }
+
+    #[test]
+    fn equivalent_expressions() {
+        let reldiff = serde_json::json!({"checkingType": "reldiff", "checkingAccuracy": 0.0001});
+        assert!(equivalent("(x+1)^2", "x^2 + 2x + 1", reldiff.clone()));
+        assert!(equivalent("a*x*y", "2y*x", reldiff.clone()));
+        assert!(equivalent("sin(x)^2 + cos(x)^2", "1", reldiff.clone()));
+        assert!(equivalent("n!/(n-1)!", "n", reldiff.clone()));
+        assert!(!equivalent("x^2", "x^3", reldiff.clone()));
+        assert!(!equivalent("x + y", "x", reldiff.clone()));
+        assert!(!equivalent("-x^2", "x^2", reldiff.clone()));
+        assert!(equivalent("-x^2", "-(x^2)", reldiff.clone()));
+        // Expressions that can't be evaluated are never equivalent
+        assert!(!equivalent("unknown_function(x)", "x", reldiff));
+    }
+
+    #[test]
+    fn checking_types() {
+        let absdiff = serde_json::json!({"checkingType": "absdiff", "checkingAccuracy": 0.01});
+        assert!(equivalent("x + 0.001", "x", absdiff.clone()));
+        assert!(!equivalent("x + 0.1", "x", absdiff));
+        let dp = serde_json::json!({"checkingType": "dp", "checkingAccuracy": 2});
+        assert!(equivalent("3.14", "pi", dp.clone()));
+        assert!(!equivalent("3.1", "pi", dp));
+        let sigfig = serde_json::json!({"checkingType": "sigfig", "checkingAccuracy": 3});
+        assert!(equivalent("1000x", "999.9x", sigfig.clone()));
+        assert!(!equivalent("1000x", "990x", sigfig));
+    }
+
+    #[test]
+    fn failure_rate() {
+        // x and abs(x) differ for the negative values of x
+        let strict = serde_json::json!({"checkingType": "absdiff", "checkingAccuracy": 0.001, "vsetRange": [-1, 1], "vsetRangePoints": 20});
+        assert!(!equivalent("abs(x)", "x", strict));
+        let lenient = serde_json::json!({"checkingType": "absdiff", "checkingAccuracy": 0.001, "vsetRange": [-1, 1], "vsetRangePoints": 20, "failureRate": 21});
+        assert!(equivalent("abs(x)", "x", lenient));
+    }
+
+    #[test]
+    fn invalid_range() {
+        let range = |start: f64, end: f64| -> JMEAccuracy {
+            serde_json::from_value(serde_json::json!({"checkingType": "absdiff", "checkingAccuracy": 0.001, "vsetRange": [start, end]})).unwrap()
+        };
+        assert_eq!(
+            check_equivalence("x", "x", range(5.0, 1.0)),
+            Err(EquivalenceError::InvalidRange(5.0, 1.0))
+        );
+        let mut infinite = range(0.0, 1.0);
+        infinite.vset_range[1].0 = f64::INFINITY;
+        assert_eq!(
+            check_equivalence("x", "x", infinite),
+            Err(EquivalenceError::InvalidRange(0.0, f64::INFINITY))
+        );
+        assert_eq!(check_equivalence("x", "x", range(1.0, 1.0)), Ok(true));
+    }
+
+    #[test]
+    fn close_values() {
+        let reldiff = JMECheckingType::default();
+        assert!(values_close(
+            &Value::List(vec![Value::Integer(1), Value::Rational(1, 2)]),
+            &Value::List(vec![Value::Number(1.0), Value::Number(0.5)]),
+            &reldiff
+        ));
+        assert!(values_close(
+            &Value::Complex(2.0, 0.0),
+            &Value::Integer(2),
+            &reldiff
+        ));
+        assert!(!values_close(
+            &Value::String("1".to_string()),
+            &Value::Integer(1),
+            &reldiff
+        ));
+    }
+}
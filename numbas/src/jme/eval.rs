@@ -0,0 +1,2840 @@
+use crate::jme::ast::{
+    ArithmeticOperator, Expr, Ident, LogicalOperator, PrefixOperator, RangeOperator,
+    RelationalOperator,
+};
+use crate::jme::builtin_functions::BuiltinFunctions;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
+
+/// The value that results from evaluating a JME expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(isize),
+    /// A normalized fraction: the denominator is always positive
+    Rational(isize, isize),
+    Number(f64),
+    /// A complex number, given by its real and imaginary part
+    Complex(f64, f64),
+    Boolean(bool),
+    String(String),
+    List(Vec<Value>),
+    Set(Vec<Value>),
+    Dictionary(BTreeMap<String, Value>),
+    /// A range given by its start, end and step. A step of zero means a continuous range.
+    Range(f64, f64, f64),
+    Vector(Vec<f64>),
+    /// A matrix, given as a list of rows
+    Matrix(Vec<Vec<f64>>),
+    Nothing,
+}
+
+impl Value {
+    /// The name of the type of this value, as used by the `isa` operator and `type` function
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "integer",
+            Value::Rational(_, _) => "rational",
+            Value::Number(_) | Value::Complex(_, _) => "number",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::Dictionary(_) => "dict",
+            Value::Range(_, _, _) => "range",
+            Value::Vector(_) => "vector",
+            Value::Matrix(_) => "matrix",
+            Value::Nothing => "nothing",
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        rank(self).is_some()
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Rational(n, d) => Some(*n as f64 / *d as f64),
+            Value::Number(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<isize> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            Value::Rational(n, 1) => Some(*n),
+            Value::Number(f)
+                if f.is_finite() && f.fract() == 0.0 && f.abs() < isize::MAX as f64 =>
+            {
+                Some(*f as isize)
+            }
+            _ => None,
+        }
+    }
+
+    /// The elements of list-like values (lists, sets, ranges and vectors)
+    pub fn as_list(&self) -> Option<Vec<Value>> {
+        match self {
+            Value::List(l) | Value::Set(l) => Some(l.clone()),
+            Value::Range(start, end, step) => range_values(*start, *end, *step),
+            Value::Vector(v) => Some(v.iter().map(|f| Value::Number(*f)).collect()),
+            _ => None,
+        }
+    }
+
+    /// The string that is used when this value is substituted into text
+    pub fn to_plain_string(&self) -> String {
+        match self {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn join<T: std::fmt::Display>(items: &[T]) -> String {
+            items
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        match self {
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Rational(n, 1) => write!(f, "{}", n),
+            Value::Rational(n, d) => write!(f, "{}/{}", n, d),
+            Value::Number(n) => write!(f, "{}", display_f64(*n)),
+            Value::Complex(re, im) => {
+                let im_part = if *im == 1.0 {
+                    "i".to_string()
+                } else if *im == -1.0 {
+                    "-i".to_string()
+                } else {
+                    format!("{}i", display_f64(*im))
+                };
+                if *re == 0.0 {
+                    write!(f, "{}", im_part)
+                } else if *im < 0.0 {
+                    write!(f, "{}{}", display_f64(*re), im_part)
+                } else {
+                    write!(f, "{}+{}", display_f64(*re), im_part)
+                }
+            }
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::List(l) => write!(f, "[{}]", join(l)),
+            Value::Set(l) => write!(f, "set({})", join(l)),
+            Value::Dictionary(d) => {
+                if d.is_empty() {
+                    write!(f, "dict()")
+                } else {
+                    write!(
+                        f,
+                        "[{}]",
+                        d.iter()
+                            .map(|(k, v)| format!("{:?}: {}", k, v))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }
+            }
+            Value::Range(start, end, step) => write!(
+                f,
+                "{}..{}#{}",
+                display_f64(*start),
+                display_f64(*end),
+                display_f64(*step)
+            ),
+            Value::Vector(v) => write!(
+                f,
+                "vector({})",
+                join(&v.iter().map(|n| display_f64(*n)).collect::<Vec<_>>())
+            ),
+            Value::Matrix(m) => write!(
+                f,
+                "matrix({})",
+                m.iter()
+                    .map(|row| format!(
+                        "[{}]",
+                        join(&row.iter().map(|n| display_f64(*n)).collect::<Vec<_>>())
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Nothing => write!(f, "nothing"),
+        }
+    }
+}
+
+fn display_f64(f: f64) -> String {
+    if f.is_nan() {
+        "NaN".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { "infinity" } else { "-infinity" }.to_string()
+    } else {
+        f.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvaluationError {
+    UnknownVariable(String),
+    UnknownFunction(String),
+    /// The builtin function exists in Numbas, but can't be evaluated by rumbas
+    UnsupportedFunction(String),
+    /// The operator or function can't be applied to arguments of the given types
+    InvalidArguments(String, Vec<&'static str>),
+    IndexOutOfRange(isize),
+    UnknownKey(String),
+    /// The value of the given type can't be cast to the target type
+    InvalidCast(&'static str, String),
+    /// The range can't be converted to a list of values
+    InvalidRange,
+    /// A random value is requested from an empty collection
+    EmptySelection,
+    /// An invalid name is given to a function that binds names (e.g. `map`)
+    InvalidName(String),
+    /// The result of the function is too large to be represented
+    Overflow(String),
+    /// A random number can't be picked from the continuous range with the given bounds
+    InvalidRandomRange(f64, f64),
+}
+
+impl std::fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EvaluationError::UnknownVariable(n) => write!(f, "Unknown variable {}", n),
+            EvaluationError::UnknownFunction(n) => write!(f, "Unknown function {}", n),
+            EvaluationError::UnsupportedFunction(n) => {
+                write!(f, "The function {} can't be evaluated by rumbas", n)
+            }
+            EvaluationError::InvalidArguments(n, types) => write!(
+                f,
+                "{} can't be applied to arguments of type ({})",
+                n,
+                types.join(", ")
+            ),
+            EvaluationError::IndexOutOfRange(i) => write!(f, "Index {} is out of range", i),
+            EvaluationError::UnknownKey(k) => write!(f, "Unknown key {:?}", k),
+            EvaluationError::InvalidCast(from, to) => {
+                write!(f, "Can't cast a value of type {} to {}", from, to)
+            }
+            EvaluationError::InvalidRange => {
+                write!(f, "Can't convert a continuous range to a list")
+            }
+            EvaluationError::EmptySelection => {
+                write!(f, "Can't select a random value from an empty collection")
+            }
+            EvaluationError::InvalidName(n) => write!(f, "{} is not a valid name", n),
+            EvaluationError::Overflow(n) => write!(f, "The result of {} is too large", n),
+            EvaluationError::InvalidRandomRange(start, end) => write!(
+                f,
+                "Can't pick a random number between {} and {}",
+                start, end
+            ),
+        }
+    }
+}
+
+pub type EvaluationResult = Result<Value, EvaluationError>;
+
+/// A custom function that is defined with a JME body
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub parameters: Vec<String>,
+    pub body: Expr,
+}
+
+/// The variables and functions that are available during evaluation.
+///
+/// Names are case insensitive, just like in Numbas.
+#[derive(Clone, Debug, Default)]
+pub struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a new scope that has access to everything in this scope
+    pub fn child(&self) -> Scope<'_> {
+        Scope {
+            parent: Some(self),
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+        }
+    }
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_lowercase(), value);
+    }
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        let name = name.to_lowercase();
+        self.variables
+            .get(&name)
+            .or_else(|| self.parent.and_then(|p| p.variable(&name)))
+    }
+    pub fn add_function(&mut self, name: &str, function: Function) {
+        self.functions.insert(name.to_lowercase(), function);
+    }
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        let name = name.to_lowercase();
+        self.functions
+            .get(&name)
+            .or_else(|| self.parent.and_then(|p| p.function(&name)))
+    }
+}
+
+/// Evaluates JME expressions.
+///
+/// All randomness comes from a seedable random number generator, so evaluating the same
+/// expressions with the same seed always gives the same values.
+pub struct Evaluator {
+    rng: StdRng,
+}
+
+impl Evaluator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr, scope: &Scope) -> EvaluationResult {
+        match expr {
+            Expr::Str(s) => Ok(Value::String(s.clone())),
+            Expr::Int(i) => Ok(Value::Integer(*i)),
+            Expr::Float(i, s) => Ok(Value::Number(
+                format!("{}.{}0", i, s)
+                    .parse()
+                    .expect("float literal to be a valid number"),
+            )),
+            Expr::Bool(b) => Ok(Value::Boolean(*b)),
+            Expr::Range(op, e1, e2) => {
+                let v1 = self.evaluate(e1, scope)?;
+                let v2 = self.evaluate(e2, scope)?;
+                match (op, &v1, &v2) {
+                    (RangeOperator::Create, _, _) => match (v1.as_f64(), v2.as_f64()) {
+                        (Some(start), Some(end)) => Ok(Value::Range(start, end, 1.0)),
+                        _ => Err(invalid_arguments("..", &[v1, v2])),
+                    },
+                    (RangeOperator::Step, Value::Range(start, end, _), _) => match v2.as_f64() {
+                        Some(step) => Ok(Value::Range(*start, *end, step)),
+                        None => Err(invalid_arguments("#", &[v1, v2])),
+                    },
+                    _ => Err(invalid_arguments("#", &[v1, v2])),
+                }
+            }
+            Expr::Arithmetic(op, e1, e2) => {
+                let v1 = self.evaluate(e1, scope)?;
+                let v2 = self.evaluate(e2, scope)?;
+                arithmetic(*op, v1, v2)
+            }
+            Expr::Ident(ident) => scope
+                .variable(ident.name())
+                .cloned()
+                .ok_or_else(|| EvaluationError::UnknownVariable(ident.name().to_owned())),
+            // Builtin constants can be disabled and replaced by variables
+            Expr::Constant(ident) => match scope.variable(ident.name()) {
+                Some(v) => Ok(v.clone()),
+                None => constant(ident),
+            },
+            Expr::Relation(op, e1, e2) => {
+                let v1 = self.evaluate(e1, scope)?;
+                let v2 = self.evaluate(e2, scope)?;
+                relation(*op, v1, v2)
+            }
+            Expr::Logic(op, e1, e2) => {
+                let v1 = self.evaluate(e1, scope)?;
+                let v2 = self.evaluate(e2, scope)?;
+                match (v1.as_bool(), v2.as_bool()) {
+                    (Some(b1), Some(b2)) => Ok(Value::Boolean(match op {
+                        LogicalOperator::And => b1 && b2,
+                        LogicalOperator::Or => b1 || b2,
+                        LogicalOperator::Xor => b1 != b2,
+                        LogicalOperator::Implies => !b1 || b2,
+                    })),
+                    _ => Err(invalid_arguments(logical_operator_name(*op), &[v1, v2])),
+                }
+            }
+            Expr::List(es) => Ok(Value::List(
+                es.iter()
+                    .map(|e| self.evaluate(e, scope))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Expr::Dictionary(es) => {
+                let mut dict = BTreeMap::new();
+                for (k, v) in es.iter() {
+                    let key = match k {
+                        Expr::Ident(ident) => ident.name().to_owned(),
+                        k => self.evaluate(k, scope)?.to_plain_string(),
+                    };
+                    dict.insert(key, self.evaluate(v, scope)?);
+                }
+                Ok(Value::Dictionary(dict))
+            }
+            Expr::FunctionApplication(ident, args) => self.apply(ident, args, scope),
+            Expr::Prefix(op, e) => {
+                let v = self.evaluate(e, scope)?;
+                match (op, v) {
+                    (PrefixOperator::Not, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+                    (PrefixOperator::Minus, v) => negate(v),
+                    (PrefixOperator::Not, v) => Err(invalid_arguments("not", &[v])),
+                }
+            }
+            Expr::Faculty(e) => {
+                let v = self.evaluate(e, scope)?;
+                factorial(v)
+            }
+            Expr::Indexation(e, index) => {
+                let v = self.evaluate(e, scope)?;
+                let index = self.evaluate(index, scope)?;
+                indexation(v, index)
+            }
+            Expr::Cast(e, target) => {
+                let v = self.evaluate(e, scope)?;
+                let target = self.evaluate(target, scope)?;
+                match target {
+                    Value::String(target) => cast(v, &target[..]),
+                    other => Err(invalid_arguments("as", &[v, other])),
+                }
+            }
+            Expr::Sequence(e1, e2) => {
+                self.evaluate(e1, scope)?;
+                self.evaluate(e2, scope)
+            }
+        }
+    }
+
+    fn apply(&mut self, ident: &Ident, args: &[Expr], scope: &Scope) -> EvaluationResult {
+        let name = ident.name();
+        if let Some(function) = scope.function(name) {
+            if function.parameters.len() != args.len() {
+                let values = self.evaluate_all(args, scope)?;
+                return Err(invalid_arguments(name, &values[..]));
+            }
+            let mut function_scope = scope.child();
+            for (parameter, arg) in function.parameters.iter().zip(args.iter()) {
+                let value = self.evaluate(arg, scope)?;
+                function_scope.set_variable(parameter, value);
+            }
+            return self.evaluate(&function.body, &function_scope);
+        }
+        let builtin = BuiltinFunctions::get(name)
+            .or_else(|| BuiltinFunctions::get(&name.to_lowercase()[..]))
+            .ok_or_else(|| EvaluationError::UnknownFunction(name.to_owned()))?;
+        if let Some(result) = self.apply_lazy(&builtin, name, args, scope) {
+            return result;
+        }
+        let values = self.evaluate_all(args, scope)?;
+        self.apply_builtin(builtin, name, values)
+    }
+
+    fn evaluate_all(
+        &mut self,
+        args: &[Expr],
+        scope: &Scope,
+    ) -> Result<Vec<Value>, EvaluationError> {
+        args.iter().map(|a| self.evaluate(a, scope)).collect()
+    }
+
+    /// Handle the functions that don't evaluate (all) their arguments directly.
+    /// Returns None if the function is not lazy.
+    fn apply_lazy(
+        &mut self,
+        builtin: &BuiltinFunctions,
+        name: &str,
+        args: &[Expr],
+        scope: &Scope,
+    ) -> Option<EvaluationResult> {
+        let result = match (builtin, args) {
+            (BuiltinFunctions::If, [condition, then, otherwise]) => {
+                match self.evaluate(condition, scope) {
+                    Ok(Value::Boolean(true)) => self.evaluate(then, scope),
+                    Ok(Value::Boolean(false)) => self.evaluate(otherwise, scope),
+                    Ok(v) => Err(invalid_arguments(name, &[v])),
+                    Err(e) => Err(e),
+                }
+            }
+            (BuiltinFunctions::Switch, _) => self.switch(name, args, scope),
+            (BuiltinFunctions::Repeat, [expr, times]) => self
+                .evaluate(times, scope)
+                .and_then(|times| {
+                    times
+                        .as_integer()
+                        .ok_or_else(|| invalid_arguments(name, &[times]))
+                })
+                .and_then(|times| {
+                    (0..times.max(0))
+                        .map(|_| self.evaluate(expr, scope))
+                        .collect::<Result<_, _>>()
+                        .map(Value::List)
+                }),
+            (BuiltinFunctions::Map, [expr, names, list]) => {
+                self.map(name, expr, names, list, scope)
+            }
+            (BuiltinFunctions::Filter, [expr, names, list]) => {
+                self.filter(name, expr, names, list, scope)
+            }
+            (BuiltinFunctions::Take, [n, expr, names, list]) => {
+                self.take(name, n, expr, names, list, scope)
+            }
+            (BuiltinFunctions::Let, [_, _, ..]) => self.let_(name, args, scope),
+            (BuiltinFunctions::FoldL, [expr, accumulator, item, initial, list]) => {
+                self.fold(name, expr, accumulator, item, initial, list, scope)
+            }
+            (BuiltinFunctions::Iterate, [expr, names, initial, times]) => {
+                self.iterate(name, expr, names, initial, times, scope)
+            }
+            (BuiltinFunctions::IterateUntil, [expr, names, initial, condition]) => {
+                self.iterate_until(name, expr, names, initial, condition, 100, scope)
+            }
+            (BuiltinFunctions::IterateUntil, [expr, names, initial, condition, max]) => self
+                .evaluate(max, scope)
+                .and_then(|max| {
+                    max.as_integer()
+                        .ok_or_else(|| invalid_arguments(name, &[max]))
+                })
+                .and_then(|max| {
+                    self.iterate_until(name, expr, names, initial, condition, max, scope)
+                }),
+            (BuiltinFunctions::Assert, [condition, value]) => {
+                match self.evaluate(condition, scope) {
+                    Ok(Value::Boolean(true)) => Ok(Value::Boolean(false)),
+                    Ok(Value::Boolean(false)) => self.evaluate(value, scope),
+                    Ok(v) => Err(invalid_arguments(name, &[v])),
+                    Err(e) => Err(e),
+                }
+            }
+            (BuiltinFunctions::Try, [expr, error_name, fallback]) => {
+                match self.evaluate(expr, scope) {
+                    Ok(v) => Ok(v),
+                    // Not an error in Numbas, so it can't be handled by the fallback
+                    Err(e @ EvaluationError::UnsupportedFunction(_)) => Err(e),
+                    Err(e) => {
+                        let mut fallback_scope = scope.child();
+                        bind_names(
+                            error_name,
+                            Value::String(e.to_string()),
+                            &mut fallback_scope,
+                        )
+                        .and_then(|_| self.evaluate(fallback, &fallback_scope))
+                    }
+                }
+            }
+            (BuiltinFunctions::IsSet, [Expr::Ident(ident)]) => {
+                Ok(Value::Boolean(scope.variable(ident.name()).is_some()))
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn switch(&mut self, name: &str, args: &[Expr], scope: &Scope) -> EvaluationResult {
+        let mut pairs = args.chunks(2);
+        for pair in &mut pairs {
+            match pair {
+                [condition, value] => match self.evaluate(condition, scope)? {
+                    Value::Boolean(true) => return self.evaluate(value, scope),
+                    Value::Boolean(false) => (),
+                    v => return Err(invalid_arguments(name, &[v])),
+                },
+                [default] => return self.evaluate(default, scope),
+                _ => unreachable!(),
+            }
+        }
+        Ok(Value::Nothing)
+    }
+
+    fn list_argument(
+        &mut self,
+        name: &str,
+        list: &Expr,
+        scope: &Scope,
+    ) -> Result<Vec<Value>, EvaluationError> {
+        let value = self.evaluate(list, scope)?;
+        match &value {
+            Value::Range(_, _, step) if *step == 0.0 => Err(EvaluationError::InvalidRange),
+            _ => value
+                .as_list()
+                .ok_or_else(|| invalid_arguments(name, &[value])),
+        }
+    }
+
+    fn map(
+        &mut self,
+        name: &str,
+        expr: &Expr,
+        names: &Expr,
+        list: &Expr,
+        scope: &Scope,
+    ) -> EvaluationResult {
+        let list = self.evaluate(list, scope)?;
+        let items = match &list {
+            Value::Range(_, _, s) if *s == 0.0 => return Err(EvaluationError::InvalidRange),
+            _ => list
+                .as_list()
+                .ok_or_else(|| invalid_arguments(name, std::slice::from_ref(&list)))?,
+        };
+        let mut result = Vec::with_capacity(items.len());
+        for item in items.into_iter() {
+            let mut item_scope = scope.child();
+            bind_names(names, item, &mut item_scope)?;
+            result.push(self.evaluate(expr, &item_scope)?);
+        }
+        // Mapping over a vector gives a vector
+        if let Value::Vector(_) = list {
+            if let Some(v) = result
+                .iter()
+                .map(|v| v.as_f64())
+                .collect::<Option<Vec<_>>>()
+            {
+                return Ok(Value::Vector(v));
+            }
+        }
+        Ok(Value::List(result))
+    }
+
+    fn filter(
+        &mut self,
+        name: &str,
+        expr: &Expr,
+        names: &Expr,
+        list: &Expr,
+        scope: &Scope,
+    ) -> EvaluationResult {
+        let items = self.list_argument(name, list, scope)?;
+        let mut result = Vec::new();
+        for item in items.into_iter() {
+            let mut item_scope = scope.child();
+            bind_names(names, item.clone(), &mut item_scope)?;
+            match self.evaluate(expr, &item_scope)? {
+                Value::Boolean(true) => result.push(item),
+                Value::Boolean(false) => (),
+                v => return Err(invalid_arguments(name, &[v])),
+            }
+        }
+        Ok(Value::List(result))
+    }
+
+    fn take(
+        &mut self,
+        name: &str,
+        n: &Expr,
+        expr: &Expr,
+        names: &Expr,
+        list: &Expr,
+        scope: &Scope,
+    ) -> EvaluationResult {
+        let n = self.evaluate(n, scope)?;
+        let n = n
+            .as_integer()
+            .ok_or_else(|| invalid_arguments(name, &[n]))?;
+        let items = self.list_argument(name, list, scope)?;
+        let mut result = Vec::new();
+        for item in items.into_iter() {
+            if result.len() as isize >= n {
+                break;
+            }
+            let mut item_scope = scope.child();
+            bind_names(names, item.clone(), &mut item_scope)?;
+            match self.evaluate(expr, &item_scope)? {
+                Value::Boolean(true) => result.push(item),
+                Value::Boolean(false) => (),
+                v => return Err(invalid_arguments(name, &[v])),
+            }
+        }
+        Ok(Value::List(result))
+    }
+
+    fn let_(&mut self, name: &str, args: &[Expr], scope: &Scope) -> EvaluationResult {
+        let (body, definitions) = args.split_last().expect("at least two arguments");
+        let mut let_scope = scope.child();
+        match definitions {
+            [dict] => match self.evaluate(dict, scope)? {
+                Value::Dictionary(d) => {
+                    for (k, v) in d.into_iter() {
+                        let_scope.set_variable(&k[..], v);
+                    }
+                }
+                v => return Err(invalid_arguments(name, &[v])),
+            },
+            definitions if definitions.len() % 2 == 0 => {
+                for definition in definitions.chunks(2) {
+                    let value = self.evaluate(&definition[1], &let_scope)?;
+                    bind_names(&definition[0], value, &mut let_scope)?;
+                }
+            }
+            _ => {
+                let values = self.evaluate_all(args, scope)?;
+                return Err(invalid_arguments(name, &values[..]));
+            }
+        }
+        self.evaluate(body, &let_scope)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fold(
+        &mut self,
+        name: &str,
+        expr: &Expr,
+        accumulator: &Expr,
+        item: &Expr,
+        initial: &Expr,
+        list: &Expr,
+        scope: &Scope,
+    ) -> EvaluationResult {
+        let mut result = self.evaluate(initial, scope)?;
+        let items = self.list_argument(name, list, scope)?;
+        for value in items.into_iter() {
+            let mut item_scope = scope.child();
+            bind_names(accumulator, result, &mut item_scope)?;
+            bind_names(item, value, &mut item_scope)?;
+            result = self.evaluate(expr, &item_scope)?;
+        }
+        Ok(result)
+    }
+
+    fn iterate(
+        &mut self,
+        name: &str,
+        expr: &Expr,
+        names: &Expr,
+        initial: &Expr,
+        times: &Expr,
+        scope: &Scope,
+    ) -> EvaluationResult {
+        let times = self.evaluate(times, scope)?;
+        let times = times
+            .as_integer()
+            .ok_or_else(|| invalid_arguments(name, &[times]))?;
+        let mut current = self.evaluate(initial, scope)?;
+        let mut result = vec![current.clone()];
+        for _ in 0..times {
+            let mut item_scope = scope.child();
+            bind_names(names, current, &mut item_scope)?;
+            current = self.evaluate(expr, &item_scope)?;
+            result.push(current.clone());
+        }
+        Ok(Value::List(result))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn iterate_until(
+        &mut self,
+        name: &str,
+        expr: &Expr,
+        names: &Expr,
+        initial: &Expr,
+        condition: &Expr,
+        max: isize,
+        scope: &Scope,
+    ) -> EvaluationResult {
+        let mut current = self.evaluate(initial, scope)?;
+        let mut result = vec![current.clone()];
+        for _ in 0..max {
+            let mut item_scope = scope.child();
+            bind_names(names, current.clone(), &mut item_scope)?;
+            match self.evaluate(condition, &item_scope)? {
+                Value::Boolean(true) => break,
+                Value::Boolean(false) => (),
+                v => return Err(invalid_arguments(name, &[v])),
+            }
+            current = self.evaluate(expr, &item_scope)?;
+            result.push(current.clone());
+        }
+        Ok(Value::List(result))
+    }
+
+    /// A random number from the continuous range, like `random(start..end#0)`
+    pub fn random_number(&mut self, start: f64, end: f64) -> Result<f64, EvaluationError> {
+        if start.is_finite() && end.is_finite() && start <= end {
+            Ok(self.rng.gen_range(start..=end))
+        } else {
+            Err(EvaluationError::InvalidRandomRange(start, end))
+        }
+    }
+
+    fn random_element(&mut self, items: Vec<Value>) -> EvaluationResult {
+        items
+            .choose(&mut self.rng)
+            .cloned()
+            .ok_or(EvaluationError::EmptySelection)
+    }
+
+    fn apply_builtin(
+        &mut self,
+        builtin: BuiltinFunctions,
+        name: &str,
+        args: Vec<Value>,
+    ) -> EvaluationResult {
+        use BuiltinFunctions as F;
+        let invalid = || invalid_arguments(name, &args[..]);
+        let overflow = || EvaluationError::Overflow(name.to_string());
+        let reals = || -> Option<Vec<f64>> { args.iter().map(|a| a.as_f64()).collect() };
+        let integers = || -> Option<Vec<isize>> { args.iter().map(|a| a.as_integer()).collect() };
+        match builtin {
+            // Numbers
+            F::Exp => match &args[..] {
+                [v] => complex_function(v, f64::exp, |re, im| {
+                    let r = re.exp();
+                    (r * im.cos(), r * im.sin())
+                })
+                .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Decimal | F::Rational | F::Int => match &args[..] {
+                [Value::String(s)] if builtin == F::Decimal => s
+                    .trim()
+                    .parse::<f64>()
+                    .map(Value::Number)
+                    .map_err(|_| invalid()),
+                [v] => cast(
+                    v.clone(),
+                    match builtin {
+                        F::Decimal => "decimal",
+                        F::Rational => "rational",
+                        _ => "integer",
+                    },
+                ),
+                _ => Err(invalid()),
+            },
+            F::Abs | F::Len => match &args[..] {
+                [Value::Integer(i)] => i.checked_abs().map(Value::Integer).ok_or_else(overflow),
+                [Value::Rational(n, d)] => n
+                    .checked_abs()
+                    .map(|n| Value::Rational(n, *d))
+                    .ok_or_else(overflow),
+                [Value::Number(f)] => Ok(Value::Number(f.abs())),
+                [Value::Complex(re, im)] => Ok(Value::Number(re.hypot(*im))),
+                [Value::String(s)] => Ok(Value::Integer(s.chars().count() as isize)),
+                [Value::Dictionary(d)] => Ok(Value::Integer(d.len() as isize)),
+                [Value::Vector(v)] => {
+                    Ok(Value::Number(v.iter().map(|x| x * x).sum::<f64>().sqrt()))
+                }
+                [Value::Range(_, _, s)] if *s == 0.0 => Err(EvaluationError::InvalidRange),
+                [v @ (Value::List(_) | Value::Set(_) | Value::Range(_, _, _))] => Ok(
+                    Value::Integer(v.as_list().map(|l| l.len()).unwrap_or(0) as isize),
+                ),
+                _ => Err(invalid()),
+            },
+            F::Arg | F::Re | F::Im | F::Conj => match &args[..] {
+                [v] => {
+                    let (re, im) = as_complex(v).ok_or_else(invalid)?;
+                    Ok(match builtin {
+                        F::Arg => Value::Number(im.atan2(re)),
+                        F::Re => Value::Number(re),
+                        F::Im => Value::Number(im),
+                        _ => complex_value(re, -im),
+                    })
+                }
+                _ => Err(invalid()),
+            },
+            F::IsInt => match &args[..] {
+                [v] => Ok(Value::Boolean(v.as_integer().is_some())),
+                _ => Err(invalid()),
+            },
+            F::IsZero => match &args[..] {
+                [v] => as_complex(v)
+                    .map(|(re, im)| Value::Boolean(re == 0.0 && im == 0.0))
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::IsZeroVector => match &args[..] {
+                [Value::Vector(v)] => Ok(Value::Boolean(v.iter().all(|x| *x == 0.0))),
+                _ => Err(invalid()),
+            },
+            F::Sqrt => match &args[..] {
+                [v] => complex_function(
+                    v,
+                    |x| x.sqrt(),
+                    |re, im| {
+                        if im == 0.0 && re < 0.0 {
+                            return (0.0, (-re).sqrt());
+                        }
+                        let r = re.hypot(im).sqrt();
+                        let theta = im.atan2(re) / 2.0;
+                        (r * theta.cos(), r * theta.sin())
+                    },
+                )
+                .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Root => match reals().as_deref() {
+                Some([x, n]) => Ok(Value::Number(if *x < 0.0 && (*n % 2.0).abs() == 1.0 {
+                    -(-x).powf(1.0 / n)
+                } else {
+                    x.powf(1.0 / n)
+                })),
+                _ => Err(invalid()),
+            },
+            F::Ln => match &args[..] {
+                [v] => complex_function(v, f64::ln, |re, im| (re.hypot(im).ln(), im.atan2(re)))
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Log => match reals().as_deref() {
+                Some([x]) => Ok(Value::Number(x.log10())),
+                Some([x, b]) => Ok(Value::Number(x.ln() / b.ln())),
+                _ => Err(invalid()),
+            },
+            F::Degrees => real_function(&args[..], f64::to_degrees).ok_or_else(invalid),
+            F::Radians => real_function(&args[..], f64::to_radians).ok_or_else(invalid),
+            F::Sign => match &args[..] {
+                [Value::Integer(i)] => Ok(Value::Integer(i.signum())),
+                [Value::Rational(n, _)] => Ok(Value::Integer(n.signum())),
+                [v] => v
+                    .as_f64()
+                    .map(|f| {
+                        Value::Integer(if f > 0.0 {
+                            1
+                        } else if f < 0.0 {
+                            -1
+                        } else {
+                            0
+                        })
+                    })
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Max | F::Min => {
+                let items = match &args[..] {
+                    [v] => v.as_list().ok_or_else(invalid)?,
+                    _ => args.clone(),
+                };
+                let mut best: Option<(f64, Value)> = None;
+                for item in items.into_iter() {
+                    let f = item.as_f64().ok_or_else(invalid)?;
+                    let better = match &best {
+                        None => true,
+                        Some((b, _)) => {
+                            (builtin == F::Max && f > *b) || (builtin == F::Min && f < *b)
+                        }
+                    };
+                    if better {
+                        best = Some((f, item));
+                    }
+                }
+                best.map(|(_, v)| v).ok_or(EvaluationError::EmptySelection)
+            }
+            F::Clamp => match reals().as_deref() {
+                Some([x, a, b]) => Ok(Value::Number(x.max(*a).min(*b))),
+                _ => Err(invalid()),
+            },
+            F::PrecRound | F::SigRound => match &args[..] {
+                [v, d] => {
+                    let d = d.as_integer().ok_or_else(invalid)?;
+                    let round = |x: f64| {
+                        if builtin == F::PrecRound {
+                            precround(x, d)
+                        } else {
+                            siground(x, d)
+                        }
+                    };
+                    map_reals(v, round).ok_or_else(invalid)
+                }
+                _ => Err(invalid()),
+            },
+            F::WithIntolerance => match reals().as_deref() {
+                Some([a, b, t]) => Ok(Value::Boolean(b - t <= *a && *a <= b + t)),
+                _ => Err(invalid()),
+            },
+            F::DPFormat => match &args[..] {
+                [x, d] => match (x.as_f64(), d.as_integer()) {
+                    (Some(x), Some(d)) if d >= 0 => {
+                        Ok(Value::String(format!("{:.*}", d as usize, precround(x, d))))
+                    }
+                    _ => Err(invalid()),
+                },
+                _ => Err(invalid()),
+            },
+            F::SigFormat => match &args[..] {
+                [x, s] => match (x.as_f64(), s.as_integer()) {
+                    (Some(x), Some(s)) if s > 0 => {
+                        let rounded = siground(x, s);
+                        let magnitude = if rounded == 0.0 {
+                            0
+                        } else {
+                            rounded.abs().log10().floor() as isize
+                        };
+                        let decimals = (s - 1 - magnitude).max(0) as usize;
+                        Ok(Value::String(format!("{:.*}", decimals, rounded)))
+                    }
+                    _ => Err(invalid()),
+                },
+                _ => Err(invalid()),
+            },
+            F::CountDP | F::CountSigFigs => match &args[..] {
+                [Value::String(s)] => Ok(Value::Integer(if builtin == F::CountDP {
+                    count_dp(s)
+                } else {
+                    count_sig_figs(s)
+                })),
+                _ => Err(invalid()),
+            },
+            F::ToNearest => match reals().as_deref() {
+                Some([a, b]) => Ok(Value::Number((a / b).round() * b)),
+                _ => Err(invalid()),
+            },
+            F::IsNan => match &args[..] {
+                [v] => Ok(Value::Boolean(
+                    v.as_f64().map(|f| f.is_nan()).unwrap_or(false),
+                )),
+                _ => Err(invalid()),
+            },
+            F::ToBinary | F::ToOctal | F::ToHexadecimal => match integers().as_deref() {
+                Some([n]) => Ok(Value::String(to_base(
+                    *n,
+                    match builtin {
+                        F::ToBinary => 2,
+                        F::ToOctal => 8,
+                        _ => 16,
+                    },
+                ))),
+                _ => Err(invalid()),
+            },
+            F::ToBase => match integers().as_deref() {
+                Some([n, base]) if (2..=36).contains(base) => {
+                    Ok(Value::String(to_base(*n, *base as u32)))
+                }
+                _ => Err(invalid()),
+            },
+            F::FromBinary | F::FromOctal | F::FromHexadecimal | F::FromBase => {
+                let (s, base) = match (&builtin, &args[..]) {
+                    (F::FromBinary, [Value::String(s)]) => (s, 2),
+                    (F::FromOctal, [Value::String(s)]) => (s, 8),
+                    (F::FromHexadecimal, [Value::String(s)]) => (s, 16),
+                    (F::FromBase, [Value::String(s), b]) => (
+                        s,
+                        b.as_integer()
+                            .filter(|b| (2..=36).contains(b))
+                            .ok_or_else(invalid)? as u32,
+                    ),
+                    _ => return Err(invalid()),
+                };
+                isize::from_str_radix(s.trim(), base)
+                    .map(Value::Integer)
+                    .or(Ok(Value::Number(f64::NAN)))
+            }
+            // Trigonometry
+            F::Sin => real_function(&args[..], f64::sin).ok_or_else(invalid),
+            F::Cos => real_function(&args[..], f64::cos).ok_or_else(invalid),
+            F::Tan => real_function(&args[..], f64::tan).ok_or_else(invalid),
+            F::Cosec => real_function(&args[..], |x| 1.0 / x.sin()).ok_or_else(invalid),
+            F::Sec => real_function(&args[..], |x| 1.0 / x.cos()).ok_or_else(invalid),
+            F::Cot => real_function(&args[..], |x| 1.0 / x.tan()).ok_or_else(invalid),
+            F::ArcSin => real_function(&args[..], f64::asin).ok_or_else(invalid),
+            F::ArcCos => real_function(&args[..], f64::acos).ok_or_else(invalid),
+            F::ArcTan => real_function(&args[..], f64::atan).ok_or_else(invalid),
+            F::ATan2 => match reals().as_deref() {
+                Some([y, x]) => Ok(Value::Number(y.atan2(*x))),
+                _ => Err(invalid()),
+            },
+            F::Sinh => real_function(&args[..], f64::sinh).ok_or_else(invalid),
+            F::Cosh => real_function(&args[..], f64::cosh).ok_or_else(invalid),
+            F::Tanh => real_function(&args[..], f64::tanh).ok_or_else(invalid),
+            F::Cosech => real_function(&args[..], |x| 1.0 / x.sinh()).ok_or_else(invalid),
+            F::Sech => real_function(&args[..], |x| 1.0 / x.cosh()).ok_or_else(invalid),
+            F::Coth => real_function(&args[..], |x| 1.0 / x.tanh()).ok_or_else(invalid),
+            F::ArcSinh => real_function(&args[..], f64::asinh).ok_or_else(invalid),
+            F::ArcCosh => real_function(&args[..], f64::acosh).ok_or_else(invalid),
+            F::ArcTanh => real_function(&args[..], f64::atanh).ok_or_else(invalid),
+            // Number theory
+            F::Fact => match &args[..] {
+                [v] => factorial(v.clone()),
+                _ => Err(invalid()),
+            },
+            F::Gamma => real_function(&args[..], gamma).ok_or_else(invalid),
+            F::Factorise => match integers().as_deref() {
+                Some([n]) if *n > 0 => Ok(Value::List(
+                    factorise(*n).into_iter().map(Value::Integer).collect(),
+                )),
+                _ => Err(invalid()),
+            },
+            F::Divisors | F::ProperDivisors => match integers().as_deref() {
+                Some([n]) => {
+                    let n = n.abs();
+                    Ok(Value::List(
+                        (1..=n)
+                            .filter(|d| n % d == 0 && (builtin == F::Divisors || *d < n))
+                            .map(Value::Integer)
+                            .collect(),
+                    ))
+                }
+                _ => Err(invalid()),
+            },
+            F::Ceil | F::Floor | F::Round | F::Trunc => match &args[..] {
+                [Value::Integer(i)] => Ok(Value::Integer(*i)),
+                [v] => map_reals(v, |x| match builtin {
+                    F::Ceil => x.ceil(),
+                    F::Floor => x.floor(),
+                    F::Round => (x + 0.5).floor(),
+                    _ => x.trunc(),
+                })
+                .map(whole_number)
+                .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Fract => real_function(&args[..], f64::fract).ok_or_else(invalid),
+            F::Mod => match (integers().as_deref(), reals().as_deref()) {
+                (Some([a, b]), _) if *b != 0 => {
+                    // The result has the sign of the divisor, like `((a % b) + b) % b`
+                    let r = a.wrapping_rem(*b);
+                    Ok(Value::Integer(if r != 0 && (r < 0) != (*b < 0) {
+                        r + b
+                    } else {
+                        r
+                    }))
+                }
+                (_, Some([a, b])) => Ok(Value::Number(((a % b) + b) % b)),
+                _ => Err(invalid()),
+            },
+            F::Perm | F::Comb => match integers().as_deref() {
+                Some([n, k]) if *n >= 0 && *k >= 0 => {
+                    if k > n {
+                        return Ok(Value::Integer(0));
+                    }
+                    let mut result: f64 = 1.0;
+                    for i in 0..*k {
+                        result *= (n - i) as f64;
+                        if builtin == F::Comb {
+                            result /= (i + 1) as f64;
+                        }
+                    }
+                    Ok(whole_number(Value::Number(result.round())))
+                }
+                _ => Err(invalid()),
+            },
+            F::GCD | F::GCDWithoutPIorI => match integers().as_deref() {
+                Some([a, b]) => gcd(*a, *b).map(Value::Integer).ok_or_else(overflow),
+                _ => Err(invalid()),
+            },
+            F::LCM => {
+                let items = match &args[..] {
+                    [v @ (Value::List(_) | Value::Set(_))] => v.as_list().ok_or_else(invalid)?,
+                    _ => args.clone(),
+                };
+                let ns = items
+                    .iter()
+                    .map(|v| v.as_integer())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(invalid)?;
+                ns.into_iter()
+                    .try_fold(1_isize, |acc, n| {
+                        if n == 0 {
+                            Some(0)
+                        } else {
+                            (acc / gcd(acc, n)?).checked_mul(n)?.checked_abs()
+                        }
+                    })
+                    .map(Value::Integer)
+                    .ok_or_else(overflow)
+            }
+            F::CoPrime => match (integers().as_deref(), reals()) {
+                (Some([a, b]), _) => Ok(Value::Boolean(gcd(*a, *b) == Some(1))),
+                (None, Some(_)) if args.len() == 2 => Ok(Value::Boolean(true)),
+                _ => Err(invalid()),
+            },
+            // Vectors and matrices
+            F::Vector => {
+                let items = match &args[..] {
+                    [v @ (Value::List(_) | Value::Range(_, _, _))] => {
+                        v.as_list().ok_or_else(invalid)?
+                    }
+                    _ => args.clone(),
+                };
+                items
+                    .iter()
+                    .map(|v| v.as_f64())
+                    .collect::<Option<Vec<_>>>()
+                    .map(Value::Vector)
+                    .ok_or_else(invalid)
+            }
+            F::Matrix | F::RowVector => {
+                let rows = match (&builtin, &args[..]) {
+                    (F::RowVector, _) => vec![Value::List(args.clone())],
+                    (_, [Value::List(rows)])
+                        if rows.iter().all(|r| matches!(r, Value::List(_))) =>
+                    {
+                        rows.clone()
+                    }
+                    _ => args.clone(),
+                };
+                rows.iter()
+                    .map(|r| {
+                        r.as_list()
+                            .and_then(|r| r.iter().map(|v| v.as_f64()).collect::<Option<Vec<_>>>())
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    // All rows should have the same length
+                    .filter(|m| {
+                        m.iter()
+                            .all(|r| Some(r.len()) == m.first().map(|f| f.len()))
+                    })
+                    .map(Value::Matrix)
+                    .ok_or_else(invalid)
+            }
+            F::Id => match integers().as_deref() {
+                Some([n]) if *n >= 0 => Ok(Value::Matrix(
+                    (0..*n)
+                        .map(|i| (0..*n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+                        .collect(),
+                )),
+                _ => Err(invalid()),
+            },
+            F::NumRows | F::NumColumns => match &args[..] {
+                [Value::Matrix(m)] => Ok(Value::Integer(if builtin == F::NumRows {
+                    m.len()
+                } else {
+                    m.first().map(|r| r.len()).unwrap_or(0)
+                } as isize)),
+                _ => Err(invalid()),
+            },
+            F::Dot => match (
+                args.first().and_then(as_vector),
+                args.get(1).and_then(as_vector),
+            ) {
+                (Some(v), Some(w)) if args.len() == 2 && v.len() == w.len() => Ok(Value::Number(
+                    v.iter().zip(w.iter()).map(|(a, b)| a * b).sum(),
+                )),
+                _ => Err(invalid()),
+            },
+            F::Cross => match (
+                args.first().and_then(as_vector),
+                args.get(1).and_then(as_vector),
+            ) {
+                (Some(v), Some(w)) if args.len() == 2 && v.len() == 3 && w.len() == 3 => {
+                    Ok(Value::Vector(vec![
+                        v[1] * w[2] - v[2] * w[1],
+                        v[2] * w[0] - v[0] * w[2],
+                        v[0] * w[1] - v[1] * w[0],
+                    ]))
+                }
+                _ => Err(invalid()),
+            },
+            F::Angle => match (
+                args.first().and_then(as_vector),
+                args.get(1).and_then(as_vector),
+            ) {
+                (Some(v), Some(w)) if args.len() == 2 && v.len() == w.len() => {
+                    let dot: f64 = v.iter().zip(w.iter()).map(|(a, b)| a * b).sum();
+                    let norm = |x: &Vec<f64>| x.iter().map(|a| a * a).sum::<f64>().sqrt();
+                    Ok(Value::Number((dot / (norm(&v) * norm(&w))).acos()))
+                }
+                _ => Err(invalid()),
+            },
+            F::Det => match &args[..] {
+                [Value::Matrix(m)] if m.iter().all(|r| r.len() == m.len()) => {
+                    Ok(Value::Number(determinant(m.clone())))
+                }
+                _ => Err(invalid()),
+            },
+            F::Transpose => match &args[..] {
+                [Value::Matrix(m)] => Ok(Value::Matrix(transpose(m))),
+                [Value::Vector(v)] => Ok(Value::Matrix(vec![v.clone()])),
+                _ => Err(invalid()),
+            },
+            F::SumCells => match &args[..] {
+                [Value::Matrix(m)] => Ok(Value::Number(m.iter().flatten().sum())),
+                [Value::Vector(v)] => Ok(Value::Number(v.iter().sum())),
+                _ => Err(invalid()),
+            },
+            // Strings
+            F::String => match &args[..] {
+                [v] => Ok(Value::String(v.to_plain_string())),
+                _ => Err(invalid()),
+            },
+            F::Safe | F::LaTeX => match &args[..] {
+                [Value::String(s)] => Ok(Value::String(s.clone())),
+                _ => Err(invalid()),
+            },
+            F::Capitalise | F::Upper | F::Lower | F::Trim => match &args[..] {
+                [Value::String(s)] => Ok(Value::String(match builtin {
+                    F::Upper => s.to_uppercase(),
+                    F::Lower => s.to_lowercase(),
+                    F::Trim => s.trim().to_owned(),
+                    _ => {
+                        let mut chars = s.chars();
+                        match chars.next() {
+                            Some(c) => c.to_uppercase().chain(chars).collect(),
+                            None => String::new(),
+                        }
+                    }
+                })),
+                _ => Err(invalid()),
+            },
+            F::Pluralise => match &args[..] {
+                [n, Value::String(singular), Value::String(plural)] => {
+                    let n = n.as_f64().ok_or_else(invalid)?;
+                    Ok(Value::String(
+                        if n == 1.0 { singular } else { plural }.clone(),
+                    ))
+                }
+                _ => Err(invalid()),
+            },
+            F::Join => match &args[..] {
+                [list, Value::String(separator)] => Ok(Value::String(
+                    list.as_list()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|v| v.to_plain_string())
+                        .collect::<Vec<_>>()
+                        .join(separator),
+                )),
+                _ => Err(invalid()),
+            },
+            F::Split => match &args[..] {
+                [Value::String(s), Value::String(separator)] => Ok(Value::List(
+                    s.split(&separator[..])
+                        .map(|p| Value::String(p.to_owned()))
+                        .collect(),
+                )),
+                _ => Err(invalid()),
+            },
+            F::LPad | F::RPad => match &args[..] {
+                [Value::String(s), n, Value::String(prefix)] => {
+                    let n = n.as_integer().ok_or_else(invalid)?;
+                    let missing = (n - s.chars().count() as isize).max(0) as usize;
+                    let padding: String = prefix.chars().cycle().take(missing).collect();
+                    Ok(Value::String(if builtin == F::LPad {
+                        format!("{}{}", padding, s)
+                    } else {
+                        format!("{}{}", s, padding)
+                    }))
+                }
+                _ => Err(invalid()),
+            },
+            F::LetterOrdinal => match integers().as_deref() {
+                Some([n]) if *n >= 0 => {
+                    let mut n = *n;
+                    let mut s = String::new();
+                    loop {
+                        s.insert(0, (b'a' + (n % 26) as u8) as char);
+                        n = n / 26 - 1;
+                        if n < 0 {
+                            break;
+                        }
+                    }
+                    Ok(Value::String(s))
+                }
+                _ => Err(invalid()),
+            },
+            F::UnPercent => match &args[..] {
+                [Value::String(s)] => s
+                    .trim()
+                    .trim_end_matches('%')
+                    .trim()
+                    .parse::<f64>()
+                    .map(|f| Value::Number(f / 100.0))
+                    .map_err(|_| invalid()),
+                _ => Err(invalid()),
+            },
+            F::Currency => match &args[..] {
+                [n, Value::String(prefix), Value::String(suffix)] => {
+                    let n = n.as_f64().ok_or_else(invalid)?;
+                    let formatted = if n.fract() == 0.0 {
+                        format!("{}", n)
+                    } else {
+                        format!("{:.2}", n)
+                    };
+                    Ok(Value::String(format!("{}{}{}", prefix, formatted, suffix)))
+                }
+                _ => Err(invalid()),
+            },
+            F::IsBool => match &args[..] {
+                [Value::Boolean(_)] => Ok(Value::Boolean(true)),
+                [Value::String(s)] => Ok(Value::Boolean(matches!(
+                    &s.trim().to_lowercase()[..],
+                    "true" | "false" | "yes" | "no"
+                ))),
+                [_] => Ok(Value::Boolean(false)),
+                _ => Err(invalid()),
+            },
+            F::IsClose => match reals().as_deref() {
+                Some([a, b]) => Ok(Value::Boolean(is_close(*a, *b, 1e-15, 1e-15))),
+                Some([a, b, rel]) => Ok(Value::Boolean(is_close(*a, *b, *rel, 1e-15))),
+                Some([a, b, rel, abs]) => Ok(Value::Boolean(is_close(*a, *b, *rel, *abs))),
+                _ => Err(invalid()),
+            },
+            // Lists
+            F::All | F::Some => match &args[..] {
+                [list] => list
+                    .as_list()
+                    .and_then(|l| l.iter().map(|v| v.as_bool()).collect::<Option<Vec<_>>>())
+                    .map(|l| {
+                        Value::Boolean(if builtin == F::All {
+                            l.into_iter().all(|b| b)
+                        } else {
+                            l.into_iter().any(|b| b)
+                        })
+                    })
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Flatten => match &args[..] {
+                [Value::List(l)] => l
+                    .iter()
+                    .map(|v| v.as_list())
+                    .collect::<Option<Vec<_>>>()
+                    .map(|l| Value::List(l.into_iter().flatten().collect()))
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Sort => match &args[..] {
+                [list] => {
+                    let mut items = list.as_list().ok_or_else(invalid)?;
+                    if items.iter().all(|v| v.is_numeric()) {
+                        items.sort_by(|a, b| {
+                            a.as_f64()
+                                .partial_cmp(&b.as_f64())
+                                .unwrap_or(std::cmp::Ordering::Equal)
+                        });
+                    } else if items.iter().all(|v| matches!(v, Value::String(_))) {
+                        items.sort_by_key(|v| v.to_plain_string());
+                    } else {
+                        return Err(invalid());
+                    }
+                    Ok(Value::List(items))
+                }
+                _ => Err(invalid()),
+            },
+            F::SortBy => match &args[..] {
+                [key, list] => {
+                    let mut items = list
+                        .as_list()
+                        .ok_or_else(invalid)?
+                        .into_iter()
+                        .map(|item| {
+                            indexation(item.clone(), key.clone()).map(|k| (k.as_f64(), k, item))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    items.sort_by(|(fa, a, _), (fb, b, _)| match (fa, fb) {
+                        (Some(fa), Some(fb)) => {
+                            fa.partial_cmp(fb).unwrap_or(std::cmp::Ordering::Equal)
+                        }
+                        _ => a.to_plain_string().cmp(&b.to_plain_string()),
+                    });
+                    Ok(Value::List(items.into_iter().map(|(_, _, i)| i).collect()))
+                }
+                _ => Err(invalid()),
+            },
+            F::Reverse => match &args[..] {
+                [list] => {
+                    let mut items = list.as_list().ok_or_else(invalid)?;
+                    items.reverse();
+                    Ok(Value::List(items))
+                }
+                _ => Err(invalid()),
+            },
+            F::Indices => match &args[..] {
+                [list, value] => Ok(Value::List(
+                    list.as_list()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, v)| values_equal(v, value))
+                        .map(|(i, _)| Value::Integer(i as isize))
+                        .collect(),
+                )),
+                _ => Err(invalid()),
+            },
+            F::Distinct => match &args[..] {
+                [list] => Ok(Value::List(distinct(list.as_list().ok_or_else(invalid)?))),
+                _ => Err(invalid()),
+            },
+            F::List => match &args[..] {
+                [Value::Dictionary(d)] => Ok(Value::List(
+                    d.iter()
+                        .map(|(k, v)| Value::List(vec![Value::String(k.clone()), v.clone()]))
+                        .collect(),
+                )),
+                [Value::Matrix(m)] => Ok(Value::List(
+                    m.iter()
+                        .map(|r| Value::List(r.iter().map(|f| Value::Number(*f)).collect()))
+                        .collect(),
+                )),
+                [v] => v.as_list().map(Value::List).ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            F::Sum | F::Prod => match &args[..] {
+                [list] => {
+                    let (start, op) = if builtin == F::Sum {
+                        (Value::Integer(0), ArithmeticOperator::Add)
+                    } else {
+                        (Value::Integer(1), ArithmeticOperator::Multiply)
+                    };
+                    list.as_list()
+                        .ok_or_else(invalid)?
+                        .into_iter()
+                        .try_fold(start, |acc, v| arithmetic(op, acc, v))
+                }
+                _ => Err(invalid()),
+            },
+            F::Product => {
+                let lists = match &args[..] {
+                    [Value::List(l)] if l.iter().all(|v| v.as_list().is_some()) => l.clone(),
+                    _ => args.clone(),
+                };
+                let lists = lists
+                    .iter()
+                    .map(|l| l.as_list())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(invalid)?;
+                let mut result: Vec<Vec<Value>> = vec![vec![]];
+                for list in lists.into_iter() {
+                    result = result
+                        .into_iter()
+                        .flat_map(|prefix| {
+                            list.iter().map(move |item| {
+                                let mut combination = prefix.clone();
+                                combination.push(item.clone());
+                                combination
+                            })
+                        })
+                        .collect();
+                }
+                Ok(Value::List(result.into_iter().map(Value::List).collect()))
+            }
+            F::Zip => {
+                let lists = args
+                    .iter()
+                    .map(|l| l.as_list())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(invalid)?;
+                let length = lists.iter().map(|l| l.len()).min().unwrap_or(0);
+                Ok(Value::List(
+                    (0..length)
+                        .map(|i| Value::List(lists.iter().map(|l| l[i].clone()).collect()))
+                        .collect(),
+                ))
+            }
+            F::Enumerate => match &args[..] {
+                [list] => Ok(Value::List(
+                    list.as_list()
+                        .ok_or_else(invalid)?
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, v)| Value::List(vec![Value::Integer(i as isize), v]))
+                        .collect(),
+                )),
+                _ => Err(invalid()),
+            },
+            F::Frequencies => match &args[..] {
+                [list] => {
+                    let items = list.as_list().ok_or_else(invalid)?;
+                    let mut frequencies: Vec<(Value, isize)> = Vec::new();
+                    for item in items.into_iter() {
+                        match frequencies.iter_mut().find(|(v, _)| values_equal(v, &item)) {
+                            Some((_, count)) => *count += 1,
+                            None => frequencies.push((item, 1)),
+                        }
+                    }
+                    Ok(Value::List(
+                        frequencies
+                            .into_iter()
+                            .map(|(v, c)| Value::List(vec![v, Value::Integer(c)]))
+                            .collect(),
+                    ))
+                }
+                _ => Err(invalid()),
+            },
+            // Dictionaries
+            F::Get => match &args[..] {
+                [Value::Dictionary(d), key, default] => Ok(d
+                    .get(&key.to_plain_string())
+                    .cloned()
+                    .unwrap_or_else(|| default.clone())),
+                _ => Err(invalid()),
+            },
+            F::Dict => match &args[..] {
+                [] => Ok(Value::Dictionary(BTreeMap::new())),
+                [Value::Dictionary(d)] => Ok(Value::Dictionary(d.clone())),
+                _ => {
+                    let pairs = match &args[..] {
+                        [Value::List(l)] if l.iter().all(|p| matches!(p, Value::List(_))) => {
+                            l.clone()
+                        }
+                        _ => args.clone(),
+                    };
+                    pairs
+                        .into_iter()
+                        .map(|p| match p {
+                            Value::List(kv) if kv.len() == 2 => {
+                                Some((kv[0].to_plain_string(), kv[1].clone()))
+                            }
+                            _ => None,
+                        })
+                        .collect::<Option<BTreeMap<_, _>>>()
+                        .map(Value::Dictionary)
+                        .ok_or_else(invalid)
+                }
+            },
+            F::Keys | F::Values | F::Items => match &args[..] {
+                [Value::Dictionary(d)] => Ok(Value::List(
+                    d.iter()
+                        .map(|(k, v)| match builtin {
+                            F::Keys => Value::String(k.clone()),
+                            F::Values => v.clone(),
+                            _ => Value::List(vec![Value::String(k.clone()), v.clone()]),
+                        })
+                        .collect(),
+                )),
+                [Value::Dictionary(d), keys] if builtin == F::Values => keys
+                    .as_list()
+                    .ok_or_else(invalid)?
+                    .iter()
+                    .map(|k| {
+                        let k = k.to_plain_string();
+                        d.get(&k).cloned().ok_or(EvaluationError::UnknownKey(k))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Value::List),
+                _ => Err(invalid()),
+            },
+            // Sets
+            F::Set => {
+                let items = match &args[..] {
+                    [v @ (Value::List(_) | Value::Set(_) | Value::Range(_, _, _))] => {
+                        v.as_list().ok_or_else(invalid)?
+                    }
+                    _ => args.clone(),
+                };
+                Ok(Value::Set(distinct(items)))
+            }
+            F::Union | F::Intersection => match &args[..] {
+                [a, b] => {
+                    let (a, b) = (
+                        a.as_list().ok_or_else(invalid)?,
+                        b.as_list().ok_or_else(invalid)?,
+                    );
+                    Ok(Value::Set(if builtin == F::Union {
+                        distinct(a.into_iter().chain(b).collect())
+                    } else {
+                        distinct(
+                            a.into_iter()
+                                .filter(|x| b.iter().any(|y| values_equal(x, y)))
+                                .collect(),
+                        )
+                    }))
+                }
+                _ => Err(invalid()),
+            },
+            // Randomisation
+            F::Random => match &args[..] {
+                [Value::Range(start, end, step)] if *step == 0.0 => {
+                    self.random_number(*start, *end).map(Value::Number)
+                }
+                [v @ (Value::List(_) | Value::Set(_) | Value::Range(_, _, _))] => {
+                    self.random_element(v.as_list().ok_or_else(invalid)?)
+                }
+                _ => self.random_element(args.clone()),
+            },
+            F::WeightedRandom => match &args[..] {
+                [list] => {
+                    let items = list
+                        .as_list()
+                        .and_then(|l| {
+                            l.into_iter()
+                                .map(|p| match p {
+                                    Value::List(p) if p.len() == 2 => {
+                                        p[1].as_f64().map(|w| (p[0].clone(), w))
+                                    }
+                                    _ => None,
+                                })
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .ok_or_else(invalid)?;
+                    let total: f64 = items.iter().map(|(_, w)| w.max(0.0)).sum();
+                    if total <= 0.0 {
+                        return Err(EvaluationError::EmptySelection);
+                    }
+                    let mut target = self.rng.gen_range(0.0..total);
+                    for (value, weight) in items.into_iter() {
+                        target -= weight.max(0.0);
+                        if target < 0.0 {
+                            return Ok(value);
+                        }
+                    }
+                    Err(EvaluationError::EmptySelection)
+                }
+                _ => Err(invalid()),
+            },
+            F::Deal => match integers().as_deref() {
+                Some([n]) if *n >= 0 => {
+                    let mut items: Vec<_> = (0..*n).map(Value::Integer).collect();
+                    items.shuffle(&mut self.rng);
+                    Ok(Value::List(items))
+                }
+                _ => Err(invalid()),
+            },
+            F::Shuffle => match &args[..] {
+                [list] => {
+                    let mut items = list.as_list().ok_or_else(invalid)?;
+                    items.shuffle(&mut self.rng);
+                    Ok(Value::List(items))
+                }
+                _ => Err(invalid()),
+            },
+            F::ShuffleTogether => match &args[..] {
+                [lists] => {
+                    let lists = lists
+                        .as_list()
+                        .and_then(|l| l.iter().map(|l| l.as_list()).collect::<Option<Vec<_>>>())
+                        .ok_or_else(invalid)?;
+                    let length = lists.first().map(|l| l.len()).unwrap_or(0);
+                    if lists.iter().any(|l| l.len() != length) {
+                        return Err(invalid());
+                    }
+                    let mut order: Vec<usize> = (0..length).collect();
+                    order.shuffle(&mut self.rng);
+                    Ok(Value::List(
+                        lists
+                            .into_iter()
+                            .map(|l| Value::List(order.iter().map(|i| l[*i].clone()).collect()))
+                            .collect(),
+                    ))
+                }
+                _ => Err(invalid()),
+            },
+            F::Reorder => match &args[..] {
+                [list, order] => {
+                    let items = list.as_list().ok_or_else(invalid)?;
+                    order
+                        .as_list()
+                        .ok_or_else(invalid)?
+                        .iter()
+                        .map(|i| {
+                            let i = i.as_integer().ok_or_else(invalid)?;
+                            usize::try_from(i)
+                                .ok()
+                                .and_then(|i| items.get(i).cloned())
+                                .ok_or(EvaluationError::IndexOutOfRange(i))
+                        })
+                        .collect::<Result<_, _>>()
+                        .map(Value::List)
+                }
+                _ => Err(invalid()),
+            },
+            // Control flow
+            F::Award => match &args[..] {
+                [a, Value::Boolean(b)] => Ok(if *b { a.clone() } else { Value::Integer(0) }),
+                _ => Err(invalid()),
+            },
+            F::Type => match &args[..] {
+                [v] => Ok(Value::String(v.type_name().to_owned())),
+                _ => Err(invalid()),
+            },
+            _ => Err(EvaluationError::UnsupportedFunction(name.to_owned())),
+        }
+    }
+}
+
+/// Bind the given value to the name(s) given by the expression.
+/// A list of names destructures a list value.
+fn bind_names(names: &Expr, value: Value, scope: &mut Scope) -> Result<(), EvaluationError> {
+    match names {
+        Expr::Ident(ident) => {
+            scope.set_variable(ident.name(), value);
+            Ok(())
+        }
+        Expr::List(names) => {
+            let values = value
+                .as_list()
+                .ok_or_else(|| invalid_arguments("destructuring", &[value]))?;
+            for (name, value) in names.iter().zip(values) {
+                bind_names(name, value, scope)?;
+            }
+            Ok(())
+        }
+        Expr::Str(name) => {
+            scope.set_variable(&name[..], value);
+            Ok(())
+        }
+        other => Err(EvaluationError::InvalidName(format!("{:?}", other))),
+    }
+}
+
+fn invalid_arguments(name: &str, args: &[Value]) -> EvaluationError {
+    EvaluationError::InvalidArguments(
+        name.to_owned(),
+        args.iter().map(|a| a.type_name()).collect(),
+    )
+}
+
+fn logical_operator_name(op: LogicalOperator) -> &'static str {
+    match op {
+        LogicalOperator::And => "and",
+        LogicalOperator::Or => "or",
+        LogicalOperator::Xor => "xor",
+        LogicalOperator::Implies => "implies",
+    }
+}
+
+fn arithmetic_operator_name(op: ArithmeticOperator) -> &'static str {
+    match op {
+        ArithmeticOperator::Add => "+",
+        ArithmeticOperator::Subtract => "-",
+        ArithmeticOperator::Multiply => "*",
+        ArithmeticOperator::Divide => "/",
+        ArithmeticOperator::Power => "^",
+        ArithmeticOperator::Except => "except",
+    }
+}
+
+fn constant(ident: &Ident) -> EvaluationResult {
+    match ident.name() {
+        "pi" | "π" => Ok(Value::Number(std::f64::consts::PI)),
+        "e" => Ok(Value::Number(std::f64::consts::E)),
+        "i" => Ok(Value::Complex(0.0, 1.0)),
+        "infinity" | "infty" | "∞" => Ok(Value::Number(f64::INFINITY)),
+        "nan" => Ok(Value::Number(f64::NAN)),
+        other => Err(EvaluationError::UnknownVariable(other.to_owned())),
+    }
+}
+
+/// The position of a numeric value in the numeric tower
+fn rank(v: &Value) -> Option<u8> {
+    match v {
+        Value::Integer(_) => Some(0),
+        Value::Rational(_, _) => Some(1),
+        Value::Number(_) => Some(2),
+        Value::Complex(_, _) => Some(3),
+        _ => None,
+    }
+}
+
+/// The greatest common divisor, `None` if it is too large (e.g. `gcd(isize::MIN, 0)`)
+fn gcd(a: isize, b: isize) -> Option<isize> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    isize::try_from(a).ok()
+}
+
+/// The reduced fraction with a positive denominator, `None` if it can't be represented
+fn rational(n: isize, d: isize) -> Option<Value> {
+    let g = gcd(n, d)?.max(1);
+    let sign = if d < 0 { -1 } else { 1 };
+    Some(Value::Rational(
+        n.checked_mul(sign)? / g,
+        d.checked_mul(sign)? / g,
+    ))
+}
+
+fn as_rational(v: &Value) -> Option<(isize, isize)> {
+    match v {
+        Value::Integer(i) => Some((*i, 1)),
+        Value::Rational(n, d) => Some((*n, *d)),
+        _ => None,
+    }
+}
+
+fn as_complex(v: &Value) -> Option<(f64, f64)> {
+    match v {
+        Value::Complex(re, im) => Some((*re, *im)),
+        v => v.as_f64().map(|f| (f, 0.0)),
+    }
+}
+
+fn as_vector(v: &Value) -> Option<Vec<f64>> {
+    match v {
+        Value::Vector(v) => Some(v.clone()),
+        Value::Matrix(m) if m.iter().all(|r| r.len() == 1) => {
+            Some(m.iter().map(|r| r[0]).collect())
+        }
+        Value::Matrix(m) if m.len() == 1 => Some(m[0].clone()),
+        Value::List(_) => v.as_list()?.iter().map(|v| v.as_f64()).collect(),
+        _ => None,
+    }
+}
+
+fn complex_value(re: f64, im: f64) -> Value {
+    if im == 0.0 {
+        Value::Number(re)
+    } else {
+        Value::Complex(re, im)
+    }
+}
+
+/// Convert a whole number to an integer value
+fn whole_number(v: Value) -> Value {
+    match v {
+        Value::Number(f) if f.is_finite() && f.abs() < isize::MAX as f64 && f.fract() == 0.0 => {
+            Value::Integer(f as isize)
+        }
+        v => v,
+    }
+}
+
+fn real_function(args: &[Value], f: impl Fn(f64) -> f64) -> Option<Value> {
+    match args {
+        [v] => map_reals(v, f),
+        _ => None,
+    }
+}
+
+/// Apply a real function to a number, or to each element of a vector or matrix
+fn map_reals(v: &Value, f: impl Fn(f64) -> f64) -> Option<Value> {
+    match v {
+        Value::Vector(v) => Some(Value::Vector(v.iter().map(|x| f(*x)).collect())),
+        Value::Matrix(m) => Some(Value::Matrix(
+            m.iter()
+                .map(|r| r.iter().map(|x| f(*x)).collect())
+                .collect(),
+        )),
+        v => v.as_f64().map(|x| Value::Number(f(x))),
+    }
+}
+
+/// Apply a function that is real for positive real numbers and complex otherwise
+fn complex_function(
+    v: &Value,
+    real: impl Fn(f64) -> f64,
+    complex: impl Fn(f64, f64) -> (f64, f64),
+) -> Option<Value> {
+    match v {
+        Value::Complex(re, im) => {
+            let (re, im) = complex(*re, *im);
+            Some(complex_value(re, im))
+        }
+        v => {
+            let x = v.as_f64()?;
+            let result = real(x);
+            if result.is_nan() && !x.is_nan() {
+                let (re, im) = complex(x, 0.0);
+                Some(complex_value(re, im))
+            } else {
+                Some(Value::Number(result))
+            }
+        }
+    }
+}
+
+fn complex_power(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    if a == (0.0, 0.0) {
+        return if b == (0.0, 0.0) {
+            (1.0, 0.0)
+        } else {
+            (0.0, 0.0)
+        };
+    }
+    let ln_r = a.0.hypot(a.1).ln();
+    let theta = a.1.atan2(a.0);
+    let re = b.0 * ln_r - b.1 * theta;
+    let im = b.1 * ln_r + b.0 * theta;
+    let r = re.exp();
+    (r * im.cos(), r * im.sin())
+}
+
+fn numeric_operation(op: ArithmeticOperator, a: &Value, b: &Value) -> Option<Value> {
+    use ArithmeticOperator::*;
+    let rank = rank(a)?.max(rank(b)?);
+    if rank == 0 {
+        let (x, y) = (a.as_integer()?, b.as_integer()?);
+        let fallback = || numeric_operation(op, &Value::Number(x as f64), &Value::Number(y as f64));
+        return match op {
+            Add => x.checked_add(y).map(Value::Integer).or_else(fallback),
+            Subtract => x.checked_sub(y).map(Value::Integer).or_else(fallback),
+            Multiply => x.checked_mul(y).map(Value::Integer).or_else(fallback),
+            Divide if y != 0 => rational(x, y).or_else(fallback),
+            Power if y >= 0 => u32::try_from(y)
+                .ok()
+                .and_then(|e| x.checked_pow(e))
+                .map(Value::Integer)
+                .or_else(fallback),
+            _ => fallback(),
+        };
+    }
+    if rank == 1 {
+        let ((n1, d1), (n2, d2)) = (as_rational(a)?, as_rational(b)?);
+        let fallback =
+            || numeric_operation(op, &Value::Number(a.as_f64()?), &Value::Number(b.as_f64()?));
+        let result = match op {
+            Add => n1
+                .checked_mul(d2)
+                .zip(n2.checked_mul(d1))
+                .and_then(|(x, y)| x.checked_add(y))
+                .zip(d1.checked_mul(d2)),
+            Subtract => n1
+                .checked_mul(d2)
+                .zip(n2.checked_mul(d1))
+                .and_then(|(x, y)| x.checked_sub(y))
+                .zip(d1.checked_mul(d2)),
+            Multiply => n1.checked_mul(n2).zip(d1.checked_mul(d2)),
+            Divide if n2 != 0 => n1.checked_mul(d2).zip(d1.checked_mul(n2)),
+            Power if d2 == 1 => {
+                let e = u32::try_from(n2.unsigned_abs()).ok()?;
+                let (n, d) = (n1.checked_pow(e), d1.checked_pow(e));
+                if n2 >= 0 {
+                    n.zip(d)
+                } else if n1 != 0 {
+                    d.zip(n)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        return result.and_then(|(n, d)| rational(n, d)).or_else(fallback);
+    }
+    if rank == 2 {
+        let (x, y) = (a.as_f64()?, b.as_f64()?);
+        return Some(match op {
+            Add => Value::Number(x + y),
+            Subtract => Value::Number(x - y),
+            Multiply => Value::Number(x * y),
+            Divide => Value::Number(x / y),
+            Power if x < 0.0 && y.fract() != 0.0 => {
+                let (re, im) = complex_power((x, 0.0), (y, 0.0));
+                complex_value(re, im)
+            }
+            Power => Value::Number(x.powf(y)),
+            Except => return None,
+        });
+    }
+    let ((a, b), (c, d)) = (as_complex(a)?, as_complex(b)?);
+    let (re, im) = match op {
+        Add => (a + c, b + d),
+        Subtract => (a - c, b - d),
+        Multiply => (a * c - b * d, a * d + b * c),
+        Divide => {
+            let denominator = c * c + d * d;
+            ((a * c + b * d) / denominator, (b * c - a * d) / denominator)
+        }
+        Power => complex_power((a, b), (c, d)),
+        Except => return None,
+    };
+    Some(complex_value(re, im))
+}
+
+fn matrix_product(m1: &[Vec<f64>], m2: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let columns = m2.first().map(|r| r.len()).unwrap_or(0);
+    if m1.iter().any(|r| r.len() != m2.len()) {
+        return None;
+    }
+    Some(
+        m1.iter()
+            .map(|r| {
+                (0..columns)
+                    .map(|j| r.iter().zip(m2.iter()).map(|(a, row)| a * row[j]).sum())
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+fn elementwise(v: &[f64], w: &[f64], f: impl Fn(f64, f64) -> f64) -> Option<Vec<f64>> {
+    if v.len() == w.len() {
+        Some(v.iter().zip(w.iter()).map(|(a, b)| f(*a, *b)).collect())
+    } else {
+        None
+    }
+}
+
+fn arithmetic(op: ArithmeticOperator, a: Value, b: Value) -> EvaluationResult {
+    use ArithmeticOperator::*;
+    if let Some(v) = numeric_operation(op, &a, &b) {
+        return Ok(v);
+    }
+    let result = match (op, &a, &b) {
+        (Add, Value::String(s), v) | (Add, v @ Value::Number(_), Value::String(s))
+            if matches!(v, Value::String(_)) || matches!(a, Value::String(_)) =>
+        {
+            Some(Value::String(if matches!(a, Value::String(_)) {
+                format!("{}{}", s, v.to_plain_string())
+            } else {
+                format!("{}{}", v.to_plain_string(), s)
+            }))
+        }
+        (Add, v, Value::String(s)) => Some(Value::String(format!("{}{}", v.to_plain_string(), s))),
+        (Add, Value::List(l1), Value::List(l2)) => {
+            Some(Value::List(l1.iter().chain(l2.iter()).cloned().collect()))
+        }
+        (Add, Value::List(l), v) => {
+            let mut l = l.clone();
+            l.push(v.clone());
+            Some(Value::List(l))
+        }
+        (Add, Value::Dictionary(d1), Value::Dictionary(d2)) => {
+            let mut d = d1.clone();
+            d.extend(d2.clone());
+            Some(Value::Dictionary(d))
+        }
+        (Except, l, Value::List(excluded)) => l.as_list().map(|l| {
+            Value::List(
+                l.into_iter()
+                    .filter(|v| !excluded.iter().any(|e| values_equal(v, e)))
+                    .collect(),
+            )
+        }),
+        (Except, l, e) => l
+            .as_list()
+            .map(|l| Value::List(l.into_iter().filter(|v| !values_equal(v, e)).collect())),
+        (Add, Value::Vector(v), Value::Vector(w)) => {
+            elementwise(v, w, |a, b| a + b).map(Value::Vector)
+        }
+        (Subtract, Value::Vector(v), Value::Vector(w)) => {
+            elementwise(v, w, |a, b| a - b).map(Value::Vector)
+        }
+        (Multiply, Value::Matrix(m), Value::Vector(v)) => {
+            let column: Vec<Vec<f64>> = v.iter().map(|x| vec![*x]).collect();
+            matrix_product(m, &column).map(|r| Value::Vector(r.into_iter().map(|r| r[0]).collect()))
+        }
+        (Multiply, s, Value::Vector(v)) | (Multiply, Value::Vector(v), s) => s
+            .as_f64()
+            .map(|s| Value::Vector(v.iter().map(|x| s * x).collect())),
+        (Divide, Value::Vector(v), s) => s
+            .as_f64()
+            .map(|s| Value::Vector(v.iter().map(|x| x / s).collect())),
+        (Add, Value::Matrix(m1), Value::Matrix(m2))
+        | (Subtract, Value::Matrix(m1), Value::Matrix(m2)) => {
+            if m1.len() == m2.len() {
+                m1.iter()
+                    .zip(m2.iter())
+                    .map(|(r1, r2)| {
+                        elementwise(r1, r2, |a, b| if op == Add { a + b } else { a - b })
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(Value::Matrix)
+            } else {
+                None
+            }
+        }
+        (Multiply, Value::Matrix(m1), Value::Matrix(m2)) => {
+            matrix_product(m1, m2).map(Value::Matrix)
+        }
+        (Multiply, s, Value::Matrix(m)) | (Multiply, Value::Matrix(m), s) => s.as_f64().map(|s| {
+            Value::Matrix(
+                m.iter()
+                    .map(|r| r.iter().map(|x| s * x).collect())
+                    .collect(),
+            )
+        }),
+        (Divide, Value::Matrix(m), s) => s.as_f64().map(|s| {
+            Value::Matrix(
+                m.iter()
+                    .map(|r| r.iter().map(|x| x / s).collect())
+                    .collect(),
+            )
+        }),
+        _ => None,
+    };
+    result.ok_or_else(|| invalid_arguments(arithmetic_operator_name(op), &[a, b]))
+}
+
+fn negate(v: Value) -> EvaluationResult {
+    match v {
+        Value::Integer(i) => Ok(i
+            .checked_neg()
+            .map(Value::Integer)
+            .unwrap_or(Value::Number(-(i as f64)))),
+        Value::Rational(n, d) => Ok(n
+            .checked_neg()
+            .map(|n| Value::Rational(n, d))
+            .unwrap_or(Value::Number(-(n as f64) / d as f64))),
+        Value::Number(f) => Ok(Value::Number(-f)),
+        Value::Complex(re, im) => Ok(Value::Complex(-re, -im)),
+        Value::Vector(v) => Ok(Value::Vector(v.into_iter().map(|x| -x).collect())),
+        Value::Matrix(m) => Ok(Value::Matrix(
+            m.into_iter()
+                .map(|r| r.into_iter().map(|x| -x).collect())
+                .collect(),
+        )),
+        v => Err(invalid_arguments("-", &[v])),
+    }
+}
+
+fn factorial(v: Value) -> EvaluationResult {
+    match v.as_integer() {
+        Some(n) if n >= 0 => {
+            let mut result: isize = 1;
+            for i in 2..=n {
+                match result.checked_mul(i) {
+                    Some(r) => result = r,
+                    None => return Ok(Value::Number(gamma(n as f64 + 1.0))),
+                }
+            }
+            Ok(Value::Integer(result))
+        }
+        _ => match v.as_f64() {
+            Some(f) => Ok(Value::Number(gamma(f + 1.0))),
+            None => Err(invalid_arguments("!", &[v])),
+        },
+    }
+}
+
+/// The gamma function, computed with the Lanczos approximation
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma(1.0 - x))
+    } else {
+        let x = x - 1.0;
+        let t = x + G + 0.5;
+        let sum = COEFFICIENTS[1..]
+            .iter()
+            .enumerate()
+            .fold(COEFFICIENTS[0], |acc, (i, c)| {
+                acc + c / (x + i as f64 + 1.0)
+            });
+        (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum
+    }
+}
+
+/// The exponents of the prime factorisation of n
+fn factorise(mut n: isize) -> Vec<isize> {
+    let mut exponents = Vec::new();
+    let mut p = 2;
+    while n > 1 {
+        let is_prime = (2..p).take_while(|d| d * d <= p).all(|d| p % d != 0);
+        if is_prime {
+            let mut exponent = 0;
+            while n % p == 0 {
+                n /= p;
+                exponent += 1;
+            }
+            exponents.push(exponent);
+        }
+        p += 1;
+    }
+    exponents
+}
+
+pub(crate) fn precround(x: f64, d: isize) -> f64 {
+    let factor = 10f64.powi(d as i32);
+    let rounded = (x * factor).round() / factor;
+    // Remove floating point noise like 1.2000000000000002
+    format!("{:.*}", d.max(0) as usize, rounded)
+        .parse()
+        .unwrap_or(rounded)
+}
+
+pub(crate) fn siground(x: f64, s: isize) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        return x;
+    }
+    let magnitude = x.abs().log10().floor() as isize;
+    precround(x, s - 1 - magnitude)
+}
+
+fn count_dp(s: &str) -> isize {
+    let s = s.trim();
+    if s.parse::<f64>().is_err() {
+        return 0;
+    }
+    s.split_once('.')
+        .map(|(_, decimals)| decimals.chars().take_while(|c| c.is_ascii_digit()).count() as isize)
+        .unwrap_or(0)
+}
+
+fn count_sig_figs(s: &str) -> isize {
+    let s = s.trim().trim_start_matches('-');
+    if s.parse::<f64>().is_err() {
+        return 0;
+    }
+    let digits: String = s.chars().filter(|c| c.is_ascii_digit()).collect();
+    let significant = digits.trim_start_matches('0');
+    if s.contains('.') {
+        significant.len() as isize
+    } else {
+        significant.trim_end_matches('0').len() as isize
+    }
+}
+
+fn to_base(n: isize, base: u32) -> String {
+    let mut digits = Vec::new();
+    let mut m = n.unsigned_abs();
+    loop {
+        digits.push(std::char::from_digit((m % base as usize) as u32, base).unwrap());
+        m /= base as usize;
+        if m == 0 {
+            break;
+        }
+    }
+    if n < 0 {
+        digits.push('-');
+    }
+    digits.into_iter().rev().collect()
+}
+
+fn is_close(a: f64, b: f64, rel: f64, abs: f64) -> bool {
+    a == b || (a - b).abs() <= (rel * a.abs().max(b.abs())).max(abs)
+}
+
+fn transpose(m: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let columns = m.first().map(|r| r.len()).unwrap_or(0);
+    (0..columns)
+        .map(|j| m.iter().map(|r| r[j]).collect())
+        .collect()
+}
+
+fn determinant(mut m: Vec<Vec<f64>>) -> f64 {
+    let n = m.len();
+    let mut det = 1.0;
+    for i in 0..n {
+        let pivot = (i..n).max_by(|a, b| {
+            m[*a][i]
+                .abs()
+                .partial_cmp(&m[*b][i].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let pivot = match pivot {
+            Some(p) if m[p][i] != 0.0 => p,
+            _ => return 0.0,
+        };
+        if pivot != i {
+            m.swap(pivot, i);
+            det = -det;
+        }
+        det *= m[i][i];
+        let pivot_row = m[i].clone();
+        for row in m.iter_mut().skip(i + 1) {
+            let factor = row[i] / pivot_row[i];
+            for (value, p) in row.iter_mut().zip(pivot_row.iter()).skip(i) {
+                *value -= factor * p;
+            }
+        }
+    }
+    det
+}
+
+fn distinct(items: Vec<Value>) -> Vec<Value> {
+    let mut result: Vec<Value> = Vec::new();
+    for item in items.into_iter() {
+        if !result.iter().any(|r| values_equal(r, &item)) {
+            result.push(item);
+        }
+    }
+    result
+}
+
+/// The values of a range with a non zero step
+fn range_values(start: f64, end: f64, step: f64) -> Option<Vec<Value>> {
+    if step == 0.0 {
+        return None;
+    }
+    let count = ((end - start) / step + 1e-10).floor() as isize + 1;
+    let integral = start.fract() == 0.0 && step.fract() == 0.0;
+    Some(
+        (0..count.max(0))
+            .map(|i| {
+                let v = start + step * i as f64;
+                if integral {
+                    Value::Integer(v as isize)
+                } else {
+                    Value::Number(v)
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Structural equality, where numbers are compared by value
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (a, b) if a.is_numeric() && b.is_numeric() => match (as_rational(a), as_rational(b)) {
+            (Some(x), Some(y)) => x == y,
+            _ => as_complex(a) == as_complex(b),
+        },
+        (Value::List(l1), Value::List(l2)) | (Value::Set(l1), Value::Set(l2)) => {
+            l1.len() == l2.len() && l1.iter().zip(l2.iter()).all(|(a, b)| values_equal(a, b))
+        }
+        (Value::Dictionary(d1), Value::Dictionary(d2)) => {
+            d1.len() == d2.len()
+                && d1
+                    .iter()
+                    .zip(d2.iter())
+                    .all(|((k1, v1), (k2, v2))| k1 == k2 && values_equal(v1, v2))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn relation(op: RelationalOperator, a: Value, b: Value) -> EvaluationResult {
+    use RelationalOperator::*;
+    let result = match (op, &a, &b) {
+        (LessThan | LessThanOrEqual | GreaterThan | GreaterThanOrEqual, _, _) => {
+            match (a.as_f64(), b.as_f64()) {
+                (Some(x), Some(y)) => Some(match op {
+                    LessThan => x < y,
+                    LessThanOrEqual => x <= y,
+                    GreaterThan => x > y,
+                    _ => x >= y,
+                }),
+                _ => None,
+            }
+        }
+        (Equals, _, _) => Some(values_equal(&a, &b)),
+        (NotEquals, _, _) => Some(!values_equal(&a, &b)),
+        (In, Value::String(s1), Value::String(s2)) => Some(s2.contains(&s1[..])),
+        (In, v, Value::Dictionary(d)) => Some(d.contains_key(&v.to_plain_string())),
+        (In, v, Value::Range(start, end, step)) => v.as_f64().map(|x| {
+            x >= *start && x <= *end && (*step == 0.0 || ((x - start) / step).fract().abs() < 1e-10)
+        }),
+        (In, v, l) => l.as_list().map(|l| l.iter().any(|x| values_equal(v, x))),
+        (IsA, v, Value::String(t)) => Some(
+            v.type_name() == t
+                || (t == "number" && v.is_numeric())
+                || (t == "rational" && matches!(v, Value::Integer(_)))
+                || (t == "complex" && matches!(v, Value::Complex(_, _))),
+        ),
+        (Divides, _, _) => match (a.as_integer(), b.as_integer()) {
+            (Some(x), Some(y)) => Some(x != 0 && y % x == 0),
+            _ => None,
+        },
+        _ => None,
+    };
+    result.map(Value::Boolean).ok_or_else(|| {
+        invalid_arguments(
+            match op {
+                LessThan => "<",
+                LessThanOrEqual => "<=",
+                GreaterThan => ">",
+                GreaterThanOrEqual => ">=",
+                Equals => "=",
+                NotEquals => "<>",
+                In => "in",
+                IsA => "isa",
+                Divides => "|",
+            },
+            &[a.clone(), b.clone()],
+        )
+    })
+}
+
+fn indexation(v: Value, index: Value) -> EvaluationResult {
+    match (&v, &index) {
+        (Value::Dictionary(d), key) => {
+            let key = key.to_plain_string();
+            d.get(&key).cloned().ok_or(EvaluationError::UnknownKey(key))
+        }
+        (Value::String(s), Value::Range(start, end, step)) => {
+            let chars: Vec<char> = s.chars().collect();
+            Ok(Value::String(
+                slice_indices(chars.len(), *start, *end, *step)
+                    .into_iter()
+                    .map(|i| chars[i])
+                    .collect(),
+            ))
+        }
+        (Value::Matrix(m), Value::Range(start, end, step)) => Ok(Value::Matrix(
+            slice_indices(m.len(), *start, *end, *step)
+                .into_iter()
+                .map(|i| m[i].clone())
+                .collect(),
+        )),
+        (_, Value::Range(start, end, step)) => match v.as_list() {
+            Some(items) => Ok(Value::List(
+                slice_indices(items.len(), *start, *end, *step)
+                    .into_iter()
+                    .map(|i| items[i].clone())
+                    .collect(),
+            )),
+            None => Err(invalid_arguments("[]", &[v.clone(), index.clone()])),
+        },
+        (Value::String(s), i) => {
+            let i = i
+                .as_integer()
+                .ok_or_else(|| invalid_arguments("[]", &[v.clone(), index.clone()]))?;
+            let chars: Vec<char> = s.chars().collect();
+            list_index(chars.len(), i).map(|i| Value::String(chars[i].to_string()))
+        }
+        (Value::Matrix(m), i) => {
+            let i = i
+                .as_integer()
+                .ok_or_else(|| invalid_arguments("[]", &[v.clone(), index.clone()]))?;
+            list_index(m.len(), i).map(|i| Value::Vector(m[i].clone()))
+        }
+        (_, i) => match (v.as_list(), i.as_integer()) {
+            (Some(items), Some(i)) => list_index(items.len(), i).map(|i| items[i].clone()),
+            _ => Err(invalid_arguments("[]", &[v.clone(), index.clone()])),
+        },
+    }
+}
+
+/// The indices in the slice `start..end#step` (end exclusive), negative bounds count from the end
+fn slice_indices(length: usize, start: f64, end: f64, step: f64) -> Vec<usize> {
+    let bound = |b: f64| {
+        let b = b as isize;
+        let b = if b < 0 { length as isize + b } else { b };
+        b.clamp(0, length as isize) as usize
+    };
+    let step = if step >= 1.0 { step as usize } else { 1 };
+    (bound(start)..bound(end)).step_by(step).collect()
+}
+
+/// Numbas allows negative indices, counting from the end of the list
+fn list_index(length: usize, i: isize) -> Result<usize, EvaluationError> {
+    let index = if i < 0 { length as isize + i } else { i };
+    if index >= 0 && (index as usize) < length {
+        Ok(index as usize)
+    } else {
+        Err(EvaluationError::IndexOutOfRange(i))
+    }
+}
+
+fn cast(v: Value, target: &str) -> EvaluationResult {
+    let invalid = || EvaluationError::InvalidCast(v.type_name(), target.to_owned());
+    match target {
+        "number" | "decimal" => match &v {
+            Value::Complex(_, _) => Ok(v.clone()),
+            Value::String(s) => s.trim().parse().map(Value::Number).map_err(|_| invalid()),
+            _ => v.as_f64().map(Value::Number).ok_or_else(invalid),
+        },
+        "integer" => match &v {
+            Value::Integer(_) => Ok(v.clone()),
+            _ => v
+                .as_f64()
+                .filter(|f| f.is_finite())
+                .map(|f| Value::Integer((f + 0.5).floor() as isize))
+                .ok_or_else(invalid),
+        },
+        "rational" => match &v {
+            Value::Integer(i) => Ok(Value::Rational(*i, 1)),
+            Value::Rational(_, _) => Ok(v.clone()),
+            _ => v
+                .as_f64()
+                .filter(|f| f.is_finite())
+                .and_then(|f| {
+                    let (n, d) = rational_approximation(f);
+                    rational(n, d)
+                })
+                .ok_or_else(invalid),
+        },
+        "string" => Ok(Value::String(v.to_plain_string())),
+        "list" => match &v {
+            Value::Dictionary(_) | Value::Matrix(_) => {
+                Evaluator::new(0).apply_builtin(BuiltinFunctions::List, "list", vec![v.clone()])
+            }
+            _ => v.as_list().map(Value::List).ok_or_else(invalid),
+        },
+        "set" => v
+            .as_list()
+            .map(|l| Value::Set(distinct(l)))
+            .ok_or_else(invalid),
+        "vector" => as_vector(&v).map(Value::Vector).ok_or_else(invalid),
+        "boolean" => match &v {
+            Value::Boolean(_) => Ok(v.clone()),
+            _ => Err(invalid()),
+        },
+        t if t == v.type_name() => Ok(v.clone()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Approximate a number by a fraction, using continued fractions.
+/// Uses the same accuracy as Numbas.
+fn rational_approximation(f: f64) -> (isize, isize) {
+    let accuracy = (-15f64).exp();
+    let (mut h1, mut h2, mut k1, mut k2) = (1isize, 0isize, 0isize, 1isize);
+    let mut x = f;
+    for _ in 0..64 {
+        let a = x.floor();
+        if a.abs() > isize::MAX as f64 {
+            break;
+        }
+        let a = a as isize;
+        let (h, k) = match (
+            a.checked_mul(h1).and_then(|v| v.checked_add(h2)),
+            a.checked_mul(k1).and_then(|v| v.checked_add(k2)),
+        ) {
+            (Some(h), Some(k)) => (h, k),
+            _ => break,
+        };
+        h2 = h1;
+        h1 = h;
+        k2 = k1;
+        k1 = k;
+        if ((h1 as f64 / k1 as f64) - f).abs() < accuracy || x == a as f64 {
+            break;
+        }
+        x = 1.0 / (x - a as f64);
+    }
+    (h1, k1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jme::parser::consume_one_expression;
+    use crate::jme::parser::parse_as_jme;
+    use serde::Deserialize;
+
+    fn parse(s: &str) -> Expr {
+        try_parse(s).unwrap()
+    }
+
+    fn try_parse(s: &str) -> Option<Expr> {
+        parse_as_jme(s)
+            .ok()
+            .and_then(|pairs| consume_one_expression(pairs).ok())
+    }
+
+    fn evaluate(s: &str) -> EvaluationResult {
+        Evaluator::new(0).evaluate(&parse(s), &Scope::new())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(evaluate("1+2*3"), Ok(Value::Integer(7)));
+        assert_eq!(evaluate("2^10"), Ok(Value::Integer(1024)));
+        assert_eq!(evaluate("1/2+1/3"), Ok(Value::Rational(5, 6)));
+        assert_eq!(evaluate("0.5*3"), Ok(Value::Number(1.5)));
+        assert_eq!(evaluate("(1+2i)*(1-2i)"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate("sqrt(-4)"), Ok(Value::Complex(0.0, 2.0)));
+        assert_eq!(evaluate("5!"), Ok(Value::Integer(120)));
+        assert_eq!(evaluate("-3+1"), Ok(Value::Integer(-2)));
+        assert_eq!(evaluate("-2^2"), Ok(Value::Integer(-4)));
+        assert_eq!(evaluate("(-2)^2"), Ok(Value::Integer(4)));
+        assert_eq!(evaluate("2*-3^2"), Ok(Value::Integer(-18)));
+        let mut scope = Scope::new();
+        scope.set_variable("x", Value::Integer(3));
+        assert_eq!(
+            Evaluator::new(0).evaluate(&parse("-x^2"), &scope),
+            Ok(Value::Integer(-9))
+        );
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(
+            evaluate("[1,2]+[3]"),
+            Ok(Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3)
+            ]))
+        );
+        assert_eq!(evaluate("[1,2,3][1]"), Ok(Value::Integer(2)));
+        assert_eq!(
+            evaluate("[\"a\": 1, \"b\": 2][\"b\"]"),
+            Ok(Value::Integer(2))
+        );
+        assert_eq!(evaluate("len(1..10#2)"), Ok(Value::Integer(5)));
+        assert_eq!(evaluate("3 in 1..5"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("sum(map(x^2,x,1..3))"), Ok(Value::Integer(14)));
+        assert_eq!(evaluate("1..4 as \"list\""), evaluate("[1,2,3,4]"));
+        assert_eq!(evaluate("[1,2,3][1..2]"), evaluate("[2]"));
+        assert_eq!(evaluate("[1,2,3,4][1..-1]"), evaluate("[2,3]"));
+        assert_eq!(
+            evaluate("\"hello\"[1..3]"),
+            Ok(Value::String("el".to_owned()))
+        );
+    }
+
+    #[test]
+    fn logic_and_control_flow() {
+        assert_eq!(evaluate("1<2 and not false"), Ok(Value::Boolean(true)));
+        assert_eq!(
+            evaluate("if(2|4, \"yes\", \"no\")"),
+            Ok(Value::String("yes".to_owned()))
+        );
+        assert_eq!(evaluate("let(a, 3, b, a+1, a*b)"), Ok(Value::Integer(12)));
+        assert_eq!(evaluate("1 isa \"number\""), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            evaluate("x+1"),
+            Err(EvaluationError::UnknownVariable("x".to_owned()))
+        );
+        assert_eq!(
+            evaluate("foo(1)"),
+            Err(EvaluationError::UnknownFunction("foo".to_owned()))
+        );
+        assert_eq!(
+            evaluate("len(true)"),
+            Err(EvaluationError::InvalidArguments(
+                "len".to_owned(),
+                vec!["boolean"]
+            ))
+        );
+        assert_eq!(
+            evaluate("[1,2][5]"),
+            Err(EvaluationError::IndexOutOfRange(5))
+        );
+        assert_eq!(
+            evaluate("transpose(matrix([1,2],[3]))"),
+            Err(EvaluationError::InvalidArguments(
+                "matrix".to_owned(),
+                vec!["list", "list"]
+            ))
+        );
+        assert_eq!(
+            evaluate("true as \"number\""),
+            Err(EvaluationError::InvalidCast("boolean", "number".to_owned()))
+        );
+    }
+
+    #[test]
+    fn scope() {
+        let mut scope = Scope::new();
+        scope.set_variable("a", Value::Integer(2));
+        scope.add_function(
+            "double",
+            Function {
+                parameters: vec!["x".to_owned()],
+                body: parse("2x"),
+            },
+        );
+        assert_eq!(
+            Evaluator::new(0).evaluate(&parse("double(A)+a"), &scope),
+            Ok(Value::Integer(6))
+        );
+    }
+
+    #[test]
+    fn seeded_randomness() {
+        let expr = parse("repeat(random(1..100), 10)");
+        let scope = Scope::new();
+        let first = Evaluator::new(42).evaluate(&expr, &scope);
+        let second = Evaluator::new(42).evaluate(&expr, &scope);
+        assert!(first.is_ok());
+        assert_eq!(first, second);
+        assert_ne!(first, Evaluator::new(43).evaluate(&expr, &scope));
+        let shuffled = Evaluator::new(1).evaluate(&parse("sort(shuffle(1..5))"), &scope);
+        assert_eq!(shuffled, evaluate("[1,2,3,4,5]"));
+    }
+
+    #[test]
+    fn random_from_continuous_range() {
+        let value = Evaluator::new(0).evaluate(&parse("random(1..5#0)"), &Scope::new());
+        assert!(matches!(value, Ok(Value::Number(x)) if (1.0..=5.0).contains(&x)));
+        assert_eq!(
+            evaluate("random(5..1#0)"),
+            Err(EvaluationError::InvalidRandomRange(5.0, 1.0))
+        );
+        for (start, end) in [(f64::NAN, 1.0), (0.0, f64::INFINITY)] {
+            let mut scope = Scope::new();
+            scope.set_variable("r", Value::Range(start, end, 0.0));
+            assert!(matches!(
+                Evaluator::new(0).evaluate(&parse("random(r)"), &scope),
+                Err(EvaluationError::InvalidRandomRange(_, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let overflow = |name: &str| Err(EvaluationError::Overflow(name.to_string()));
+        assert_eq!(evaluate("abs(-9223372036854775807-1)"), overflow("abs"));
+        assert_eq!(evaluate("gcd(-9223372036854775807-1, 0)"), overflow("gcd"));
+        assert_eq!(evaluate("lcm(9223372036854775807, 2)"), overflow("lcm"));
+        assert_eq!(evaluate("lcm(4, 6)"), Ok(Value::Integer(12)));
+        assert_eq!(evaluate("gcd(-12, 18)"), Ok(Value::Integer(6)));
+        assert_eq!(
+            evaluate("mod(-9223372036854775807-1, -1)"),
+            Ok(Value::Integer(0))
+        );
+    }
+
+    #[test]
+    fn modulo_has_the_sign_of_the_divisor() {
+        assert_eq!(evaluate("mod(5, 3)"), Ok(Value::Integer(2)));
+        assert_eq!(evaluate("mod(-5, 3)"), Ok(Value::Integer(1)));
+        assert_eq!(evaluate("mod(5, -3)"), Ok(Value::Integer(-1)));
+        assert_eq!(evaluate("mod(-5, -3)"), Ok(Value::Integer(-2)));
+        assert_eq!(evaluate("mod(5.5, -3)"), Ok(Value::Number(-0.5)));
+    }
+
+    #[derive(Deserialize)]
+    struct DocTest {
+        fns: Vec<DocTestFn>,
+    }
+
+    #[derive(Deserialize)]
+    struct DocTestFn {
+        examples: Vec<DocTestFnExample>,
+    }
+
+    #[derive(Deserialize)]
+    struct DocTestFnExample {
+        r#in: String,
+        out: String,
+    }
+
+    fn approximately_equal(a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (a, b) if a.is_numeric() && b.is_numeric() => {
+                let ((a1, a2), (b1, b2)) = (as_complex(a).unwrap(), as_complex(b).unwrap());
+                is_close(a1, b1, 1e-9, 1e-9) && is_close(a2, b2, 1e-9, 1e-9)
+            }
+            (Value::List(l1), Value::List(l2)) | (Value::Set(l1), Value::Set(l2)) => {
+                l1.len() == l2.len()
+                    && l1
+                        .iter()
+                        .zip(l2.iter())
+                        .all(|(a, b)| approximately_equal(a, b))
+            }
+            (a, b) => values_equal(a, b),
+        }
+    }
+
+    #[test]
+    fn numbas_doc_tests() {
+        let doc_tests: Vec<DocTest> =
+            serde_json::from_str(include_str!("numbas-jme-doc-tests.json")).unwrap();
+        let mut failures = Vec::new();
+        let mut evaluated = 0;
+        for example in doc_tests
+            .into_iter()
+            .flat_map(|t| t.fns.into_iter())
+            .flat_map(|f| f.examples.into_iter())
+        {
+            // Only compare the examples that can be evaluated without free variables
+            let (input, output) = match (try_parse(&example.r#in), try_parse(&example.out)) {
+                (Some(i), Some(o)) => (i, o),
+                _ => continue,
+            };
+            let scope = Scope::new();
+            let (input, output) = match (
+                Evaluator::new(0).evaluate(&input, &scope),
+                Evaluator::new(0).evaluate(&output, &scope),
+            ) {
+                (Ok(i), Ok(o)) => (i, o),
+                _ => continue,
+            };
+            evaluated += 1;
+            if !approximately_equal(&input, &output) {
+                failures.push(format!(
+                    "{} gave {} instead of {}",
+                    example.r#in, input, output
+                ));
+            }
+        }
+        assert!(
+            evaluated > 100,
+            "Only {} doc tests were evaluated",
+            evaluated
+        );
+        assert_eq!(failures, Vec::<String>::new());
+    }
+}
@@ -0,0 +1,607 @@
+//! Render jme expressions and values as LaTeX, following the display rules of Numbas.
+//!
+//! Expressions are rendered like `\simplify{}` shows them: divisions become fractions, powers
+//! superscripts and well known functions get their usual notation. Values are rendered like
+//! `\var{}` shows them.
+
+use crate::jme::ast::{
+    ArithmeticOperator, Expr, Ident, LogicalOperator, PrefixOperator, RangeOperator,
+    RelationalOperator,
+};
+use crate::jme::builtin_functions::BuiltinFunctions;
+use crate::jme::eval::Value;
+use crate::jme::printer::{precedence, MINUS_PRECEDENCE, POSTFIX_PRECEDENCE, PREFIX_PRECEDENCE};
+use crate::jme::JMEString;
+
+/// The greek letters that have a LaTeX command with the same name
+const GREEK_LETTERS: [&str; 37] = [
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "iota",
+    "kappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "omicron",
+    "pi",
+    "rho",
+    "sigma",
+    "tau",
+    "upsilon",
+    "phi",
+    "chi",
+    "psi",
+    "omega",
+    "Gamma",
+    "Delta",
+    "Theta",
+    "Lambda",
+    "Xi",
+    "Pi",
+    "Sigma",
+    "Upsilon",
+    "Phi",
+    "Psi",
+    "Omega",
+    "varepsilon",
+    "varphi",
+];
+
+/// The functions that have a LaTeX command with the same name
+const LATEX_FUNCTIONS: [&str; 18] = [
+    "sin", "cos", "tan", "sec", "csc", "cot", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "ln", "exp", "max", "min", "det",
+];
+
+fn parenthesize(s: &str) -> String {
+    format!("\\left( {} \\right)", s)
+}
+
+fn join(items: &[Expr]) -> String {
+    items
+        .iter()
+        .map(|i| i.to_latex())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Escape the characters that have a special meaning in LaTeX
+fn escape_text(s: &str) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '%' | '&' | '#' | '_' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '^' => result.push_str("\\^{}"),
+            '~' => result.push_str("\\~{}"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn text(s: &str) -> String {
+    format!("\\textrm{{{}}}", escape_text(s))
+}
+
+/// Render a name (without annotations), e.g. `x1` becomes `x_{1}` and `alpha` becomes `\alpha`
+fn latex_name(name: &str) -> String {
+    let primes = name.len() - name.trim_end_matches('\'').len();
+    let name = name.trim_end_matches('\'');
+    let (base, subscript) = if let Some((base, subscript)) = name.split_once('_') {
+        (base, Some(subscript))
+    } else {
+        let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        if base.is_empty() || base.len() == name.len() {
+            (name, None)
+        } else {
+            (base, Some(&name[base.len()..]))
+        }
+    };
+    let mut result = if GREEK_LETTERS.contains(&base) {
+        format!("\\{}", base)
+    } else if base.chars().count() > 1 {
+        format!("\\mathrm{{{}}}", base)
+    } else {
+        base.to_string()
+    };
+    if let Some(subscript) = subscript.filter(|s| !s.is_empty()) {
+        result.push_str(&format!("_{{{}}}", latex_name(subscript)));
+    }
+    result.push_str(&"'".repeat(primes));
+    result
+}
+
+/// Render an identifier, the annotations are applied from the inside out
+fn latex_ident(ident: &Ident) -> String {
+    ident
+        .annotations()
+        .iter()
+        .rev()
+        .fold(
+            latex_name(ident.name()),
+            |inner, annotation| match annotation.as_str() {
+                "v" | "vector" => format!("\\boldsymbol{{{}}}", inner),
+                "unit" => format!("\\hat{{{}}}", inner),
+                "dot" => format!("\\dot{{{}}}", inner),
+                "m" | "matrix" => format!("\\mathrm{{{}}}", inner),
+                "diff" => format!("\\mathrm{{d}}{}", inner),
+                "degrees" => format!("{}^{{\\circ}}", inner),
+                "op" => format!("\\operatorname{{{}}}", ident.name()),
+                "verb" => ident.name().to_string(),
+                "bar" => format!("\\bar{{{}}}", inner),
+                "hat" => format!("\\hat{{{}}}", inner),
+                "tilde" => format!("\\tilde{{{}}}", inner),
+                _ => inner,
+            },
+        )
+}
+
+fn latex_constant(ident: &Ident) -> String {
+    match ident.name() {
+        "pi" | "π" => "\\pi".to_string(),
+        "infinity" | "infty" | "∞" => "\\infty".to_string(),
+        "nan" => "\\text{NaN}".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Whether the expression is rendered starting with a number, a multiplication sign is needed
+/// in front of it
+fn starts_with_number(expr: &Expr) -> bool {
+    match expr {
+        Expr::Int(_) | Expr::Float(_, _) | Expr::Prefix(PrefixOperator::Minus, _) => true,
+        Expr::Arithmetic(ArithmeticOperator::Divide, _, _) => false,
+        Expr::Arithmetic(_, lhs, _)
+        | Expr::Range(_, lhs, _)
+        | Expr::Relation(_, lhs, _)
+        | Expr::Logic(_, lhs, _)
+        | Expr::Cast(lhs, _)
+        | Expr::Sequence(lhs, _)
+        | Expr::Faculty(lhs)
+        | Expr::Indexation(lhs, _) => {
+            precedence(lhs) > PREFIX_PRECEDENCE && starts_with_number(lhs)
+        }
+        _ => false,
+    }
+}
+
+fn is_negative(expr: &Expr) -> bool {
+    match expr {
+        Expr::Int(i) | Expr::Float(i, _) => *i < 0,
+        Expr::Prefix(PrefixOperator::Minus, _) => true,
+        _ => false,
+    }
+}
+
+/// Render the operand, between parentheses if it binds less tight than `minimum`
+fn latex_operand(expr: &Expr, minimum: u8) -> String {
+    // Fractions are grouped by themselves
+    let own = match expr {
+        Expr::Arithmetic(ArithmeticOperator::Divide, _, _) => POSTFIX_PRECEDENCE,
+        _ => precedence(expr),
+    };
+    if own < minimum {
+        parenthesize(&expr.to_latex())
+    } else {
+        expr.to_latex()
+    }
+}
+
+fn latex_binary(operator: &str, parent: &Expr, lhs: &Expr, rhs: &Expr) -> String {
+    let own = precedence(parent);
+    let rhs = if is_negative(rhs) {
+        parenthesize(&rhs.to_latex())
+    } else {
+        latex_operand(rhs, own + 1)
+    };
+    format!("{} {} {}", latex_operand(lhs, own), operator, rhs)
+}
+
+/// A column vector or matrix, given by its rows
+fn pmatrix(rows: &[Vec<String>]) -> String {
+    format!(
+        "\\begin{{pmatrix}} {} \\end{{pmatrix}}",
+        rows.iter()
+            .map(|row| row.join(" & "))
+            .collect::<Vec<_>>()
+            .join(" \\\\ ")
+    )
+}
+
+/// The items of a list literal, or the arguments themselves
+fn list_items(arguments: &[Expr]) -> &[Expr] {
+    match arguments {
+        [Expr::List(items)] => items,
+        _ => arguments,
+    }
+}
+
+/// The derivative `d^n f / d x^n`, with `d` being `\mathrm{d}` or `\partial`
+fn latex_derivative(d: &str, arguments: &[Expr]) -> Option<String> {
+    let (f, x, order) = match arguments {
+        [f, x] => (f, x, None),
+        [f, x, Expr::Int(1)] => (f, x, None),
+        [f, x, n] => (f, x, Some(n.to_latex())),
+        _ => return None,
+    };
+    let order = order.map(|n| format!("^{{{}}}", n)).unwrap_or_default();
+    Some(format!(
+        "\\frac{{{d}{order} {}}}{{{d} {}{order}}}",
+        latex_operand(f, POSTFIX_PRECEDENCE),
+        latex_operand(x, POSTFIX_PRECEDENCE + 1),
+        d = d,
+        order = order
+    ))
+}
+
+/// The functions with a special notation, None if the normal function notation should be used
+fn latex_special_function(name: &str, arguments: &[Expr]) -> Option<String> {
+    Some(match (name, arguments) {
+        ("sqrt", [x]) => format!("\\sqrt{{{}}}", x.to_latex()),
+        ("root", [x, n]) => format!("\\sqrt[{}]{{{}}}", n.to_latex(), x.to_latex()),
+        ("abs", [x]) => format!("\\left| {} \\right|", x.to_latex()),
+        ("floor", [x]) => format!("\\left\\lfloor {} \\right\\rfloor", x.to_latex()),
+        ("ceil", [x]) => format!("\\left\\lceil {} \\right\\rceil", x.to_latex()),
+        ("exp", [x]) => format!("e^{{{}}}", x.to_latex()),
+        ("log", [x]) => format!("\\log_{{10}} {}", parenthesize(&x.to_latex())),
+        ("log", [x, b]) => format!("\\log_{{{}}} {}", b.to_latex(), parenthesize(&x.to_latex())),
+        ("fact", [x]) => format!("{}!", latex_operand(x, POSTFIX_PRECEDENCE + 1)),
+        ("binomial", [n, k]) => format!("\\binom{{{}}}{{{}}}", n.to_latex(), k.to_latex()),
+        ("set", _) => format!("\\left\\{{ {} \\right\\}}", join(list_items(arguments))),
+        ("vector", _) => pmatrix(
+            &list_items(arguments)
+                .iter()
+                .map(|e| vec![e.to_latex()])
+                .collect::<Vec<_>>(),
+        ),
+        ("matrix", _) => {
+            let rows = list_items(arguments)
+                .iter()
+                .map(|row| match row {
+                    Expr::List(items) => Some(items.iter().map(|e| e.to_latex()).collect()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            pmatrix(&rows)
+        }
+        ("int", [f, x]) => format!(
+            "\\int {} \\, \\mathrm{{d}}{}",
+            latex_operand(f, 5),
+            x.to_latex()
+        ),
+        ("defint", [f, x, a, b]) => format!(
+            "\\int_{{{}}}^{{{}}} {} \\, \\mathrm{{d}}{}",
+            a.to_latex(),
+            b.to_latex(),
+            latex_operand(f, 5),
+            x.to_latex()
+        ),
+        ("diff", _) => latex_derivative("\\mathrm{d}", arguments)?,
+        ("partialdiff", _) => latex_derivative("\\partial", arguments)?,
+        _ => return None,
+    })
+}
+
+fn latex_function_application(ident: &Ident, arguments: &[Expr]) -> String {
+    if ident.annotations().is_empty() {
+        if let Some(special) = latex_special_function(ident.name(), arguments) {
+            return special;
+        }
+    }
+    let name = if !ident.annotations().is_empty() {
+        latex_ident(ident)
+    } else if LATEX_FUNCTIONS.contains(&ident.name()) {
+        format!("\\{}", ident.name())
+    } else if BuiltinFunctions::get(ident.name()).is_some() {
+        format!("\\operatorname{{{}}}", escape_text(ident.name()))
+    } else {
+        latex_name(ident.name())
+    };
+    format!("{} {}", name, parenthesize(&join(arguments)))
+}
+
+impl Expr {
+    /// Render this expression as LaTeX, as `\simplify{}` would show it (without simplification)
+    pub fn to_latex(&self) -> String {
+        match self {
+            Expr::Str(s) => text(s),
+            Expr::Int(i) => i.to_string(),
+            Expr::Float(i, broken) => format!("{}.{}", i, broken),
+            Expr::Bool(b) => text(&b.to_string()),
+            Expr::Ident(ident) => latex_ident(ident),
+            Expr::Constant(ident) => latex_constant(ident),
+            Expr::Arithmetic(ArithmeticOperator::Divide, lhs, rhs) => {
+                format!("\\frac{{{}}}{{{}}}", lhs.to_latex(), rhs.to_latex())
+            }
+            Expr::Arithmetic(ArithmeticOperator::Power, lhs, rhs) => {
+                let base = match **lhs {
+                    Expr::Ident(_) | Expr::Constant(_) | Expr::FunctionApplication(_, _) => {
+                        lhs.to_latex()
+                    }
+                    Expr::Int(i) if i >= 0 => lhs.to_latex(),
+                    _ => parenthesize(&lhs.to_latex()),
+                };
+                format!("{}^{{{}}}", base, rhs.to_latex())
+            }
+            Expr::Arithmetic(ArithmeticOperator::Multiply, lhs, rhs) => {
+                let own = precedence(self);
+                let rhs_latex = if is_negative(rhs) {
+                    parenthesize(&rhs.to_latex())
+                } else {
+                    latex_operand(rhs, own + 1)
+                };
+                let separator = if starts_with_number(rhs) && !is_negative(rhs) {
+                    " \\times "
+                } else {
+                    " "
+                };
+                format!("{}{}{}", latex_operand(lhs, own), separator, rhs_latex)
+            }
+            Expr::Arithmetic(o, lhs, rhs) => {
+                let operator = match o {
+                    ArithmeticOperator::Add => "+",
+                    ArithmeticOperator::Subtract => "-",
+                    ArithmeticOperator::Except => "\\setminus",
+                    _ => unreachable!(),
+                };
+                latex_binary(operator, self, lhs, rhs)
+            }
+            Expr::Range(o, lhs, rhs) => {
+                let operator = match o {
+                    RangeOperator::Create => "\\ldots",
+                    RangeOperator::Step => "\\#",
+                };
+                latex_binary(operator, self, lhs, rhs)
+            }
+            Expr::Relation(o, lhs, rhs) => {
+                let operator = match o {
+                    RelationalOperator::LessThan => "<",
+                    RelationalOperator::LessThanOrEqual => "\\leq",
+                    RelationalOperator::GreaterThan => ">",
+                    RelationalOperator::GreaterThanOrEqual => "\\geq",
+                    RelationalOperator::Equals => "=",
+                    RelationalOperator::NotEquals => "\\neq",
+                    RelationalOperator::In => "\\in",
+                    RelationalOperator::IsA => "\\text{ isa }",
+                    RelationalOperator::Divides => "\\mid",
+                };
+                latex_binary(operator, self, lhs, rhs)
+            }
+            Expr::Logic(o, lhs, rhs) => {
+                let operator = match o {
+                    LogicalOperator::And => "\\land",
+                    LogicalOperator::Or => "\\lor",
+                    LogicalOperator::Xor => "\\oplus",
+                    LogicalOperator::Implies => "\\implies",
+                };
+                latex_binary(operator, self, lhs, rhs)
+            }
+            Expr::Cast(lhs, rhs) => latex_binary("\\text{ as }", self, lhs, rhs),
+            Expr::Sequence(lhs, rhs) => latex_binary(";", self, lhs, rhs),
+            Expr::List(items) => format!("\\left[ {} \\right]", join(items)),
+            Expr::Dictionary(items) => format!(
+                "\\left[ {} \\right]",
+                items
+                    .iter()
+                    .map(|(key, value)| format!("{} : {}", key.to_latex(), value.to_latex()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expr::FunctionApplication(ident, arguments) => {
+                latex_function_application(ident, arguments)
+            }
+            // A superscript binds tighter than the minus sign
+            Expr::Prefix(PrefixOperator::Minus, operand) => {
+                format!("-{}", latex_operand(operand, MINUS_PRECEDENCE))
+            }
+            Expr::Prefix(PrefixOperator::Not, operand) => {
+                format!("\\neg {}", latex_operand(operand, PREFIX_PRECEDENCE))
+            }
+            Expr::Faculty(operand) => {
+                format!("{}!", latex_operand(operand, POSTFIX_PRECEDENCE + 1))
+            }
+            Expr::Indexation(operand, index) => format!(
+                "{}\\left[ {} \\right]",
+                latex_operand(operand, POSTFIX_PRECEDENCE),
+                index.to_latex()
+            ),
+        }
+    }
+}
+
+fn latex_number(f: f64) -> String {
+    if f.is_nan() {
+        "\\text{NaN}".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { "\\infty" } else { "-\\infty" }.to_string()
+    } else {
+        f.to_string()
+    }
+}
+
+fn join_values(items: &[Value]) -> String {
+    items
+        .iter()
+        .map(|i| i.to_latex())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Value {
+    /// Render this value as LaTeX, as `\var{}` would show it
+    pub fn to_latex(&self) -> String {
+        match self {
+            Value::Integer(i) => i.to_string(),
+            Value::Rational(n, 1) => n.to_string(),
+            Value::Rational(n, d) if *n < 0 => format!("-\\frac{{{}}}{{{}}}", -n, d),
+            Value::Rational(n, d) => format!("\\frac{{{}}}{{{}}}", n, d),
+            Value::Number(n) => latex_number(*n),
+            Value::Complex(re, im) => {
+                let im_part = if *im == 1.0 {
+                    "i".to_string()
+                } else if *im == -1.0 {
+                    "-i".to_string()
+                } else {
+                    format!("{}i", latex_number(*im))
+                };
+                if *re == 0.0 {
+                    im_part
+                } else if *im < 0.0 {
+                    format!("{} {}", latex_number(*re), im_part.replacen('-', "- ", 1))
+                } else {
+                    format!("{} + {}", latex_number(*re), im_part)
+                }
+            }
+            Value::Boolean(b) => text(&b.to_string()),
+            Value::String(s) => text(s),
+            Value::List(l) => format!("\\left[ {} \\right]", join_values(l)),
+            Value::Set(l) => format!("\\left\\{{ {} \\right\\}}", join_values(l)),
+            Value::Dictionary(d) => format!(
+                "\\left[ {} \\right]",
+                d.iter()
+                    .map(|(k, v)| format!("{} : {}", text(k), v.to_latex()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Range(start, end, step) if *step == 1.0 => {
+                format!(
+                    "\\left\\{{ {} \\ldots {} \\right\\}}",
+                    latex_number(*start),
+                    latex_number(*end)
+                )
+            }
+            Value::Range(start, end, step) => format!(
+                "\\left\\{{ {} \\ldots {} \\; (\\text{{step }} {}) \\right\\}}",
+                latex_number(*start),
+                latex_number(*end),
+                latex_number(*step)
+            ),
+            Value::Vector(v) => {
+                pmatrix(&v.iter().map(|n| vec![latex_number(*n)]).collect::<Vec<_>>())
+            }
+            Value::Matrix(m) => pmatrix(
+                &m.iter()
+                    .map(|row| row.iter().map(|n| latex_number(*n)).collect())
+                    .collect::<Vec<_>>(),
+            ),
+            Value::Nothing => "\\text{nothing}".to_string(),
+        }
+    }
+}
+
+impl JMEString {
+    /// The LaTeX rendering of this jme string, None if the string is empty
+    pub fn to_latex(&self) -> Option<String> {
+        self.ast().map(|ast| ast.to_latex())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn latex(s: &str) -> String {
+        JMEString::try_from(s.to_string())
+            .unwrap()
+            .to_latex()
+            .unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(latex("a+b-c"), "a + b - c");
+        assert_eq!(latex("a-(b+c)"), "a - \\left( b + c \\right)");
+        assert_eq!(latex("(a+1)/(b-2)"), "\\frac{a + 1}{b - 2}");
+        assert_eq!(latex("2*x"), "2 x");
+        assert_eq!(latex("x*2"), "x \\times 2");
+        assert_eq!(latex("2*(x+1)"), "2 \\left( x + 1 \\right)");
+        assert_eq!(latex("a*b/c"), "\\frac{a b}{c}");
+        assert_eq!(latex("x - -1"), "x - \\left( -1 \\right)");
+        assert_eq!(latex("x^2"), "x^{2}");
+        assert_eq!(latex("(x+1)^(n-1)"), "\\left( x + 1 \\right)^{n - 1}");
+        assert_eq!(latex("(-2)^x"), "\\left( -2 \\right)^{x}");
+        assert_eq!(latex("-(x^2)"), "-x^{2}");
+        assert_eq!(latex("-x^2"), "-x^{2}");
+        assert_eq!(latex("(-x)^2"), "\\left( -x \\right)^{2}");
+        assert_eq!(latex("n!"), "n!");
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(latex("x1"), "x_{1}");
+        assert_eq!(latex("x_max"), "x_{\\mathrm{max}}");
+        assert_eq!(latex("alpha + Omega"), "\\alpha + \\Omega");
+        assert_eq!(latex("speed"), "\\mathrm{speed}");
+        assert_eq!(latex("f'"), "f'");
+        assert_eq!(latex("v:x"), "\\boldsymbol{x}");
+        assert_eq!(latex("pi*r^2"), "\\pi r^{2}");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(latex("sqrt(x)"), "\\sqrt{x}");
+        assert_eq!(latex("root(x, 3)"), "\\sqrt[3]{x}");
+        assert_eq!(latex("sin(x)^2"), "\\sin \\left( x \\right)^{2}");
+        assert_eq!(latex("abs(x-1)"), "\\left| x - 1 \\right|");
+        assert_eq!(latex("exp(2x)"), "e^{2 x}");
+        assert_eq!(latex("log(x, 2)"), "\\log_{2} \\left( x \\right)");
+        assert_eq!(latex("binomial(n, k)"), "\\binom{n}{k}");
+        assert_eq!(latex("len(l)"), "\\operatorname{len} \\left( l \\right)");
+        assert_eq!(latex("f(x, y)"), "f \\left( x, y \\right)");
+        assert_eq!(
+            latex("matrix([1, 2], [3, 4])"),
+            "\\begin{pmatrix} 1 & 2 \\\\ 3 & 4 \\end{pmatrix}"
+        );
+        assert_eq!(
+            latex("vector(1, 2)"),
+            "\\begin{pmatrix} 1 \\\\ 2 \\end{pmatrix}"
+        );
+        assert_eq!(
+            latex("diff(f, x, 2)"),
+            "\\frac{\\mathrm{d}^{2} f}{\\mathrm{d} x^{2}}"
+        );
+        assert_eq!(
+            latex("defint(x^2, x, 0, 1)"),
+            "\\int_{0}^{1} x^{2} \\, \\mathrm{d}x"
+        );
+    }
+
+    #[test]
+    fn other_expressions() {
+        assert_eq!(latex("a <= b and not c"), "a \\leq b \\land \\neg c");
+        assert_eq!(latex("x <> 1"), "x \\neq 1");
+        assert_eq!(latex("1..5"), "1 \\ldots 5");
+        assert_eq!(latex("[1, x]"), "\\left[ 1, x \\right]");
+        assert_eq!(latex("'a_b'"), "\\textrm{a\\_b}");
+        assert_eq!(latex("true"), "\\textrm{true}");
+        assert_eq!(latex("l[0]"), "l\\left[ 0 \\right]");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(Value::Rational(-1, 2).to_latex(), "-\\frac{1}{2}");
+        assert_eq!(Value::Complex(1.0, -2.0).to_latex(), "1 - 2i");
+        assert_eq!(Value::Complex(0.0, 1.0).to_latex(), "i");
+        assert_eq!(Value::Number(f64::INFINITY).to_latex(), "\\infty");
+        assert_eq!(
+            Value::Matrix(vec![vec![1.0, 0.0], vec![0.0, 1.0]]).to_latex(),
+            "\\begin{pmatrix} 1 & 0 \\\\ 0 & 1 \\end{pmatrix}"
+        );
+        assert_eq!(
+            Value::Set(vec![Value::Integer(1), Value::Integer(2)]).to_latex(),
+            "\\left\\{ 1, 2 \\right\\}"
+        );
+    }
+}
@@ -7,7 +7,12 @@ use structdoc::StructDoc;
 
 pub mod ast;
 pub mod builtin_functions;
+pub mod equivalence;
+pub mod eval;
+pub mod latex;
 pub mod parser;
+pub mod printer;
+pub mod types;
 
 macro_rules! impl_string_json_schema {
     ($t: ty, $e: expr) => {
@@ -100,6 +105,10 @@ impl JMEString {
     pub fn is_empty(&self) -> bool {
         self.s.is_empty()
     }
+    /// The parsed expression, None if the string is empty
+    pub fn ast(&self) -> Option<&ast::Expr> {
+        self.ast.as_ref()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Comparable, Eq, Default, StructDoc)]
@@ -162,6 +171,32 @@ impl EmbracedJMEString {
             asts: None,
         }
     }
+    /// The parsed expressions between braces, empty if the string is empty
+    pub fn asts(&self) -> &[ast::Expr] {
+        self.asts.as_deref().unwrap_or_default()
+    }
+    /// The string with each expression (including its braces) replaced by the result of
+    /// `replacement`
+    pub fn substitute<E>(
+        &self,
+        mut replacement: impl FnMut(&ast::Expr) -> Result<String, E>,
+    ) -> Result<String, E> {
+        let spans = match parser::parse_as_embraced_jme(&self.s) {
+            Ok(pairs) => parser::consume_expression_spans(pairs),
+            Err(_) => return Ok(self.s.clone()),
+        };
+        let mut result = String::new();
+        let mut end_of_previous = 0;
+        for ((start, end), ast) in spans.into_iter().zip(self.asts().iter()) {
+            let open = self.s[..start].rfind('{').unwrap_or(start);
+            let close = self.s[end..].find('}').map(|i| end + i + 1).unwrap_or(end);
+            result.push_str(&self.s[end_of_previous..open]);
+            result.push_str(&replacement(ast)?);
+            end_of_previous = close;
+        }
+        result.push_str(&self.s[end_of_previous..]);
+        Ok(result)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Comparable, Eq, Default, StructDoc)]
@@ -211,6 +246,10 @@ impl ContentAreaString {
     pub fn is_empty(&self) -> bool {
         self.s.is_empty()
     }
+    /// The parsed expressions, empty if the string is empty
+    pub fn asts(&self) -> &[ast::Expr] {
+        self.asts.as_deref().unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Comparable, Eq, Default, StructDoc)]
@@ -287,6 +326,24 @@ mod test {
         assert_eq!(res.unwrap().asts.unwrap().len(), 3);
     }
 
+    #[test]
+    fn content_area_with_simplify() {
+        let res = ContentAreaString::try_from(
+            r#"<p>Differentiate $\simplify{{a}x^2}$ to find $\var{b}$.</p>"#.to_string(),
+        );
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().asts().len(), 1);
+        let res = ContentAreaString::try_from(r#"<p>$\simplify{x^}$</p>"#.to_string());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn substitute_embraced_jme() {
+        let res = EmbracedJMEString::try_from("{a}x + { b+1 } \\{c\\}".to_string()).unwrap();
+        let substituted: Result<_, ()> = res.substitute(|e| Ok(format!("({})", e)));
+        assert_eq!(substituted.unwrap(), "(a)x + (b + 1) \\{c\\}");
+    }
+
     #[test]
     fn diagnosys() {
         let s = include_str!("test_assets/diagnosys.jme");
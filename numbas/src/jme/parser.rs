@@ -54,7 +54,7 @@ pub enum ParserExpr<'i> {
     FunctionApplication(String, Vec<ParserNode<'i>>),
     Prefix(String, Box<ParserNode<'i>>),
     Faculty(Box<ParserNode<'i>>),
-    Indexation(Box<ParserNode<'i>>),
+    Indexation(Box<ParserNode<'i>>, Box<ParserNode<'i>>),
     Superscript(Box<ParserNode<'i>>, String),
     Cast(Box<ParserNode<'i>>, Box<ParserNode<'i>>),
     Sequence(Box<ParserNode<'i>>, Box<ParserNode<'i>>),
@@ -128,7 +128,9 @@ impl<'i> std::convert::TryFrom<ParserExpr<'i>> for ast::Expr {
             ),
             ParserExpr::Prefix(s, n) => ast::Expr::Prefix(s.into(), Box::new((*n).try_into()?)),
             ParserExpr::Faculty(n) => ast::Expr::Faculty(Box::new((*n).try_into()?)),
-            ParserExpr::Indexation(n) => ast::Expr::Indexation(Box::new((*n).try_into()?)),
+            ParserExpr::Indexation(n1, n2) => {
+                ast::Expr::Indexation(Box::new((*n1).try_into()?), Box::new((*n2).try_into()?))
+            }
             ParserExpr::Cast(n1, n2) => {
                 ast::Expr::Cast(Box::new((*n1).try_into()?), Box::new((*n2).try_into()?))
             }
@@ -216,16 +218,24 @@ impl std::fmt::Display for ConsumeError {
     }
 }
 
+/// Consume the expressions in a content area.
+///
+/// The expressions in `\simplify{}` are checked, but not returned because the names in them
+/// are not necessarily variables.
 pub fn consume_content_area_expressions(
     pairs: Pairs<HTMLRule>,
 ) -> Result<Vec<ast::Expr>, ConsumeError> {
     let pairs = pairs.clone().next().unwrap().into_inner();
     let mut asts = vec![];
-    for expression in pairs.filter(|p| p.as_rule() == HTMLRule::expression) {
+    for expression in pairs.filter(|p| {
+        p.as_rule() == HTMLRule::expression || p.as_rule() == HTMLRule::simplify_expression
+    }) {
         let parsed_jme =
             parse_as_jme(expression.as_str()).map_err(|e| ConsumeError::JMEParseError(vec![e]))?;
         let ast = consume_one_expression(parsed_jme)?;
-        asts.push(ast);
+        if expression.as_rule() == HTMLRule::expression {
+            asts.push(ast);
+        }
     }
     Ok(asts)
 }
@@ -282,6 +292,36 @@ pub fn consume_expressions(pairs: Pairs<Rule>) -> Result<Vec<ast::Expr>, Consume
     }
 }
 
+/// The start and end positions of the expressions in the parsed string
+pub fn consume_expression_spans(pairs: Pairs<Rule>) -> Vec<(usize, usize)> {
+    let pairs = pairs.clone().next().unwrap().into_inner();
+    pairs
+        .filter(|pair| pair.as_rule() == Rule::expression)
+        .map(|pair| (pair.as_span().start(), pair.as_span().end()))
+        .collect()
+}
+
+/// Consume a string literal, delimited by `"""`, `"` or `'`
+fn consume_string(pair: Pair<'_, Rule>) -> ParserExpr<'_> {
+    let literal = pair.as_str().trim();
+    let delimiter_length = if literal.len() >= 6 && literal.starts_with("\"\"\"") {
+        3
+    } else {
+        1
+    };
+    let string = unescape(&literal[delimiter_length..literal.len() - delimiter_length])
+        .expect("incorrect string literal");
+    ParserExpr::Str(string)
+}
+
+/// Consume a dictionary key: a string or name
+fn consume_key(pair: Pair<'_, Rule>) -> ParserExpr<'_> {
+    match pair.as_rule() {
+        Rule::string => consume_string(pair),
+        _ => ParserExpr::AnnotatedIdent(pair.as_str().trim().to_owned()),
+    }
+}
+
 pub fn consume_one_expression(pairs: Pairs<Rule>) -> Result<ast::Expr, ConsumeError> {
     consume_expressions(pairs).map(|v| v.into_iter().next().unwrap())
 }
@@ -331,7 +371,10 @@ fn consume_expression_with_spans(pairs: Pairs<Rule>) -> ParserResult<Vec<ParserN
 fn consume_expression(expression: Pair<Rule>) -> ParserResult<ParserNode, Rule> {
     let climber = PrecClimber::new(vec![
         Operator::new(Rule::sequence_operator, Assoc::Left),
-        Operator::new(Rule::logic_binary_operator, Assoc::Left),
+        Operator::new(Rule::implies_operator, Assoc::Left),
+        Operator::new(Rule::xor_operator, Assoc::Left),
+        Operator::new(Rule::or_operator, Assoc::Left),
+        Operator::new(Rule::and_operator, Assoc::Left),
         Operator::new(Rule::relational_operator, Assoc::Left),
         Operator::new(Rule::cast_operator, Assoc::Left),
         Operator::new(Rule::add, Assoc::Left)
@@ -347,6 +390,29 @@ fn consume_expression(expression: Pair<Rule>) -> ParserResult<ParserNode, Rule>
     consume_expression_internal(expression.into_inner().peekable(), &climber)
 }
 
+/// Unary minus binds less tight than the power operator, so -x^2 is parsed as -(x^2)
+fn consume_power<'i>(lhs: ParserNode<'i>, rhs: ParserNode<'i>) -> ParserNode<'i> {
+    let span = lhs.span.start_pos().span(&rhs.span.end_pos());
+    let ungrouped = lhs.span.as_str().starts_with('-');
+    match lhs.expr {
+        ParserExpr::Prefix(op, operand) if op == "-" && ungrouped => ParserNode {
+            expr: ParserExpr::Prefix(op, Box::new(consume_power(*operand, rhs))),
+            span,
+        },
+        expr => ParserNode {
+            expr: ParserExpr::Arithmetic(
+                ast::ArithmeticOperator::Power,
+                Box::new(ParserNode {
+                    expr,
+                    span: lhs.span,
+                }),
+                Box::new(rhs),
+            ),
+            span,
+        },
+    }
+}
+
 fn consume_expression_internal<'i>(
     pairs: Peekable<Pairs<'i, Rule>>,
     climber: &PrecClimber<Rule>,
@@ -372,6 +438,13 @@ fn consume_expression_internal<'i>(
                     Rule::expression => {
                         consume_expression_internal(pair.into_inner().peekable(), climber)
                     }
+                    // The span includes the parentheses, so (-x)^2 can be told apart from -x^2
+                    Rule::grouped_expression => {
+                        let span = pair.as_span();
+                        let expression = pair.into_inner().next().unwrap();
+                        consume_expression_internal(expression.into_inner().peekable(), climber)
+                            .map(|node| ParserNode { span, ..node })
+                    }
                     Rule::annotated_ident => Ok(ParserNode {
                         expr: ParserExpr::AnnotatedIdent(pair.as_str().trim().to_owned()),
                         span: pair.clone().as_span(),
@@ -380,14 +453,10 @@ fn consume_expression_internal<'i>(
                         expr: ParserExpr::AnnotatedConstant(pair.as_str().trim().to_owned()),
                         span: pair.clone().as_span(),
                     }),
-                    Rule::string => {
-                        let string =
-                            unescape(pair.as_str().trim()).expect("incorrect string literal");
-                        Ok(ParserNode {
-                            expr: ParserExpr::Str(string[1..string.len() - 1].to_owned()),
-                            span: pair.clone().as_span(),
-                        })
-                    }
+                    Rule::string => Ok(ParserNode {
+                        span: pair.clone().as_span(),
+                        expr: consume_string(pair),
+                    }),
                     Rule::boolean => {
                         let b: bool = pair
                             .as_str()
@@ -444,15 +513,15 @@ fn consume_expression_internal<'i>(
                         let span = pair.as_span();
                         let pairs = pair.into_inner();
                         let mut elements = Vec::new();
-                        for p in pairs.filter(|p| p.as_rule() == Rule::expression) {
+                        for p in pairs.filter(|p| p.as_rule() == Rule::dictionary_item) {
                             let mut item = p.into_inner();
                             let key_pair = item.next().unwrap();
                             let value_pair = item.next().unwrap();
                             elements.push((
-                                consume_expression_internal(
-                                    key_pair.into_inner().peekable(),
-                                    climber,
-                                )?,
+                                ParserNode {
+                                    span: key_pair.as_span(),
+                                    expr: consume_key(key_pair),
+                                },
                                 consume_expression_internal(
                                     value_pair.into_inner().peekable(),
                                     climber,
@@ -503,9 +572,15 @@ fn consume_expression_internal<'i>(
                         }
                         Rule::index_operator => {
                             let start = node.span.start_pos();
+                            let end = pair.as_span().end_pos();
+                            let index_pair = pair.into_inner().next().unwrap();
+                            let index = consume_expression_internal(
+                                index_pair.into_inner().peekable(),
+                                climber,
+                            )?;
                             Ok(ParserNode {
-                                expr: ParserExpr::Indexation(Box::new(node)),
-                                span: start.span(&pair.as_span().end_pos()),
+                                expr: ParserExpr::Indexation(Box::new(node), Box::new(index)),
+                                span: start.span(&end),
                             })
                         }
                         Rule::superscript_operator => {
@@ -617,22 +692,7 @@ fn consume_expression_internal<'i>(
                 span: start.span(&end),
             })
         }
-        Rule::power => {
-            let lhs = lhs?;
-            let rhs = rhs?;
-
-            let start = lhs.span.start_pos();
-            let end = rhs.span.end_pos();
-
-            Ok(ParserNode {
-                expr: ParserExpr::Arithmetic(
-                    ast::ArithmeticOperator::Power,
-                    Box::new(lhs),
-                    Box::new(rhs),
-                ),
-                span: start.span(&end),
-            })
-        }
+        Rule::power => Ok(consume_power(lhs?, rhs?)),
         Rule::range_separator => {
             let lhs = lhs?;
             let rhs = rhs?;
@@ -681,7 +741,7 @@ fn consume_expression_internal<'i>(
                 span: start.span(&end),
             })
         }
-        Rule::logic_binary_operator => {
+        Rule::and_operator | Rule::or_operator | Rule::xor_operator | Rule::implies_operator => {
             let lhs = lhs?;
             let rhs = rhs?;
 
@@ -852,6 +912,7 @@ mod test {
         assert!(parse_as_jme("id(4)[1]").is_ok());
         assert!(parse_as_jme("info[\"name\"]").is_ok());
         assert!(parse_as_jme("\"Numbas\"[0]").is_ok());
+        assert!(parse_as_jme("x[len(x)-1]").is_ok());
     }
 
     #[test]
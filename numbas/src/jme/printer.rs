@@ -0,0 +1,437 @@
+//! Print jme expressions in a canonical form.
+//!
+//! Binary operators are surrounded by spaces, except for `^`, `..` and `#`. Parentheses are only
+//! added where they are needed to keep the same expression tree.
+
+use crate::jme::ast::{
+    ArithmeticOperator, Expr, LogicalOperator, Note, PrefixOperator, RangeOperator,
+    RelationalOperator,
+};
+use crate::jme::parser;
+use crate::jme::{EmbracedJMEString, JMENotesString, JMEString};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result};
+
+/// The precedence of the top level operation of an expression, higher binds tighter
+pub(crate) fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Sequence(_, _) => 1,
+        Expr::Logic(LogicalOperator::Implies, _, _) => 2,
+        Expr::Logic(LogicalOperator::Xor, _, _) => 3,
+        Expr::Logic(LogicalOperator::Or, _, _) => 4,
+        Expr::Logic(LogicalOperator::And, _, _) => 5,
+        Expr::Relation(_, _, _) => 6,
+        Expr::Cast(_, _) => 7,
+        Expr::Arithmetic(ArithmeticOperator::Add, _, _)
+        | Expr::Arithmetic(ArithmeticOperator::Subtract, _, _)
+        | Expr::Arithmetic(ArithmeticOperator::Except, _, _) => 8,
+        Expr::Arithmetic(ArithmeticOperator::Multiply, _, _)
+        | Expr::Arithmetic(ArithmeticOperator::Divide, _, _) => 9,
+        Expr::Range(RangeOperator::Step, _, _) => 10,
+        Expr::Range(RangeOperator::Create, _, _) => 11,
+        Expr::Prefix(PrefixOperator::Minus, _) => MINUS_PRECEDENCE,
+        Expr::Int(i) | Expr::Float(i, _) if *i < 0 => MINUS_PRECEDENCE,
+        Expr::Arithmetic(ArithmeticOperator::Power, _, _) => POWER_PRECEDENCE,
+        Expr::Prefix(PrefixOperator::Not, _) => PREFIX_PRECEDENCE,
+        Expr::Faculty(_) | Expr::Indexation(_, _) => POSTFIX_PRECEDENCE,
+        _ => 16,
+    }
+}
+
+/// Unary minus binds less tight than `^`: -x^2 is -(x^2)
+pub(crate) const MINUS_PRECEDENCE: u8 = 12;
+pub(crate) const POWER_PRECEDENCE: u8 = 13;
+pub(crate) const PREFIX_PRECEDENCE: u8 = 14;
+pub(crate) const POSTFIX_PRECEDENCE: u8 = 15;
+
+/// Write the expression, between parentheses if it binds less tight than `minimum`
+fn write_operand(f: &mut Formatter, expr: &Expr, minimum: u8) -> Result {
+    if precedence(expr) < minimum {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+fn write_binary(
+    f: &mut Formatter,
+    operator: &str,
+    parent: &Expr,
+    lhs: &Expr,
+    rhs: &Expr,
+) -> Result {
+    let own = precedence(parent);
+    // Power is the only right associative operator
+    let (lhs_minimum, rhs_minimum) = match parent {
+        Expr::Arithmetic(ArithmeticOperator::Power, _, _) => (own + 1, own),
+        _ => (own, own + 1),
+    };
+    write_operand(f, lhs, lhs_minimum)?;
+    write!(f, "{}", operator)?;
+    write_operand(f, rhs, rhs_minimum)
+}
+
+fn write_list(f: &mut Formatter, items: &[Expr]) -> Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Write a string literal, the escapes are the ones that are understood by the parser
+fn write_string(f: &mut Formatter, s: &str) -> Result {
+    write!(f, "\"")?;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // `\{` and `\}` are kept as is by the parser
+            '\\' if matches!(chars.peek(), Some('{') | Some('}')) => write!(f, "\\")?,
+            '\\' => write!(f, "\\\\")?,
+            '"' => write!(f, "\\\"")?,
+            '\n' => write!(f, "\\n")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl Display for ArithmeticOperator {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            ArithmeticOperator::Add => write!(f, " + "),
+            ArithmeticOperator::Subtract => write!(f, " - "),
+            ArithmeticOperator::Multiply => write!(f, " * "),
+            ArithmeticOperator::Divide => write!(f, " / "),
+            ArithmeticOperator::Power => write!(f, "^"),
+            ArithmeticOperator::Except => write!(f, " except "),
+        }
+    }
+}
+
+impl Display for RelationalOperator {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            RelationalOperator::LessThan => write!(f, " < "),
+            RelationalOperator::LessThanOrEqual => write!(f, " <= "),
+            RelationalOperator::GreaterThan => write!(f, " > "),
+            RelationalOperator::GreaterThanOrEqual => write!(f, " >= "),
+            RelationalOperator::Equals => write!(f, " = "),
+            RelationalOperator::NotEquals => write!(f, " <> "),
+            RelationalOperator::In => write!(f, " in "),
+            RelationalOperator::IsA => write!(f, " isa "),
+            RelationalOperator::Divides => write!(f, " | "),
+        }
+    }
+}
+
+impl Display for LogicalOperator {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            LogicalOperator::And => write!(f, " and "),
+            LogicalOperator::Or => write!(f, " or "),
+            LogicalOperator::Xor => write!(f, " xor "),
+            LogicalOperator::Implies => write!(f, " implies "),
+        }
+    }
+}
+
+impl Display for RangeOperator {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            RangeOperator::Create => write!(f, ".."),
+            RangeOperator::Step => write!(f, "#"),
+        }
+    }
+}
+
+impl Display for PrefixOperator {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            PrefixOperator::Not => write!(f, "not "),
+            PrefixOperator::Minus => write!(f, "-"),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Expr::Str(s) => write_string(f, s),
+            Expr::Int(i) => write!(f, "{}", i),
+            Expr::Float(i, broken) => write!(f, "{}.{}", i, broken),
+            Expr::Bool(b) => write!(f, "{}", b),
+            Expr::Ident(ident) | Expr::Constant(ident) => write!(f, "{}", ident),
+            Expr::Range(o, lhs, rhs) => write_binary(f, &o.to_string(), self, lhs, rhs),
+            Expr::Arithmetic(o, lhs, rhs) => write_binary(f, &o.to_string(), self, lhs, rhs),
+            Expr::Relation(o, lhs, rhs) => write_binary(f, &o.to_string(), self, lhs, rhs),
+            Expr::Logic(o, lhs, rhs) => write_binary(f, &o.to_string(), self, lhs, rhs),
+            Expr::Cast(lhs, rhs) => write_binary(f, " as ", self, lhs, rhs),
+            Expr::Sequence(lhs, rhs) => write_binary(f, "; ", self, lhs, rhs),
+            Expr::List(items) => {
+                write!(f, "[")?;
+                write_list(f, items)?;
+                write!(f, "]")
+            }
+            // An empty dictionary can't be written as a literal
+            Expr::Dictionary(items) if items.is_empty() => write!(f, "dict()"),
+            Expr::Dictionary(items) => {
+                write!(f, "[")?;
+                for (i, (key, value)) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "]")
+            }
+            Expr::FunctionApplication(name, arguments) => {
+                write!(f, "{}(", name)?;
+                write_list(f, arguments)?;
+                write!(f, ")")
+            }
+            Expr::Prefix(o, operand) => {
+                write!(f, "{}", o)?;
+                write_operand(f, operand, precedence(self))
+            }
+            Expr::Faculty(operand) => {
+                write_operand(f, operand, POSTFIX_PRECEDENCE)?;
+                write!(f, "!")
+            }
+            Expr::Indexation(operand, index) => {
+                write_operand(f, operand, POSTFIX_PRECEDENCE)?;
+                write!(f, "[{}]", index)
+            }
+        }
+    }
+}
+
+impl Display for Note {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}", self.name)?;
+        if let Some(description) = &self.description {
+            write!(f, "({})", description)?;
+        }
+        write!(f, ": {}", self.expression)
+    }
+}
+
+/// Comments are not part of the parsed expressions, so strings with comments are not formatted.
+/// This also skips strings that contain `//` in a string literal, which is fine.
+fn has_comments(s: &str) -> bool {
+    s.contains("//") || s.contains("/*")
+}
+
+impl JMEString {
+    /// The canonical form of this jme string, see the module documentation.
+    /// The string is kept as is if it contains comments.
+    pub fn formatted(&self) -> Self {
+        match &self.ast {
+            Some(ast) if !has_comments(&self.s) => JMEString::try_from(ast.to_string())
+                .ok()
+                .filter(|formatted| formatted.ast == self.ast)
+                .unwrap_or_else(|| self.clone()),
+            _ => self.clone(),
+        }
+    }
+}
+
+impl EmbracedJMEString {
+    /// The canonical form of this embraced jme string: the expressions between braces are
+    /// formatted, all other text is kept as is.
+    pub fn formatted(&self) -> Self {
+        let spans = match parser::parse_as_embraced_jme(&self.s) {
+            Ok(pairs) => parser::consume_expression_spans(pairs),
+            Err(_) => return self.clone(),
+        };
+        let mut result = String::new();
+        let mut end_of_previous = 0;
+        for ((start, end), ast) in spans.into_iter().zip(self.asts().iter()) {
+            result.push_str(&self.s[end_of_previous..start]);
+            let expression = &self.s[start..end];
+            if has_comments(expression) {
+                result.push_str(expression);
+            } else {
+                result.push_str(&ast.to_string());
+            }
+            end_of_previous = end;
+        }
+        result.push_str(&self.s[end_of_previous..]);
+        EmbracedJMEString::try_from(result)
+            .ok()
+            .filter(|formatted| formatted.asts == self.asts)
+            .unwrap_or_else(|| self.clone())
+    }
+}
+
+impl JMENotesString {
+    /// The canonical form of this notes string: each note is formatted and the notes are
+    /// separated by an empty line.
+    /// The string is kept as is if it contains comments.
+    pub fn formatted(&self) -> Self {
+        let notes = match &self.notes {
+            Some(notes) if !has_comments(&self.s) => notes,
+            _ => return self.clone(),
+        };
+        let formatted = notes
+            .iter()
+            .map(|note| note.to_string())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        JMENotesString::try_from(formatted)
+            .ok()
+            .filter(|formatted| {
+                let formatted_notes = formatted.notes.as_ref().unwrap();
+                formatted_notes.len() == notes.len()
+                    && formatted_notes.iter().zip(notes.iter()).all(|(a, b)| {
+                        a.name == b.name
+                            && a.description == b.description
+                            && a.expression == b.expression
+                    })
+            })
+            .unwrap_or_else(|| self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jme::parser::{consume_one_expression, parse_as_jme};
+    use serde::Deserialize;
+
+    fn parse(s: &str) -> Expr {
+        consume_one_expression(parse_as_jme(s).unwrap()).unwrap()
+    }
+
+    fn format(s: &str) -> String {
+        parse(s).to_string()
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(format("a+b*c"), "a + b * c");
+        assert_eq!(format("2x"), "2 * x");
+        assert_eq!(format("x^2"), "x^2");
+        assert_eq!(format("random(1..10#2,5)"), "random(1..10#2, 5)");
+        assert_eq!(format("a<b&&!c"), "a < b and not c");
+        assert_eq!(format("x  as  'number'"), "x as \"number\"");
+        assert_eq!(format("a=1;a"), "a = 1; a");
+        assert_eq!(format("[a:1,'b c':[1,2]]"), "[a: 1, \"b c\": [1, 2]]");
+        assert_eq!(format("v:x[0]!"), "v:x[0]!");
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        assert_eq!(format("((a+b))+(c)"), "a + b + c");
+        assert_eq!(format("a+(b+c)"), "a + (b + c)");
+        assert_eq!(format("(a-b)*c"), "(a - b) * c");
+        assert_eq!(format("(a^b)^c"), "(a^b)^c");
+        assert_eq!(format("a^(b^c)"), "a^b^c");
+        assert_eq!(format("-x^2"), "-x^2");
+        assert_eq!(format("-(x^2)"), "-x^2");
+        assert_eq!(format("(-x)^2"), "(-x)^2");
+        assert_eq!(format("(-2)^2"), "(-2)^2");
+        assert_eq!(format("2^-x"), "2^(-x)");
+        assert_eq!(format("-x*y"), "-x * y");
+        assert_eq!(format("(a+b)!"), "(a + b)!");
+        assert_eq!(format("(1..5)#2"), "1..5#2");
+        assert_eq!(format("1..(5#2)"), "1..(5#2)");
+        assert_eq!(format("not (a or b)"), "not (a or b)");
+    }
+
+    #[test]
+    fn logic_precedence() {
+        assert_eq!(format("(a or b) and c"), "(a or b) and c");
+        assert_eq!(format("a or (b and c)"), "a or b and c");
+        assert_eq!(format("(a implies b) or c"), "(a implies b) or c");
+        assert_eq!(format("a implies (b or c)"), "a implies b or c");
+        assert_eq!(format("(a xor b) and c"), "(a xor b) and c");
+        assert_eq!(format("a or b xor c"), "a or b xor c");
+        assert_eq!(format("a or (b xor c)"), "a or (b xor c)");
+        assert_eq!(format("a && b || c"), "a and b or c");
+        for s in [
+            "(a or b) and c",
+            "(a implies b) or c",
+            "a xor b implies c and d",
+            "(a xor b) and (c implies d)",
+        ] {
+            assert_eq!(parse(&format(s)), parse(s), "{}", s);
+        }
+        assert_eq!(format("x⁽¹⁺¹⁾"), "x^(1 + 1)");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            format(r#""a \"quoted\" \\ word""#),
+            r#""a \"quoted\" \\ word""#
+        );
+        assert_eq!(format(r#"'it\'s'"#), r#""it's""#);
+        assert_eq!(format(r#""\{x\}""#), r#""\{x\}""#);
+        assert_eq!(format(r#""line\nbreak""#), r#""line\nbreak""#);
+    }
+
+    #[test]
+    fn formatted_strings() {
+        let jme = JMEString::try_from("  (a+b)*  c".to_string()).unwrap();
+        assert_eq!(jme.formatted().to_string(), "(a + b) * c");
+        let jme = JMEString::try_from("a+b // sum".to_string()).unwrap();
+        assert_eq!(jme.formatted().to_string(), "a+b // sum");
+
+        let embraced =
+            EmbracedJMEString::try_from("Answer {a+b} and \\{x\\} {f( x )}".to_string()).unwrap();
+        assert_eq!(
+            embraced.formatted().to_string(),
+            "Answer {a + b} and \\{x\\} {f(x)}"
+        );
+
+        let notes =
+            JMENotesString::try_from("a (the first note):\n  1+\n  2\n\n\nb:\n a*a".to_string())
+                .unwrap();
+        assert_eq!(
+            notes.formatted().to_string(),
+            "a(the first note): 1 + 2\n\nb: a * a"
+        );
+    }
+
+    #[derive(Deserialize)]
+    struct DocTest {
+        fns: Vec<DocTestFn>,
+    }
+
+    #[derive(Deserialize)]
+    struct DocTestFn {
+        examples: Vec<DocTestFnExample>,
+    }
+
+    #[derive(Deserialize)]
+    struct DocTestFnExample {
+        r#in: String,
+    }
+
+    #[test]
+    fn numbas_doc_tests_round_trip() {
+        let doc_tests: Vec<DocTest> =
+            serde_json::from_str(include_str!("numbas-jme-doc-tests.json")).unwrap();
+        let mut failures = Vec::new();
+        for example in doc_tests
+            .into_iter()
+            .flat_map(|t| t.fns.into_iter())
+            .flat_map(|f| f.examples.into_iter())
+        {
+            let ast = parse(&example.r#in);
+            let printed = ast.to_string();
+            let reparsed = parse_as_jme(&printed)
+                .ok()
+                .and_then(|pairs| consume_one_expression(pairs).ok());
+            if reparsed.as_ref() != Some(&ast) {
+                failures.push(format!("{} was printed as {}", example.r#in, printed));
+            } else if reparsed.unwrap().to_string() != printed {
+                failures.push(format!("printing {} is not idempotent", example.r#in));
+            }
+        }
+        assert_eq!(failures, Vec::<String>::new());
+    }
+}
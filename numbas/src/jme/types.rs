@@ -0,0 +1,787 @@
+//! Static type inference for jme expressions.
+//!
+//! Each builtin function has one or more signatures. The types of an expression are inferred
+//! bottom up, the types of the variables are taken from the `ValidationScope`. Unknown types are
+//! `Any` and match everything, so only clear mistakes are reported.
+
+use crate::jme::ast::{ArithmeticOperator, Expr, PrefixOperator, RangeOperator, ValidationScope};
+use crate::jme::builtin_functions::BuiltinFunctions;
+use std::collections::BTreeSet;
+
+/// The type of a jme value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JMEType {
+    Number,
+    Integer,
+    Rational,
+    Decimal,
+    Boolean,
+    String,
+    List,
+    Set,
+    Dictionary,
+    Range,
+    Vector,
+    Matrix,
+    Nothing,
+    Expression,
+    HTML,
+    /// The type is not known
+    Any,
+}
+
+impl JMEType {
+    /// The type with the given Numbas type name, e.g. as used in `x as "number"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "number" => JMEType::Number,
+            "integer" => JMEType::Integer,
+            "rational" => JMEType::Rational,
+            "decimal" => JMEType::Decimal,
+            "boolean" => JMEType::Boolean,
+            "string" => JMEType::String,
+            "list" => JMEType::List,
+            "set" => JMEType::Set,
+            "dict" => JMEType::Dictionary,
+            "range" => JMEType::Range,
+            "vector" => JMEType::Vector,
+            "matrix" => JMEType::Matrix,
+            "nothing" => JMEType::Nothing,
+            "expression" => JMEType::Expression,
+            "html" => JMEType::HTML,
+            "name" | "op" | "function" | "keypair" => JMEType::Any,
+            _ => return None,
+        })
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            JMEType::Number | JMEType::Integer | JMEType::Rational | JMEType::Decimal
+        )
+    }
+
+    /// Whether a value of type `self` can be passed where `parameter` is expected.
+    /// Numbers are converted to each other, and Numbas converts lists to vectors, ranges to
+    /// lists etc.
+    pub fn fits(&self, parameter: &JMEType) -> bool {
+        use JMEType::*;
+        match (self, parameter) {
+            (Any, _) | (_, Any) => true,
+            (a, b) if a == b => true,
+            (a, b) if a.is_numeric() && b.is_numeric() => true,
+            (Range | Set | Vector, List) => true,
+            (List | Range, Set) => true,
+            (List, Vector | Matrix) => true,
+            (String, HTML | Expression) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether a value of type `self` can be cast to `target` with the `as` operator
+    pub fn can_cast_to(&self, target: &JMEType) -> bool {
+        use JMEType::*;
+        match target {
+            _ if self.fits(target) => true,
+            Number | Decimal => matches!(self, String),
+            Integer | Rational | Boolean => false,
+            List => matches!(self, Dictionary | Matrix),
+            Set => matches!(self, Vector),
+            Vector => matches!(self, Matrix | Range | Set),
+            _ => true,
+        }
+    }
+}
+
+impl std::fmt::Display for JMEType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            JMEType::Number => "number",
+            JMEType::Integer => "integer",
+            JMEType::Rational => "rational",
+            JMEType::Decimal => "decimal",
+            JMEType::Boolean => "boolean",
+            JMEType::String => "string",
+            JMEType::List => "list",
+            JMEType::Set => "set",
+            JMEType::Dictionary => "dict",
+            JMEType::Range => "range",
+            JMEType::Vector => "vector",
+            JMEType::Matrix => "matrix",
+            JMEType::Nothing => "nothing",
+            JMEType::Expression => "expression",
+            JMEType::HTML => "html",
+            JMEType::Any => "?",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The argument types and the return type of a function
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    parameters: Vec<JMEType>,
+    /// The parameters that can be left out, after the required parameters
+    optional: Vec<JMEType>,
+    /// The type of the extra arguments, if any amount of extra arguments is allowed
+    rest: Option<JMEType>,
+    output: JMEType,
+}
+
+fn sig(parameters: &[JMEType], output: JMEType) -> Signature {
+    Signature {
+        parameters: parameters.to_vec(),
+        optional: vec![],
+        rest: None,
+        output,
+    }
+}
+
+impl Signature {
+    fn optional(mut self, optional: &[JMEType]) -> Self {
+        self.optional = optional.to_vec();
+        self
+    }
+    fn rest(mut self, rest: JMEType) -> Self {
+        self.rest = Some(rest);
+        self
+    }
+    pub fn output(&self) -> JMEType {
+        self.output
+    }
+    pub fn minimum_arguments(&self) -> usize {
+        self.parameters.len()
+    }
+    /// The maximum amount of arguments, None if there is no maximum
+    pub fn maximum_arguments(&self) -> Option<usize> {
+        match self.rest {
+            Some(_) => None,
+            None => Some(self.parameters.len() + self.optional.len()),
+        }
+    }
+    pub fn accepts_amount(&self, amount: usize) -> bool {
+        amount >= self.minimum_arguments()
+            && self
+                .maximum_arguments()
+                .map(|m| amount <= m)
+                .unwrap_or(true)
+    }
+    /// The expected type of the argument at the given position
+    fn parameter(&self, index: usize) -> Option<JMEType> {
+        self.parameters
+            .iter()
+            .chain(self.optional.iter())
+            .nth(index)
+            .copied()
+            .or(self.rest)
+    }
+    pub fn accepts(&self, arguments: &[JMEType]) -> bool {
+        self.accepts_amount(arguments.len())
+            && arguments
+                .iter()
+                .enumerate()
+                .all(|(i, a)| self.parameter(i).map(|p| a.fits(&p)).unwrap_or(false))
+    }
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|p| p.to_string())
+            .chain(self.optional.iter().map(|p| format!("{}?", p)))
+            .chain(self.rest.iter().map(|p| format!("{}*", p)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "({}) -> {}", parameters, self.output)
+    }
+}
+
+impl BuiltinFunctions {
+    /// The signatures of this function, it can be called if one of them accepts the arguments
+    pub fn signatures(&self) -> Vec<Signature> {
+        use BuiltinFunctions as F;
+        use JMEType::*;
+        match self {
+            // Numbers
+            F::Exp | F::Sqrt | F::Ln | F::Degrees | F::Radians | F::Sign | F::Fact | F::Gamma => {
+                vec![sig(&[Number], Number)]
+            }
+            F::Sin | F::Cos | F::Tan | F::Cosec | F::Sec | F::Cot | F::ArcSin | F::ArcCos => {
+                vec![sig(&[Number], Number)]
+            }
+            F::ArcTan | F::Sinh | F::Cosh | F::Tanh | F::Cosech | F::Sech | F::Coth => {
+                vec![sig(&[Number], Number)]
+            }
+            F::ArcSinh | F::ArcCosh | F::ArcTanh | F::Arg | F::Re | F::Im | F::Conj => {
+                vec![sig(&[Number], Number)]
+            }
+            F::ATan2 | F::Root | F::ToNearest | F::Mod | F::Perm | F::Comb => {
+                vec![sig(&[Number, Number], Number)]
+            }
+            F::GCD | F::GCDWithoutPIorI => vec![sig(&[Number, Number], Number)],
+            F::CoPrime => vec![sig(&[Number, Number], Boolean)],
+            F::LCM => vec![sig(&[Number], Number).rest(Number), sig(&[List], Number)],
+            F::Decimal => vec![sig(&[Number], Decimal), sig(&[String], Decimal)],
+            F::Rational => vec![sig(&[Number], Rational)],
+            F::Int => vec![sig(&[Number], Integer)],
+            F::Abs => vec![
+                sig(&[Number], Number),
+                sig(&[Vector], Number),
+                sig(&[String], Integer),
+                sig(&[List], Integer),
+                sig(&[Dictionary], Integer),
+            ],
+            F::Len => vec![
+                sig(&[String], Integer),
+                sig(&[List], Integer),
+                sig(&[Dictionary], Integer),
+            ],
+            F::IsInt | F::IsZero | F::IsNan => vec![sig(&[Number], Boolean)],
+            F::Log => vec![sig(&[Number], Number).optional(&[Number])],
+            F::Max | F::Min => vec![sig(&[Number], Number).rest(Number), sig(&[List], Any)],
+            F::Clamp => vec![sig(&[Number, Number, Number], Number)],
+            F::PrecRound | F::SigRound => vec![
+                sig(&[Number, Number], Number),
+                sig(&[Vector, Number], Vector),
+                sig(&[Matrix, Number], Matrix),
+            ],
+            F::Ceil | F::Floor | F::Round | F::Trunc | F::Fract => vec![
+                sig(&[Number], Number),
+                sig(&[Vector], Vector),
+                sig(&[Matrix], Matrix),
+            ],
+            F::WithIntolerance => vec![sig(&[Number, Number, Number], Boolean)],
+            F::DPFormat | F::SigFormat => vec![sig(&[Number, Number], String).optional(&[String])],
+            F::CountDP | F::CountSigFigs => vec![sig(&[String], Integer)],
+            F::ToGivenPrecision => vec![sig(&[String, String, Number, Boolean], Boolean)],
+            F::FormatNumber => vec![sig(&[Number, Any], String)],
+            F::ScientificNumberLaTeX => vec![sig(&[Number], String)],
+            F::ScientificNumberHTML => vec![sig(&[Number], HTML)],
+            F::CleanNumber => vec![sig(&[String], String).optional(&[Any])],
+            F::MatchNumber => vec![sig(&[String, Any], List)],
+            F::ParseNumber => vec![sig(&[String, Any], Number)],
+            F::ParseNumberOrFraction => vec![sig(&[String], Number).optional(&[Any])],
+            F::ParseDecimal => vec![sig(&[String, Any], Decimal)],
+            F::ParseDecimalOrFraction => vec![sig(&[String], Decimal).optional(&[Any])],
+            F::ToBinary | F::ToOctal | F::ToHexadecimal => vec![sig(&[Number], String)],
+            F::ToBase => vec![sig(&[Number, Number], String)],
+            F::FromBinary | F::FromOctal | F::FromHexadecimal => vec![sig(&[String], Integer)],
+            F::FromBase => vec![sig(&[String, Number], Integer)],
+            F::Factorise | F::Divisors | F::ProperDivisors => vec![sig(&[Number], List)],
+            F::RationalApproximation => vec![sig(&[Number], List).optional(&[Number])],
+            // Vectors and matrices
+            F::Vector => vec![sig(&[], Vector).rest(Number), sig(&[List], Vector)],
+            F::Matrix => vec![sig(&[], Matrix).rest(List), sig(&[], Matrix).rest(Vector)],
+            F::RowVector => vec![sig(&[], Matrix).rest(Number), sig(&[List], Matrix)],
+            F::Id => vec![sig(&[Number], Matrix)],
+            F::NumRows | F::NumColumns => vec![sig(&[Matrix], Integer)],
+            F::Dot => vec![sig(&[Any, Any], Number)],
+            F::Cross => vec![sig(&[Any, Any], Vector)],
+            F::Angle => vec![sig(&[Vector, Vector], Number)],
+            F::IsZeroVector => vec![sig(&[Vector], Boolean)],
+            F::IsScalarMultiple => {
+                vec![sig(&[Vector, Vector], Boolean).optional(&[Number, Number])]
+            }
+            F::Det | F::SumCells => vec![sig(&[Matrix], Number)],
+            F::Transpose => vec![sig(&[Matrix], Matrix), sig(&[Vector], Matrix)],
+            F::Augment | F::Stack | F::CombineDiagonally => vec![sig(&[Any, Any], Matrix)],
+            // Strings
+            F::String => vec![sig(&[Any], String).optional(&[Any])],
+            F::LaTeX => vec![sig(&[Any], String)],
+            F::Safe => vec![sig(&[String], String)],
+            F::Render => vec![sig(&[String], String).optional(&[Dictionary])],
+            F::Capitalise | F::Upper | F::Lower | F::Trim => vec![sig(&[String], String)],
+            F::Pluralise => vec![sig(&[Number, String, String], String)],
+            F::Join => vec![sig(&[List, String], String)],
+            F::Split => vec![sig(&[String, String], List)],
+            F::MatchRegex | F::SplitRegex => vec![sig(&[String, String], List).optional(&[String])],
+            F::ReplaceRegex => {
+                vec![sig(&[String, String, String], String).optional(&[String])]
+            }
+            F::Currency => vec![sig(&[Number, String, String], String)],
+            F::SeparateThousands => vec![sig(&[Number, String], String)],
+            F::UnPercent => vec![sig(&[String], Number)],
+            F::LPad | F::RPad => vec![sig(&[String, Number, String], String)],
+            F::FormatString => vec![sig(&[String, List], String)],
+            F::LetterOrdinal => vec![sig(&[Number], String)],
+            F::Translate => vec![sig(&[String], String).optional(&[Dictionary])],
+            F::IsBool => vec![sig(&[Any], Boolean)],
+            // Logic
+            F::IsClose => vec![sig(&[Number, Number], Boolean).optional(&[Number, Number])],
+            F::ResultsEqual => vec![sig(&[Any, Any, String, Number], Boolean)],
+            F::Award => vec![sig(&[Number, Boolean], Number)],
+            F::If => vec![sig(&[Boolean, Any, Any], Any)],
+            F::Switch => vec![sig(&[Any, Any, Any], Any).rest(Any)],
+            F::Assert => vec![sig(&[Boolean, Any], Any)],
+            F::Try => vec![sig(&[Any, Any, Any], Any)],
+            // Collections
+            F::Repeat => vec![sig(&[Any, Number], List)],
+            F::All | F::Some => vec![sig(&[List], Boolean)],
+            F::Map => vec![sig(&[Any, Any, Any], Any)],
+            F::Filter => vec![sig(&[Any, Any, List], List)],
+            F::FoldL => vec![sig(&[Any, Any, Any, Any, List], Any)],
+            F::Iterate => vec![sig(&[Any, Any, Any, Number], List)],
+            F::IterateUntil => vec![sig(&[Any, Any, Any, Any], List).optional(&[Number])],
+            F::Take => vec![sig(&[Number, Any, Any, List], List)],
+            F::Flatten | F::Sort | F::SortDestinations | F::Reverse | F::Distinct => {
+                vec![sig(&[List], List)]
+            }
+            F::Frequencies | F::Enumerate | F::Shuffle | F::ShuffleTogether => {
+                vec![sig(&[List], List)]
+            }
+            F::Let => vec![sig(&[Any, Any], Any).rest(Any)],
+            F::SortBy | F::GroupBy => vec![sig(&[Any, List], List)],
+            F::Indices => vec![sig(&[List, Any], List)],
+            F::List => vec![sig(&[Any], List)],
+            F::MakeVariables => vec![sig(&[Dictionary], Dictionary).optional(&[Any])],
+            F::Satisfy => vec![sig(&[List, List, List], List).optional(&[Number])],
+            F::Sum | F::Prod => vec![sig(&[List], Number)],
+            F::Product => vec![sig(&[List], List).rest(List), sig(&[List, Number], List)],
+            F::Zip => vec![sig(&[List], List).rest(List)],
+            F::Combinations | F::CombinationsWithReplacement | F::Permutations => {
+                vec![sig(&[List, Number], List)]
+            }
+            F::Get => vec![sig(&[Dictionary, String, Any], Any)],
+            F::Dict => vec![sig(&[], Dictionary).rest(Any)],
+            F::Keys | F::Items => vec![sig(&[Dictionary], List)],
+            F::Values => vec![sig(&[Dictionary], List).optional(&[List])],
+            F::Set => vec![sig(&[], Set).rest(Any)],
+            F::Union | F::Intersection => vec![sig(&[Set, Set], Set)],
+            // Randomisation
+            F::Random => vec![sig(&[Any], Any).rest(Any)],
+            F::WeightedRandom => vec![sig(&[List], Any)],
+            F::Deal => vec![sig(&[Number], List)],
+            F::Reorder => vec![sig(&[List, List], List)],
+            // HTML
+            F::HTML => vec![sig(&[String], HTML)],
+            F::IsNonEmptyHTML => vec![sig(&[Any], Boolean)],
+            F::Table => vec![sig(&[List], HTML).optional(&[List])],
+            F::Image => vec![sig(&[String], HTML).optional(&[Number, Number])],
+            F::MaxWidth | F::MaxHeight => vec![sig(&[Number, HTML], HTML)],
+            // JSON
+            F::JsonDecode => vec![sig(&[String], Any)],
+            F::JsonEncode => vec![sig(&[Any], String)],
+            // Sub-expressions
+            F::Parse => vec![sig(&[String], Expression)],
+            F::Eval => vec![sig(&[Expression], Any).optional(&[Dictionary])],
+            F::Args | F::FindVars => vec![sig(&[Expression], List)],
+            F::Type => vec![sig(&[Any], String)],
+            F::Name | F::Op | F::Function => vec![sig(&[String], Any)],
+            F::Exec => vec![sig(&[Any, List], Expression)],
+            F::Substitute => vec![sig(&[Dictionary, Expression], Expression)],
+            F::Simplify => vec![sig(&[Expression, Any], Expression)],
+            F::ExpandJuxtapositions => {
+                vec![sig(&[Expression], Expression).optional(&[Dictionary])]
+            }
+            F::CanonicalCompare => vec![sig(&[Expression, Expression], Integer)],
+            F::NumericalCompare => vec![sig(&[Expression, Expression], Boolean)],
+            F::ScopeCaseSensitive => vec![sig(&[Any], Any).optional(&[Boolean])],
+            F::Diff => vec![sig(&[Expression, Any], Expression).optional(&[Number])],
+            F::Match => vec![sig(&[Expression, String], Dictionary)],
+            F::Matches => vec![sig(&[Expression, String], Boolean)],
+            F::Replace => vec![sig(&[String, String, Expression], Expression)],
+            F::InferVariableTypes => vec![sig(&[Expression], Dictionary)],
+            F::InferType => vec![sig(&[Expression], String)],
+            F::DefinedVariables => vec![sig(&[], List)],
+            F::IsSet => vec![sig(&[Any], Boolean)],
+            F::Unset => vec![sig(&[Any, Any], Any)],
+        }
+    }
+}
+
+/// A type error in a jme expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeError {
+    /// A function is called with a wrong amount of arguments
+    WrongArity {
+        function: String,
+        signatures: Vec<Signature>,
+        found: usize,
+    },
+    /// None of the signatures of the function accepts the types of the arguments
+    ArgumentMismatch {
+        function: String,
+        signatures: Vec<Signature>,
+        arguments: Vec<JMEType>,
+    },
+    /// The target of `as` is not the name of a type
+    UnknownCastTarget(String),
+    /// The target of `as` is not a string
+    InvalidCastTarget(JMEType),
+    /// A value of this type can't be cast to the target type
+    InvalidCast(JMEType, JMEType),
+}
+
+fn write_signatures(
+    f: &mut std::fmt::Formatter,
+    function: &str,
+    signatures: &[Signature],
+) -> std::fmt::Result {
+    let signatures = signatures
+        .iter()
+        .map(|s| format!("{}{}", function, s))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    write!(f, "expected {}", signatures)
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TypeError::WrongArity {
+                function,
+                signatures,
+                found,
+            } => {
+                write!(f, "{} can't be called with {} arguments, ", function, found)?;
+                write_signatures(f, function, signatures)
+            }
+            TypeError::ArgumentMismatch {
+                function,
+                signatures,
+                arguments,
+            } => {
+                write!(
+                    f,
+                    "{} can't be called with ({}), ",
+                    function,
+                    arguments
+                        .iter()
+                        .map(|a| a.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )?;
+                write_signatures(f, function, signatures)
+            }
+            TypeError::UnknownCastTarget(target) => {
+                write!(f, "as: \"{}\" is not the name of a type", target)
+            }
+            TypeError::InvalidCastTarget(target) => {
+                write!(f, "as: the type should be a string, found {}", target)
+            }
+            TypeError::InvalidCast(from, to) => write!(f, "as: can't cast {} to {}", from, to),
+        }
+    }
+}
+
+/// The names that are bound by the given function call, e.g. `x` in `map(x^2, x, l)`
+fn bound_by_call(function: &str, arguments: &[Expr]) -> Vec<String> {
+    let names: Vec<&Expr> = match (function, arguments) {
+        ("map" | "filter", [_, names, _]) => vec![names],
+        ("take", [_, _, names, _]) => vec![names],
+        ("foldl", [_, accumulator, item, _, _]) => vec![accumulator, item],
+        ("iterate", [_, names, _, _]) | ("iterate_until", [_, names, ..]) => vec![names],
+        ("try", [_, name, _]) => vec![name],
+        ("let", [definitions @ .., _]) if definitions.len() % 2 == 0 => {
+            definitions.iter().step_by(2).collect()
+        }
+        _ => vec![],
+    };
+    names.into_iter().flat_map(|n| n.bound_names()).collect()
+}
+
+/// The common output type of the signatures, Any if they differ
+fn common_output<'a>(signatures: impl Iterator<Item = &'a Signature>) -> JMEType {
+    let outputs: BTreeSet<_> = signatures.map(|s| s.output().to_string()).collect();
+    match outputs.iter().next() {
+        Some(output) if outputs.len() == 1 => JMEType::from_name(output).unwrap_or(JMEType::Any),
+        _ => JMEType::Any,
+    }
+}
+
+fn arithmetic_type(operator: &ArithmeticOperator, lhs: JMEType, rhs: JMEType) -> JMEType {
+    use JMEType::*;
+    match (operator, lhs, rhs) {
+        (ArithmeticOperator::Except, lhs, _) => lhs,
+        (_, Integer, Integer) if *operator != ArithmeticOperator::Divide => match operator {
+            ArithmeticOperator::Power => Number,
+            _ => Integer,
+        },
+        (_, a, b) if a.is_numeric() && b.is_numeric() => Number,
+        (ArithmeticOperator::Add, String, _) | (ArithmeticOperator::Add, _, String) => String,
+        (ArithmeticOperator::Add, List, _) | (ArithmeticOperator::Add, _, List) => List,
+        (ArithmeticOperator::Add | ArithmeticOperator::Subtract, Vector, Vector) => Vector,
+        (ArithmeticOperator::Add | ArithmeticOperator::Subtract, Matrix, Matrix) => Matrix,
+        (ArithmeticOperator::Multiply, n, Vector) if n.is_numeric() => Vector,
+        (ArithmeticOperator::Multiply | ArithmeticOperator::Divide, Vector, n)
+            if n.is_numeric() =>
+        {
+            Vector
+        }
+        (ArithmeticOperator::Multiply, n, Matrix) if n.is_numeric() => Matrix,
+        (ArithmeticOperator::Multiply | ArithmeticOperator::Divide, Matrix, n)
+            if n.is_numeric() =>
+        {
+            Matrix
+        }
+        (ArithmeticOperator::Multiply, Matrix, Vector) => Vector,
+        (ArithmeticOperator::Multiply, Matrix, Matrix) => Matrix,
+        _ => Any,
+    }
+}
+
+fn indexation_type(collection: JMEType, index: JMEType) -> JMEType {
+    use JMEType::*;
+    match (collection, index) {
+        (String, _) => String,
+        (List | Vector, Range) => collection,
+        (Vector | Range, _) => Number,
+        (Matrix, Range) => Matrix,
+        (Matrix, _) => Vector,
+        _ => Any,
+    }
+}
+
+impl Expr {
+    /// The inferred type of this expression, `JMEType::Any` if it is not known
+    pub fn infer_type(&self, scope: &ValidationScope) -> JMEType {
+        self.infer(scope, &BTreeSet::new(), &mut Vec::new())
+    }
+
+    /// The type errors in this expression
+    pub fn type_errors(&self, scope: &ValidationScope) -> Vec<TypeError> {
+        let mut errors = Vec::new();
+        self.infer(scope, &BTreeSet::new(), &mut errors);
+        errors
+    }
+
+    /// Infer the type of this expression, `bound` are the names that are bound by functions like
+    /// `map`, their type is unknown.
+    fn infer(
+        &self,
+        scope: &ValidationScope,
+        bound: &BTreeSet<String>,
+        errors: &mut Vec<TypeError>,
+    ) -> JMEType {
+        match self {
+            Expr::Str(_) => JMEType::String,
+            Expr::Int(_) => JMEType::Integer,
+            Expr::Float(_, _) => JMEType::Number,
+            Expr::Bool(_) => JMEType::Boolean,
+            Expr::Constant(_) => JMEType::Number,
+            Expr::Ident(ident) => {
+                if bound.contains(&ident.name().to_lowercase()) {
+                    JMEType::Any
+                } else {
+                    scope.variable_type(ident.name())
+                }
+            }
+            Expr::Arithmetic(o, lhs, rhs) => {
+                let lhs = lhs.infer(scope, bound, errors);
+                let rhs = rhs.infer(scope, bound, errors);
+                arithmetic_type(o, lhs, rhs)
+            }
+            Expr::Range(o, lhs, rhs) => {
+                lhs.infer(scope, bound, errors);
+                rhs.infer(scope, bound, errors);
+                match o {
+                    RangeOperator::Create | RangeOperator::Step => JMEType::Range,
+                }
+            }
+            Expr::Relation(_, lhs, rhs) | Expr::Logic(_, lhs, rhs) => {
+                lhs.infer(scope, bound, errors);
+                rhs.infer(scope, bound, errors);
+                JMEType::Boolean
+            }
+            Expr::List(items) => {
+                for item in items.iter() {
+                    item.infer(scope, bound, errors);
+                }
+                JMEType::List
+            }
+            Expr::Dictionary(items) => {
+                for (_, value) in items.iter() {
+                    value.infer(scope, bound, errors);
+                }
+                JMEType::Dictionary
+            }
+            Expr::Prefix(PrefixOperator::Not, operand) => {
+                operand.infer(scope, bound, errors);
+                JMEType::Boolean
+            }
+            Expr::Prefix(PrefixOperator::Minus, operand) => operand.infer(scope, bound, errors),
+            Expr::Faculty(operand) => {
+                operand.infer(scope, bound, errors);
+                JMEType::Number
+            }
+            Expr::Indexation(collection, index) => {
+                let collection = collection.infer(scope, bound, errors);
+                let index = index.infer(scope, bound, errors);
+                indexation_type(collection, index)
+            }
+            Expr::Sequence(lhs, rhs) => {
+                lhs.infer(scope, bound, errors);
+                rhs.infer(scope, bound, errors)
+            }
+            Expr::Cast(value, target) => {
+                let value = value.infer(scope, bound, errors);
+                let target = match &**target {
+                    Expr::Str(name) => match JMEType::from_name(name) {
+                        Some(t) => t,
+                        None => {
+                            errors.push(TypeError::UnknownCastTarget(name.clone()));
+                            return JMEType::Any;
+                        }
+                    },
+                    target => {
+                        let target = target.infer(scope, bound, errors);
+                        if !target.fits(&JMEType::String) {
+                            errors.push(TypeError::InvalidCastTarget(target));
+                        }
+                        return JMEType::Any;
+                    }
+                };
+                if !value.can_cast_to(&target) {
+                    errors.push(TypeError::InvalidCast(value, target));
+                }
+                target
+            }
+            Expr::FunctionApplication(ident, arguments) => {
+                let name = ident.name().to_lowercase();
+                let mut bound = bound.clone();
+                bound.extend(bound_by_call(&name, arguments));
+                if name == "isset" {
+                    return JMEType::Boolean;
+                }
+                let argument_types: Vec<_> = arguments
+                    .iter()
+                    .map(|a| a.infer(scope, &bound, errors))
+                    .collect();
+                if scope.has_function(ident.name()) {
+                    return scope.function_type(ident.name());
+                }
+                let builtin = match BuiltinFunctions::get(ident.name())
+                    .or_else(|| BuiltinFunctions::get(&name))
+                {
+                    Some(builtin) => builtin,
+                    None => return JMEType::Any,
+                };
+                let signatures = builtin.signatures();
+                let with_amount: Vec<_> = signatures
+                    .iter()
+                    .filter(|s| s.accepts_amount(arguments.len()))
+                    .collect();
+                if with_amount.is_empty() {
+                    errors.push(TypeError::WrongArity {
+                        function: ident.name().to_string(),
+                        signatures,
+                        found: arguments.len(),
+                    });
+                    return JMEType::Any;
+                }
+                let matching: Vec<_> = with_amount
+                    .iter()
+                    .filter(|s| s.accepts(&argument_types))
+                    .collect();
+                if matching.is_empty() {
+                    errors.push(TypeError::ArgumentMismatch {
+                        function: ident.name().to_string(),
+                        signatures,
+                        arguments: argument_types,
+                    });
+                    return JMEType::Any;
+                }
+                common_output(matching.into_iter().copied())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jme::parser::{consume_one_expression, parse_as_jme};
+
+    fn parse(input: &str) -> Expr {
+        consume_one_expression(parse_as_jme(input).unwrap()).unwrap()
+    }
+
+    fn scope() -> ValidationScope {
+        let mut scope = ValidationScope::new();
+        scope.add_variable("n");
+        scope.set_variable_type("n", JMEType::Integer);
+        scope.add_variable("l");
+        scope.set_variable_type("l", JMEType::List);
+        scope.add_variable("s");
+        scope.set_variable_type("s", JMEType::String);
+        scope
+    }
+
+    #[test]
+    fn infer_types() {
+        let scope = scope();
+        let cases = vec![
+            ("1 + 2", JMEType::Integer),
+            ("n / 2", JMEType::Number),
+            ("len(l) + 1", JMEType::Integer),
+            ("s + n", JMEType::String),
+            ("l[0..2]", JMEType::List),
+            ("n < 3 and true", JMEType::Boolean),
+            ("1..n", JMEType::Range),
+            ("s as \"number\"", JMEType::Number),
+            ("map(x^2, x, l)", JMEType::Any),
+            ("unknown", JMEType::Any),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input).infer_type(&scope), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn type_errors() {
+        let scope = scope();
+        let errors = parse("len(n)").type_errors(&scope);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            TypeError::ArgumentMismatch { function, arguments, .. }
+                if function == "len" && arguments == &vec![JMEType::Integer]
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "len can't be called with (integer), expected len(string) -> integer or len(list) -> integer or len(dict) -> integer"
+        );
+
+        let errors = parse("sqrt(1, 2)").type_errors(&scope);
+        assert!(matches!(
+            &errors[..],
+            [TypeError::WrongArity { function, found: 2, .. }] if function == "sqrt"
+        ));
+
+        assert_eq!(
+            parse("n as \"foo\"").type_errors(&scope),
+            vec![TypeError::UnknownCastTarget("foo".to_string())]
+        );
+        assert_eq!(
+            parse("n as 3").type_errors(&scope),
+            vec![TypeError::InvalidCastTarget(JMEType::Integer)]
+        );
+        assert_eq!(
+            parse("true as \"number\"").type_errors(&scope),
+            vec![TypeError::InvalidCast(JMEType::Boolean, JMEType::Number)]
+        );
+    }
+
+    #[test]
+    fn no_false_positives() {
+        let mut scope = scope();
+        scope.add_function("f");
+        let inputs = vec![
+            "len(s) + len(l) + len(1..5) + len([\"a\": 1])",
+            "abs(vector(1, 2)) + abs(-3)",
+            "map(len(x), x, l)",
+            "f(1, 2, 3)",
+            "max(1, 2, 3) + max(l) + sum(l)",
+            "random(1..6) + random(1, 2, 3)",
+            "\"5\" as \"number\" + l as \"vector\" * 2",
+            "let(a, 2, b, \"x\", len(b) + a)",
+            "dpformat(n, 2)",
+            "isset(k)",
+        ];
+        for input in inputs {
+            assert_eq!(parse(input).type_errors(&scope), vec![], "{}", input);
+        }
+    }
+}
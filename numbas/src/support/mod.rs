@@ -1,3 +1,4 @@
 pub mod answer_style;
 pub mod primitive;
 pub mod serde_functions;
+pub mod student_answer;
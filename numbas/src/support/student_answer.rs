@@ -0,0 +1,107 @@
+use comparable::Comparable;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use structdoc::StructDoc;
+
+#[derive(Serialize, Deserialize, Comparable, JsonSchema, Debug, Clone, PartialEq, StructDoc)]
+#[serde(untagged)]
+/// A single value that a student enters
+pub enum AnswerValue {
+    /// An integer / whole number
+    Integer(isize),
+    /// A real number
+    Float(f64),
+    /// Any other text, e.g. a fraction or an expression
+    String(String),
+}
+
+impl std::fmt::Display for AnswerValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnswerValue::Integer(i) => write!(f, "{}", i),
+            AnswerValue::Float(fl) => write!(f, "{}", fl),
+            AnswerValue::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl AnswerValue {
+    /// The value as an index (of a choice or an answer)
+    pub fn as_index(&self) -> Option<usize> {
+        match self {
+            AnswerValue::Integer(i) if *i >= 0 => Some(*i as usize),
+            AnswerValue::Float(f) if f.fract() == 0.0 && *f >= 0.0 => Some(*f as usize),
+            AnswerValue::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Comparable, JsonSchema, Debug, Clone, PartialEq, StructDoc)]
+#[serde(untagged)]
+/// The answer that a student gives to a part
+pub enum StudentAnswer {
+    /// A single value: the answer of a number entry, pattern match or jme part or the index of the
+    /// chosen choice of a choose one part
+    Single(AnswerValue),
+    /// A list of values: the indices of the ticked choices of a choose multiple part
+    List(Vec<AnswerValue>),
+    /// A list of lists: the rows of a matrix or, for each choice of a match answers part, the
+    /// indices of the ticked answers
+    Nested(Vec<Vec<AnswerValue>>),
+}
+
+impl std::fmt::Display for StudentAnswer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |values: &Vec<AnswerValue>| {
+            format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        match self {
+            StudentAnswer::Single(v) => write!(f, "{}", v),
+            StudentAnswer::List(values) => write!(f, "{}", list(values)),
+            StudentAnswer::Nested(rows) => write!(
+                f,
+                "[{}]",
+                rows.iter().map(list).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_student_answers() {
+        let answer: StudentAnswer = serde_json::from_str("5").unwrap();
+        assert_eq!(answer, StudentAnswer::Single(AnswerValue::Integer(5)));
+        let answer: StudentAnswer = serde_json::from_str("\"x^2+1\"").unwrap();
+        assert_eq!(
+            answer,
+            StudentAnswer::Single(AnswerValue::String("x^2+1".to_string()))
+        );
+        let answer: StudentAnswer = serde_json::from_str("[0, 2]").unwrap();
+        assert_eq!(
+            answer,
+            StudentAnswer::List(vec![AnswerValue::Integer(0), AnswerValue::Integer(2)])
+        );
+        let answer: StudentAnswer = serde_json::from_str("[[1, 0.5], [\"1/2\"]]").unwrap();
+        assert_eq!(
+            answer,
+            StudentAnswer::Nested(vec![
+                vec![AnswerValue::Integer(1), AnswerValue::Float(0.5)],
+                vec![AnswerValue::String("1/2".to_string())]
+            ])
+        );
+        assert_eq!(answer.to_string(), "[[1, 0.5], [1/2]]");
+    }
+}
@@ -0,0 +1,263 @@
+use crate::cli::check::{files_from_paths, read_file};
+use crate::cli::compile::{CompilationContext, FileCompilationContext, OUTPUT_FOLDER};
+use crate::cli::deps::{dependency_graph, question_preview_path};
+use rumbas::support::cli::MessageFormat;
+use rumbas::support::default::exam_default_file_paths;
+use rumbas::support::dependency_graph::file_kind;
+use rumbas::support::rc::within_repo;
+use rumbas_support::path::RumbasPath;
+use rumbas_support::preamble::Input;
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub fn affected(exam_question_paths: Vec<String>, since: String, compile: bool) {
+    match affected_internal(exam_question_paths, since, compile) {
+        Ok(_) => (),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+fn affected_internal(
+    exam_question_paths: Vec<String>,
+    since: String,
+    compile: bool,
+) -> Result<(), ()> {
+    let root = within_repo(Path::new(".")).ok_or_else(|| {
+        log::error!("The current folder doesn't seem to belong to a rumbas project.");
+    })?;
+    let changed = changed_files(root.root(), &since).map_err(|e| {
+        log::error!(
+            "Failed to find the changes since {}: {}",
+            since,
+            e.message()
+        );
+    })?;
+    log::info!("Found {} changed files since {}.", changed.len(), since);
+
+    let files = files_from_paths(exam_question_paths)?;
+    let affected = affected_files(&root, files, &changed);
+    log::info!("{} exams and questions are affected.", affected.len());
+
+    let mut stdout = std::io::stdout().lock();
+    for file in affected.iter() {
+        writeln!(stdout, "{}", file.display()).expect("Writing to stdout");
+    }
+    std::mem::drop(stdout);
+
+    if compile && !affected.is_empty() {
+        let result = crate::cli::compile::compile_internal(
+            CompilationContext {
+                compile_paths: affected
+                    .iter()
+                    .map(|file| root.absolute().join(file).to_string_lossy().to_string())
+                    .collect(),
+                message_format: MessageFormat::Human,
+                jobs: None,
+            },
+            FileCompilationContext {
+                use_scorm: false,
+                as_zip: false,
+                minify: true,
+                force: false,
+                numbas_workers: false,
+                exam_only: None,
+                output_folder: Path::new(OUTPUT_FOLDER).to_path_buf(),
+            },
+        );
+        if result.has_failures {
+            return Err(());
+        }
+    }
+    Ok(())
+}
+
+/// The files (within the rumbas project) that differ between the revision and the working tree,
+/// the untracked files are included
+fn changed_files(root: &Path, since: &str) -> Result<HashSet<PathBuf>, git2::Error> {
+    let repo = git2::Repository::discover(root)?;
+    let workdir = repo
+        .workdir()
+        .and_then(|w| w.canonicalize().ok())
+        .ok_or_else(|| git2::Error::from_str("The git repository has no working tree"))?;
+    let tree = repo.revparse_single(since)?.peel_to_tree()?;
+    let mut options = git2::DiffOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))?;
+
+    let mut changed = HashSet::new();
+    for delta in diff.deltas() {
+        // Both paths of a renamed file are changed
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path() {
+                if let Ok(project_path) = workdir.join(path).strip_prefix(root) {
+                    changed.insert(project_path.to_path_buf());
+                }
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// The exams and questions that are changed or that depend on a changed file
+fn affected_files(
+    root: &RumbasPath,
+    files: HashSet<RumbasPath>,
+    changed: &HashSet<PathBuf>,
+) -> BTreeSet<PathBuf> {
+    let graph = dependency_graph(files.clone());
+    // Themes and resources can be folders, a change of a file in them changes the folder
+    let changed_in_graph: Vec<_> = graph
+        .files()
+        .filter(|f| changed.iter().any(|p| p.starts_with(f)))
+        .cloned()
+        .collect();
+    let dependants = graph.transitive_dependants(&changed_in_graph[..]);
+
+    // Removed default files are not in the graph, they applied to all files next to their folder
+    let default_folders: Vec<_> = changed
+        .iter()
+        .filter(|p| file_kind(p) == "default" && !graph.contains(p))
+        .filter_map(|p| p.parent().and_then(|defaults| defaults.parent()))
+        .collect();
+
+    // The previews of questions also depend on the preview template and the exam default files
+    let preview = question_preview_path(root);
+    let preview_changed = changed.contains(preview.project())
+        || preview.is_file()
+            && read_file(&preview)
+                .map(|input| {
+                    input
+                        .dependencies(&preview)
+                        .iter()
+                        .any(|d| changed.contains(d.project()))
+                })
+                .unwrap_or(false);
+
+    files
+        .into_iter()
+        .filter(|file| {
+            let project = file.project();
+            dependants.contains(project)
+                || default_folders.iter().any(|f| project.starts_with(f))
+                || (file.in_main_folder(rumbas::QUESTIONS_FOLDER)
+                    && (preview_changed
+                        || exam_default_file_paths(file.clone())
+                            .iter()
+                            .any(|d| changed.contains(d.project()))))
+        })
+        .map(|file| file.project().to_path_buf())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::{temp_folder_with_files, write_files};
+
+    #[test]
+    fn changed_files_since_revision() {
+        let folder = temp_folder_with_files(&[
+            ("outside.txt", "outside"),
+            ("project/questions/changed.yaml", "changed"),
+            ("project/questions/renamed.yaml", "renamed"),
+            ("project/questions/unchanged.yaml", "unchanged"),
+        ]);
+        let repo_root = folder.path();
+        let root = repo_root.join("project");
+        let repo = git2::Repository::init(repo_root).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("rumbas", "rumbas@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+            .unwrap();
+
+        write_files(
+            repo_root,
+            &[
+                ("outside.txt", "changed outside"),
+                ("project/questions/changed.yaml", "changed content"),
+                ("project/questions/untracked/new.yaml", "new"),
+            ],
+        );
+        std::fs::rename(
+            root.join("questions/renamed.yaml"),
+            root.join("questions/new_name.yaml"),
+        )
+        .unwrap();
+
+        let changed = changed_files(&root.canonicalize().unwrap(), "HEAD").unwrap();
+        let expected: HashSet<_> = [
+            "questions/changed.yaml",
+            "questions/renamed.yaml",
+            "questions/new_name.yaml",
+            "questions/untracked/new.yaml",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        assert_eq!(changed, expected);
+    }
+
+    #[test]
+    fn affected_by_changes() {
+        let question =
+            "---\ntype: normal\nstatement: Statement\nadvice: Advice\nparts: []\nvariables: {}\n";
+        let folder = temp_folder_with_files(&[
+            (
+                "exams/exam.yaml",
+                "---\ntype: normal\nname: Exam\nquestion_groups:\n  - name: Group\n    picking_strategy:\n      type: all_ordered\n    questions:\n      - question.yaml\n",
+            ),
+            (
+                "questions/question.yaml",
+                "---\ntype: template\ntemplate: base.yaml\nanswer: 5\n",
+            ),
+            ("questions/base.yaml", question),
+            ("questions/other.yaml", question),
+            ("questions/sub/question.yaml", question),
+        ]);
+        let root = folder.path();
+        let root_path = RumbasPath::create(root, root).unwrap();
+        let files: HashSet<_> = [
+            "exams/exam.yaml",
+            "questions/question.yaml",
+            "questions/base.yaml",
+            "questions/other.yaml",
+            "questions/sub/question.yaml",
+        ]
+        .iter()
+        .map(|f| RumbasPath::create(&root.join(f), root).unwrap())
+        .collect();
+        let affected = |changed: &[&str]| -> Vec<String> {
+            let changed = changed.iter().map(PathBuf::from).collect();
+            affected_files(&root_path, files.clone(), &changed)
+                .into_iter()
+                .map(|f| f.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        // The template of a question changes the question and the exam that uses it
+        assert_eq!(
+            affected(&["questions/base.yaml"]),
+            vec![
+                "exams/exam.yaml",
+                "questions/base.yaml",
+                "questions/question.yaml"
+            ]
+        );
+        assert_eq!(
+            affected(&["questions/other.yaml"]),
+            vec!["questions/other.yaml"]
+        );
+        // A removed default file changes the files next to its folder
+        assert_eq!(
+            affected(&["questions/sub/defaults/question.yaml"]),
+            vec!["questions/sub/question.yaml"]
+        );
+        assert!(affected(&["resources/unused.png"]).is_empty());
+    }
+}
@@ -1,11 +1,16 @@
 use rayon::prelude::*;
+use rumbas::support::cli::MessageFormat;
 use rumbas::support::dependency_manager::DEPENDENCIES;
 use rumbas::support::file_manager::CACHE;
 use rumbas::support::rc::within_repo;
+use rumbas::support::source_location::{locate_diagnostic, locator};
 use rumbas::support::to_numbas::ToNumbas;
+use rumbas_support::diagnostic::{Diagnostic, DiagnosticKind};
 use rumbas_support::path::RumbasPath;
 use rumbas_support::preamble::Input;
+use rumbas_support::rumbas_check::RumbasCheck;
 use std::collections::HashSet;
+use std::io::Write;
 use std::path::Path;
 
 pub fn find_all_files(path: RumbasPath) -> Vec<RumbasPath> {
@@ -50,28 +55,34 @@ pub fn files_from_paths(exam_question_paths: Vec<String>) -> Result<HashSet<Rumb
     Ok(files)
 }
 
-pub fn check(exam_question_paths: Vec<String>) {
-    match check_internal(exam_question_paths) {
+pub fn check(exam_question_paths: Vec<String>, message_format: MessageFormat) {
+    match check_internal(exam_question_paths, message_format) {
         Ok(_) => (),
         Err(_) => std::process::exit(1),
     }
 }
 
-pub fn check_internal(exam_question_paths: Vec<String>) -> Result<(), ()> {
+pub fn check_internal(
+    exam_question_paths: Vec<String>,
+    message_format: MessageFormat,
+) -> Result<(), ()> {
     let mut files: HashSet<_> = files_from_paths(exam_question_paths)?;
     let check_results: Vec<(CheckResult, _)> = files
         .into_par_iter()
         .map(|file| (check_file(&file), file))
         .collect();
     let check_count = check_results.len();
+    if message_format == MessageFormat::Json {
+        print_diagnostics(
+            check_results
+                .iter()
+                .flat_map(|(result, path)| result.diagnostics(path)),
+        );
+    }
 
     let failures: Vec<_> = check_results
         .par_iter()
-        .filter(|(result, _)| match result {
-            CheckResult::Partial(p) => !p.failed.is_empty(),
-            CheckResult::Template => false,
-            _ => true,
-        })
+        .filter(|(result, _)| result.has_failure())
         .collect();
     if !failures.is_empty() {
         for (check_result, path) in failures.iter() {
@@ -101,12 +112,16 @@ pub enum CheckResult {
 
 pub struct RumbasCheckData {
     failed: Vec<(String, rumbas_support::rumbas_check::RumbasCheckResult)>,
+    /// The check results of the passed locales, they only contain warnings
+    warnings: Vec<(String, rumbas_support::rumbas_check::RumbasCheckResult)>,
     passed: Vec<(
         String,
         numbas::exam::Exam,
         rumbas::exam::locale::SupportedLocale,
         String,
     )>,
+    /// The names of the question files per question group
+    question_paths: Vec<Vec<String>>,
 }
 
 impl RumbasCheckData {
@@ -118,7 +133,7 @@ impl RumbasCheckData {
                 path.display()
             );
 
-            check_result.log();
+            check_result.log_located(&locator(path));
         }
     }
     pub fn passed(
@@ -131,85 +146,158 @@ impl RumbasCheckData {
     )> {
         self.passed.clone()
     }
+    pub fn question_paths(&self) -> Vec<Vec<String>> {
+        self.question_paths.clone()
+    }
     pub fn failed(&self) -> Vec<(String, rumbas_support::rumbas_check::RumbasCheckResult)> {
         self.failed.clone()
     }
+    pub fn warnings(&self) -> Vec<(String, rumbas_support::rumbas_check::RumbasCheckResult)> {
+        self.warnings.clone()
+    }
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.failed
+            .iter()
+            .chain(self.warnings.iter())
+            .flat_map(|(locale, check_result)| {
+                check_result
+                    .diagnostics()
+                    .into_iter()
+                    .map(move |d| d.with_locale(locale.clone()))
+            })
+            .collect()
+    }
 }
 
 impl CheckResult {
+    pub fn has_failure(&self) -> bool {
+        match self {
+            Self::Partial(p) => !p.failed.is_empty(),
+            Self::Template => false,
+            _ => true,
+        }
+    }
     pub fn log(&self, path: &RumbasPath) {
         match self {
             Self::FailedParsing(e) => log::error!("{}", e),
             Self::LocalesNotSet => log::error!("Locales not set for {}!", path.display()),
-            Self::FailedInputCheck(e) => e.log(path),
+            Self::FailedInputCheck(e) => e.log_located(path, &locator(path)),
             Self::Partial(r) => r.log(path),
             Self::Template => (),
         }
     }
+    pub fn diagnostics(&self, path: &RumbasPath) -> Vec<Diagnostic> {
+        match self {
+            Self::FailedParsing(e) => {
+                vec![Diagnostic::error(DiagnosticKind::ParseError, e.to_string())]
+            }
+            Self::LocalesNotSet => vec![Diagnostic::error(
+                DiagnosticKind::LocalesNotSet,
+                "locales not set".to_string(),
+            )],
+            Self::FailedInputCheck(e) => e.diagnostics(),
+            Self::Partial(r) => r.diagnostics(),
+            Self::Template => vec![],
+        }
+        .into_iter()
+        .map(|d| locate_diagnostic(path, d))
+        .collect()
+    }
 }
 
-/// Return true if parsing is ok
-pub fn check_file(path: &RumbasPath) -> CheckResult {
-    log::info!("Checking {:?}", path.display());
-    let exam_input_result = rumbas::exam::RecursiveTemplateExamInput::from_file(path);
-    match exam_input_result {
-        Ok(mut exam_input) => {
-            exam_input.normalize(path);
+/// Print the diagnostics to stdout, one json record per line
+pub fn print_diagnostics(diagnostics: impl Iterator<Item = Diagnostic>) {
+    let mut stdout = std::io::stdout().lock();
+    for diagnostic in diagnostics {
+        writeln!(stdout, "{}", diagnostic.to_json()).expect("Writing to stdout");
+    }
+}
 
-            DEPENDENCIES.add_dependencies(path.clone(), exam_input.dependencies(path));
+/// Read the exam (or question) at the given path and load its templates, default files and the
+/// files it refers to
+pub fn read_file(
+    path: &RumbasPath,
+) -> Result<rumbas::exam::RecursiveTemplateExamInput, CheckResult> {
+    let mut exam_input = rumbas::exam::RecursiveTemplateExamInput::from_file(path)
+        .map_err(CheckResult::FailedParsing)?;
+    exam_input.normalize(path);
 
-            let exam_result = exam_input.to_normal_safe();
-            match exam_result {
-                Ok(exam) => {
-                    if exam.data.locales().is_empty() {
-                        CheckResult::LocalesNotSet
-                    } else {
-                        let mut failed_locales = Vec::new();
-                        let mut passed_locales = Vec::new();
-                        for locale_item in exam.data.locales().iter() {
-                            let locale = locale_item.name.to_owned();
-                            let numbas = exam.to_numbas_safe(&locale, &());
-                            match numbas {
-                                Ok(numbas_exam) => {
-                                    passed_locales.push((
-                                        locale,
-                                        numbas_exam,
-                                        locale_item.numbas_locale,
-                                        exam.data.numbas_settings().theme,
-                                    ));
-                                }
-                                Err(check_result) => {
-                                    failed_locales.push((locale, check_result));
-                                }
-                            }
-                        }
-                        CheckResult::Partial(RumbasCheckData {
-                            passed: passed_locales,
-                            failed: failed_locales,
-                        })
-                    }
+    DEPENDENCIES.add_dependencies(path.clone(), exam_input.dependencies(path));
+    Ok(exam_input)
+}
+
+/// Load the exam (or question) at the given path, with all templates and defaults resolved
+pub fn load_file(path: &RumbasPath) -> Result<rumbas::exam::RecursiveTemplateExam, CheckResult> {
+    let exam_input = read_file(path)?;
+    exam_input.to_normal_safe().map_err(|check_result| {
+        if check_result.is_empty_except_template_keys() {
+            let missing_template_keys = check_result.missing_template_keys();
+            if let Some(self_defined) = exam_input.self_defined_template_keys {
+                if missing_template_keys
+                    .iter()
+                    .any(|x| !self_defined.contains(&x.key))
+                {
+                    CheckResult::FailedInputCheck(check_result)
+                } else {
+                    CheckResult::Template
                 }
-                Err(check_result) => {
-                    if check_result.is_empty_except_template_keys() {
-                        let missing_template_keys = check_result.missing_template_keys();
-                        if let Some(self_defined) = exam_input.self_defined_template_keys {
-                            if missing_template_keys
-                                .iter()
-                                .any(|x| !self_defined.contains(&x.key))
-                            {
-                                CheckResult::FailedInputCheck(check_result)
-                            } else {
-                                CheckResult::Template
-                            }
-                        } else {
-                            CheckResult::FailedInputCheck(check_result)
-                        }
+            } else {
+                CheckResult::FailedInputCheck(check_result)
+            }
+        } else {
+            CheckResult::FailedInputCheck(check_result)
+        }
+    })
+}
+
+/// Return true if parsing is ok
+pub fn check_file(path: &RumbasPath) -> CheckResult {
+    log::info!("Checking {:?}", path.display());
+    match load_file(path) {
+        Ok(exam) => {
+            if exam.data.locales().is_empty() {
+                CheckResult::LocalesNotSet
+            } else {
+                let mut failed_locales = Vec::new();
+                let mut passed_locales = Vec::new();
+                let mut warnings = Vec::new();
+                for locale_item in exam.data.locales().iter() {
+                    let locale = locale_item.name.to_owned();
+                    // Same as to_numbas_safe, but the warnings are kept
+                    let check_result = exam.check(&locale);
+                    if check_result.is_empty() {
+                        check_result.log_warnings_located(&locator(path));
+                        passed_locales.push((
+                            locale.clone(),
+                            exam.to_numbas(&locale, &()),
+                            locale_item.numbas_locale,
+                            exam.data.numbas_settings().theme,
+                        ));
+                        warnings.push((locale, check_result));
                     } else {
-                        CheckResult::FailedInputCheck(check_result)
+                        failed_locales.push((locale, check_result));
                     }
                 }
+                let question_paths = exam
+                    .data
+                    .question_groups()
+                    .iter()
+                    .map(|group| {
+                        group
+                            .questions
+                            .iter()
+                            .map(|q| q.question_path.clone().unwrap_or_default())
+                            .collect()
+                    })
+                    .collect();
+                CheckResult::Partial(RumbasCheckData {
+                    passed: passed_locales,
+                    failed: failed_locales,
+                    warnings,
+                    question_paths,
+                })
             }
         }
-        Err(e) => CheckResult::FailedParsing(e),
+        Err(check_result) => check_result,
     }
 }
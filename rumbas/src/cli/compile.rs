@@ -1,7 +1,14 @@
-use crate::cli::check::CheckResult;
+use crate::cli::check::{print_diagnostics, CheckResult};
+use crate::cli::numbas_worker::{NumbasOutput, NUMBAS_WORKERS};
 use rayon::prelude::*;
+use rumbas::support::cli::MessageFormat;
+use rumbas::support::numbas_error::{parse_numbas_errors, NumbasError};
 use rumbas::support::rc::within_repo;
+use rumbas::support::source_location::{locate_diagnostic, locator};
+use rumbas_support::diagnostic::{Diagnostic, DiagnosticKind};
 use rumbas_support::path::RumbasPath;
+use rumbas_support::rumbas_check::located;
+use sha1::{Digest, Sha1};
 use std::collections::HashSet;
 use std::env;
 use std::path::Path;
@@ -14,13 +21,37 @@ pub const CACHE_FOLDER: &str = ".rumbas";
 /// The name of the local folder used for the output.
 pub const OUTPUT_FOLDER: &str = "_output";
 
-pub fn compile(compile_paths: Vec<String>, use_scorm: bool, as_zip: bool, no_minification: bool) {
+#[allow(clippy::too_many_arguments)]
+pub fn compile(
+    compile_paths: Vec<String>,
+    use_scorm: bool,
+    as_zip: bool,
+    no_minification: bool,
+    force: bool,
+    jobs: Option<usize>,
+    numbas_workers: bool,
+    exam_only: Option<PathBuf>,
+    message_format: MessageFormat,
+) {
+    // The folder is given relative to the working directory
+    let exam_only = exam_only.map(|folder| {
+        env::current_dir()
+            .map(|dir| dir.join(&folder))
+            .unwrap_or(folder)
+    });
     match compile_internal(
-        CompilationContext { compile_paths },
+        CompilationContext {
+            compile_paths,
+            message_format,
+            jobs,
+        },
         FileCompilationContext {
             use_scorm,
             as_zip,
             minify: !no_minification,
+            force,
+            numbas_workers,
+            exam_only,
             output_folder: Path::new(OUTPUT_FOLDER).to_path_buf(),
         },
     )
@@ -34,6 +65,10 @@ pub fn compile(compile_paths: Vec<String>, use_scorm: bool, as_zip: bool, no_min
 #[derive(Debug, Clone)]
 pub struct CompilationContext {
     pub compile_paths: Vec<String>,
+    pub message_format: MessageFormat,
+    /// The maximal number of exams that are compiled at the same time, the number of cpus if
+    /// not set
+    pub jobs: Option<usize>,
 }
 
 pub struct InternalCompilationResult {
@@ -71,22 +106,40 @@ pub fn compile_internal(
             };
         }
     }
-    let compile_results: Vec<(CompileResult, _)> = files
-        .into_par_iter()
-        .map(|file| (compile_file(&file_context, &file), file))
-        .collect();
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(context.jobs.unwrap_or(0))
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(e) => {
+            log::error!("Failed to create the compilation threads: {}", e);
+            return InternalCompilationResult {
+                has_failures: true,
+                created_outputs: vec![],
+            };
+        }
+    };
+    let compile_results: Vec<(CompileResult, _)> = pool.install(|| {
+        files
+            .into_par_iter()
+            .map(|file| (compile_file(&file_context, &file), file))
+            .collect()
+    });
     let compile_count = compile_results
         .iter()
         .filter(|(c, _)| !matches!(c, CompileResult::Template))
         .count();
+    if context.message_format == MessageFormat::Json {
+        print_diagnostics(
+            compile_results
+                .iter()
+                .flat_map(|(result, path)| result.diagnostics(path)),
+        );
+    }
 
     let failures: Vec<_> = compile_results
         .par_iter()
-        .filter(|(result, _)| match result {
-            CompileResult::Partial(p) => p.has_failure(),
-            CompileResult::Template => false,
-            _ => true,
-        })
+        .filter(|(result, _)| result.has_failure())
         .collect();
     let has_failures = if !failures.is_empty() {
         for (check_result, path) in failures.iter() {
@@ -136,7 +189,8 @@ pub struct PassedRumbasCompileData {
 
 pub struct RumbasCompileData {
     failed_check: Vec<(String, rumbas_support::rumbas_check::RumbasCheckResult)>,
-    failed: Vec<String>, // locale
+    warnings: Vec<(String, rumbas_support::rumbas_check::RumbasCheckResult)>,
+    failed: Vec<(String, Vec<NumbasError>)>, // locale and the errors of numbas
     passed: Vec<PassedRumbasCompileData>,
 }
 
@@ -147,6 +201,31 @@ impl RumbasCompileData {
     pub fn created_outputs(&self) -> Vec<PassedRumbasCompileData> {
         self.passed.clone()
     }
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.failed_check
+            .iter()
+            .chain(self.warnings.iter())
+            .flat_map(|(locale, check_result)| {
+                check_result
+                    .diagnostics()
+                    .into_iter()
+                    .map(move |d| d.with_locale(locale.clone()))
+            })
+            .chain(self.failed.iter().flat_map(|(locale, errors)| {
+                let diagnostics = if errors.is_empty() {
+                    vec![Diagnostic::error(
+                        DiagnosticKind::CompilationFailed,
+                        "numbas failed to compile the exam".to_string(),
+                    )]
+                } else {
+                    errors.iter().map(|e| e.diagnostic()).collect()
+                };
+                diagnostics
+                    .into_iter()
+                    .map(move |d| d.with_locale(locale.clone()))
+            }))
+            .collect()
+    }
     pub fn log(&self, path: &RumbasPath) {
         for (locale, check_result) in self.failed_check.iter() {
             log::error!(
@@ -155,9 +234,10 @@ impl RumbasCompileData {
                 path.display()
             );
 
-            check_result.log();
+            check_result.log_located(&locator(path));
         }
-        for locale in self.failed.iter() {
+        // The errors of numbas are logged when numbas fails
+        for (locale, _) in self.failed.iter() {
             log::error!(
                 "Error when compiling locale {} for {} with numbas.",
                 locale,
@@ -177,11 +257,18 @@ impl RumbasCompileData {
 }
 
 impl CompileResult {
+    pub fn has_failure(&self) -> bool {
+        match self {
+            Self::Partial(p) => p.has_failure(),
+            Self::Template => false,
+            _ => true,
+        }
+    }
     pub fn log(&self, path: &RumbasPath) {
         match self {
             Self::FailedParsing(e) => log::error!("{}", e),
             Self::LocalesNotSet => log::error!("Locales not set for {}!", path.display()),
-            Self::FailedInputCheck(e) => e.log(path),
+            Self::FailedInputCheck(e) => e.log_located(path, &locator(path)),
             Self::Partial(r) => r.log(path),
             Self::Template => log::info!(
                 "{} is a template and isn't processed for compilation.",
@@ -189,6 +276,23 @@ impl CompileResult {
             ),
         }
     }
+    pub fn diagnostics(&self, path: &RumbasPath) -> Vec<Diagnostic> {
+        match self {
+            Self::FailedParsing(e) => {
+                vec![Diagnostic::error(DiagnosticKind::ParseError, e.to_string())]
+            }
+            Self::LocalesNotSet => vec![Diagnostic::error(
+                DiagnosticKind::LocalesNotSet,
+                "locales not set".to_string(),
+            )],
+            Self::FailedInputCheck(e) => e.diagnostics(),
+            Self::Partial(r) => r.diagnostics(),
+            Self::Template => vec![],
+        }
+        .into_iter()
+        .map(|d| locate_diagnostic(path, d))
+        .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -196,6 +300,12 @@ pub struct FileCompilationContext {
     pub use_scorm: bool,
     pub as_zip: bool,
     pub minify: bool,
+    /// Run numbas, even if the output of the unchanged exam already exists
+    pub force: bool,
+    /// Run numbas in long-lived worker processes instead of starting python for each exam
+    pub numbas_workers: bool,
+    /// Only write the .exam files to this folder (per locale), numbas is not run
+    pub exam_only: Option<PathBuf>,
     pub output_folder: PathBuf,
 }
 
@@ -209,6 +319,7 @@ pub fn compile_file(context: &FileCompilationContext, path: &RumbasPath) -> Comp
         CheckResult::Partial(p) => {
             let mut passed_compilations = Vec::new();
             let mut failed_compilations = Vec::new();
+            let question_paths = p.question_paths();
             for (locale, numbas_exam, numbas_locale, theme) in p.passed() {
                 let exam_name = numbas_exam.basic_settings.name.clone();
                 let compiler = NumbasCompiler {
@@ -220,23 +331,27 @@ pub fn compile_file(context: &FileCompilationContext, path: &RumbasPath) -> Comp
                     theme,
                     exam: numbas_exam,
                     minify: context.minify,
+                    force: context.force,
+                    numbas_workers: context.numbas_workers,
+                    exam_only: context.exam_only.clone(),
+                    question_paths: question_paths.clone(),
                     output_folder: path.keep_root(context.output_folder.as_path()),
                 };
-                if compiler.compile() {
-                    passed_compilations.push(PassedRumbasCompileData {
+                match compiler.compile() {
+                    Ok(()) => passed_compilations.push(PassedRumbasCompileData {
                         locale,
-                        generated_path: compiler.output_path(),
+                        generated_path: compiler.generated_path(),
                         exam_path: path.project().to_path_buf(),
                         exam_name,
-                    })
-                } else {
-                    failed_compilations.push(locale)
+                    }),
+                    Err(errors) => failed_compilations.push((locale, errors)),
                 }
             }
             CompileResult::Partial(RumbasCompileData {
                 passed: passed_compilations,
                 failed: failed_compilations,
                 failed_check: p.failed(),
+                warnings: p.warnings(),
             })
         }
     }
@@ -250,15 +365,24 @@ pub struct NumbasCompiler {
     numbas_locale: String,
     theme: String,
     minify: bool,
+    force: bool,
+    numbas_workers: bool,
+    exam_only: Option<PathBuf>,
+    /// The names of the question files per question group, to locate the errors of numbas
+    question_paths: Vec<Vec<String>>,
     exam: numbas::exam::Exam,
     output_folder: RumbasPath,
 }
 
 impl NumbasCompiler {
-    /// Return the locale folder within the cache folder
+    /// Return the locale folder within the cache folder (or the folder for the .exam files)
     fn numbas_exam_folder(&self) -> RumbasPath {
+        let folder = self
+            .exam_only
+            .as_deref()
+            .unwrap_or_else(|| Path::new(CACHE_FOLDER));
         self.exam_path
-            .keep_root(Path::new(CACHE_FOLDER).join(&self.locale).as_path())
+            .keep_root(folder.join(&self.locale).as_path())
     }
     /// Returns the path where the numbas exam should be saved
     fn numbas_exam_path(&self) -> PathBuf {
@@ -277,6 +401,14 @@ impl NumbasCompiler {
             .with_extension(self.output_extension());
         self.locale_output_folder().join(output_file)
     }
+    /// The path of the generated exam: the output of numbas or the .exam file
+    pub fn generated_path(&self) -> PathBuf {
+        if self.exam_only.is_some() {
+            self.numbas_exam_path()
+        } else {
+            self.output_path()
+        }
+    }
     /// Return the extension of the output
     fn output_extension(&self) -> &'static str {
         if self.as_zip {
@@ -304,83 +436,452 @@ impl NumbasCompiler {
                 .expect("Failed creating folder for output");
         };
     }
-    /// Execute numbas through the python3 cli interface
-    fn execute_numbas(&self) -> std::process::Output {
+    /// Execute numbas through the python3 cli interface, in a worker if `numbas_workers` is set
+    fn execute_numbas(&self) -> std::io::Result<NumbasOutput> {
         let numbas_path = env::var(rumbas::NUMBAS_FOLDER_ENV)
-            .expect(&format!("{} to be set", rumbas::NUMBAS_FOLDER_ENV)[..]);
+            .unwrap_or_else(|_| panic!("{} to be set", rumbas::NUMBAS_FOLDER_ENV));
 
-        let mut args: Vec<&str> = vec!["-l", &self.numbas_locale[..], "-t", &self.theme[..]];
+        let mut args: Vec<String> = vec![
+            "-l".to_string(),
+            self.numbas_locale.clone(),
+            "-t".to_string(),
+            self.theme.clone(),
+        ];
 
         if self.use_scorm {
-            args.push("-s");
+            args.push("-s".to_string());
         }
         if self.as_zip {
-            args.push("-z");
+            args.push("-z".to_string());
         }
         if self.minify {
-            args.push("--minify_js");
-            args.push("uglifyjs");
+            args.push("--minify_js".to_string());
+            args.push("uglifyjs".to_string());
 
-            args.push("--minify_css");
-            args.push("uglifycss");
+            args.push("--minify_css".to_string());
+            args.push("uglifycss".to_string());
         }
 
-        args.push("-o");
-        let output_path = self.output_path();
-        args.push(output_path.to_str().unwrap());
+        args.push("-o".to_string());
+        args.push(self.output_path().to_str().unwrap().to_string());
 
-        let exam_path = self.numbas_exam_path().canonicalize().unwrap();
-        args.push(exam_path.to_str().unwrap());
+        let exam_path = self.numbas_exam_path().canonicalize()?;
+        args.push(exam_path.to_str().unwrap().to_string());
 
         log::debug!("Compile numbas with args {:?}", args.join(", "));
 
-        std::process::Command::new("python3")
-            .current_dir(numbas_path)
-            .arg("bin/numbas.py")
-            .args(&args)
-            .output()
-            .expect("failed to execute numbas process")
+        if self.numbas_workers {
+            NUMBAS_WORKERS.run(&numbas_path, &args)
+        } else {
+            std::process::Command::new("python3")
+                .current_dir(numbas_path)
+                .arg("bin/numbas.py")
+                .args(&args)
+                .output()
+                .map(NumbasOutput::from)
+        }
     }
-    /// Compile the numbas exam
-    pub fn compile(&self) -> bool {
-        self.create_folder_structure();
+    /// Returns the path of the file with the hash of the last successful numbas run for this
+    /// output
+    fn compilation_hash_path(&self) -> PathBuf {
+        let output_kind = if self.as_zip { "zip" } else { "html" };
+        self.numbas_exam_path()
+            .with_extension(format!("{}.hash", output_kind))
+    }
+    /// A hash of everything that determines the output of numbas: the generated exam, its
+    /// resources, the theme, the extensions, the locale, the options and the numbas version
+    fn compilation_hash(&self, exam_json: &str, numbas_path: &str) -> String {
+        let mut hasher = Sha1::new();
+        let output_path = self.output_path();
+        let fields = [
+            exam_json,
+            &self.numbas_locale[..],
+            &self.theme[..],
+            if self.use_scorm { "scorm" } else { "" },
+            if self.as_zip { "zip" } else { "" },
+            if self.minify { "minify" } else { "" },
+            &output_path.to_string_lossy(),
+            &numbas_version(numbas_path),
+        ];
+        for field in fields.iter() {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+        for resource in self.exam.resources.iter() {
+            hash_path(&mut hasher, Path::new(&resource.0[1]));
+        }
+        hash_path(&mut hasher, &theme_path(numbas_path, &self.theme));
+        for extension in self.exam.extensions.iter() {
+            hash_path(
+                &mut hasher,
+                &Path::new(numbas_path).join("extensions").join(extension),
+            );
+        }
+        format!("{:x}", hasher.finalize())
+    }
+    /// Whether the output of a numbas run with the same hash exists
+    fn is_up_to_date(&self, hash: &str) -> bool {
+        self.output_path().exists()
+            && std::fs::read_to_string(self.compilation_hash_path())
+                .map(|previous| previous == hash)
+                .unwrap_or(false)
+    }
+    /// Save the .exam file that is given to numbas
+    fn write_exam_file(&self, exam_json: &str) -> Result<(), Vec<NumbasError>> {
         let exam_file_path = self.numbas_exam_path();
-        let exam_write_res = self.exam.write(exam_file_path.to_str().unwrap());
-        match exam_write_res {
-            numbas::exam::WriteResult::IOError(e) => {
-                log::error!(
-                    "Failed saving the exam file {} because of {}.",
-                    exam_file_path.to_str().unwrap(),
-                    e
-                );
-                return false;
+        if let Err(e) = std::fs::write(&exam_file_path, numbas::exam::Exam::to_exam_str(exam_json))
+        {
+            let message = format!(
+                "Failed saving the exam file {} because of {}.",
+                exam_file_path.to_str().unwrap(),
+                e
+            );
+            log::error!("{}", message);
+            return Err(vec![NumbasError::new(message)]);
+        }
+        log::info!(
+            "Generated and saved exam file for exam {} with locale {}.",
+            self.exam_path.project().display(),
+            self.locale
+        );
+        Ok(())
+    }
+    /// The json of the numbas exam
+    fn exam_json(&self, exam: &numbas::exam::Exam) -> Result<String, Vec<NumbasError>> {
+        serde_json::to_string(exam).map_err(|e| {
+            let message = format!(
+                "Failed generating the exam file {} because of {}.",
+                self.numbas_exam_path().to_str().unwrap(),
+                e
+            );
+            log::error!("{}", message);
+            vec![NumbasError::new(message)]
+        })
+    }
+    /// Compile the numbas exam, the errors are logged and returned
+    pub fn compile(&self) -> Result<(), Vec<NumbasError>> {
+        if self.exam_only.is_some() {
+            // The extensions are referred to by their name, independent of the numbas folder
+            let exam_json = self.exam_json(&self.exam)?;
+            if let Some(folder) = self.numbas_exam_path().parent() {
+                std::fs::create_dir_all(folder)
+                    .expect("Failed to create the folder for the .exam file");
             }
-            numbas::exam::WriteResult::JSONError(e) => {
-                log::error!(
-                    "Failed generating the exam file {} because of {}.",
-                    exam_file_path.to_str().unwrap(),
-                    e
-                );
-                return false;
+            return self.write_exam_file(&exam_json);
+        }
+        let numbas_path = env::var(rumbas::NUMBAS_FOLDER_ENV)
+            .unwrap_or_else(|_| panic!("{} to be set", rumbas::NUMBAS_FOLDER_ENV));
+        // Numbas finds the extensions by their path in the numbas folder
+        let mut exam = self.exam.clone();
+        exam.extensions = exam
+            .extensions
+            .iter()
+            .map(|name| format!("{}/extensions/{}", numbas_path, name))
+            .collect();
+        let exam_json = self.exam_json(&exam)?;
+        let hash = self.compilation_hash(&exam_json, &numbas_path);
+        if !self.force && self.is_up_to_date(&hash) {
+            log::info!(
+                "The output of exam {} with locale {} is up to date, numbas is not run.",
+                self.exam_path.project().display(),
+                self.locale
+            );
+            return Ok(());
+        }
+        self.create_folder_structure();
+        // The output is removed, so the previous hash is no longer valid
+        std::fs::remove_file(self.compilation_hash_path()).unwrap_or(()); //If error, don't mind
+        self.write_exam_file(&exam_json)?;
+
+        let output = match self.execute_numbas() {
+            Ok(output) => output,
+            Err(e) => {
+                let message = format!("Failed to execute numbas: {}", e);
+                log::error!("{}", message);
+                return Err(vec![NumbasError::new(message)]);
             }
-            numbas::exam::WriteResult::Ok => {
-                log::info!(
-                    "Generated and saved exam file for exam {} with locale {}.",
-                    self.exam_path.project().display(),
-                    self.locale
-                );
-
-                let output = self.execute_numbas();
-                if !output.stdout.is_empty() {
-                    log::debug!("{}", std::str::from_utf8(&output.stdout).unwrap());
-                }
-                if !output.stderr.is_empty() {
-                    log::error!("Compilation failed. Use -v to see more");
-                    log::debug!("{}", std::str::from_utf8(&output.stderr).unwrap());
-                    return false;
-                }
+        };
+        if !output.stdout.is_empty() {
+            log::debug!("{}", output.stdout);
+        }
+        if !output.stderr.is_empty() {
+            log::debug!("{}", output.stderr);
+            let errors: Vec<_> = parse_numbas_errors(&output.stderr)
+                .into_iter()
+                .map(|e| e.locate(&self.exam, &self.question_paths))
+                .collect();
+            log::error!(
+                "Numbas failed to compile exam {} with locale {}:",
+                self.exam_path.project().display(),
+                self.locale
+            );
+            log_numbas_errors(&self.exam_path, &errors);
+            return Err(errors);
+        }
+        if let Err(e) = std::fs::write(self.compilation_hash_path(), hash) {
+            log::warn!(
+                "Failed saving the compilation hash for exam {}: {}",
+                self.exam_path.project().display(),
+                e
+            );
+        }
+        Ok(())
+    }
+}
+
+fn log_numbas_errors(path: &RumbasPath, errors: &[NumbasError]) {
+    let locate = locator(path);
+    for (idx, error) in errors.iter().enumerate() {
+        let location = error
+            .path
+            .as_ref()
+            .map(|p| located(&locate, p))
+            .unwrap_or_default();
+        log::error!("{}\t{}{}", idx + 1, location, error);
+    }
+}
+
+/// The folder of the theme, numbas resolves it in the same way: the theme is a path (relative to
+/// the numbas folder) or the name of one of the themes in the numbas folder
+fn theme_path(numbas_path: &str, theme: &str) -> PathBuf {
+    let path = Path::new(numbas_path).join(theme);
+    if path.exists() {
+        path
+    } else {
+        Path::new(numbas_path).join("themes").join(theme)
+    }
+}
+
+/// Identifies the numbas installation: the commit of its git repository and the uncommitted
+/// changes or, if it is not a git repository (e.g. in the docker image), the modification time of
+/// numbas.py
+fn numbas_version(numbas_path: &str) -> String {
+    git2::Repository::open(numbas_path)
+        .ok()
+        .and_then(|repo| {
+            let commit = repo.head().ok()?.peel_to_commit().ok()?;
+            let mut options = git2::DiffOptions::new();
+            options
+                .include_untracked(true)
+                .recurse_untracked_dirs(true)
+                .show_untracked_content(true)
+                .show_binary(true);
+            let diff = repo
+                .diff_tree_to_workdir_with_index(Some(&commit.tree().ok()?), Some(&mut options))
+                .ok()?;
+            let mut changes = Sha1::new();
+            diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+                changes.update([line.origin() as u8]);
+                changes.update(line.content());
+                true
+            })
+            .ok()?;
+            Some(format!("{} {:x}", commit.id(), changes.finalize()))
+        })
+        .or_else(|| {
+            let modified = std::fs::metadata(Path::new(numbas_path).join("bin/numbas.py"))
+                .ok()?
+                .modified()
+                .ok()?;
+            Some(format!("{:?}", modified))
+        })
+        .unwrap_or_default()
+}
+
+/// Add the content of the file, or of all files in the folder, to the hash
+fn hash_path(hasher: &mut Sha1, path: &Path) {
+    if path.is_file() {
+        if let Ok(content) = std::fs::read(path) {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(content);
+        }
+    } else if path.is_dir() {
+        if let Ok(entries) = std::fs::read_dir(path) {
+            let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+            entries.sort();
+            for entry in entries {
+                hash_path(hasher, &entry);
             }
         }
-        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::temp_folder_with_files;
+
+    #[test]
+    fn compilation_hash_changes_with_each_input() {
+        let folder = temp_folder_with_files(&[
+            ("numbas/bin/numbas.py", "numbas"),
+            ("numbas/themes/theme/style.css", "style"),
+            ("numbas/custom_theme/style.css", "custom style"),
+            ("numbas/extensions/stats/stats.js", "stats"),
+            ("resources/image.png", "image"),
+        ]);
+        let root = folder.path();
+        let numbas_path = root.join("numbas");
+        let resource = root.join("resources/image.png");
+        let exam_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../cli-tests/numbas_exams/exam-110396-getting-started.exam");
+        let mut exam =
+            numbas::exam::Exam::from_exam_str(&std::fs::read_to_string(exam_file).unwrap())
+                .unwrap();
+        exam.resources = vec![numbas::question::resource::Resource([
+            "image.png".to_string(),
+            resource.to_string_lossy().to_string(),
+        ])];
+        exam.extensions = vec!["stats".to_string()];
+        let compiler = || NumbasCompiler {
+            use_scorm: false,
+            as_zip: false,
+            exam_path: RumbasPath::create(&root.join("exams/exam.yaml"), root).unwrap(),
+            locale: "en".to_string(),
+            numbas_locale: "en-GB".to_string(),
+            theme: "theme".to_string(),
+            minify: false,
+            force: false,
+            numbas_workers: false,
+            exam_only: None,
+            question_paths: Vec::new(),
+            exam: exam.clone(),
+            output_folder: RumbasPath::create(&root.join(OUTPUT_FOLDER), root).unwrap(),
+        };
+        let numbas = numbas_path.to_string_lossy().to_string();
+        let hash = |compiler: NumbasCompiler| compiler.compilation_hash("{}", &numbas);
+
+        let original = hash(compiler());
+        assert_eq!(hash(compiler()), original);
+        // The options that don't change the output of numbas don't change the hash
+        assert_eq!(
+            hash(NumbasCompiler {
+                force: true,
+                numbas_workers: true,
+                ..compiler()
+            }),
+            original
+        );
+
+        let changed = vec![
+            compiler().compilation_hash("{\"name\": \"other\"}", &numbas),
+            hash(NumbasCompiler {
+                use_scorm: true,
+                ..compiler()
+            }),
+            hash(NumbasCompiler {
+                as_zip: true,
+                ..compiler()
+            }),
+            hash(NumbasCompiler {
+                minify: true,
+                ..compiler()
+            }),
+            hash(NumbasCompiler {
+                locale: "nl".to_string(),
+                ..compiler()
+            }),
+            hash(NumbasCompiler {
+                numbas_locale: "nl-NL".to_string(),
+                ..compiler()
+            }),
+            hash(NumbasCompiler {
+                theme: "other_theme".to_string(),
+                ..compiler()
+            }),
+            hash(NumbasCompiler {
+                output_folder: RumbasPath::create(&root.join("other_output"), root).unwrap(),
+                ..compiler()
+            }),
+            {
+                std::fs::write(&resource, "changed image").unwrap();
+                hash(compiler())
+            },
+            {
+                std::fs::write(numbas_path.join("themes/theme/style.css"), "changed style")
+                    .unwrap();
+                hash(compiler())
+            },
+            hash(NumbasCompiler {
+                theme: "custom_theme".to_string(),
+                ..compiler()
+            }),
+            {
+                std::fs::write(numbas_path.join("custom_theme/style.css"), "changed").unwrap();
+                hash(NumbasCompiler {
+                    theme: "custom_theme".to_string(),
+                    ..compiler()
+                })
+            },
+            {
+                std::fs::write(numbas_path.join("extensions/stats/stats.js"), "changed").unwrap();
+                hash(compiler())
+            },
+            {
+                std::fs::File::options()
+                    .write(true)
+                    .open(numbas_path.join("bin/numbas.py"))
+                    .unwrap()
+                    .set_modified(std::time::SystemTime::UNIX_EPOCH)
+                    .unwrap();
+                hash(compiler())
+            },
+            {
+                let repo = git2::Repository::init(&numbas_path).unwrap();
+                let mut index = repo.index().unwrap();
+                index.add_path(Path::new("bin/numbas.py")).unwrap();
+                index.write().unwrap();
+                let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+                let signature = git2::Signature::now("rumbas", "rumbas@example.com").unwrap();
+                repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+                    .unwrap();
+                hash(compiler())
+            },
+            {
+                std::fs::write(numbas_path.join("bin/numbas.py"), "changed numbas").unwrap();
+                hash(compiler())
+            },
+            {
+                std::fs::write(numbas_path.join("bin/untracked.py"), "untracked").unwrap();
+                hash(compiler())
+            },
+        ];
+        for (index, changed_hash) in changed.iter().enumerate() {
+            assert_ne!(changed_hash, &original, "change {} keeps the hash", index);
+        }
+        let distinct: HashSet<_> = changed.iter().collect();
+        assert_eq!(distinct.len(), changed.len());
+    }
+
+    #[test]
+    fn exam_only_output_refers_to_extension_names() {
+        let folder = tempfile::tempdir().unwrap();
+        let root = folder.path();
+        let exam_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../cli-tests/numbas_exams/exam-110396-getting-started.exam");
+        let mut exam =
+            numbas::exam::Exam::from_exam_str(&std::fs::read_to_string(exam_file).unwrap())
+                .unwrap();
+        exam.extensions = vec!["jsxgraph".to_string(), "stats".to_string()];
+        let compiler = NumbasCompiler {
+            use_scorm: false,
+            as_zip: false,
+            exam_path: RumbasPath::create(&root.join("exams/exam.yaml"), root).unwrap(),
+            locale: "en".to_string(),
+            numbas_locale: "en-GB".to_string(),
+            theme: "default".to_string(),
+            minify: false,
+            force: false,
+            numbas_workers: false,
+            exam_only: Some(PathBuf::from("exam_files")),
+            question_paths: Vec::new(),
+            exam,
+            output_folder: RumbasPath::create(&root.join(OUTPUT_FOLDER), root).unwrap(),
+        };
+        compiler.compile().unwrap();
+
+        let written = std::fs::read_to_string(root.join("exam_files/en/exams/exam.exam")).unwrap();
+        let written = numbas::exam::Exam::from_exam_str(&written).unwrap();
+        assert_eq!(written.extensions, vec!["jsxgraph", "stats"]);
     }
 }
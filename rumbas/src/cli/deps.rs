@@ -0,0 +1,334 @@
+use crate::cli::check::{files_from_paths, read_file};
+use rayon::prelude::*;
+use rumbas::exam::question_group::{QuestionFromTemplateInput, QuestionPathOrTemplate};
+use rumbas::support::cli::DependencyGraphFormat;
+use rumbas::support::default::{exam_default_file_paths, question_default_file_paths};
+use rumbas::support::dependency_graph::{DependencyGraph, DependencyKind};
+use rumbas::support::file_manager::{QuestionFileToRead, CACHE};
+use rumbas::support::rc::within_repo;
+use rumbas_support::path::RumbasPath;
+use rumbas_support::preamble::Input;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub fn deps(
+    exam_question_paths: Vec<String>,
+    format: DependencyGraphFormat,
+    dependants_of: Vec<String>,
+    dependencies_of: Vec<String>,
+    kinds: Vec<DependencyKind>,
+) {
+    match deps_internal(
+        exam_question_paths,
+        format,
+        dependants_of,
+        dependencies_of,
+        kinds,
+    ) {
+        Ok(_) => (),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+fn deps_internal(
+    exam_question_paths: Vec<String>,
+    format: DependencyGraphFormat,
+    dependants_of: Vec<String>,
+    dependencies_of: Vec<String>,
+    kinds: Vec<DependencyKind>,
+) -> Result<(), ()> {
+    let dependants_of = project_paths(dependants_of)?;
+    let dependencies_of = project_paths(dependencies_of)?;
+    let files = files_from_paths(exam_question_paths)?;
+
+    let mut graph = dependency_graph(files);
+    if !dependants_of.is_empty() {
+        graph = graph.restrict(&graph.transitive_dependants(&dependants_of[..]));
+    }
+    if !dependencies_of.is_empty() {
+        graph = graph.restrict(&graph.transitive_dependencies(&dependencies_of[..]));
+    }
+    if !kinds.is_empty() {
+        graph = graph.with_kinds(&kinds[..]);
+    }
+
+    let output = match format {
+        DependencyGraphFormat::Json => format!("{}\n", graph.to_json()),
+        DependencyGraphFormat::Dot => graph.to_dot(),
+    };
+    write!(std::io::stdout().lock(), "{}", output).expect("Writing to stdout");
+    Ok(())
+}
+
+/// The paths within the project of the given files
+fn project_paths(paths: Vec<String>) -> Result<Vec<PathBuf>, ()> {
+    paths
+        .iter()
+        .map(|path| match within_repo(Path::new(path)) {
+            Some(p) => Ok(p.project().to_path_buf()),
+            None => {
+                log::error!("{:?} doesn't seem to belong to a rumbas project.", path);
+                Err(())
+            }
+        })
+        .collect()
+}
+
+/// The files that a file depends on
+#[derive(Default)]
+struct ScannedFile {
+    /// All files that it depends on, also the files that its questions and templates depend on
+    dependencies: HashSet<RumbasPath>,
+    /// The default files and theme, these only apply to the file itself
+    own_dependencies: Vec<(RumbasPath, DependencyKind)>,
+    /// The question files of an exam, its other dependencies in the questions folder are
+    /// templates. Only known if all templates of the exam are resolved.
+    questions: Option<HashSet<RumbasPath>>,
+}
+
+/// Exams and questions can depend on other exams and questions (as templates or questions), these
+/// are scanned as well
+fn is_exam_or_question(path: &RumbasPath) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("yaml")
+        && (path.in_main_folder(rumbas::EXAMS_FOLDER)
+            || path.in_main_folder(rumbas::QUESTIONS_FOLDER))
+        && path.is_file()
+}
+
+/// Build the dependency graph of the given exams and questions, and of the templates and
+/// questions that they use. Each file is only linked to the files it depends on directly: an exam
+/// depends on the default files of a question through the question.
+pub fn dependency_graph(files: HashSet<RumbasPath>) -> DependencyGraph {
+    let mut scanned: HashMap<RumbasPath, ScannedFile> = HashMap::new();
+    let mut todo: Vec<RumbasPath> = files.into_iter().collect();
+    while !todo.is_empty() {
+        let new_files: Vec<_> = todo
+            .into_par_iter()
+            .map(|file| {
+                let scanned_file = scan_file(&file);
+                (file, scanned_file)
+            })
+            .collect();
+        let mut next: HashSet<RumbasPath> = HashSet::new();
+        for (file, scanned_file) in new_files {
+            next.extend(
+                scanned_file
+                    .dependencies
+                    .iter()
+                    .filter(|d| !scanned.contains_key(*d) && is_exam_or_question(d))
+                    .cloned(),
+            );
+            scanned.insert(file, scanned_file);
+        }
+        todo = next
+            .into_iter()
+            .filter(|d| !scanned.contains_key(d))
+            .collect();
+    }
+
+    let mut graph = DependencyGraph::default();
+    for (file, scanned_file) in scanned.iter() {
+        graph.add_file(file.project().to_path_buf());
+        let indirect: HashSet<_> = scanned_file
+            .dependencies
+            .iter()
+            .filter_map(|dependency| scanned.get(dependency))
+            .flat_map(|dependency| dependency.dependencies.iter())
+            .collect();
+        for dependency in scanned_file
+            .dependencies
+            .iter()
+            .filter(|d| !indirect.contains(d))
+        {
+            let kind = match (
+                DependencyKind::of(file, dependency),
+                &scanned_file.questions,
+            ) {
+                (DependencyKind::Question, Some(questions)) if !questions.contains(dependency) => {
+                    DependencyKind::Template
+                }
+                (kind, _) => kind,
+            };
+            graph.add_dependency(
+                file.project().to_path_buf(),
+                dependency.project().to_path_buf(),
+                kind,
+            );
+        }
+        for (dependency, kind) in scanned_file.own_dependencies.iter() {
+            graph.add_dependency(
+                file.project().to_path_buf(),
+                dependency.project().to_path_buf(),
+                *kind,
+            );
+        }
+    }
+    log::info!(
+        "Found the dependencies of {} exams and questions.",
+        scanned.len()
+    );
+    graph
+}
+
+/// Scan a question without its preview: the question is read as it would be read by an exam
+fn scan_question(
+    path: &RumbasPath,
+    own_dependencies: Vec<(RumbasPath, DependencyKind)>,
+) -> ScannedFile {
+    let question_name = path
+        .project()
+        .with_extension("")
+        .strip_prefix(rumbas::QUESTIONS_FOLDER)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut question_input: QuestionFromTemplateInput =
+        QuestionPathOrTemplate::QuestionPath(question_name).into();
+    loop {
+        let files_to_load = question_input.files_to_load(path);
+        if files_to_load.is_empty() {
+            break;
+        }
+        let loaded_files = CACHE.read_files(files_to_load);
+        question_input.insert_loaded_files(path, &loaded_files);
+    }
+    if let Some(error_message) = question_input.error_message.as_ref() {
+        log::warn!(
+            "The dependencies of {} are unknown: {}",
+            path.display(),
+            error_message
+        );
+    }
+    let mut dependencies = question_input.dependencies(path);
+    dependencies.remove(path);
+    if let Ok(question) = question_input.to_normal_safe() {
+        for resource in question.data.resources {
+            dependencies.insert(path.keep_root(&resource.resource_path));
+        }
+    }
+    ScannedFile {
+        dependencies,
+        own_dependencies,
+        // The files in the questions folder that a question depends on are templates
+        questions: Some(HashSet::new()),
+    }
+}
+
+/// The template that is used to compile the previews of questions
+pub fn question_preview_path(path: &RumbasPath) -> RumbasPath {
+    path.keep_root(
+        &Path::new(rumbas::EXAMS_FOLDER)
+            .join(rumbas::QUESTION_PREVIEW_TEMPLATE_NAME)
+            .with_extension("yaml"),
+    )
+}
+
+fn scan_file(path: &RumbasPath) -> ScannedFile {
+    log::debug!("Scanning the dependencies of {}", path.display());
+    let is_question = path.in_main_folder(rumbas::QUESTIONS_FOLDER);
+    let mut own_dependencies: Vec<_> = if is_question {
+        question_default_file_paths(path.clone())
+    } else {
+        exam_default_file_paths(path.clone())
+    }
+    .into_iter()
+    .map(|p| (p, DependencyKind::Default))
+    .collect();
+
+    let preview = question_preview_path(path);
+    // Questions are read as their preview, or on their own if there is no preview template
+    if is_question && !preview.is_file() {
+        return scan_question(path, own_dependencies);
+    }
+    let exam_input = match read_file(path) {
+        Ok(exam_input) => exam_input,
+        Err(check_result) => {
+            log::warn!("The dependencies of {} are unknown:", path.display());
+            check_result.log(path);
+            return ScannedFile {
+                own_dependencies,
+                ..Default::default()
+            };
+        }
+    };
+    let mut dependencies = exam_input.dependencies(path);
+    dependencies.remove(path);
+
+    if is_question {
+        // The preview template isn't a dependency of the question itself
+        if let Ok(preview_input) = read_file(&preview) {
+            for dependency in preview_input.dependencies(&preview) {
+                dependencies.remove(&dependency);
+            }
+        }
+        dependencies.remove(&preview);
+    }
+
+    // Resources, themes and questions are only known when all templates are resolved
+    let mut questions = None;
+    if let Ok(exam) = exam_input.to_normal_safe() {
+        let mut question_files = HashSet::new();
+        for group in exam.data.question_groups() {
+            for question in group.questions {
+                // The question is the first template if the exam fills in its template keys
+                let question_path = question.question_path.clone().or_else(|| {
+                    question
+                        .template_data
+                        .first()
+                        .map(|t| t.relative_template_path.clone())
+                });
+                if let Some(question_path) = question_path {
+                    question_files
+                        .insert(QuestionFileToRead::with_file_name(question_path, path).into());
+                }
+                for resource in question.data.resources {
+                    dependencies.insert(path.keep_root(&resource.resource_path));
+                }
+            }
+        }
+        if !is_question {
+            let theme = path.keep_root(
+                &Path::new(rumbas::THEMES_FOLDER).join(exam.data.numbas_settings().theme),
+            );
+            // The other themes are part of numbas
+            if theme.is_dir() {
+                own_dependencies.push((theme, DependencyKind::Theme));
+            }
+        }
+        questions = Some(question_files);
+    }
+    ScannedFile {
+        dependencies,
+        own_dependencies,
+        questions,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::temp_folder_with_files;
+
+    #[test]
+    fn question_without_preview() {
+        let folder = temp_folder_with_files(&[
+            (
+                "questions/question.yaml",
+                "---\ntype: template\ntemplate: base.yaml\nanswer: 5\n",
+            ),
+            (
+                "questions/base.yaml",
+                "---\ntype: normal\nstatement: Statement\nadvice: Advice\nparts: []\nvariables: {}\n",
+            ),
+        ]);
+        let root = folder.path();
+        let question = RumbasPath::create(&root.join("questions/question.yaml"), root).unwrap();
+        let graph = dependency_graph(vec![question].into_iter().collect());
+        assert_eq!(
+            graph
+                .dependencies(Path::new("questions/question.yaml"))
+                .get(Path::new("questions/base.yaml")),
+            Some(&DependencyKind::Template)
+        );
+    }
+}
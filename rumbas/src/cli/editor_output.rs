@@ -2,6 +2,7 @@ use super::compile::PassedRumbasCompileData;
 use super::compile::{
     compile_internal, CompilationContext, FileCompilationContext, InternalCompilationResult,
 };
+use rumbas::support::cli::MessageFormat;
 use rumbas::support::rc::find_root;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -88,22 +89,34 @@ pub fn create_editor_output_internal(context: EditorOutputContext) -> Result<(),
     let scorm_compilation_result = compile_internal(
         CompilationContext {
             compile_paths: compile_paths.clone(),
+            message_format: MessageFormat::Human,
+            jobs: None,
         },
         FileCompilationContext {
             use_scorm: true,
             as_zip: true,
             minify: true,
+            force: false,
+            numbas_workers: false,
+            exam_only: None,
             output_folder: context.output_path.clone(),
         },
     );
 
     println!("Compiling (preview) exam html-outputs.");
     let folder_compilation_result = compile_internal(
-        CompilationContext { compile_paths },
+        CompilationContext {
+            compile_paths,
+            message_format: MessageFormat::Human,
+            jobs: None,
+        },
         FileCompilationContext {
             use_scorm: false,
             as_zip: false,
             minify: false,
+            force: false,
+            numbas_workers: false,
+            exam_only: None,
             output_folder: context.output_path.clone(),
         },
     );
@@ -1,24 +1,49 @@
 use super::check;
 use rayon::prelude::*;
+use regex::Regex;
+use rumbas::support::cli::MessageFormat;
 use rumbas::support::file_manager::CACHE;
+use rumbas::support::source_location::{locate, SourceMap};
+use rumbas_support::diagnostic::{Diagnostic, DiagnosticKind};
 use rumbas_support::path::RumbasPath;
 use rumbas_support::preamble::FileToLoad;
-use std::collections::HashSet;
+use rumbas_support::rumbas_check::RumbasCheck;
+use std::collections::{HashMap, HashSet};
 use yaml_subset::yaml::parse_yaml_file;
 
-pub fn fmt(exam_question_paths: Vec<String>) {
-    match fmt_internal(exam_question_paths) {
+lazy_static::lazy_static! {
+    /// Splits a line of a yaml file into its indentation (with the dashes of list items), its
+    /// key and its value
+    static ref YAML_LINE: Regex = Regex::new(
+        r##"^(?P<prefix>(?P<indent>\s*(?:- )*)(?:(?P<key>[^\s'"#][^:#]*?|'[^']*'|"[^"]*"):(?:\s+|$))?)(?P<value>.*)$"##,
+    )
+    .unwrap();
+}
+
+pub fn fmt(exam_question_paths: Vec<String>, jme: bool, message_format: MessageFormat) {
+    match fmt_internal(exam_question_paths, jme, message_format) {
         Ok(_) => (),
         Err(_) => std::process::exit(1),
     }
 }
 
-pub fn fmt_internal(exam_question_paths: Vec<String>) -> Result<(), ()> {
+pub fn fmt_internal(
+    exam_question_paths: Vec<String>,
+    jme: bool,
+    message_format: MessageFormat,
+) -> Result<(), ()> {
     let files: HashSet<_> = check::files_from_paths(exam_question_paths)?;
     let check_results: Vec<(RumbasFormatResult, _)> = files
         .into_par_iter()
-        .map(|file| (format_file(&file), file))
+        .map(|file| (format_file(&file, jme), file))
         .collect();
+    if message_format == MessageFormat::Json {
+        check::print_diagnostics(
+            check_results
+                .iter()
+                .flat_map(|(result, path)| result.diagnostic(path)),
+        );
+    }
 
     let failures: Vec<_> = check_results
         .par_iter()
@@ -48,23 +73,33 @@ pub enum RumbasFormatResult {
 }
 
 impl RumbasFormatResult {
+    fn message(&self) -> &'static str {
+        match self {
+            Self::FailedReadingFile => "Can't read the file.",
+            Self::FailedParsingYaml => "Can't parse the file.",
+            Self::NotFormattableFile => "File can't be formatted.",
+            Self::FailedConvertingToYamlString => "Failed generating the formatted yaml string.",
+            Self::FailedWritingFile => "Formatted yaml can't be written to file.",
+            Self::Ok => "Formatting worked.",
+        }
+    }
     pub fn log(&self, path: &RumbasPath) {
         log::error!("Error when processing {}.", path.display());
+        log::error!("{}", self.message());
+    }
+    pub fn diagnostic(&self, path: &RumbasPath) -> Option<Diagnostic> {
         match self {
-            Self::FailedReadingFile => log::error!("Can't read the file."),
-            Self::FailedParsingYaml => log::error!("Can't parse the file."),
-            Self::NotFormattableFile => log::error!("File can't be formatted."),
-
-            Self::FailedConvertingToYamlString => {
-                log::error!("Failed generating the formatted yaml string.")
-            }
-            Self::FailedWritingFile => log::error!("Formatted yaml can't be written to file."),
-            Self::Ok => log::error!("Formatting worked."),
+            Self::Ok => None,
+            _ => Some(
+                Diagnostic::error(DiagnosticKind::FormatFailed, self.message().to_string())
+                    .with_file(path.display().to_string()),
+            ),
         }
     }
 }
 
-pub fn format_file(path: &RumbasPath) -> RumbasFormatResult {
+/// Format a file, if `jme` is true, the jme strings are also normalized
+pub fn format_file(path: &RumbasPath, jme: bool) -> RumbasFormatResult {
     log::info!("Formatting {:?}", path.display());
     match CACHE.read_file(FileToLoad {
         file_path: path.clone(),
@@ -91,7 +126,15 @@ pub fn format_file(path: &RumbasPath) -> RumbasFormatResult {
                         }*/
                         let dump_res = yaml.format();
                         match dump_res {
-                            Ok(res) => match std::fs::write(path, res) {
+                            Ok(res) => match std::fs::write(
+                                path,
+                                if jme {
+                                    let replacements = jme_replacements(path, &n.content, &res);
+                                    normalize_jme_strings(&res, &replacements)
+                                } else {
+                                    res
+                                },
+                            ) {
                                 Ok(_) => RumbasFormatResult::Ok,
                                 Err(_) => RumbasFormatResult::FailedWritingFile,
                             },
@@ -109,3 +152,255 @@ pub fn format_file(path: &RumbasPath) -> RumbasFormatResult {
         None => RumbasFormatResult::FailedReadingFile,
     }
 }
+
+/// Find the jme strings of the file that are not in their canonical form
+///
+/// The keys are the lines of the formatted yaml where the strings are written (starting at 1),
+/// the values the original and formatted strings. The strings of other files (question files,
+/// templates and default files) are normalized when those files are formatted.
+fn jme_replacements(
+    path: &RumbasPath,
+    original_yaml: &str,
+    formatted_yaml: &str,
+) -> HashMap<usize, (String, String)> {
+    let mut replacements = HashMap::new();
+    let exam = match check::load_file(path) {
+        Ok(exam) => exam,
+        Err(_) => {
+            log::debug!(
+                "Can't load {:?}, the jme strings are not normalized.",
+                path.display()
+            );
+            return replacements;
+        }
+    };
+    let original_map = SourceMap::from_yaml(original_yaml);
+    let formatted_map = SourceMap::from_yaml(formatted_yaml);
+    for locale_item in exam.data.locales().iter() {
+        for unformatted in exam
+            .check_jme_formatting(&locale_item.name)
+            .unformatted_jme()
+        {
+            let line = locate(path, &unformatted.path().segments())
+                .filter(|location| location.file == *path)
+                .and_then(|location| original_map.path_at(location.line, location.column))
+                .and_then(|yaml_path| formatted_map.get(&yaml_path));
+            if let Some((line, _)) = line {
+                replacements.insert(
+                    line,
+                    (
+                        unformatted.original().to_string(),
+                        unformatted.formatted().to_string(),
+                    ),
+                );
+            }
+        }
+    }
+    replacements
+}
+
+/// The amount of leading spaces of a line
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Parse the value of a yaml scalar, returns None if it is not a string
+fn parse_yaml_string(scalar: &str) -> Option<String> {
+    match serde_yaml::from_str(&format!("v: {}", scalar)) {
+        Ok(serde_yaml::Value::Mapping(m)) => m
+            .get(&serde_yaml::Value::String("v".to_string()))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string()),
+        _ => None,
+    }
+}
+
+/// Create the yaml lines for a string value that starts with `prefix`
+fn yaml_string_lines(prefix: &str, value: &str, content_indentation: usize) -> Vec<String> {
+    if value.contains('\n') {
+        let header = if value.ends_with('\n') { "|" } else { "|-" };
+        let mut lines = vec![format!("{}{}", prefix, header)];
+        for line in value.trim_end_matches('\n').split('\n') {
+            if line.is_empty() {
+                lines.push(String::new());
+            } else {
+                lines.push(format!("{}{}", " ".repeat(content_indentation), line));
+            }
+        }
+        lines
+    } else if parse_yaml_string(value).as_deref() == Some(value) {
+        vec![format!("{}{}", prefix, value)]
+    } else {
+        vec![format!("{}'{}'", prefix, value.replace('\'', "''"))]
+    }
+}
+
+/// Replace the string values in a yaml file, based on a map from the line of a value to its
+/// original and new value. A value is only replaced if it still equals the original value.
+///
+/// Values with comments and flow collections are kept as they are.
+fn normalize_jme_strings(yaml: &str, replacements: &HashMap<usize, (String, String)>) -> String {
+    if replacements.is_empty() {
+        return yaml.to_string();
+    }
+    let replacement = |line_nb: usize, value: Option<String>| {
+        replacements
+            .get(&line_nb)
+            .filter(|(original, _)| value.as_ref() == Some(original))
+            .map(|(_, formatted)| formatted)
+    };
+    let lines: Vec<_> = yaml.lines().collect();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let line_nb = i + 1;
+        i += 1;
+        let captures = match YAML_LINE.captures(line) {
+            Some(c) => c,
+            None => {
+                result.push(line.to_string());
+                continue;
+            }
+        };
+        let prefix = captures.name("prefix").unwrap().as_str();
+        let indent = captures.name("indent").unwrap().as_str();
+        let value = captures.name("value").unwrap().as_str();
+        let is_item = captures.name("key").is_some() || indent.trim_end().ends_with('-');
+        if !is_item || value.is_empty() || value.contains(" #") {
+            result.push(line.to_string());
+            continue;
+        }
+        // Values of keys are more indented than the key, items of lists more than the dash
+        let parent_indentation = if captures.name("key").is_some() {
+            indent.len()
+        } else {
+            indent.len() - 2
+        };
+        let content_indentation = parent_indentation + 2;
+        if value.starts_with('|') || value.starts_with('>') {
+            let mut end = i;
+            while end < lines.len()
+                && (lines[end].trim().is_empty() || indentation(lines[end]) > parent_indentation)
+            {
+                end += 1;
+            }
+            while end > i && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            let block = &lines[i..end];
+            let block_indentation = block
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| indentation(l))
+                .min()
+                .unwrap_or(content_indentation);
+            let scalar: String = std::iter::once(value.to_string())
+                .chain(
+                    block
+                        .iter()
+                        .map(|l| format!("  {}", l.get(block_indentation..).unwrap_or_default())),
+                )
+                .map(|l| format!("{}\n", l))
+                .collect();
+            let replacement = if value[1..].chars().any(|c| c.is_ascii_digit()) {
+                None // Explicit indentation indicators are kept as they are
+            } else {
+                replacement(line_nb, parse_yaml_string(&scalar))
+            };
+            match replacement {
+                Some(formatted) => {
+                    result.extend(yaml_string_lines(prefix, formatted, block_indentation));
+                }
+                None => {
+                    result.push(line.to_string());
+                    result.extend(block.iter().map(|l| l.to_string()));
+                }
+            }
+            i = end;
+        } else {
+            let is_multiline = lines
+                .get(i)
+                .map(|l| !l.trim().is_empty() && indentation(l) > parent_indentation)
+                .unwrap_or(false);
+            let is_flow = value.starts_with('[') || value.starts_with('{');
+            let replacement = if is_multiline || is_flow {
+                None
+            } else {
+                replacement(line_nb, parse_yaml_string(value))
+            };
+            match replacement {
+                Some(formatted) => {
+                    result.extend(yaml_string_lines(prefix, formatted, content_indentation))
+                }
+                None => result.push(line.to_string()),
+            }
+        }
+    }
+    let mut res = result.join("\n");
+    if yaml.ends_with('\n') {
+        res.push('\n');
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn replacements(lines: &[(usize, &str, &str)]) -> HashMap<usize, (String, String)> {
+        lines
+            .iter()
+            .map(|(line, original, formatted)| {
+                (*line, (original.to_string(), formatted.to_string()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_line_values() {
+        let yaml = "variables:\n  a: x+ 1\n  b: '(a*b)'\n  c: x+ 1 # comment\nlist:\n  - (a*b)\n  - other\n";
+        let replacements = replacements(&[
+            (2, "x+ 1", "x + 1"),
+            (3, "(a*b)", "a * b"),
+            (4, "x+ 1", "x + 1"),
+            (6, "(a*b)", "a * b"),
+        ]);
+        assert_eq!(
+            normalize_jme_strings(yaml, &replacements),
+            "variables:\n  a: x + 1\n  b: a * b\n  c: x+ 1 # comment\nlist:\n  - a * b\n  - other\n"
+        );
+    }
+
+    #[test]
+    fn only_replaced_at_their_line() {
+        // The name has the same text as the jme string, but it is not a jme string
+        let yaml = "name: x+ 1\nvariables:\n  a: x+ 1\n  b: y\n";
+        let replacements = replacements(&[(3, "x+ 1", "x + 1"), (4, "x+ 1", "x + 1")]);
+        assert_eq!(
+            normalize_jme_strings(yaml, &replacements),
+            "name: x+ 1\nvariables:\n  a: x + 1\n  b: y\n"
+        );
+    }
+
+    #[test]
+    fn quoted_values() {
+        let yaml = "statement: \"{ x+1 }\"\n";
+        assert_eq!(
+            normalize_jme_strings(yaml, &replacements(&[(1, "{ x+1 }", "{x + 1}")])),
+            "statement: '{x + 1}'\n"
+        );
+    }
+
+    #[test]
+    fn block_values() {
+        let yaml = "parts:\n  - custom_marking_algorithm_notes: |\n      a:x\n      b:  a+ 1\n    marks: 1\n";
+        assert_eq!(
+            normalize_jme_strings(
+                yaml,
+                &replacements(&[(2, "a:x\nb:  a+ 1\n", "a: x\n\nb: a + 1")])
+            ),
+            "parts:\n  - custom_marking_algorithm_notes: |-\n      a: x\n\n      b: a + 1\n    marks: 1\n"
+        );
+    }
+}
@@ -1,12 +1,22 @@
 use crate::cli::fmt::fmt_internal;
+use comparable::{Changed, Comparable};
 use numbas::exam::Exam as NExam;
+use rayon::prelude::*;
 use rumbas::exam::convert_numbas_exam;
-use rumbas::exam::question_group::QuestionFromTemplate;
-use rumbas::question::custom_part_type::CustomPartTypeDefinitionPath;
-use rumbas::question::QuestionFileType;
-use rumbas::support::to_rumbas::ToRumbas;
+use rumbas::exam::question_group::{convert_numbas_question, QuestionFromTemplate};
+use rumbas::exam::{ExamFileType, ExamFileTypeInput};
+use rumbas::question::custom_part_type::{
+    CustomPartTypeDefinitionInput, CustomPartTypeDefinitionPath,
+};
+use rumbas::question::{Question, QuestionFileType, QuestionFileTypeInput};
+use rumbas::support::cli::MessageFormat;
+use rumbas::support::sanitize::sanitize;
+use rumbas::support::to_rumbas::ConversionError;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
 
-fn read_pretty_exam(path: &std::path::Path) -> String {
+fn read_pretty_exam(path: &Path) -> Result<String, ImportError> {
     let pretty_path = path.with_extension("exam.pretty");
     let exam_changed = pretty_path
         .metadata()
@@ -22,81 +32,320 @@ fn read_pretty_exam(path: &std::path::Path) -> String {
     let should_create_pretty =
         !pretty_path.exists() || exam_changed.is_err() || exam_changed.unwrap();
     if should_create_pretty {
-        let normal_content = std::fs::read_to_string(path).expect("Invalid file path");
+        let normal_content =
+            std::fs::read_to_string(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
         let json_content = numbas::exam::Exam::clean_exam_str(&normal_content[..]);
         let v: serde_json::Value =
-            serde_json::from_str(json_content).expect("failed parsing exam json");
+            serde_json::from_str(json_content).map_err(ImportError::Parse)?;
         let pretty_exam =
             serde_json::to_string_pretty(&v).expect("failed generating json of parsed exam json");
         let pretty_exam_content = numbas::exam::Exam::to_exam_str(&pretty_exam[..]);
         std::fs::write(&pretty_path, pretty_exam_content)
-            .expect("Writing pretty exam file to work");
+            .map_err(|e| ImportError::Io(pretty_path.clone(), e))?;
     }
-    std::fs::read_to_string(pretty_path).expect("Invalid file path")
+    std::fs::read_to_string(&pretty_path).map_err(|e| ImportError::Io(pretty_path, e))
 }
 
-macro_rules! read_exam {
-    ($file_name: expr) => {{
-        let content = read_pretty_exam($file_name);
-        NExam::from_exam_str(content.as_ref())
-    }};
+/// The numbas exam file that is imported, with the place where its resources are stored
+enum ImportSource {
+    /// A `.exam` file, its resources are searched in the `resources` folder next to it
+    ExamFile(PathBuf),
+    /// The zip file of a compiled exam, as downloaded from the numbas editor
+    Zip {
+        archive: zip::ZipArchive<std::fs::File>,
+        /// The folder in the archive that contains the `.exam` file
+        prefix: String,
+        exam_entry: String,
+    },
+    /// The folder of a compiled exam
+    Folder { folder: PathBuf, exam_file: PathBuf },
 }
 
-macro_rules! read_question {
-    ($file_name: expr) => {{
-        let content = read_pretty_exam($file_name);
-        numbas::question::Question::from_question_exam_str(content.as_ref())
-    }};
+#[derive(Debug)]
+enum ImportError {
+    Io(PathBuf, std::io::Error),
+    Zip(PathBuf, zip::result::ZipError),
+    MissingExamFile(PathBuf),
+    Parse(serde_json::Error),
+    Conversion(ConversionError),
+    Yaml(serde_yaml::Error),
+    Format(PathBuf),
 }
 
-pub fn import(path: String, is_question: bool) {
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            Self::Zip(path, e) => {
+                write!(f, "Failed to read the zip file {}: {}", path.display(), e)
+            }
+            Self::MissingExamFile(path) => {
+                write!(f, "No .exam file found in {}", path.display())
+            }
+            Self::Parse(e) => write!(f, "Failed to parse the exam: {}", e),
+            Self::Conversion(e) => write!(f, "Failed to convert the exam: {}", e),
+            Self::Yaml(e) => write!(f, "Failed to create the yaml: {}", e),
+            Self::Format(path) => write!(f, "Failed to format {}", path.display()),
+        }
+    }
+}
+
+/// The name of the `.exam` file in the output of numbas
+const NUMBAS_SOURCE_FILE: &str = "source.exam";
+
+impl ImportSource {
+    fn new(path: &Path) -> Result<Self, ImportError> {
+        if path.is_dir() {
+            let exam_file = find_exam_file(path)?;
+            Ok(Self::Folder {
+                folder: path.to_path_buf(),
+                exam_file,
+            })
+        } else if path.extension().and_then(|e| e.to_str()) == Some("zip") {
+            let file =
+                std::fs::File::open(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+            let archive =
+                zip::ZipArchive::new(file).map_err(|e| ImportError::Zip(path.to_path_buf(), e))?;
+            let exam_entry = {
+                let exam_entries: Vec<_> = archive
+                    .file_names()
+                    .filter(|name| name.ends_with(".exam"))
+                    .collect();
+                exam_entries
+                    .iter()
+                    .filter(|name| {
+                        Path::new(name).file_name().and_then(|n| n.to_str())
+                            == Some(NUMBAS_SOURCE_FILE)
+                    })
+                    .min_by_key(|name| name.len())
+                    .or_else(|| exam_entries.iter().min())
+                    .map(|name| name.to_string())
+                    .ok_or_else(|| ImportError::MissingExamFile(path.to_path_buf()))?
+            };
+            let prefix = exam_entry
+                .rfind('/')
+                .map(|i| exam_entry[..=i].to_string())
+                .unwrap_or_default();
+            Ok(Self::Zip {
+                archive,
+                prefix,
+                exam_entry,
+            })
+        } else {
+            Ok(Self::ExamFile(path.to_path_buf()))
+        }
+    }
+
+    /// The content of the `.exam` file
+    fn read_exam(&mut self) -> Result<String, ImportError> {
+        match self {
+            Self::ExamFile(path) => read_pretty_exam(path),
+            Self::Zip {
+                archive,
+                exam_entry,
+                ..
+            } => {
+                let mut content = String::new();
+                archive
+                    .by_name(exam_entry)
+                    .map_err(|e| ImportError::Zip(PathBuf::from(exam_entry.as_str()), e))?
+                    .read_to_string(&mut content)
+                    .map_err(|e| ImportError::Io(PathBuf::from(exam_entry.as_str()), e))?;
+                Ok(content)
+            }
+            Self::Folder { exam_file, .. } => std::fs::read_to_string(&exam_file)
+                .map_err(|e| ImportError::Io(exam_file.to_path_buf(), e)),
+        }
+    }
+
+    /// Copy the resource with the given name (a relative path within the resources folder) to
+    /// the resources folder of the project.
+    /// Returns false if the resource is not found.
+    fn copy_resource(&mut self, name: &Path) -> Result<bool, ImportError> {
+        let destination = Path::new(rumbas::RESOURCES_FOLDER).join(name);
+        let content = match self {
+            Self::ExamFile(path) => {
+                let next_to_exam = path
+                    .parent()
+                    .unwrap_or_else(|| Path::new("."))
+                    .join(rumbas::RESOURCES_FOLDER)
+                    .join(name);
+                if next_to_exam.is_file() {
+                    Some(
+                        std::fs::read(&next_to_exam)
+                            .map_err(|e| ImportError::Io(next_to_exam, e))?,
+                    )
+                } else {
+                    None
+                }
+            }
+            Self::Zip {
+                archive, prefix, ..
+            } => {
+                let entry_name = format!(
+                    "{}{}/{}",
+                    prefix,
+                    rumbas::RESOURCES_FOLDER,
+                    name.to_string_lossy()
+                );
+                match archive.by_name(&entry_name) {
+                    Ok(mut entry) => {
+                        let mut content = Vec::new();
+                        entry
+                            .read_to_end(&mut content)
+                            .map_err(|e| ImportError::Io(PathBuf::from(&entry_name), e))?;
+                        Some(content)
+                    }
+                    Err(zip::result::ZipError::FileNotFound) => None,
+                    Err(e) => return Err(ImportError::Zip(PathBuf::from(entry_name), e)),
+                }
+            }
+            Self::Folder { folder, .. } => {
+                let path = folder.join(rumbas::RESOURCES_FOLDER).join(name);
+                if path.is_file() {
+                    Some(std::fs::read(&path).map_err(|e| ImportError::Io(path, e))?)
+                } else {
+                    None
+                }
+            }
+        };
+        let content = match content {
+            Some(content) => content,
+            None => return Ok(false),
+        };
+        if destination.exists() {
+            if std::fs::read(&destination).ok().as_ref() != Some(&content) {
+                log::warn!(
+                    "{} already exists with a different content, it is not overwritten.",
+                    destination.display()
+                );
+            }
+            return Ok(true);
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ImportError::Io(parent.to_path_buf(), e))?;
+        }
+        log::info!("Writing to {}", destination.display());
+        std::fs::write(&destination, content).map_err(|e| ImportError::Io(destination, e))?;
+        Ok(true)
+    }
+
+    /// Copy the resources to the resources folder of the project
+    fn copy_resources(
+        &mut self,
+        resources: Vec<&numbas::question::resource::Resource>,
+    ) -> Result<(), ImportError> {
+        let mut copied = HashSet::new();
+        for resource in resources {
+            if !copied.insert(resource.0[0].clone()) {
+                continue;
+            }
+            let name = match resource_name(&resource.0[0]) {
+                Some(name) => name,
+                None => {
+                    log::warn!(
+                        "Resource {} is not copied, its name is not a path within the {} folder.",
+                        resource.0[0],
+                        rumbas::RESOURCES_FOLDER
+                    );
+                    continue;
+                }
+            };
+            if !self.copy_resource(&name)? {
+                log::warn!(
+                    "Resource {} is not found, it has to be placed in {} manually.",
+                    resource.0[0],
+                    Path::new(rumbas::RESOURCES_FOLDER)
+                        .join(&resource.0[0])
+                        .display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The name of a resource as a relative path, names that would leave the resources folder are
+/// rejected
+fn resource_name(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for segment in name.split('/') {
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(c)), None) => path.push(c),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// The `.exam` file in the folder of a compiled exam
+fn find_exam_file(folder: &Path) -> Result<PathBuf, ImportError> {
+    let source = folder.join(NUMBAS_SOURCE_FILE);
+    if source.is_file() {
+        return Ok(source);
+    }
+    let mut exam_files: Vec<_> = std::fs::read_dir(folder)
+        .map_err(|e| ImportError::Io(folder.to_path_buf(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("exam"))
+        .collect();
+    exam_files.sort();
+    exam_files
+        .into_iter()
+        .next()
+        .ok_or_else(|| ImportError::MissingExamFile(folder.to_path_buf()))
+}
+
+pub fn import(paths: Vec<String>, is_question: bool) {
     let repo_path = std::path::Path::new(".");
     let repo_path = rumbas::support::rc::within_repo(&repo_path);
     if let Some(repo_path) = repo_path {
         if !crate::cli::rc::check_rc(&repo_path, false) {
             std::process::exit(1);
         }
-        let path = std::path::Path::new(&path);
-        if is_question {
-            let question_res = read_question!(path);
-            match question_res {
-                Ok(question) => {
-                    let rumbas_question: QuestionFromTemplate = question.to_rumbas();
-                    let data = rumbas_question.data.clone();
-                    for cpt in data.custom_part_types.iter() {
-                        create_custom_part_type(cpt.to_owned());
-                    }
-                    create_question(rumbas_question);
-                }
-                Err(e) => {
-                    log::error!("{:?}", e);
-                    std::process::exit(1)
-                }
+        let mut files = Vec::new();
+        for path in paths.iter() {
+            find_import_files(Path::new(path), true, &mut files);
+        }
+        if files.is_empty() {
+            log::error!("No numbas files found in {}.", paths.join(", "));
+            std::process::exit(1);
+        }
+        log::info!("Importing {} files.", files.len());
+
+        // The files are read and converted in parallel. They are written one by one, so files
+        // with the same name are never written at the same time.
+        let converted: Vec<_> = files
+            .into_par_iter()
+            .map(|file| {
+                let result = convert_file(&file, is_question);
+                (file, result)
+            })
+            .collect();
+        let total = converted.len();
+        let mut project = ProjectFiles::read();
+        let mut failures = Vec::new();
+        for (file, result) in converted.into_iter() {
+            log::info!("Importing {}", file.display());
+            if let Err(e) = result.and_then(|imported| imported.write(&mut project)) {
+                log::error!("Failed to import {}: {}", file.display(), e);
+                failures.push((file, e));
             }
+        }
+        if failures.is_empty() {
+            log::info!("Imported {} files.", total);
         } else {
-            let exam_res = read_exam!(path);
-            match exam_res {
-                Ok(exam) => {
-                    //println!("{:?}", exam);
-                    let (name, rumbas_exam, qs, cpts) = convert_numbas_exam(exam);
-                    for qp in qs.into_iter() {
-                        create_question(qp)
-                    }
-                    for cpt in cpts.into_iter() {
-                        create_custom_part_type(cpt);
-                    }
-                    let exam_yaml = rumbas_exam.to_yaml().unwrap();
-                    let file = format!("{}/{}.yaml", rumbas::EXAMS_FOLDER, name);
-                    log::info!("Writing to {}", file);
-                    std::fs::write(&file, exam_yaml).unwrap();
-                    //fix handle result
-                    fmt_internal(vec![file]).unwrap();
-                }
-                Err(e) => {
-                    log::error!("{:?}", e);
-                    std::process::exit(1)
-                }
+            log::error!(
+                "Imported {} files, {} files failed:",
+                total - failures.len(),
+                failures.len()
+            );
+            for (file, e) in failures.iter() {
+                log::error!("{}: {}", file.display(), e);
             }
+            std::process::exit(1)
         }
     } else {
         log::error!(
@@ -107,27 +356,488 @@ pub fn import(path: String, is_question: bool) {
     }
 }
 
-fn create_question(qf: QuestionFromTemplate) {
-    match qf.question_path {
-        Some(path) => {
-            let q_name = path.clone();
-            let q_yaml = QuestionFileType::Normal(Box::new(qf.data))
-                .to_yaml()
-                .unwrap();
-            let file = format!("{}/{}.yaml", rumbas::QUESTIONS_FOLDER, q_name);
-            log::info!("Writing to {}", file);
-            std::fs::write(&file, q_yaml).unwrap(); //fix handle result
-            fmt_internal(vec![file]).unwrap();
+/// Find the files to import: `.exam` files, zip files and the folders of compiled exams. The
+/// given path is always imported if it is a file.
+fn find_import_files(path: &Path, given: bool, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        if path.join(NUMBAS_SOURCE_FILE).is_file() {
+            files.push(path.to_path_buf());
+            return;
+        }
+        let mut entries: Vec<_> = match std::fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
+            Err(e) => {
+                log::error!("Failed to read {}: {}", path.display(), e);
+                return;
+            }
+        };
+        entries.sort();
+        for entry in entries {
+            find_import_files(&entry, false, files);
+        }
+    } else if given {
+        files.push(path.to_path_buf());
+    } else if let Some("exam" | "zip") = path.extension().and_then(|e| e.to_str()) {
+        files.push(path.to_path_buf());
+    }
+}
+
+/// A converted numbas exam or question that is ready to be written to the project
+struct Imported {
+    source: ImportSource,
+    resources: Vec<numbas::question::resource::Resource>,
+    /// The name and content of the exam, not set when a question is imported
+    exam: Option<(String, ExamFileType)>,
+    /// The imported question, the questions of an exam are part of the exam
+    questions: Vec<QuestionFromTemplate>,
+    custom_part_types: Vec<CustomPartTypeDefinitionPath>,
+}
+
+/// Read and convert a `.exam` file, the zip file of a compiled exam or the folder of a compiled
+/// exam
+fn convert_file(path: &Path, is_question: bool) -> Result<Imported, ImportError> {
+    let mut source = ImportSource::new(path)?;
+    let content = source.read_exam()?;
+    // The name of the file is used when the exam or question has no name
+    let file_name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if is_question {
+        let question = numbas::question::Question::from_question_exam_str(content.as_ref())
+            .map_err(ImportError::Parse)?;
+        let resources = question.resources.clone();
+        let mut rumbas_question =
+            convert_numbas_question(&question).map_err(ImportError::Conversion)?;
+        name_question(&mut rumbas_question, &file_name);
+        let custom_part_types = rumbas_question.data.custom_part_types.clone();
+        Ok(Imported {
+            source,
+            resources,
+            exam: None,
+            questions: vec![rumbas_question],
+            custom_part_types,
+        })
+    } else {
+        let exam = NExam::from_exam_str(content.as_ref()).map_err(ImportError::Parse)?;
+        let resources = exam
+            .resources
+            .iter()
+            .chain(
+                exam.question_groups
+                    .iter()
+                    .flat_map(|g| g.questions.iter())
+                    .flat_map(|q| q.resources.iter()),
+            )
+            .cloned()
+            .collect();
+        let (name, mut rumbas_exam, _, custom_part_types) =
+            convert_numbas_exam(exam).map_err(ImportError::Conversion)?;
+        let name = Some(sanitize(&name))
+            .filter(|n| !n.is_empty())
+            .unwrap_or(file_name);
+        for (index, question) in rumbas_exam.questions_mut().into_iter().enumerate() {
+            name_question(question, &format!("{} question {}", name, index + 1));
+        }
+        Ok(Imported {
+            source,
+            resources,
+            exam: Some((name, rumbas_exam)),
+            questions: Vec::new(),
+            custom_part_types,
+        })
+    }
+}
+
+/// Give a question without a name the default name
+fn name_question(question: &mut QuestionFromTemplate, default_name: &str) {
+    if question
+        .question_path
+        .as_deref()
+        .unwrap_or_default()
+        .is_empty()
+    {
+        log::warn!(
+            "Using the name {} for a question without a name.",
+            default_name
+        );
+        question.question_path = Some(sanitize(default_name));
+    }
+}
+
+impl Imported {
+    fn write(mut self, project: &mut ProjectFiles) -> Result<(), ImportError> {
+        self.source
+            .copy_resources(self.resources.iter().collect())?;
+        let questions = match self.exam {
+            Some((_, ref mut exam)) => exam.questions_mut(),
+            None => self.questions.iter_mut().collect(),
+        };
+
+        // The custom part types are stored first, the parts of the questions use their name
+        let mut renamed = Vec::new();
+        for cpt in self.custom_part_types.into_iter() {
+            let name = project.store_custom_part_type(&cpt)?;
+            if name != cpt.file_name {
+                renamed.push((cpt.file_name, name));
+            }
+        }
+        for question in questions {
+            for (old_name, new_name) in renamed.iter() {
+                question.data.rename_custom_part_type(old_name, new_name);
+            }
+            // Imported questions are always named
+            let name = question.question_path.clone().expect("a named question");
+            question.question_path = Some(project.store_question(&name, &question.data)?);
+        }
+
+        if let Some((name, exam)) = self.exam {
+            project.store_exam(&name, &exam)?;
+        }
+        Ok(())
+    }
+}
+
+/// The exams, questions and custom part types of the project (by their name), used to reuse the
+/// files that have the same content as an imported exam, question or custom part type
+#[derive(Default)]
+struct ProjectFiles {
+    exams: Vec<(String, ExamFileTypeInput)>,
+    questions: Vec<(String, QuestionFileTypeInput)>,
+    custom_part_types: Vec<(String, CustomPartTypeDefinitionInput)>,
+}
+
+impl ProjectFiles {
+    fn read() -> Self {
+        Self {
+            exams: read_yaml_files(rumbas::EXAMS_FOLDER),
+            questions: read_yaml_files(rumbas::QUESTIONS_FOLDER),
+            custom_part_types: read_yaml_files(rumbas::CUSTOM_PART_TYPES_FOLDER),
+        }
+    }
+
+    /// Write the exam unless an exam with the same content exists. Returns the name of the exam
+    /// file.
+    fn store_exam(&mut self, name: &str, exam: &ExamFileType) -> Result<String, ImportError> {
+        let yaml = exam.to_yaml().map_err(ImportError::Yaml)?;
+        let input = serde_yaml::from_str(&yaml).map_err(ImportError::Yaml)?;
+        store(rumbas::EXAMS_FOLDER, name, yaml, input, &mut self.exams)
+    }
+
+    /// Write the question unless a question with the same content exists. Returns the name of the
+    /// question file.
+    fn store_question(&mut self, name: &str, question: &Question) -> Result<String, ImportError> {
+        let yaml = QuestionFileType::Normal(Box::new(question.clone()))
+            .to_yaml()
+            .map_err(ImportError::Yaml)?;
+        let input = serde_yaml::from_str(&yaml).map_err(ImportError::Yaml)?;
+        let name = store(
+            rumbas::QUESTIONS_FOLDER,
+            name,
+            yaml,
+            input,
+            &mut self.questions,
+        )?;
+        Ok(name)
+    }
+
+    /// Write the custom part type unless a custom part type with the same content exists. Returns
+    /// the name of the custom part type file.
+    fn store_custom_part_type(
+        &mut self,
+        cpt: &CustomPartTypeDefinitionPath,
+    ) -> Result<String, ImportError> {
+        let yaml = cpt.data.to_yaml().map_err(ImportError::Yaml)?;
+        let input = serde_yaml::from_str(&yaml).map_err(ImportError::Yaml)?;
+        store(
+            rumbas::CUSTOM_PART_TYPES_FOLDER,
+            &cpt.file_name,
+            yaml,
+            input,
+            &mut self.custom_part_types,
+        )
+    }
+}
+
+/// The yaml files in the folder (and its subfolders) that can be parsed, by their name
+fn read_yaml_files<T: serde::de::DeserializeOwned + Send>(folder: &str) -> Vec<(String, T)> {
+    let mut files = Vec::new();
+    find_yaml_files(Path::new(folder), &mut files);
+    let mut parsed: Vec<_> = files
+        .into_par_iter()
+        .filter_map(|file| {
+            let name = file
+                .strip_prefix(folder)
+                .ok()?
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let content = std::fs::read_to_string(&file).ok()?;
+            match serde_yaml::from_str(&content) {
+                Ok(data) => Some((name, data)),
+                Err(e) => {
+                    log::debug!("Failed to parse {}: {}", file.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    parsed.sort_by(|a, b| a.0.cmp(&b.0));
+    parsed
+}
+
+fn find_yaml_files(folder: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+        if path.is_dir() {
+            find_yaml_files(&path, files);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("yaml") {
+            files.push(path);
         }
-        _ => unimplemented!(),
     }
 }
 
-fn create_custom_part_type(cpt: CustomPartTypeDefinitionPath) {
-    let c_name = cpt.file_name.clone();
-    let c_yaml = cpt.data.to_yaml().unwrap();
-    let file = format!("{}/{}.yaml", rumbas::CUSTOM_PART_TYPES_FOLDER, c_name);
+/// Write the yaml file to the folder, unless one of the files has the same content. A file with
+/// the same name but another content is not overwritten, another name is used. Returns the name
+/// of the file.
+fn store<T: Comparable>(
+    folder: &str,
+    name: &str,
+    yaml: String,
+    data: T,
+    files: &mut Vec<(String, T)>,
+) -> Result<String, ImportError> {
+    let path = |name: &str| Path::new(folder).join(name).with_extension("yaml");
+    match store_name(folder, name, &data, files) {
+        StoreName::SameContent(existing_name) => {
+            if existing_name != name {
+                log::info!(
+                    "Using {}/{}.yaml for {}, it has the same content.",
+                    folder,
+                    existing_name,
+                    name
+                );
+            }
+            Ok(existing_name)
+        }
+        StoreName::Free(new_name) => {
+            if new_name != name {
+                log::warn!(
+                    "{} already exists with a different content, {} is written to {}.",
+                    path(name).display(),
+                    name,
+                    path(&new_name).display()
+                );
+            }
+            write_file(path(&new_name).to_string_lossy().to_string(), yaml)?;
+            files.push((new_name.clone(), data));
+            Ok(new_name)
+        }
+    }
+}
+
+/// The name that is used to store data
+#[derive(Debug, PartialEq, Eq)]
+enum StoreName {
+    /// The name of a file with the same content
+    SameContent(String),
+    /// A name that is not used by a file in the folder, nor by one of the files
+    Free(String),
+}
+
+/// Find the name to store the data with: a file with the same content is reused (preferably the
+/// one with the given name), otherwise the given name or the first free `name-i`
+fn store_name<T: Comparable>(
+    folder: &str,
+    name: &str,
+    data: &T,
+    files: &[(String, T)],
+) -> StoreName {
+    let same_content = files
+        .iter()
+        .filter(|(_, existing)| matches!(existing.comparison(data), Changed::Unchanged))
+        .map(|(existing_name, _)| existing_name)
+        .min_by_key(|existing_name| existing_name.as_str() != name);
+    if let Some(existing_name) = same_content {
+        return StoreName::SameContent(existing_name.to_owned());
+    }
+    let path = |name: &str| Path::new(folder).join(name).with_extension("yaml");
+    let new_name = (1..)
+        .map(|i| {
+            if i == 1 {
+                name.to_string()
+            } else {
+                format!("{}-{}", name, i)
+            }
+        })
+        .find(|n| !path(n).exists() && !files.iter().any(|(f, _)| f == n))
+        .expect("a free name");
+    StoreName::Free(new_name)
+}
+
+/// Write the yaml file and format it
+fn write_file(file: String, yaml: String) -> Result<(), ImportError> {
     log::info!("Writing to {}", file);
-    std::fs::write(&file, c_yaml).unwrap(); //fix handle result
-    fmt_internal(vec![file]).unwrap();
+    std::fs::write(&file, yaml).map_err(|e| ImportError::Io(PathBuf::from(&file), e))?;
+    fmt_internal(vec![file.clone()], false, MessageFormat::Human)
+        .map_err(|_| ImportError::Format(PathBuf::from(file)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resource_names() {
+        assert_eq!(resource_name("image.png"), Some(PathBuf::from("image.png")));
+        assert_eq!(
+            resource_name("question-resources/image.png"),
+            Some(PathBuf::from("question-resources/image.png"))
+        );
+        assert_eq!(resource_name("../image.png"), None);
+        assert_eq!(resource_name("a/../../image.png"), None);
+        assert_eq!(resource_name("/etc/passwd"), None);
+        assert_eq!(resource_name("./image.png"), None);
+        assert_eq!(resource_name(""), None);
+    }
+
+    /// Write a copy of the numbas exam of the cli tests, changed by `change`, to `folder`
+    fn changed_exam(
+        folder: &Path,
+        file: &str,
+        change: impl FnOnce(&mut serde_json::Value),
+    ) -> PathBuf {
+        let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../cli-tests/numbas_exams")
+            .join(file);
+        let content = std::fs::read_to_string(source).unwrap();
+        let mut exam: serde_json::Value =
+            serde_json::from_str(NExam::clean_exam_str(&content)).unwrap();
+        change(&mut exam);
+        let path = folder.join(file);
+        std::fs::write(&path, NExam::to_exam_str(&exam.to_string())).unwrap();
+        path
+    }
+
+    /// Change the default value of all percentage settings of custom part types
+    fn change_percentages(value: &mut serde_json::Value, default_value: &str) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if map.get("input_type").and_then(|t| t.as_str()) == Some("percent") {
+                    map.insert("default_value".to_string(), default_value.into());
+                }
+                map.values_mut()
+                    .for_each(|v| change_percentages(v, default_value));
+            }
+            serde_json::Value::Array(values) => values
+                .iter_mut()
+                .for_each(|v| change_percentages(v, default_value)),
+            _ => (),
+        }
+    }
+
+    #[test]
+    fn diagnostic_exam_without_diagnostic_settings() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = changed_exam(folder.path(), "exam-115828-diagnosys.exam", |exam| {
+            exam.as_object_mut().unwrap().remove("diagnostic");
+        });
+        let result = convert_file(&path, false);
+        assert!(matches!(
+            result,
+            Err(ImportError::Conversion(ConversionError::MissingDiagnostic))
+        ));
+    }
+
+    #[test]
+    fn invalid_percentage() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = changed_exam(folder.path(), "exam-149063-trig-geometry.exam", |exam| {
+            change_percentages(exam, "twenty")
+        });
+        let result = convert_file(&path, false);
+        match result {
+            Err(ImportError::Conversion(ConversionError::InvalidPercentage { value, .. })) => {
+                assert_eq!(value, "twenty")
+            }
+            _ => panic!("expected an invalid percentage"),
+        }
+    }
+
+    #[test]
+    fn valid_exams_are_converted() {
+        let folder = tempfile::tempdir().unwrap();
+        for file in [
+            "exam-115828-diagnosys.exam",
+            "exam-149063-trig-geometry.exam",
+        ] {
+            let path = changed_exam(folder.path(), file, |_| ());
+            let result = convert_file(&path, false);
+            assert!(result.is_ok(), "{} is not converted", file);
+        }
+    }
+
+    #[test]
+    fn store_names() {
+        let folder = tempfile::tempdir().unwrap();
+        // A file that exists, but is not read (e.g. because it can't be parsed)
+        std::fs::write(folder.path().join("unparsed.yaml"), "").unwrap();
+        let folder_str = folder.path().to_string_lossy().to_string();
+        let mut files = vec![
+            ("a".to_string(), "content a".to_string()),
+            ("b".to_string(), "content b".to_string()),
+            ("c".to_string(), "content b".to_string()),
+        ];
+        let name = |name: &str, data: &str, files: &mut Vec<(String, String)>| {
+            let result = store_name(&folder_str, name, &data.to_string(), files);
+            // Like `store` does when it writes a new file
+            if let StoreName::Free(ref new_name) = result {
+                files.push((new_name.clone(), data.to_string()));
+            }
+            result
+        };
+
+        // The same content reuses the file, preferably the one with the same name
+        assert_eq!(
+            name("a", "content a", &mut files),
+            StoreName::SameContent("a".to_string())
+        );
+        assert_eq!(
+            name("x", "content a", &mut files),
+            StoreName::SameContent("a".to_string())
+        );
+        assert_eq!(
+            name("c", "content b", &mut files),
+            StoreName::SameContent("c".to_string())
+        );
+        // The same name with a different content gets a free name
+        assert_eq!(
+            name("a", "other", &mut files),
+            StoreName::Free("a-2".to_string())
+        );
+        assert_eq!(
+            name("unparsed", "content", &mut files),
+            StoreName::Free("unparsed-2".to_string())
+        );
+        // The names of the files that are stored in the same import are not reused
+        assert_eq!(
+            name("a", "another", &mut files),
+            StoreName::Free("a-3".to_string())
+        );
+        assert_eq!(
+            name("a", "other", &mut files),
+            StoreName::SameContent("a-2".to_string())
+        );
+        assert_eq!(
+            name("new", "new", &mut files),
+            StoreName::Free("new".to_string())
+        );
+        assert_eq!(
+            name("new", "newer", &mut files),
+            StoreName::Free("new-2".to_string())
+        );
+    }
 }
@@ -0,0 +1,154 @@
+use crate::cli::check::{files_from_paths, load_file, print_diagnostics, CheckResult};
+use rayon::prelude::*;
+use rumbas::support::cli::MessageFormat;
+use rumbas::support::lint::{lint_exam, LintWarning};
+use rumbas::support::rc::RC;
+use rumbas::support::source_location::{locate_diagnostic, locator};
+use rumbas_support::diagnostic::Diagnostic;
+use rumbas_support::path::RumbasPath;
+use rumbas_support::rumbas_check::located;
+use std::collections::BTreeMap;
+
+pub fn lint(exam_question_paths: Vec<String>, message_format: MessageFormat) {
+    match lint_internal(exam_question_paths, message_format) {
+        Ok(_) => (),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+/// Fails if a file can't be loaded, lint warnings don't make it fail
+pub fn lint_internal(
+    exam_question_paths: Vec<String>,
+    message_format: MessageFormat,
+) -> Result<(), ()> {
+    let files = files_from_paths(exam_question_paths)?;
+    let results: Vec<(Result<Vec<LocalizedLintWarning>, CheckResult>, _)> = files
+        .into_par_iter()
+        .map(|file| (lint_file(&file), file))
+        .collect();
+    if message_format == MessageFormat::Json {
+        print_diagnostics(results.iter().flat_map(|(result, path)| {
+            match result {
+                Ok(warnings) => warnings
+                    .iter()
+                    .map(|w| locate_diagnostic(path, w.diagnostic()))
+                    .collect(),
+                Err(check_result) => check_result.diagnostics(path),
+            }
+        }));
+    }
+
+    let mut failures = 0;
+    let mut warning_count = 0;
+    for (result, path) in results.iter() {
+        match result {
+            Ok(warnings) => {
+                warning_count += warnings.len();
+                log_warnings(path, warnings);
+            }
+            Err(check_result) => {
+                failures += 1;
+                log::error!("Loading {} failed:", path.display());
+                check_result.log(path);
+            }
+        }
+    }
+    log::info!(
+        "Linted {} files and found {} warnings.",
+        results.len() - failures,
+        warning_count
+    );
+    if failures > 0 {
+        log::error!("{} files could not be loaded.", failures);
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// A lint warning with the locale that it occurs in, no locale if it occurs in all locales
+pub struct LocalizedLintWarning {
+    warning: LintWarning,
+    locale: Option<String>,
+}
+
+impl LocalizedLintWarning {
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = self.warning.diagnostic();
+        match &self.locale {
+            Some(locale) => diagnostic.with_locale(locale.clone()),
+            None => diagnostic,
+        }
+    }
+}
+
+fn log_warnings(path: &RumbasPath, warnings: &[LocalizedLintWarning]) {
+    if warnings.is_empty() {
+        return;
+    }
+    log::warn!(
+        "Found {} lint warnings for {}:",
+        warnings.len(),
+        path.display()
+    );
+    let locate = locator(path);
+    for (idx, warning) in warnings.iter().enumerate() {
+        log::warn!(
+            "{}\t{}{}{}",
+            idx + 1,
+            located(&locate, &warning.warning.path),
+            warning.warning,
+            warning
+                .locale
+                .as_ref()
+                .map(|l| format!(" (locale {})", l))
+                .unwrap_or_default()
+        );
+    }
+}
+
+/// Lint the exam (or question) for all its locales
+pub fn lint_file(path: &RumbasPath) -> Result<Vec<LocalizedLintWarning>, CheckResult> {
+    log::info!("Linting {:?}", path.display());
+    let exam = match load_file(path) {
+        Ok(exam) => exam,
+        Err(CheckResult::Template) => {
+            log::info!("{} is a template, skipping it.", path.display());
+            return Ok(Vec::new());
+        }
+        Err(check_result) => return Err(check_result),
+    };
+    let locales = exam.data.locales();
+    if locales.is_empty() {
+        return Err(CheckResult::LocalesNotSet);
+    }
+    let rc = RC::from_path(path).unwrap_or_default();
+    let mut warnings: BTreeMap<LintWarning, Vec<String>> = BTreeMap::new();
+    for locale in locales.iter() {
+        for warning in lint_exam(&exam.data, &locale.name, &rc) {
+            warnings
+                .entry(warning)
+                .or_default()
+                .push(locale.name.clone());
+        }
+    }
+    Ok(warnings
+        .into_iter()
+        .flat_map(|(warning, warning_locales)| {
+            if warning_locales.len() == locales.len() {
+                vec![LocalizedLintWarning {
+                    warning,
+                    locale: None,
+                }]
+            } else {
+                warning_locales
+                    .into_iter()
+                    .map(|locale| LocalizedLintWarning {
+                        warning: warning.clone(),
+                        locale: Some(locale),
+                    })
+                    .collect()
+            }
+        })
+        .collect())
+}
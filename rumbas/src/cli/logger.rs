@@ -1,5 +1,8 @@
 // See https://github.com/daboross/fern/blob/master/examples/pretty-colored.rs
-pub fn setup(level: log::LevelFilter) -> Result<(), fern::InitError> {
+use rumbas::support::cli::LogOutput;
+
+/// Setup the logger, the log is written to the given output
+pub fn setup(level: log::LevelFilter, output: LogOutput) -> Result<(), fern::InitError> {
     // configure colors for the whole line
     let colors_line = fern::colors::ColoredLevelConfig::new()
         .error(fern::colors::Color::Red)
@@ -15,7 +18,7 @@ pub fn setup(level: log::LevelFilter) -> Result<(), fern::InitError> {
     // just clone `colors_line` and overwrite our changes
     let colors_level = colors_line.info(fern::colors::Color::Green);
 
-    fern::Dispatch::new()
+    let dispatch = fern::Dispatch::new()
         .format(move |out, message, record| {
             out.finish(format_args!(
                 "{color_line}{date}[{target}][{level}{color_line}] {message}\x1B[0m",
@@ -29,9 +32,12 @@ pub fn setup(level: log::LevelFilter) -> Result<(), fern::InitError> {
                 message = message
             ))
         })
-        .level(level)
-        .chain(std::io::stdout())
-        //.chain(fern::log_file("output.log")?)
-        .apply()?;
+        .level(level);
+    match output {
+        LogOutput::Stdout => dispatch.chain(std::io::stdout()),
+        LogOutput::Stderr => dispatch.chain(std::io::stderr()),
+    }
+    //.chain(fern::log_file("output.log")?)
+    .apply()?;
     Ok(())
 }
@@ -0,0 +1,428 @@
+use crate::cli::check::check_file;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionResponse, DiagnosticSeverity,
+    Documentation, GotoDefinitionResponse, Hover, HoverContents, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, NumberOrString, OneOf, Position, PublishDiagnosticsParams, Range,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use rumbas::support::dependency_manager::DEPENDENCIES;
+use rumbas::support::file_manager::{
+    CustomPartTypeFileToRead, ExamFileToRead, QuestionFileToRead, TextFileToRead, CACHE,
+};
+use rumbas::support::file_reference::FILE_PREFIX;
+use rumbas::support::rc::within_repo;
+use rumbas::support::schema_lookup::SchemaLookup;
+use rumbas::support::source_location::SourceMap;
+use rumbas_support::diagnostic::{Diagnostic, Severity};
+use rumbas_support::input::FileToLoad;
+use rumbas_support::path::RumbasPath;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub fn lsp() {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![":".to_string(), " ".to_string()]),
+            ..Default::default()
+        }),
+        definition_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    let capabilities = serde_json::to_value(capabilities).expect("Serializable capabilities");
+    if let Err(e) = connection.initialize(capabilities) {
+        log::error!("Failed initializing the language server: {}", e);
+        return;
+    }
+    log::info!("The rumbas language server is running.");
+
+    let mut server = LanguageServer::default();
+    for message in &connection.receiver {
+        let responses = match message {
+            Message::Request(request) => match connection.handle_shutdown(&request) {
+                Ok(true) => break,
+                Ok(false) => vec![Message::Response(server.handle_request(request))],
+                Err(e) => {
+                    log::error!("Failed handling the shutdown request: {}", e);
+                    break;
+                }
+            },
+            Message::Notification(notification) => server
+                .handle_notification(notification)
+                .into_iter()
+                .map(Message::Notification)
+                .collect(),
+            Message::Response(_) => Vec::new(),
+        };
+        for response in responses {
+            if let Err(e) = connection.sender.send(response) {
+                log::error!("Failed sending a message to the client: {}", e);
+            }
+        }
+    }
+    drop(connection);
+    if let Err(e) = io_threads.join() {
+        log::error!("Failed stopping the language server: {}", e);
+    }
+}
+
+#[derive(Default)]
+struct LanguageServer {
+    /// The content of the opened documents
+    documents: HashMap<Url, String>,
+    /// The diagnostics of the checked exams and questions
+    diagnostics: HashMap<RumbasPath, Vec<Diagnostic>>,
+    /// The documents that have published diagnostics
+    published: HashSet<Url>,
+}
+
+impl LanguageServer {
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match &request.method[..] {
+            HoverRequest::METHOD => request
+                .extract::<lsp_types::HoverParams>(HoverRequest::METHOD)
+                .map(|(_, params)| {
+                    let position = params.text_document_position_params;
+                    serde_json::to_value(self.hover(&position.text_document.uri, position.position))
+                }),
+            Completion::METHOD => request
+                .extract::<lsp_types::CompletionParams>(Completion::METHOD)
+                .map(|(_, params)| {
+                    let position = params.text_document_position;
+                    serde_json::to_value(CompletionResponse::Array(
+                        self.completion(&position.text_document.uri, position.position),
+                    ))
+                }),
+            GotoDefinition::METHOD => request
+                .extract::<lsp_types::GotoDefinitionParams>(GotoDefinition::METHOD)
+                .map(|(_, params)| {
+                    let position = params.text_document_position_params;
+                    serde_json::to_value(
+                        self.definition(&position.text_document.uri, position.position)
+                            .map(GotoDefinitionResponse::Scalar),
+                    )
+                }),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request {}", method),
+                )
+            }
+        };
+        match result {
+            Ok(Ok(value)) => Response::new_ok(id, value),
+            Ok(Err(e)) => Response::new_err(id, ErrorCode::InternalError as i32, e.to_string()),
+            Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    /// Returns the notifications that should be sent to the client
+    fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let changed = match &notification.method[..] {
+            DidOpenTextDocument::METHOD => notification
+                .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+                .ok()
+                .map(|params| (params.text_document.uri, params.text_document.text)),
+            DidChangeTextDocument::METHOD => notification
+                .extract::<lsp_types::DidChangeTextDocumentParams>(DidChangeTextDocument::METHOD)
+                .ok()
+                .and_then(|params| {
+                    params
+                        .content_changes
+                        .into_iter()
+                        .last()
+                        .map(|change| (params.text_document.uri, change.text))
+                }),
+            DidSaveTextDocument::METHOD => notification
+                .extract::<lsp_types::DidSaveTextDocumentParams>(DidSaveTextDocument::METHOD)
+                .ok()
+                .and_then(|params| {
+                    let uri = params.text_document.uri;
+                    params
+                        .text
+                        .or_else(|| self.documents.get(&uri).cloned())
+                        .map(|text| (uri, text))
+                }),
+            DidCloseTextDocument::METHOD => {
+                if let Ok(params) = notification
+                    .extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+                {
+                    let uri = params.text_document.uri;
+                    self.documents.remove(&uri);
+                    if let Some(path) = rumbas_path(&uri) {
+                        // Use the content on disk again
+                        CACHE.delete_file(FileToLoad {
+                            file_path: path,
+                            locale_dependant: false,
+                        });
+                    }
+                }
+                None
+            }
+            _ => None,
+        };
+        match changed {
+            Some((uri, text)) => {
+                self.documents.insert(uri.clone(), text.clone());
+                self.update(&uri, text);
+                self.publish_diagnostics()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Check the exams and questions that use the changed document
+    fn update(&mut self, uri: &Url, text: String) {
+        let path = match rumbas_path(uri) {
+            Some(path) => path,
+            None => return,
+        };
+        CACHE.set_content(path.clone(), text);
+        let mut files = DEPENDENCIES.get_dependants(path.clone());
+        if path.in_main_folder(rumbas::EXAMS_FOLDER)
+            || path.in_main_folder(rumbas::QUESTIONS_FOLDER)
+        {
+            files.insert(path);
+        }
+        for file in files.into_iter() {
+            let diagnostics = check_file(&file).diagnostics(&file);
+            self.diagnostics.insert(file, diagnostics);
+        }
+    }
+
+    /// The notifications with the diagnostics for all files that have (or had) diagnostics
+    fn publish_diagnostics(&mut self) -> Vec<Notification> {
+        let mut by_file: HashMap<Url, Vec<lsp_types::Diagnostic>> = HashMap::new();
+        for (main_file, diagnostics) in self.diagnostics.iter() {
+            for diagnostic in diagnostics.iter() {
+                let file = match &diagnostic.location {
+                    Some(location) => main_file.keep_root(Path::new(&location.file)),
+                    None => main_file.clone(),
+                };
+                if let Ok(uri) = Url::from_file_path(file.absolute()) {
+                    let diagnostics = by_file.entry(uri).or_default();
+                    let diagnostic = to_lsp_diagnostic(diagnostic);
+                    // An exam and its questions can report the same problem
+                    if !diagnostics.contains(&diagnostic) {
+                        diagnostics.push(diagnostic);
+                    }
+                }
+            }
+        }
+        let uris: HashSet<Url> = by_file.keys().cloned().collect();
+        let notifications = uris
+            .union(&self.published)
+            .map(|uri| {
+                Notification::new(
+                    PublishDiagnostics::METHOD.to_string(),
+                    PublishDiagnosticsParams {
+                        uri: uri.clone(),
+                        diagnostics: by_file.get(uri).cloned().unwrap_or_default(),
+                        version: None,
+                    },
+                )
+            })
+            .collect();
+        self.published = uris;
+        notifications
+    }
+
+    /// The content of the document, from the editor or from disk
+    fn text(&self, uri: &Url) -> Option<String> {
+        self.documents
+            .get(uri)
+            .cloned()
+            .or_else(|| std::fs::read_to_string(uri.to_file_path().ok()?).ok())
+    }
+
+    fn hover(&self, uri: &Url, position: Position) -> Option<Hover> {
+        let lookup = SchemaLookup::for_file(&rumbas_path(uri)?)?;
+        let map = SourceMap::from_yaml(&self.text(uri)?);
+        let path = map.path_at(position.line as usize + 1, position.character as usize + 1)?;
+        let description = lookup.description(&path, &type_finder(&map))?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("`{}`\n\n{}", path.join("."), description),
+            }),
+            range: None,
+        })
+    }
+
+    /// Complete the field names of mappings and the enum variants of values
+    fn completion(&self, uri: &Url, position: Position) -> Vec<CompletionItem> {
+        let lookup = match rumbas_path(uri).and_then(|path| SchemaLookup::for_file(&path)) {
+            Some(lookup) => lookup,
+            None => return Vec::new(),
+        };
+        let text = self.text(uri).unwrap_or_default();
+        let map = SourceMap::from_yaml(&text);
+        let line_nb = position.line as usize + 1;
+        let line = text.lines().nth(position.line as usize).unwrap_or_default();
+        let before: String = line.chars().take(position.character as usize).collect();
+
+        // The column where the key of the line starts, after the dashes of list items
+        let mut key_column = before.len() - before.trim_start().len();
+        let mut rest = before.trim_start();
+        while let Some(item) = rest.strip_prefix('-') {
+            let trimmed = item.trim_start();
+            key_column += rest.len() - trimmed.len();
+            rest = trimmed;
+        }
+        if rest.contains(": ") {
+            let path = match map.path_at(line_nb, key_column + 1) {
+                Some(path) => path,
+                None => return Vec::new(),
+            };
+            lookup
+                .variants(&path, &type_finder(&map))
+                .into_iter()
+                .map(|variant| CompletionItem {
+                    label: variant,
+                    kind: Some(CompletionItemKind::ENUM_MEMBER),
+                    ..Default::default()
+                })
+                .collect()
+        } else {
+            let parent = map.parent_at(line_nb, key_column + 1);
+            lookup
+                .fields(&parent, &type_finder(&map))
+                .into_iter()
+                .filter(|field| {
+                    let mut path = parent.clone();
+                    path.push(field.name.clone());
+                    map.get(&path).map(|(l, _)| l == line_nb).unwrap_or(true)
+                })
+                .map(|field| CompletionItem {
+                    insert_text: Some(format!("{}: ", field.name)),
+                    label: field.name,
+                    kind: Some(CompletionItemKind::FIELD),
+                    documentation: field.description.map(Documentation::String),
+                    ..Default::default()
+                })
+                .collect()
+        }
+    }
+
+    /// Go to the files that are referenced: templates, `file:` references, questions and custom
+    /// part types
+    fn definition(&self, uri: &Url, position: Position) -> Option<Location> {
+        let file = rumbas_path(uri)?;
+        let map = SourceMap::from_yaml(&self.text(uri)?);
+        let path = map.path_at(position.line as usize + 1, position.character as usize + 1)?;
+        let value = map.value(&path)?.trim().to_string();
+        let segments: Vec<&str> = path.iter().map(|s| &s[..]).collect();
+        let is_index = |s: &str| s.parse::<usize>().is_ok();
+        let target: RumbasPath = if let Some(file_name) = value
+            .strip_prefix(FILE_PREFIX)
+            .and_then(|v| v.strip_prefix(':'))
+        {
+            localized_file(TextFileToRead::with_file_name(file_name.to_string(), &file).into())
+        } else {
+            match &segments[..] {
+                ["template"] if file.in_main_folder(rumbas::EXAMS_FOLDER) => {
+                    ExamFileToRead::with_file_name(value, &file).into()
+                }
+                [.., "template"] => QuestionFileToRead::with_file_name(value, &file).into(),
+                [.., "questions", index] if is_index(index) => {
+                    QuestionFileToRead::with_file_name(value, &file).into()
+                }
+                [.., "custom_part_types", index] if is_index(index) => {
+                    CustomPartTypeFileToRead::with_file_name(value, &file).into()
+                }
+                [.., "parts" | "gaps", index, "type"] if is_index(index) => {
+                    CustomPartTypeFileToRead::with_file_name(value, &file).into()
+                }
+                _ => return None,
+            }
+        };
+        if !target.absolute().is_file() {
+            return None;
+        }
+        Some(Location {
+            uri: Url::from_file_path(target.absolute()).ok()?,
+            range: Range::default(),
+        })
+    }
+}
+
+fn rumbas_path(uri: &Url) -> Option<RumbasPath> {
+    let path: PathBuf = uri.to_file_path().ok()?;
+    within_repo(&path)
+}
+
+/// Finds the `type` of the mapping at a path in the source map
+fn type_finder(map: &SourceMap) -> impl Fn(&[String]) -> Option<String> + '_ {
+    move |path| {
+        let mut type_path = path.to_vec();
+        type_path.push("type".to_string());
+        map.value(&type_path).map(|t| t.to_string())
+    }
+}
+
+/// Text files can also be placed in the locale folders next to them
+fn localized_file(path: RumbasPath) -> RumbasPath {
+    if path.absolute().is_file() {
+        return path;
+    }
+    let (folder, file_name) = match (path.project().parent(), path.project().file_name()) {
+        (Some(folder), Some(file_name)) => (folder.to_path_buf(), file_name.to_os_string()),
+        _ => return path,
+    };
+    std::fs::read_dir(path.keep_root(&folder).absolute())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(rumbas::LOCALE_FOLDER_PREFIX)
+        })
+        .map(|entry| path.keep_root(&folder.join(entry.file_name()).join(&file_name)))
+        .find(|localized| localized.absolute().is_file())
+        .unwrap_or(path)
+}
+
+fn to_lsp_diagnostic(diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
+    let (line, column) = diagnostic
+        .location
+        .as_ref()
+        .map(|location| (location.line, location.column))
+        .unwrap_or((1, 1));
+    let start = Position::new(
+        line.saturating_sub(1) as u32,
+        column.saturating_sub(1) as u32,
+    );
+    let mut message = match &diagnostic.path {
+        Some(path) => format!("{}: {}", path, diagnostic.message),
+        None => diagnostic.message.clone(),
+    };
+    if let Some(locale) = &diagnostic.locale {
+        message.push_str(&format!(" (locale {})", locale));
+    }
+    lsp_types::Diagnostic {
+        // The end of the line
+        range: Range::new(start, Position::new(start.line, u32::MAX)),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        code: serde_json::to_value(diagnostic.kind)
+            .ok()
+            .and_then(|kind| kind.as_str().map(|k| NumberOrString::String(k.to_string()))),
+        source: Some("rumbas".to_string()),
+        message,
+        ..Default::default()
+    }
+}
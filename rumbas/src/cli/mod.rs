@@ -1,25 +1,61 @@
+mod affected;
 mod check;
 mod compile;
+mod deps;
 mod editor_output;
 mod export;
 mod fmt;
 mod import;
 mod init;
+mod lint;
 pub mod logger;
+mod lsp;
+mod numbas_worker;
 mod rc;
 mod schema;
+mod serve;
 mod shell_completion;
+mod test;
+mod unused;
 mod update_repo;
+mod variables;
 mod watch;
 
+pub use affected::affected;
 pub use check::check;
 pub use compile::compile;
+pub use deps::deps;
 pub use editor_output::create_editor_output;
 pub use export::export;
 pub use fmt::fmt;
 pub use import::import;
 pub use init::init;
+pub use lint::lint;
+pub use lsp::lsp;
 pub use schema::schema;
+pub use serve::serve;
 pub use shell_completion::complete;
+pub use test::test;
+pub use unused::unused;
 pub use update_repo::update_repo;
+pub use variables::variables;
 pub use watch::watch;
+
+/// Write the files (paths relative to the root and their content), creating their folders
+#[cfg(test)]
+fn write_files<C: AsRef<[u8]>>(root: &std::path::Path, files: &[(&str, C)]) {
+    for (name, content) in files.iter() {
+        let file = root.join(name);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+    }
+}
+
+/// Create a temporary folder with the given files, the folder is removed when the returned value
+/// is dropped (also when the test fails)
+#[cfg(test)]
+fn temp_folder_with_files<C: AsRef<[u8]>>(files: &[(&str, C)]) -> tempfile::TempDir {
+    let folder = tempfile::tempdir().unwrap();
+    write_files(folder.path(), files);
+    folder
+}
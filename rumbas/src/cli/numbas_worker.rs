@@ -0,0 +1,177 @@
+//! Long-lived python processes that run the numbas compiler.
+//!
+//! Starting the python interpreter and importing numbas takes most of the time of a numbas run.
+//! A worker starts once and runs `bin/numbas.py` for each job it reads on its stdin.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+/// The prefix of the line with the result of a job, the other lines on stdout are written by
+/// processes that numbas starts (e.g. uglifyjs)
+const RESULT_PREFIX: &str = "rumbas-numbas-worker-result:";
+
+/// The python script of a worker, it is started in the numbas folder.
+/// Each line on stdin is a json object with the cli arguments of numbas.py.
+const WORKER_SCRIPT: &str = r#"
+import io, json, os, runpy, sys, traceback
+PREFIX = 'rumbas-numbas-worker-result:'
+cwd = os.getcwd()
+sys.path.insert(0, os.path.join(cwd, 'bin'))
+while True:
+    line = sys.stdin.readline()
+    if not line:
+        break
+    job = json.loads(line)
+    stdout, stderr = io.StringIO(), io.StringIO()
+    sys.stdout, sys.stderr = stdout, stderr
+    sys.argv = ['bin/numbas.py'] + job['args']
+    status = 0
+    try:
+        runpy.run_path('bin/numbas.py', run_name='__main__')
+    except SystemExit as e:
+        status = e.code if isinstance(e.code, int) else (0 if e.code is None else 1)
+    except BaseException:
+        traceback.print_exc()
+        status = 1
+    finally:
+        sys.stdout, sys.stderr = sys.__stdout__, sys.__stderr__
+        os.chdir(cwd)
+    result = {'status': status, 'stdout': stdout.getvalue(), 'stderr': stderr.getvalue()}
+    sys.stdout.write(PREFIX + json.dumps(result) + '\n')
+    sys.stdout.flush()
+"#;
+
+/// The output of a numbas run
+#[derive(Debug, Default)]
+pub struct NumbasOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl From<std::process::Output> for NumbasOutput {
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct WorkerJob<'a> {
+    args: &'a [String],
+}
+
+#[derive(serde::Deserialize)]
+struct WorkerResult {
+    status: i32,
+    stdout: String,
+    stderr: String,
+}
+
+struct NumbasWorker {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl NumbasWorker {
+    fn spawn(numbas_path: &str) -> std::io::Result<Self> {
+        log::debug!("Starting a numbas worker");
+        let mut child = Command::new("python3")
+            .current_dir(numbas_path)
+            .arg("-u")
+            .arg("-c")
+            .arg(WORKER_SCRIPT)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = child
+            .stdout
+            .take()
+            .map(BufReader::new)
+            .expect("The stdout of the worker to be piped");
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    fn run(&mut self, args: &[String]) -> std::io::Result<NumbasOutput> {
+        let job = serde_json::to_string(&WorkerJob { args })?;
+        let stdin = self
+            .stdin
+            .as_mut()
+            .expect("The stdin of the worker to be piped");
+        writeln!(stdin, "{}", job)?;
+        stdin.flush()?;
+
+        let mut other_output = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "The numbas worker stopped",
+                ));
+            }
+            if let Some(result) = line.strip_prefix(RESULT_PREFIX) {
+                let result: WorkerResult = serde_json::from_str(result)?;
+                let mut stderr = result.stderr;
+                if result.status != 0 && stderr.is_empty() {
+                    stderr = format!("numbas.py exited with status {}", result.status);
+                }
+                return Ok(NumbasOutput {
+                    stdout: other_output + &result.stdout,
+                    stderr,
+                });
+            }
+            other_output.push_str(&line);
+        }
+    }
+}
+
+impl Drop for NumbasWorker {
+    fn drop(&mut self) {
+        // Closing stdin stops the worker
+        self.stdin.take();
+        self.child.wait().ok();
+    }
+}
+
+/// The idle workers, a worker is started when a job is run while all workers are busy.
+/// The number of workers is thus limited by the number of exams that are compiled at the same
+/// time.
+#[derive(Default)]
+pub struct NumbasWorkerPool {
+    idle: Mutex<Vec<NumbasWorker>>,
+}
+
+impl NumbasWorkerPool {
+    /// Run numbas.py with the given arguments in a worker
+    pub fn run(&self, numbas_path: &str, args: &[String]) -> std::io::Result<NumbasOutput> {
+        let idle = self
+            .idle
+            .lock()
+            .expect("No panics while holding the lock")
+            .pop();
+        let mut worker = match idle {
+            Some(worker) => worker,
+            None => NumbasWorker::spawn(numbas_path)?,
+        };
+        let output = worker.run(args)?;
+        // A worker that failed is dropped, the next job starts a new one
+        self.idle
+            .lock()
+            .expect("No panics while holding the lock")
+            .push(worker);
+        Ok(output)
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref NUMBAS_WORKERS: NumbasWorkerPool = NumbasWorkerPool::default();
+}
@@ -0,0 +1,380 @@
+//! A local http server for the output folder: an index page of the compiled exams and the exams
+//! themselves. The watcher recompiles the exams and the open pages of the recompiled exams are
+//! reloaded with server-sent events.
+
+use crate::cli::compile::OUTPUT_FOLDER;
+use crate::cli::watch::{watch_with_handler, WatchCompiler};
+use rumbas_support::path::RumbasPath;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The url of the stream of reload events
+const EVENTS_URL: &str = "/__rumbas/events";
+
+/// Added to the served html pages. An event contains the url path of a recompiled exam (without
+/// the locale), the index page is reloaded for all events.
+const RELOAD_SCRIPT: &str = r#"<script>
+new EventSource("/__rumbas/events").onmessage = function(event) {
+    if (location.pathname === "/" || location.pathname.indexOf(encodeURI(event.data)) !== -1) {
+        location.reload();
+    }
+};
+</script>"#;
+
+pub fn serve(watch_path: String, port: u16) {
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to listen on port {}: {}", port, e);
+            std::process::exit(1)
+        }
+    };
+    let reloader = Reloader::default();
+    let server_reloader = reloader.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let reloader = server_reloader.clone();
+                    std::thread::spawn(move || handle_connection(stream, &reloader));
+                }
+                Err(e) => log::warn!("Failed to accept a connection: {}", e),
+            }
+        }
+    });
+    log::info!("Serving {} on http://127.0.0.1:{}/", OUTPUT_FOLDER, port);
+    watch_with_handler(
+        &watch_path,
+        &WatchCompiler {
+            reloader: Some(reloader),
+        },
+    );
+}
+
+/// The event streams of the open pages
+#[derive(Clone, Default)]
+pub struct Reloader {
+    pages: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl Reloader {
+    /// Reload the open pages of the outputs of the exam (or question)
+    pub fn reload(&self, exam: &RumbasPath) {
+        let url_path = format!(
+            "/{}/",
+            exam.project()
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/")
+        );
+        log::debug!("Reloading the pages of {}", url_path);
+        // The pages that are closed are removed
+        self.pages
+            .lock()
+            .expect("No panics while holding the lock")
+            .retain(|page| page.send(url_path.clone()).is_ok());
+    }
+    fn subscribe(&self) -> Receiver<String> {
+        let (sender, receiver) = channel();
+        self.pages
+            .lock()
+            .expect("No panics while holding the lock")
+            .push(sender);
+        receiver
+    }
+}
+
+fn handle_connection(stream: TcpStream, reloader: &Reloader) {
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(_) => return,
+    };
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // The headers are not used
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => (),
+        }
+    }
+    let mut request = request_line.split_whitespace();
+    let method = request.next().unwrap_or_default();
+    let target = request.next().unwrap_or_default();
+    let mut stream = stream;
+    let result = match method {
+        "GET" | "HEAD" => respond(&mut stream, target, method == "HEAD", reloader),
+        _ => write_response(
+            &mut stream,
+            405,
+            &[("Content-Type", "text/plain")],
+            b"Method not allowed",
+            false,
+        ),
+    };
+    if let Err(e) = result {
+        log::debug!("Failed to respond to {} {}: {}", method, target, e);
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    target: &str,
+    head_only: bool,
+    reloader: &Reloader,
+) -> std::io::Result<()> {
+    let url_path = target.split(['?', '#']).next().unwrap_or("/");
+    if url_path == EVENTS_URL {
+        return stream_events(stream, reloader);
+    }
+    let html = ("Content-Type", "text/html; charset=utf-8");
+    if url_path == "/" {
+        return write_response(stream, 200, &[html], index_page().as_bytes(), head_only);
+    }
+    let not_found = |stream: &mut TcpStream| {
+        write_response(
+            stream,
+            404,
+            &[("Content-Type", "text/plain")],
+            b"Not found",
+            head_only,
+        )
+    };
+    let mut file = match output_file(url_path) {
+        Some(file) => file,
+        None => return not_found(stream),
+    };
+    if file.is_dir() {
+        // The relative urls in the exam need the trailing slash
+        if !url_path.ends_with('/') {
+            let location = format!("{}/", url_path);
+            return write_response(stream, 301, &[("Location", &location)], b"", head_only);
+        }
+        file = file.join("index.html");
+    }
+    match std::fs::read(&file) {
+        Ok(content) => {
+            let content_type = content_type(&file);
+            let content = if content_type == html.1 {
+                with_reload_script(content)
+            } else {
+                content
+            };
+            write_response(
+                stream,
+                200,
+                &[("Content-Type", content_type)],
+                &content,
+                head_only,
+            )
+        }
+        Err(_) => not_found(stream),
+    }
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    head_only: bool,
+) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        301 => "Moved Permanently",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    write!(stream, "HTTP/1.1 {} {}\r\n", status, reason)?;
+    for (name, value) in headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+/// Send the reload events until the page is closed
+fn stream_events(stream: &mut TcpStream, reloader: &Reloader) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n"
+    )?;
+    stream.flush()?;
+    let events = reloader.subscribe();
+    loop {
+        match events.recv_timeout(Duration::from_secs(15)) {
+            Ok(url_path) => write!(stream, "data: {}\n\n", url_path)?,
+            // A comment, writing it fails when the page is closed
+            Err(RecvTimeoutError::Timeout) => write!(stream, ": keep-alive\n\n")?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        stream.flush()?;
+    }
+}
+
+/// The path of the file in the output folder, paths that would leave it are rejected
+fn output_file(url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(url_path)?;
+    let mut file = Path::new(OUTPUT_FOLDER).to_path_buf();
+    for segment in decoded.split('/').filter(|s| !s.is_empty()) {
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(c)), None) => file.push(c),
+            _ => return None,
+        }
+    }
+    Some(file)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+    {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Add the reload script at the end of the body
+fn with_reload_script(content: Vec<u8>) -> Vec<u8> {
+    match String::from_utf8(content) {
+        Ok(mut html) => {
+            let position = html.to_lowercase().rfind("</body>").unwrap_or(html.len());
+            html.insert_str(position, RELOAD_SCRIPT);
+            html.into_bytes()
+        }
+        Err(e) => e.into_bytes(),
+    }
+}
+
+/// The index page: the compiled exams (the folders with an index.html) per locale
+fn index_page() -> String {
+    let mut body = String::new();
+    for locale_folder in sorted_folders(Path::new(OUTPUT_FOLDER)) {
+        let mut outputs = Vec::new();
+        find_outputs(&locale_folder, &mut outputs);
+        if outputs.is_empty() {
+            continue;
+        }
+        let locale = locale_folder
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        body.push_str(&format!("<h2>{}</h2>\n<ul>\n", html_escape(&locale)));
+        for output in outputs {
+            let name = output
+                .strip_prefix(&locale_folder)
+                .unwrap_or(&output)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let url = format!("/{}/{}/", locale, name);
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                html_escape(&percent_encode(&url)),
+                html_escape(&name)
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+    if body.is_empty() {
+        body = format!("<p>There are no compiled exams in {}.</p>\n", OUTPUT_FOLDER);
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>rumbas</title></head>\n<body>\n<h1>Compiled exams</h1>\n{}{}\n</body>\n</html>\n",
+        body, RELOAD_SCRIPT
+    )
+}
+
+fn sorted_folders(folder: &Path) -> Vec<PathBuf> {
+    let mut folders: Vec<_> = std::fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    folders.sort();
+    folders
+}
+
+fn find_outputs(folder: &Path, outputs: &mut Vec<PathBuf>) {
+    for folder in sorted_folders(folder) {
+        if folder.join("index.html").is_file() {
+            outputs.push(folder);
+        } else {
+            find_outputs(&folder, outputs);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::cli::check::{files_from_paths, load_file, CheckResult};
+use rumbas::question::marking::MarkingTestResult;
+use rumbas_support::path::RumbasPath;
+
+pub fn test(exam_question_paths: Vec<String>, seeds: u64, first_seed: u64) {
+    match test_internal(exam_question_paths, seeds, first_seed) {
+        Ok(_) => (),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+pub fn test_internal(
+    exam_question_paths: Vec<String>,
+    seeds: u64,
+    first_seed: u64,
+) -> Result<(), ()> {
+    let files = files_from_paths(exam_question_paths)?;
+    let mut success = true;
+    for file in files.into_iter() {
+        success &= test_for_file(&file, seeds, first_seed);
+    }
+    if success {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+/// Returns true if all marking tests of all questions pass
+fn test_for_file(path: &RumbasPath, seeds: u64, first_seed: u64) -> bool {
+    log::info!("Testing the marking of {}", path.display());
+    let exam = match load_file(path) {
+        Ok(exam) => exam,
+        Err(CheckResult::Template) => {
+            log::info!("{} is a template, skipping it.", path.display());
+            return true;
+        }
+        Err(check_result) => {
+            log::error!("Loading {} failed:", path.display());
+            check_result.log(path);
+            return false;
+        }
+    };
+    let locale = match exam.data.locales().first() {
+        Some(locale) => locale.name.clone(),
+        None => {
+            CheckResult::LocalesNotSet.log(path);
+            return false;
+        }
+    };
+    let mut success = true;
+    for question in exam
+        .data
+        .question_groups()
+        .into_iter()
+        .flat_map(|g| g.questions.into_iter())
+    {
+        let name = question
+            .question_path
+            .clone()
+            .unwrap_or_else(|| path.display().to_string());
+        for seed in first_seed..first_seed + seeds {
+            let results = question.data.run_marking_tests(&locale, seed);
+            if results.is_empty() {
+                log::info!("Question {} has no marking tests.", name);
+                break;
+            }
+            success &= report(&name, seed, &results);
+        }
+    }
+    success
+}
+
+/// Log the failing tests. Returns false if a test fails.
+fn report(name: &str, seed: u64, results: &[MarkingTestResult]) -> bool {
+    let failed: Vec<_> = results.iter().filter(|r| !r.passed()).collect();
+    for result in failed.iter() {
+        match &result.marks {
+            Ok(marks) => log::error!(
+                "Question {} (seed {}), {}: answer {} expected {} marks, got {}",
+                name,
+                seed,
+                result.part,
+                result.answer,
+                result.expected,
+                marks
+            ),
+            Err(e) => log::error!(
+                "Question {} (seed {}), {}: answer {} can't be marked: {}",
+                name,
+                seed,
+                result.part,
+                result.answer,
+                e
+            ),
+        }
+    }
+    log::info!(
+        "Question {} (seed {}): {} of {} marking tests passed.",
+        name,
+        seed,
+        results.len() - failed.len(),
+        results.len()
+    );
+    failed.is_empty()
+}
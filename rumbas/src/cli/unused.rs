@@ -0,0 +1,225 @@
+use crate::cli::check::read_file;
+use crate::cli::deps::{dependency_graph, question_preview_path};
+use rumbas::support::file_manager::CACHE;
+use rumbas::support::rc::within_repo;
+use rumbas_support::path::RumbasPath;
+use rumbas_support::preamble::Input;
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The folders with files that are only used by exams
+const USED_BY_EXAMS_FOLDERS: [&str; 5] = [
+    rumbas::QUESTIONS_FOLDER,
+    rumbas::RESOURCES_FOLDER,
+    rumbas::CUSTOM_PART_TYPES_FOLDER,
+    rumbas::THEMES_FOLDER,
+    rumbas::DEFAULTS_FOLDER,
+];
+
+pub fn unused() {
+    match unused_internal() {
+        Ok(_) => (),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+fn unused_internal() -> Result<(), ()> {
+    let root = within_repo(Path::new(".")).ok_or_else(|| {
+        log::error!("The current folder doesn't seem to belong to a rumbas project.");
+    })?;
+    if !crate::cli::rc::check_rc(&root, false) {
+        return Err(());
+    }
+    let (unused_files, unused_templates) = unused_files(&root);
+
+    let mut stdout = std::io::stdout().lock();
+    log::info!("{} files are not used by any exam:", unused_files.len());
+    for file in unused_files.iter() {
+        writeln!(stdout, "{}", file.display()).expect("Writing to stdout");
+    }
+    log::info!(
+        "{} other templates are not used by any file:",
+        unused_templates.len()
+    );
+    for template in unused_templates.iter() {
+        writeln!(stdout, "{}", template.display()).expect("Writing to stdout");
+    }
+    Ok(())
+}
+
+/// The files that no exam uses, and the other templates that no file uses
+fn unused_files(root: &RumbasPath) -> (BTreeSet<PathBuf>, BTreeSet<PathBuf>) {
+    let exams = all_files(root, rumbas::EXAMS_FOLDER);
+    let questions = all_files(root, rumbas::QUESTIONS_FOLDER);
+    let graph = dependency_graph(exams.iter().chain(questions.iter()).cloned().collect());
+
+    // Questions are read as their preview
+    let preview = question_preview_path(root);
+    let templates: BTreeSet<_> = exams
+        .iter()
+        .chain(questions.iter().filter(|_| preview.is_file()))
+        .filter(|file| is_template(file))
+        .map(|file| file.project().to_path_buf())
+        .collect();
+    let used = graph.transitive_dependencies(
+        &exams
+            .iter()
+            .map(|exam| exam.project().to_path_buf())
+            .filter(|exam| !templates.contains(exam))
+            .collect::<Vec<_>>()[..],
+    );
+
+    let unused_files: BTreeSet<_> = USED_BY_EXAMS_FOLDERS
+        .iter()
+        .flat_map(|folder| {
+            let mut files = Vec::new();
+            find_files(&root.absolute().join(folder), &mut files);
+            files
+        })
+        .filter_map(|file| file.strip_prefix(root.absolute()).ok().map(PathBuf::from))
+        .filter(|file| !is_used(file, &used))
+        .collect();
+
+    // The previews of questions use the question preview template
+    let unused_templates = templates
+        .into_iter()
+        .filter(|template| {
+            template.as_path() != preview.project()
+                && !unused_files.contains(template)
+                && graph.dependants(template).is_empty()
+        })
+        .collect();
+    (unused_files, unused_templates)
+}
+
+/// All exams (or questions) in the main folder
+fn all_files(root: &RumbasPath, folder: &str) -> HashSet<RumbasPath> {
+    let folder = root.keep_root(Path::new(folder));
+    if !folder.is_dir() {
+        return HashSet::new();
+    }
+    if folder.in_main_folder(rumbas::QUESTIONS_FOLDER) {
+        CACHE.find_all_questions_in_folder(folder)
+    } else {
+        CACHE.find_all_exams_in_folder(folder)
+    }
+    .into_iter()
+    .map(|f| f.file_path)
+    .collect()
+}
+
+/// A file is a template if some of its template keys have no (default) value
+fn is_template(file: &RumbasPath) -> bool {
+    read_file(file)
+        .map(|input| !input.find_missing().missing_template_keys().is_empty())
+        .unwrap_or(false)
+}
+
+/// The files in the folder and its subfolders, hidden files are skipped
+fn find_files(folder: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            find_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// The file is used if it, or a folder it is in, is used. The translations in a locale folder are
+/// used if the file they translate is used.
+fn is_used(file: &Path, used: &BTreeSet<PathBuf>) -> bool {
+    let translated = file.parent().and_then(|parent| {
+        let folder_name = parent.file_name()?.to_str()?;
+        if folder_name.starts_with(rumbas::LOCALE_FOLDER_PREFIX) {
+            Some(parent.parent()?.join(file.file_name()?))
+        } else {
+            None
+        }
+    });
+    translated
+        .as_deref()
+        .unwrap_or(file)
+        .ancestors()
+        .any(|path| used.contains(path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::temp_folder_with_files;
+
+    #[test]
+    fn used_files() {
+        let used: BTreeSet<_> = ["questions/question.yaml", "themes/theme"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert!(is_used(Path::new("questions/question.yaml"), &used));
+        assert!(is_used(Path::new("themes/theme/style.css"), &used));
+        assert!(is_used(
+            Path::new("questions/locale-nl/question.yaml"),
+            &used
+        ));
+        assert!(!is_used(Path::new("questions/other.yaml"), &used));
+        assert!(!is_used(Path::new("themes/other/style.css"), &used));
+        assert!(!is_used(Path::new("questions/locale-nl/other.yaml"), &used));
+    }
+
+    #[test]
+    fn unused_files_and_templates() {
+        let question =
+            "---\ntype: normal\nstatement: Statement\nadvice: Advice\nparts: []\nvariables: {}\n";
+        let exam = |questions: &str, name: &str| {
+            format!(
+                "---\ntype: normal\nname: {}\nquestion_groups:\n  - name: Group\n    picking_strategy:\n      type: all_ordered\n    questions:\n{}",
+                name, questions
+            )
+        };
+        let files = [
+            (
+                "exams/exam.yaml",
+                exam(
+                    "      - question.yaml\n      - type: template\n        template: template.yaml\n        a: 3\n",
+                    "Exam",
+                ),
+            ),
+            (
+                "exams/exam_template.yaml",
+                exam("      - question.yaml\n", "template:name"),
+            ),
+            ("questions/question.yaml", question.to_string()),
+            ("questions/locale-nl/question.yaml", question.to_string()),
+            (
+                "questions/template.yaml",
+                question.replace("variables: {}", "variables:\n  a: template:a"),
+            ),
+            ("questions/unused.yaml", question.to_string()),
+            ("resources/unused.png", String::new()),
+            ("themes/unused/style.css", String::new()),
+        ];
+        let folder = temp_folder_with_files(&files);
+        let root_path = RumbasPath::create(folder.path(), folder.path()).unwrap();
+
+        let (unused_files, unused_templates) = unused_files(&root_path);
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<BTreeSet<_>>();
+        assert_eq!(
+            unused_files,
+            paths(&[
+                "questions/unused.yaml",
+                "resources/unused.png",
+                "themes/unused/style.css"
+            ])
+        );
+        assert_eq!(unused_templates, paths(&["exams/exam_template.yaml"]));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::cli::check::{files_from_paths, load_file, CheckResult};
+use numbas::jme::eval::EvaluationError;
+use rumbas::question::variable_sampling::{VariableError, VariablesSample};
+use rumbas::question::Question;
+use rumbas_support::path::RumbasPath;
+use std::ops::Range;
+
+pub fn variables(exam_question_paths: Vec<String>, seeds: u64, first_seed: u64) {
+    match variables_internal(exam_question_paths, seeds, first_seed) {
+        Ok(_) => (),
+        Err(_) => std::process::exit(1),
+    }
+}
+
+pub fn variables_internal(
+    exam_question_paths: Vec<String>,
+    seeds: u64,
+    first_seed: u64,
+) -> Result<(), ()> {
+    let seeds = match first_seed.checked_add(seeds) {
+        Some(end) => first_seed..end,
+        None => {
+            log::error!(
+                "Can't use {} seeds starting from seed {}, the seeds would be larger than {}.",
+                seeds,
+                first_seed,
+                u64::MAX
+            );
+            return Err(());
+        }
+    };
+    let files = files_from_paths(exam_question_paths)?;
+    let mut success = true;
+    for file in files.into_iter() {
+        success &= variables_for_file(&file, seeds.clone());
+    }
+    if success {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+/// Returns true if the variables of all questions can be generated
+fn variables_for_file(path: &RumbasPath, seeds: Range<u64>) -> bool {
+    log::info!("Generating variables for {}", path.display());
+    let exam = match load_file(path) {
+        Ok(exam) => exam,
+        Err(CheckResult::Template) => {
+            log::info!("{} is a template, skipping it.", path.display());
+            return true;
+        }
+        Err(check_result) => {
+            log::error!("Loading {} failed:", path.display());
+            check_result.log(path);
+            return false;
+        }
+    };
+    let locale = match exam.data.locales().first() {
+        Some(locale) => locale.name.clone(),
+        None => {
+            CheckResult::LocalesNotSet.log(path);
+            return false;
+        }
+    };
+    let mut success = true;
+    for question in exam
+        .data
+        .question_groups()
+        .into_iter()
+        .flat_map(|g| g.questions.into_iter())
+    {
+        let name = question
+            .question_path
+            .clone()
+            .unwrap_or_else(|| path.display().to_string());
+        log::info!("Variables of question {} (locale {})", name, locale);
+        success &= report(
+            &question.data,
+            question.data.sample_variables(&locale, seeds.clone()),
+        );
+    }
+    success
+}
+
+/// Log the generated values and the variables test statistics.
+/// Returns false if some variables can't be generated or the variables test fails.
+fn report(question: &Question, samples: Vec<VariablesSample>) -> bool {
+    let names: Vec<String> = samples
+        .first()
+        .map(|s| s.values.keys().cloned().collect())
+        .unwrap_or_default();
+    if names.is_empty() {
+        log::info!("The question has no variables.");
+    } else {
+        log_table(&names, &samples);
+    }
+
+    let mut success = true;
+    let mut reported = std::collections::HashSet::new();
+    for sample in samples.iter() {
+        for (name, value) in sample.values.iter() {
+            match value {
+                // Only the original error is reported
+                Err(VariableError::FailingDependency(_)) => (),
+                Err(e) if reported.insert(name.clone()) => {
+                    if let VariableError::Evaluation(EvaluationError::UnsupportedFunction(_)) = e
+                    {
+                        log::warn!("Variable {} can't be generated: {}", name, e);
+                    } else {
+                        log::error!("Variable {} can't be generated: {}", name, e);
+                        success = false;
+                    }
+                }
+                _ => (),
+            }
+        }
+        if let Some(Err(e)) = &sample.condition {
+            if reported.insert("variables test".to_string()) {
+                log::error!("The variables test can't be evaluated: {}", e);
+                success = false;
+            }
+        }
+    }
+
+    if question.variables_test.condition.is_empty() {
+        return success;
+    }
+    let max_runs = question.variables_test.max_runs.max(1);
+    let runs: usize = samples.iter().map(|s| s.runs).sum();
+    let passed = samples.iter().filter(|s| s.passed()).count();
+    let pass_rate = if runs == 0 {
+        0.0
+    } else {
+        passed as f64 / runs as f64
+    };
+    log::info!(
+        "The variables test passed for {} of {} generated sets of variables ({:.1}%).",
+        passed,
+        runs,
+        pass_rate * 100.0
+    );
+    let failure_chance = (1.0 - pass_rate).powi(max_runs as i32);
+    if passed < samples.len() {
+        log::error!(
+            "For {} of {} seeds, no set of variables satisfying the variables test was found within max_runs ({}). The estimated chance that a student gets a broken question is {:.1}%.",
+            samples.len() - passed,
+            samples.len(),
+            max_runs,
+            failure_chance * 100.0
+        );
+        success = false;
+    } else if failure_chance > 0.0001 {
+        log::warn!(
+            "The estimated chance that no set of variables satisfying the variables test is found within max_runs ({}) is {:.2}%.",
+            max_runs,
+            failure_chance * 100.0
+        );
+    }
+    success
+}
+
+fn log_table(names: &[String], samples: &[VariablesSample]) {
+    let header: Vec<String> = vec!["seed".to_string(), "runs".to_string()]
+        .into_iter()
+        .chain(names.iter().cloned())
+        .collect();
+    let rows: Vec<Vec<String>> = samples
+        .iter()
+        .map(|sample| {
+            vec![sample.seed.to_string(), sample.runs.to_string()]
+                .into_iter()
+                .chain(names.iter().map(|name| match &sample.values[name] {
+                    Ok(value) => value.to_string(),
+                    Err(_) => "error".to_string(),
+                }))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(rows.iter())
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let format_row = |row: &Vec<String>| {
+        row.iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    };
+    log::info!("{}", format_row(&header));
+    log::info!(
+        "{}",
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .join("-+-")
+    );
+    for row in rows.iter() {
+        log::info!("{}", format_row(row));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn too_many_seeds() {
+        assert_eq!(variables_internal(Vec::new(), 2, u64::MAX - 1), Err(()));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::cli::compile::{CompilationContext, FileCompilationContext};
+use crate::cli::serve::Reloader;
 use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use rumbas::support::cli::MessageFormat;
 use rumbas::support::dependency_manager::DEPENDENCIES;
 use rumbas::support::file_manager::RumbasRepoFileData;
 use rumbas::support::file_manager::CACHE;
@@ -24,22 +26,31 @@ pub struct WatchContext {
 }
 
 fn watch_internal(context: WatchContext) {
-    let path = Path::new(".");
-    if path.is_absolute() {
-        // TODO
-        log::error!("Absolute path's are not supported");
-        return;
+    if context.only_check {
+        watch_with_handler(&context.watch_path, &WatchChecker)
+    } else {
+        watch_with_handler(&context.watch_path, &WatchCompiler { reloader: None })
     }
+}
 
-    let handler: &dyn WatchHandler = if context.only_check {
-        &WatchChecker
-    } else {
-        &WatchCompiler
+/// Handle the given path and keep handling the files that change
+pub fn watch_with_handler(watch_path: &str, handler: &dyn WatchHandler) {
+    let watched = match within_repo(Path::new(watch_path)) {
+        Some(watched) => watched,
+        None => {
+            log::error!(
+                "{:?} doesn't seem to belong to a rumbas project.",
+                watch_path
+            );
+            return;
+        }
     };
 
-    handler.handle_setup(&context.watch_path);
+    handler.handle_setup(watch_path);
 
-    log::info!("Watching {:?}", path.display());
+    // The whole project is watched, the dependencies of the watched files can be anywhere in it
+    let root = watched.root().to_path_buf();
+    log::info!("Watching {:?}", root.display());
 
     // Create a channel to receive the events.
     let (tx, rx) = channel();
@@ -50,16 +61,24 @@ fn watch_internal(context: WatchContext) {
 
     // Add a path to be watched. All files and directories at that path and
     // below will be monitored for changes.
-    watcher.watch(path, RecursiveMode::Recursive).unwrap();
+    watcher.watch(&root, RecursiveMode::Recursive).unwrap();
 
     loop {
         match rx.recv() {
             Ok(event) => match event {
-                DebouncedEvent::Create(p) => handle_if_needed(&p, handler), // Shouldn't do anything?
-                DebouncedEvent::Write(p) => handle_if_needed(&p, handler),
-                DebouncedEvent::Chmod(p) => handle_if_needed(&p, handler),
-                DebouncedEvent::Remove(p) => handle_if_needed(&p, handler),
-                DebouncedEvent::Rename(_previous, _new) => (), // TODO do the rename in the dependencies?
+                DebouncedEvent::Create(p) => handle_if_needed(&p, &watched, handler),
+                DebouncedEvent::Write(p) => handle_if_needed(&p, &watched, handler),
+                DebouncedEvent::Chmod(p) => handle_if_needed(&p, &watched, handler),
+                DebouncedEvent::Remove(p) => handle_removed(&p, &watched, handler),
+                DebouncedEvent::Rename(previous, new) => {
+                    if let (Some(previous), Some(new)) =
+                        (repo_file(&previous, &watched), repo_file(&new, &watched))
+                    {
+                        DEPENDENCIES.rename(&previous, &new);
+                    }
+                    handle_removed(&previous, &watched, handler);
+                    handle_if_needed(&new, &watched, handler);
+                }
                 _ => (),
             },
             Err(e) => log::error!("watch error: {:?}", e),
@@ -67,19 +86,55 @@ fn watch_internal(context: WatchContext) {
     }
 }
 
-fn handle_if_needed(path: &Path, handler: &dyn WatchHandler) {
-    if let Some(path) = within_repo(&path) {
-        if path.in_main_folder(crate::cli::compile::CACHE_FOLDER)
-            || path.in_main_folder(crate::cli::compile::OUTPUT_FOLDER)
-        {
-            // do nothing
-        } else {
-            handler.recompile_dependant(path)
+/// The path within the project, if it is not in the cache or output folder.
+/// The file doesn't need to exist (anymore).
+fn repo_file(path: &Path, watched: &RumbasPath) -> Option<RumbasPath> {
+    RumbasPath::create(path, watched.root()).filter(|path| {
+        !(path.in_main_folder(crate::cli::compile::CACHE_FOLDER)
+            || path.in_main_folder(crate::cli::compile::OUTPUT_FOLDER))
+    })
+}
+
+/// The file can be added or removed, so the cached entries of its folder are outdated
+fn forget_folder_entries(path: &RumbasPath) {
+    if let Some(folder) = path.parent() {
+        CACHE.delete_folder(&folder);
+    }
+}
+
+fn handle_if_needed(path: &Path, watched: &RumbasPath, handler: &dyn WatchHandler) {
+    if let Some(path) = repo_file(path, watched) {
+        forget_folder_entries(&path);
+        // An exam or question that is not compiled yet
+        let is_new = path.is_file()
+            && path.absolute().starts_with(watched.absolute())
+            && !DEPENDENCIES.get_dependants(path.clone()).contains(&path)
+            && crate::cli::check::find_all_files(watched.clone()).contains(&path);
+        handler.recompile_dependant(path.clone());
+        if is_new {
+            log::info!("Found new file {}", path.display());
+            handler.handle_file(&path);
+        }
+    }
+}
+
+fn handle_removed(path: &Path, watched: &RumbasPath, handler: &dyn WatchHandler) {
+    if let Some(path) = repo_file(path, watched) {
+        forget_folder_entries(&path);
+        DEPENDENCIES.remove_dependant(&path);
+        let dependants = DEPENDENCIES.get_dependants(path.clone());
+        if !dependants.is_empty() {
+            log::warn!(
+                "{} is removed, but {} files depend on it.",
+                path.display(),
+                dependants.len()
+            );
         }
+        handler.recompile_dependant(path);
     }
 }
 
-trait WatchHandler {
+pub trait WatchHandler {
     fn handle_setup(&self, path: &str);
     fn handle_file(&self, path: &RumbasPath);
     fn recompile_dependant(&self, path: RumbasPath) {
@@ -102,20 +157,30 @@ pub struct WatchChecker;
 impl WatchHandler for WatchChecker {
     fn handle_setup(&self, path: &str) {
         // TODO
-        crate::cli::check::check_internal(vec![path.to_string()]);
+        crate::cli::check::check_internal(vec![path.to_string()], MessageFormat::Human);
     }
     fn handle_file(&self, path: &RumbasPath) {
-        crate::cli::check::check_file(&path);
+        let check_result = crate::cli::check::check_file(path);
+        if check_result.has_failure() {
+            log::error!("Check for {} failed:", path.display());
+            check_result.log(path);
+        }
     }
 }
 
-pub struct WatchCompiler;
+pub struct WatchCompiler {
+    /// Reloads the open previews of the recompiled exams
+    pub reloader: Option<Reloader>,
+}
 impl WatchCompiler {
     fn file_context() -> FileCompilationContext {
         FileCompilationContext {
             use_scorm: false,
             as_zip: false,
             minify: false,
+            force: false,
+            numbas_workers: false,
+            exam_only: None,
             output_folder: Path::new(crate::cli::compile::OUTPUT_FOLDER).to_path_buf(),
         }
     }
@@ -126,11 +191,20 @@ impl WatchHandler for WatchCompiler {
         crate::cli::compile::compile_internal(
             CompilationContext {
                 compile_paths: vec![path.to_string()],
+                message_format: MessageFormat::Human,
+                jobs: None,
             },
             Self::file_context(),
         );
     }
     fn handle_file(&self, path: &RumbasPath) {
-        crate::cli::compile::compile_file(&Self::file_context(), &path);
+        let compile_result = crate::cli::compile::compile_file(&Self::file_context(), path);
+        if compile_result.has_failure() {
+            log::error!("Compilation for {} failed:", path.display());
+            compile_result.log(path);
+        }
+        if let Some(reloader) = &self.reloader {
+            reloader.reload(path);
+        }
     }
 }
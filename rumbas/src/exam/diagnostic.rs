@@ -76,7 +76,7 @@ impl ToNumbas<numbas::exam::Exam> for DiagnosticExam {
                 qg.clone().questions.into_iter().map(|q| q.data.extensions) // todo: extract?
             })
             .fold(Extensions::default(), Extensions::combine)
-            .to_paths();
+            .to_numbas(locale, &());
 
         let custom_part_types: Vec<numbas::question::custom_part_type::CustomPartType> = self
             .question_groups
@@ -320,7 +320,10 @@ pub fn convert_diagnostic_numbas_exam(
             numbas_settings: NumbasSettings {
                 theme: "default".to_string(),
             }, // todo: argument?
-            diagnostic: exam.diagnostic.unwrap().to_rumbas(), // Always set for a diagnostic exam
+            diagnostic: exam
+                .diagnostic
+                .expect("checked by convert_numbas_exam")
+                .to_rumbas(),
         },
         question_groups
             .into_iter()
@@ -15,12 +15,14 @@ use crate::exam::locale::Locale;
 use crate::exam::normal::convert_normal_numbas_exam;
 use crate::exam::normal::NormalExam;
 use crate::exam::question_group::QuestionFromTemplate;
-use crate::question::custom_part_type::CustomPartTypeDefinitionPath;
+use crate::question::custom_part_type::{
+    check_numbas_custom_part_types, CustomPartTypeDefinitionPath,
+};
 use crate::support::default::combine_exam_with_default_files;
 use crate::support::file_manager::{FileToRead, CACHE};
 use crate::support::template::{TemplateFile, TemplateFileInputEnum};
 use crate::support::to_numbas::ToNumbas;
-use crate::support::to_rumbas::ToRumbas;
+use crate::support::to_rumbas::{ConversionError, ToRumbas};
 use crate::support::yaml::parse_yaml;
 use crate::support::yaml::YamlError;
 use comparable::Comparable;
@@ -68,6 +70,13 @@ impl Exam {
             Exam::Diagnostic(n) => n.numbas_settings.clone(),
         }
     }
+
+    pub fn question_groups(&self) -> Vec<question_group::QuestionGroup> {
+        match self {
+            Exam::Normal(n) => n.question_groups.clone(),
+            Exam::Diagnostic(n) => n.question_groups.clone(),
+        }
+    }
 }
 impl ExamInput {
     pub fn combine_with_defaults(&mut self, path: &RumbasPath) {
@@ -160,6 +169,18 @@ impl ExamFileType {
     pub fn to_yaml(&self) -> serde_yaml::Result<String> {
         ExamFileTypeInput::from_normal(self.to_owned()).to_yaml()
     }
+    /// The questions of the exam, an exam that uses a template has none
+    pub fn questions_mut(&mut self) -> Vec<&mut question_group::QuestionFromTemplate> {
+        let question_groups = match self {
+            Self::Template(_) => return Vec::new(),
+            Self::Normal(n) => &mut n.question_groups,
+            Self::Diagnostic(n) => &mut n.question_groups,
+        };
+        question_groups
+            .iter_mut()
+            .flat_map(|g| g.questions.iter_mut())
+            .collect()
+    }
 }
 
 /// Convert a numbas exam to rumbas data
@@ -167,14 +188,24 @@ impl ExamFileType {
 /// and vectors of questions and custom part type definitions
 pub fn convert_numbas_exam(
     exam: numbas::exam::Exam,
-) -> (
-    String,
-    ExamFileType,
-    Vec<QuestionFromTemplate>,
-    Vec<CustomPartTypeDefinitionPath>,
-) {
+) -> Result<
+    (
+        String,
+        ExamFileType,
+        Vec<QuestionFromTemplate>,
+        Vec<CustomPartTypeDefinitionPath>,
+    ),
+    ConversionError,
+> {
+    check_numbas_custom_part_types(&exam.custom_part_types)?;
+    for question in exam.question_groups.iter().flat_map(|g| g.questions.iter()) {
+        check_numbas_custom_part_types(&question.custom_part_types)?;
+    }
     let (name, exam, qgs, cpts) = match exam.navigation.navigation_mode {
         numbas::exam::navigation::NavigationMode::Diagnostic(ref _d) => {
+            if exam.diagnostic.is_none() {
+                return Err(ConversionError::MissingDiagnostic);
+            }
             let (exam, qgs, cpts) = convert_diagnostic_numbas_exam(exam);
             (exam.name.clone(), ExamFileType::Diagnostic(exam), qgs, cpts)
         }
@@ -183,12 +214,12 @@ pub fn convert_numbas_exam(
             (exam.name.clone(), ExamFileType::Normal(exam), qgs, cpts)
         }
     };
-    (
+    Ok((
         name.to_string("").expect("no locale needed"),
         exam,
         qgs,
         cpts,
-    )
+    ))
 }
 
 #[derive(Serialize, Deserialize, Comparable, Debug, Clone, JsonSchema)]
@@ -483,6 +514,12 @@ impl RumbasCheck for RecursiveTemplateExam {
         */
         previous_result
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        self.data.check_identifiers(locale, scope)
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        self.data.check_jme_formatting(locale)
+    }
 }
 
 impl Overwrite<RecursiveTemplateExamInput> for RecursiveTemplateExamInput {
@@ -71,7 +71,7 @@ impl ToNumbas<numbas::exam::Exam> for NormalExam {
             .iter()
             .flat_map(|qg| qg.clone().questions.into_iter().map(|q| q.data.extensions))
             .fold(Extensions::default(), Extensions::combine)
-            .to_paths();
+            .to_numbas(locale, &());
 
         let custom_part_types: Vec<numbas::question::custom_part_type::CustomPartType> = self
             .question_groups
@@ -1,4 +1,5 @@
 use crate::exam::ParseError;
+use crate::question::custom_part_type::check_numbas_custom_part_types;
 use crate::question::Question;
 use crate::question::{QuestionFileTypeInput, QuestionInput};
 use crate::support::default::combine_question_with_default_files;
@@ -6,7 +7,7 @@ use crate::support::file_manager::*;
 use crate::support::sanitize::sanitize;
 use crate::support::template::{TemplateFile, TemplateFileInput};
 use crate::support::to_numbas::ToNumbas;
-use crate::support::to_rumbas::ToRumbas;
+use crate::support::to_rumbas::{ConversionError, ToRumbas};
 use crate::support::translatable::TranslatableString;
 use crate::support::yaml::{parse_yaml, YamlError};
 use comparable::Comparable;
@@ -515,18 +516,40 @@ impl Input for QuestionFromTemplateInput {
     }
 }
 
+impl QuestionFromTemplate {
+    /// The segment of the question in the path of check results: the question path or the path
+    /// of the template, without extension, relative to the `questions` folder
+    fn check_path(&self) -> String {
+        if let Some(p) = self.question_path.as_ref() {
+            p.clone()
+        } else {
+            let template_path = &self.template_data.first().unwrap().relative_template_path;
+            template_path
+                .strip_suffix(".yaml")
+                .unwrap_or(template_path)
+                .to_string()
+        }
+    }
+}
+
 impl RumbasCheck for QuestionFromTemplate {
     fn check(&self, locale: &str) -> RumbasCheckResult {
         let mut previous_result = self.data.check(locale);
-        previous_result.extend_path(if let Some(p) = self.question_path.as_ref() {
-            p.clone()
-        } else {
-            self.template_data
-                .first()
-                .unwrap()
-                .relative_template_path
-                .clone()
-        });
+        // The variable checks need a question without other problems
+        if previous_result.is_empty() {
+            previous_result = self.data.check_variables(locale);
+            previous_result.union(&self.data.check_question_identifiers(locale));
+        }
+        previous_result.extend_path(self.check_path());
+        previous_result
+    }
+    /// The identifiers of a question are checked in the scope of the question itself
+    fn check_identifiers(&self, _locale: &str, _scope: &ValidationScope) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        let mut previous_result = self.data.check_jme_formatting(locale);
+        previous_result.extend_path(self.check_path());
         previous_result
     }
 }
@@ -560,6 +583,14 @@ impl ToRumbas<QuestionFromTemplate> for numbas::question::Question {
     }
 }
 
+/// Convert a numbas question to rumbas data
+pub fn convert_numbas_question(
+    question: &numbas::question::Question,
+) -> Result<QuestionFromTemplate, ConversionError> {
+    check_numbas_custom_part_types(&question.custom_part_types)?;
+    Ok(question.to_rumbas())
+}
+
 impl std::hash::Hash for QuestionFromTemplate {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.template_data.hash(state);
@@ -26,30 +26,83 @@ fn main() {
         (false, _) => log::LevelFilter::Debug, // debug for 4 or more v's
     };
 
-    cli::logger::setup(log_level).expect("Working logger");
+    cli::logger::setup(log_level, args.command.log_output()).expect("Working logger");
 
     match args.command {
         Command::Import {
-            exam_path,
+            exam_paths,
             question,
-        } => cli::import(exam_path, question),
+        } => cli::import(exam_paths, question),
         Command::Compile {
             exam_or_question_paths,
             scorm,
             zip,
             no_minification,
-        } => cli::compile(exam_or_question_paths, scorm, zip, no_minification),
+            force,
+            jobs,
+            numbas_workers,
+            exam_only,
+            message_format,
+        } => cli::compile(
+            exam_or_question_paths,
+            scorm,
+            zip,
+            no_minification,
+            force,
+            jobs.map(usize::from),
+            numbas_workers,
+            exam_only,
+            message_format,
+        ),
         Command::Watch { path, only_check } => cli::watch(path, only_check),
+        Command::Serve { path, port } => cli::serve(path, port),
         Command::Check {
             exam_or_question_paths,
-        } => cli::check(exam_or_question_paths),
+            message_format,
+        } => cli::check(exam_or_question_paths, message_format),
+        Command::Lint {
+            exam_or_question_paths,
+            message_format,
+        } => cli::lint(exam_or_question_paths, message_format),
+        Command::Variables {
+            exam_or_question_paths,
+            seeds,
+            first_seed,
+        } => cli::variables(exam_or_question_paths, seeds, first_seed),
+        Command::Test {
+            exam_or_question_paths,
+            seeds,
+            first_seed,
+        } => cli::test(exam_or_question_paths, seeds, first_seed),
         Command::UpdateRepo => cli::update_repo(),
         Command::Init { summative } => cli::init(summative),
+        Command::Lsp => cli::lsp(),
         Command::Schema => cli::schema(),
         Command::GenerateShellCompletion { shell } => cli::complete(Cli::command(), shell),
         Command::Fmt {
             exam_or_question_paths,
-        } => cli::fmt(exam_or_question_paths),
+            jme,
+            message_format,
+        } => cli::fmt(exam_or_question_paths, jme, message_format),
+        Command::Deps {
+            exam_or_question_paths,
+            format,
+            dependants_of,
+            dependencies_of,
+            kind,
+        } => cli::deps(
+            exam_or_question_paths,
+            format,
+            dependants_of,
+            dependencies_of,
+            kind,
+        ),
+        Command::Affected {
+            exam_or_question_paths,
+            since,
+            compile,
+        } => cli::affected(exam_or_question_paths, since, compile),
+        Command::Unused => cli::unused(),
         Command::Export {
             exam_or_question_paths,
         } => cli::export(exam_or_question_paths),
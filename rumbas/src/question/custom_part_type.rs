@@ -4,7 +4,7 @@ use crate::support::file_manager::*;
 use crate::support::noneable::Noneable;
 use crate::support::sanitize::sanitize;
 use crate::support::to_numbas::ToNumbas;
-use crate::support::to_rumbas::ToRumbas;
+use crate::support::to_rumbas::{ConversionError, ToRumbas};
 use crate::support::translatable::EmbracedJMETranslatableString;
 use crate::support::translatable::JMETranslatableString;
 use crate::support::translatable::TranslatableString;
@@ -508,6 +508,28 @@ impl ToNumbas<numbas::question::custom_part_type::CustomPartTypeSettingPercentag
     }
 }
 
+/// Check that the numbas custom part types can be converted
+pub fn check_numbas_custom_part_types(
+    custom_part_types: &[numbas::question::custom_part_type::CustomPartType],
+) -> Result<(), ConversionError> {
+    for cpt in custom_part_types.iter() {
+        for setting in cpt.settings.iter() {
+            if let numbas::question::custom_part_type::CustomPartTypeSetting::Percentage(p) =
+                setting
+            {
+                if !p.default_value.is_empty() && p.default_value.parse::<f64>().is_err() {
+                    return Err(ConversionError::InvalidPercentage {
+                        custom_part_type: cpt.short_name.clone(),
+                        setting: p.shared_data.name.clone(),
+                        value: p.default_value.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 impl ToRumbas<CustomPartTypeSettingPercentage>
     for numbas::question::custom_part_type::CustomPartTypeSettingPercentage
 {
@@ -520,7 +542,7 @@ impl ToRumbas<CustomPartTypeSettingPercentage>
                 Noneable::NotNone(
                     self.default_value
                         .parse()
-                        .expect("Floating point percentage in custom part type"),
+                        .expect("checked by check_numbas_custom_part_types"),
                 )
             },
         }
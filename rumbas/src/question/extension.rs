@@ -55,21 +55,6 @@ macro_rules! extensions {
                     ),*
                 }
             }
-
-            pub fn to_paths(&self) -> Vec<String> {
-                let numbas_path = std::env::var(crate::NUMBAS_FOLDER_ENV)
-                    .expect(&format!("{} to be set", crate::NUMBAS_FOLDER_ENV)[..]);
-                let mut paths = Vec::new();
-                $(
-                    if self.$name {
-                        paths.push($path);
-                    }
-                )*
-                paths
-                    .into_iter()
-                    .map(|s| format!("{}/extensions/{}", numbas_path, s))
-                    .collect()
-            }
         }
 
         impl Default for Extensions {
@@ -0,0 +1,92 @@
+//! Check that the jme expressions of a question only use names that are defined.
+
+use crate::question::function::FunctionDefinition;
+use crate::question::Question;
+use rumbas_support::preamble::*;
+
+impl Question {
+    /// The names that can be used in the jme expressions of this question: the variables, the
+    /// enabled builtin constants, the custom constants and the functions.
+    pub fn validation_scope(&self) -> ValidationScope {
+        let mut scope = self.validation_scope_without_variables();
+        for name in self.all_variables().keys() {
+            scope.add_variable(name);
+        }
+        scope
+    }
+
+    /// The names that can be used in the jme expressions of this question, except for the
+    /// variables
+    pub(crate) fn validation_scope_without_variables(&self) -> ValidationScope {
+        let mut scope = ValidationScope::new();
+        if !self.builtin_constants.e {
+            scope.disable_constant("e");
+        }
+        if !self.builtin_constants.pi {
+            scope.disable_constant("pi");
+            scope.disable_constant("π");
+        }
+        if !self.builtin_constants.i {
+            scope.disable_constant("i");
+        }
+        for constant in self.custom_constants.iter() {
+            scope.add_variable(&constant.name);
+        }
+        for name in self.functions.keys() {
+            scope.add_function(name);
+        }
+        scope
+    }
+
+    /// Check the identifiers and types in the statement, advice, parts, variables test and
+    /// functions, and the types in the variable definitions
+    pub fn check_question_identifiers(&self, locale: &str) -> RumbasCheckResult {
+        let scope = self.typed_validation_scope(locale);
+        let mut result = self.check_variable_types(locale, &scope);
+        result.union(&self.check_usage_identifiers(locale, &scope));
+        result
+    }
+
+    /// Check the identifiers in the statement, advice, parts, variables test and functions: all
+    /// places where the variables can be used, except for the definitions of other variables
+    pub(crate) fn check_usage_identifiers(
+        &self,
+        locale: &str,
+        scope: &ValidationScope,
+    ) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+
+        let mut previous_result = self.statement.check_identifiers(locale, scope);
+        previous_result.extend_path("statement".to_string());
+        result.union(&previous_result);
+
+        let mut previous_result = self.advice.check_identifiers(locale, scope);
+        previous_result.extend_path("advice".to_string());
+        result.union(&previous_result);
+
+        let mut previous_result = self.parts.check_identifiers(locale, scope);
+        previous_result.extend_path("parts".to_string());
+        result.union(&previous_result);
+
+        let mut previous_result = self.variables_test.check_identifiers(locale, scope);
+        previous_result.extend_path("variables_test".to_string());
+        result.union(&previous_result);
+
+        for (name, function) in self.functions.iter() {
+            if let FunctionDefinition::JME(jme) = &function.definition {
+                // The parameters can be used in the body of the function
+                let mut function_scope = scope.clone();
+                for (parameter, parameter_type) in function.parameters.iter() {
+                    function_scope.add_variable(parameter);
+                    function_scope.set_variable_type(parameter, parameter_type.jme_type());
+                }
+                let mut previous_result = jme.definition.check_identifiers(locale, &function_scope);
+                previous_result.extend_path("definition".to_string());
+                previous_result.extend_path(name.clone());
+                previous_result.extend_path("functions".to_string());
+                result.union(&previous_result);
+            }
+        }
+        result
+    }
+}
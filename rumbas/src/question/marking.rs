@@ -0,0 +1,690 @@
+//! Simulate the marking of the builtin part types, without using Numbas.
+//!
+//! This is used to run the `tests` of the parts: each test gives an answer of a student and the
+//! marks that it should be awarded.
+
+use crate::question::part::question_part::QuestionPart;
+use crate::question::variable_sampling::VariableSampler;
+use crate::question::Question;
+use crate::support::to_numbas::ToNumbas;
+use numbas::jme::ast::Expr;
+use numbas::jme::equivalence::{expressions_equivalent, EquivalenceError};
+use numbas::jme::eval::{EvaluationError, Evaluator, Scope, Value};
+use numbas::jme::{EmbracedJMEString, JMEString};
+use numbas::question::part::choose_multiple::{
+    MultipleChoiceMarkingMethod, QuestionPartChooseMultiple,
+};
+use numbas::question::part::choose_one::QuestionPartChooseOne;
+use numbas::question::part::jme::QuestionPartJME;
+use numbas::question::part::match_answers::{
+    MatchAnswersWithChoicesDisplayType, QuestionPartMatchAnswersWithChoices,
+};
+use numbas::question::part::matrix::QuestionPartMatrix;
+use numbas::question::part::number_entry::{NumberEntryAnswerType, QuestionPartNumberEntry};
+use numbas::question::part::pattern_match::{PatternMatchMode, QuestionPartPatternMatch};
+use numbas::support::primitive::{Number, VariableValued};
+use numbas::support::student_answer::{AnswerValue, StudentAnswer};
+use regex::RegexBuilder;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkingError {
+    /// The marking of this part type can't be simulated
+    UnsupportedPartType(&'static str),
+    /// The answer doesn't have the right form for the part type
+    InvalidAnswer(String),
+    /// An expression of the part is empty or not valid
+    InvalidExpression(String),
+    /// An expression of the part can't be evaluated
+    Evaluation(String, EvaluationError),
+    /// An expression of the part evaluates to a value of the wrong type
+    UnexpectedValue(String, &'static str, Value),
+    /// The answer pattern of a pattern match part is not a valid regular expression
+    InvalidPattern(String),
+    /// The answer of a jme part can't be compared with the correct answer
+    Equivalence(EquivalenceError),
+}
+
+impl Display for MarkingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedPartType(t) => {
+                write!(f, "The marking of {} parts can't be simulated", t)
+            }
+            Self::InvalidAnswer(expected) => write!(f, "The answer should be {}", expected),
+            Self::InvalidExpression(e) => write!(f, "Invalid expression: {}", e),
+            Self::Evaluation(e, error) => write!(f, "Evaluating {} failed: {}", e, error),
+            Self::UnexpectedValue(e, expected, value) => {
+                write!(f, "{} should be {}, but it is {}", e, expected, value)
+            }
+            Self::InvalidPattern(p) => write!(f, "Invalid answer pattern: {}", p),
+            Self::Equivalence(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+type MarkingResult = Result<f64, MarkingError>;
+
+/// The result of one test of a part
+#[derive(Debug, Clone)]
+pub struct MarkingTestResult {
+    /// The path of the part, e.g. `parts/0/gaps/1`
+    pub part: String,
+    pub answer: StudentAnswer,
+    /// The marks that the test expects
+    pub expected: f64,
+    /// The simulated marks
+    pub marks: MarkingResult,
+}
+
+impl MarkingTestResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.marks, Ok(marks) if (marks - self.expected).abs() < 1e-9)
+    }
+}
+
+impl Question {
+    /// Simulate the marking of the tests of all parts, steps and gaps, with the variables that
+    /// are generated for the given seed.
+    pub fn run_marking_tests(&self, locale: &str, seed: u64) -> Vec<MarkingTestResult> {
+        let sampler = VariableSampler::new(self, locale);
+        let scope = sampler.scope(&sampler.sample(seed));
+        let mut evaluator = Evaluator::new(seed);
+        let mut results = Vec::new();
+        for (idx, part) in self.parts.iter().enumerate() {
+            part.run_marking_tests(
+                format!("parts/{}", idx),
+                locale,
+                &mut evaluator,
+                &scope,
+                &mut results,
+            );
+        }
+        results
+    }
+}
+
+impl QuestionPart {
+    fn run_marking_tests(
+        &self,
+        path: String,
+        locale: &str,
+        evaluator: &mut Evaluator,
+        scope: &Scope,
+        results: &mut Vec<MarkingTestResult>,
+    ) {
+        let tests = self.tests();
+        if !tests.is_empty() {
+            let part = self.to_numbas(locale, &());
+            for test in tests.iter() {
+                results.push(MarkingTestResult {
+                    part: path.clone(),
+                    answer: test.answer.clone(),
+                    expected: number(&test.marks),
+                    marks: mark(&part, &test.answer, evaluator, scope),
+                });
+            }
+        }
+        for (sub_path, sub_part) in self.sub_parts() {
+            sub_part.run_marking_tests(
+                format!("{}/{}", path, sub_path.join("/")),
+                locale,
+                evaluator,
+                scope,
+                results,
+            );
+        }
+    }
+}
+
+/// Simulate the marking of the answer for the given part
+pub fn mark(
+    part: &numbas::question::part::QuestionPart,
+    answer: &StudentAnswer,
+    evaluator: &mut Evaluator,
+    scope: &Scope,
+) -> MarkingResult {
+    use numbas::question::part::QuestionPartBuiltin as B;
+    let mut marker = Marker { evaluator, scope };
+    match part {
+        numbas::question::part::QuestionPart::Builtin(b) => match b {
+            B::JME(p) => marker.jme(p, answer),
+            B::NumberEntry(p) => marker.number_entry(p, answer),
+            B::Matrix(p) => marker.matrix(p, answer),
+            B::PatternMatch(p) => marker.pattern_match(p, answer),
+            B::ChooseOne(p) => marker.choose_one(p, answer),
+            B::ChooseMultiple(p) => marker.choose_multiple(p, answer),
+            B::MatchAnswersWithChoices(p) => marker.match_answers(p, answer),
+            B::GapFill(_) => Err(MarkingError::UnsupportedPartType("gapfill")),
+            B::Information(_) => Err(MarkingError::UnsupportedPartType("information")),
+            B::Extension(_) => Err(MarkingError::UnsupportedPartType("extension")),
+        },
+        numbas::question::part::QuestionPart::Custom(_) => {
+            Err(MarkingError::UnsupportedPartType("custom"))
+        }
+    }
+}
+
+struct Marker<'a, 'b> {
+    evaluator: &'a mut Evaluator,
+    scope: &'a Scope<'b>,
+}
+
+impl<'a, 'b> Marker<'a, 'b> {
+    fn evaluate_expr(&mut self, expr: &Expr, scope: &Scope) -> Result<Value, MarkingError> {
+        self.evaluator
+            .evaluate(expr, scope)
+            .map_err(|e| MarkingError::Evaluation(expr.to_string(), e))
+    }
+
+    fn evaluate(&mut self, jme: &JMEString) -> Result<Value, MarkingError> {
+        match jme.ast() {
+            Some(expr) => self.evaluate_expr(expr, self.scope),
+            None => Err(MarkingError::InvalidExpression(jme.to_string())),
+        }
+    }
+
+    fn evaluate_number(&mut self, jme: &JMEString) -> MarkingResult {
+        let value = self.evaluate(jme)?;
+        value
+            .as_f64()
+            .ok_or_else(|| MarkingError::UnexpectedValue(jme.to_string(), "a number", value))
+    }
+
+    /// Evaluate a list of marks, given as a list of expressions or as one expression
+    fn evaluate_marks(
+        &mut self,
+        marks: &VariableValued<Vec<JMEString>>,
+    ) -> Result<Vec<f64>, MarkingError> {
+        match marks {
+            VariableValued::Value(marks) => marks.iter().map(|m| self.evaluate_number(m)).collect(),
+            VariableValued::Variable(jme) => {
+                let value = self.evaluate(jme)?;
+                value
+                    .as_list()
+                    .and_then(|l| l.iter().map(|v| v.as_f64()).collect())
+                    .ok_or_else(|| {
+                        MarkingError::UnexpectedValue(jme.to_string(), "a list of numbers", value)
+                    })
+            }
+        }
+    }
+
+    /// Evaluate a marking matrix, given as a list of lists of expressions or as one expression
+    fn evaluate_marking_matrix(
+        &mut self,
+        matrix: &VariableValued<Vec<Vec<JMEString>>>,
+    ) -> Result<Vec<Vec<f64>>, MarkingError> {
+        match matrix {
+            VariableValued::Value(rows) => rows
+                .iter()
+                .map(|row| row.iter().map(|m| self.evaluate_number(m)).collect())
+                .collect(),
+            VariableValued::Variable(jme) => {
+                let value = self.evaluate(jme)?;
+                matrix_rows(&value).ok_or_else(|| {
+                    MarkingError::UnexpectedValue(jme.to_string(), "a matrix", value)
+                })
+            }
+        }
+    }
+
+    /// Replace the expressions between braces by their values
+    fn substitute(&mut self, jme: &EmbracedJMEString, plain: bool) -> Result<String, MarkingError> {
+        jme.substitute(|expr| {
+            let value = self.evaluate_expr(expr, self.scope)?;
+            Ok(match value {
+                Value::String(s) if plain => s,
+                v if plain => v.to_plain_string(),
+                v => format!("({})", v),
+            })
+        })
+    }
+
+    fn number_entry(
+        &mut self,
+        part: &QuestionPartNumberEntry,
+        answer: &StudentAnswer,
+    ) -> MarkingResult {
+        let marks = number(&part.part_data.marks);
+        let (value, reduced) = match answer_number(single(answer)?, part.allow_fractions) {
+            Some(v) => v,
+            None => return Ok(0.0),
+        };
+        let (min, max) = match &part.answer {
+            NumberEntryAnswerType::MinMax {
+                min_value,
+                max_value,
+            } => (
+                self.evaluate_number(min_value)?,
+                self.evaluate_number(max_value)?,
+            ),
+            NumberEntryAnswerType::Answer { answer } => {
+                let value = self.evaluate_number(answer)?;
+                (value, value)
+            }
+        };
+        let tolerance = 1e-12 * min.abs().max(max.abs()).max(1.0);
+        if value < min - tolerance || value > max + tolerance {
+            Ok(0.0)
+        } else if part.fractions_must_be_reduced && !reduced {
+            // Numbas stores the partial credit as a percentage
+            Ok(marks * number(&part.partial_credit_if_fraction_not_reduced) / 100.0)
+        } else {
+            Ok(marks)
+        }
+    }
+
+    fn pattern_match(
+        &mut self,
+        part: &QuestionPartPatternMatch,
+        answer: &StudentAnswer,
+    ) -> MarkingResult {
+        let marks = number(&part.part_data.marks);
+        let student = single(answer)?.to_string();
+        let pattern = self.substitute(&part.answer, true)?;
+        let matches = |case_insensitive: bool| match part.match_mode {
+            PatternMatchMode::Regex => RegexBuilder::new(&format!("^(?:{})$", pattern))
+                .case_insensitive(case_insensitive)
+                .build()
+                .map(|re| re.is_match(&student))
+                .map_err(|_| MarkingError::InvalidPattern(pattern.clone())),
+            PatternMatchMode::Exact => Ok(if case_insensitive {
+                student.to_lowercase() == pattern.to_lowercase()
+            } else {
+                student == pattern
+            }),
+        };
+        if matches(!part.case_sensitive)? {
+            Ok(marks)
+        } else if part.case_sensitive && matches(true)? {
+            Ok(marks * part.partial_credit.0 / 100.0)
+        } else {
+            Ok(0.0)
+        }
+    }
+
+    fn choose_one(
+        &mut self,
+        part: &QuestionPartChooseOne,
+        answer: &StudentAnswer,
+    ) -> MarkingResult {
+        let marks = self.evaluate_marks(&part.marking_matrix)?;
+        let choice = single_index(answer)?;
+        marks.get(choice).copied().ok_or_else(|| {
+            MarkingError::InvalidAnswer(format!("the index of one of the {} choices", marks.len()))
+        })
+    }
+
+    fn choose_multiple(
+        &mut self,
+        part: &QuestionPartChooseMultiple,
+        answer: &StudentAnswer,
+    ) -> MarkingResult {
+        let marks = self.evaluate_marks(&part.marking_matrix)?;
+        let ticked = match answer {
+            StudentAnswer::Single(_) => vec![single_index(answer)?],
+            StudentAnswer::List(values) => indices(values)?,
+            StudentAnswer::Nested(_) => {
+                return Err(MarkingError::InvalidAnswer(
+                    "a list with the indices of the ticked choices".to_string(),
+                ))
+            }
+        };
+        if let Some(i) = ticked.iter().find(|i| **i >= marks.len()) {
+            return Err(MarkingError::InvalidAnswer(format!(
+                "a list of indices of the {} choices, not {}",
+                marks.len(),
+                i
+            )));
+        }
+        let cells: Vec<_> = marks
+            .into_iter()
+            .enumerate()
+            .map(|(i, m)| (m, ticked.contains(&i)))
+            .collect();
+        let max_marks = part.max_marks.0 as f64;
+        let available = if max_marks > 0.0 {
+            max_marks
+        } else {
+            number(&part.part_data.marks)
+        };
+        let score = multiple_response_score(&cells, &part.marking_method, available);
+        Ok(clamp_marks(score, part.min_marks.0 as f64, max_marks))
+    }
+
+    fn match_answers(
+        &mut self,
+        part: &QuestionPartMatchAnswersWithChoices,
+        answer: &StudentAnswer,
+    ) -> MarkingResult {
+        let matrix = self.evaluate_marking_matrix(&part.marking_matrix)?;
+        let ticked = match answer {
+            StudentAnswer::Nested(rows) => rows
+                .iter()
+                .map(|row| indices(row))
+                .collect::<Result<Vec<_>, _>>()?,
+            // One answer for each choice
+            StudentAnswer::List(values) => indices(values)?.into_iter().map(|i| vec![i]).collect(),
+            StudentAnswer::Single(_) => {
+                return Err(MarkingError::InvalidAnswer(
+                    "a list with the indices of the ticked answers for each choice".to_string(),
+                ))
+            }
+        };
+        if ticked.len() > matrix.len()
+            || ticked
+                .iter()
+                .zip(matrix.iter())
+                .any(|(t, row)| t.iter().any(|i| *i >= row.len()))
+        {
+            return Err(MarkingError::InvalidAnswer(format!(
+                "a list with the indices of the ticked answers for each of the {} choices",
+                matrix.len()
+            )));
+        }
+        let cells: Vec<_> = matrix
+            .into_iter()
+            .enumerate()
+            .flat_map(|(c, row)| {
+                let ticked = ticked.get(c).cloned().unwrap_or_default();
+                row.into_iter()
+                    .enumerate()
+                    .map(move |(a, m)| (m, ticked.contains(&a)))
+            })
+            .collect();
+        let method = match &part.display_type {
+            MatchAnswersWithChoicesDisplayType::Check(c) => c.marking_method.clone(),
+            MatchAnswersWithChoicesDisplayType::Radio => {
+                MultipleChoiceMarkingMethod::SumTickedCells
+            }
+        };
+        let max_marks = part.max_marks.0 as f64;
+        let available = if max_marks > 0.0 {
+            max_marks
+        } else {
+            number(&part.part_data.marks)
+        };
+        let score = multiple_response_score(&cells, &method, available);
+        Ok(clamp_marks(score, part.min_marks.0 as f64, max_marks))
+    }
+
+    fn matrix(&mut self, part: &QuestionPartMatrix, answer: &StudentAnswer) -> MarkingResult {
+        let marks = number(&part.part_data.marks);
+        let rows = match answer {
+            StudentAnswer::Nested(rows) => rows,
+            _ => {
+                return Err(MarkingError::InvalidAnswer(
+                    "the rows of a matrix".to_string(),
+                ))
+            }
+        };
+        let student: Option<Vec<Vec<f64>>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|v| answer_number(v, part.allow_fractions).map(|(n, _)| n))
+                    .collect()
+            })
+            .collect();
+        let student = match student {
+            Some(student) => student,
+            None => return Ok(0.0),
+        };
+        let correct_value = self.evaluate(&part.correct_answer)?;
+        let correct = matrix_rows(&correct_value).ok_or_else(|| {
+            MarkingError::UnexpectedValue(
+                part.correct_answer.to_string(),
+                "a matrix",
+                correct_value,
+            )
+        })?;
+        if student.len() != correct.len()
+            || student
+                .iter()
+                .zip(correct.iter())
+                .any(|(s, c)| s.len() != c.len())
+        {
+            return Ok(0.0);
+        }
+        let cells: Vec<bool> = student
+            .iter()
+            .flatten()
+            .zip(correct.iter().flatten())
+            .map(|(s, c)| (s - c).abs() <= part.tolerance + 1e-12)
+            .collect();
+        let correct_cells = cells.iter().filter(|c| **c).count();
+        if part.mark_per_cell {
+            if cells.is_empty() {
+                Ok(marks)
+            } else {
+                Ok(marks * correct_cells as f64 / cells.len() as f64)
+            }
+        } else if correct_cells == cells.len() {
+            Ok(marks)
+        } else {
+            Ok(0.0)
+        }
+    }
+
+    fn jme(&mut self, part: &QuestionPartJME, answer: &StudentAnswer) -> MarkingResult {
+        let marks = number(&part.part_data.marks);
+        let student = match JMEString::try_from(single(answer)?.to_string())
+            .ok()
+            .and_then(|s| s.ast().cloned())
+        {
+            Some(student) => student,
+            None => return Ok(0.0),
+        };
+        let correct = self.substitute(&part.answer, false)?;
+        let correct = JMEString::try_from(correct.clone())
+            .ok()
+            .and_then(|s| s.ast().cloned())
+            .ok_or(MarkingError::InvalidExpression(correct))?;
+        let equivalent = expressions_equivalent(
+            &student,
+            &correct,
+            &part.accuracy,
+            &part.value_generators[..],
+            self.evaluator,
+            self.scope,
+        )
+        .map_err(MarkingError::Equivalence)?;
+        if equivalent {
+            Ok(marks)
+        } else {
+            Ok(0.0)
+        }
+    }
+}
+
+fn number(n: &Number) -> f64 {
+    match n {
+        Number::Integer(i) => *i as f64,
+        Number::Float(f) => *f,
+    }
+}
+
+fn single(answer: &StudentAnswer) -> Result<&AnswerValue, MarkingError> {
+    match answer {
+        StudentAnswer::Single(v) => Ok(v),
+        _ => Err(MarkingError::InvalidAnswer("a single value".to_string())),
+    }
+}
+
+fn single_index(answer: &StudentAnswer) -> Result<usize, MarkingError> {
+    single(answer)?
+        .as_index()
+        .ok_or_else(|| MarkingError::InvalidAnswer("the index of a choice".to_string()))
+}
+
+fn indices(values: &[AnswerValue]) -> Result<Vec<usize>, MarkingError> {
+    values
+        .iter()
+        .map(|v| {
+            v.as_index()
+                .ok_or_else(|| MarkingError::InvalidAnswer(format!("an index, not {}", v)))
+        })
+        .collect()
+}
+
+/// The number that the student entered and whether it is reduced (when it is a fraction).
+/// None if it is not a valid number.
+fn answer_number(value: &AnswerValue, allow_fractions: bool) -> Option<(f64, bool)> {
+    match value {
+        AnswerValue::Integer(i) => Some((*i as f64, true)),
+        AnswerValue::Float(f) => Some((*f, true)),
+        AnswerValue::String(s) => {
+            let s = s.trim();
+            if let Ok(f) = s.parse::<f64>() {
+                return Some((f, true));
+            }
+            if !allow_fractions {
+                return None;
+            }
+            let (numerator, denominator) = s.split_once('/')?;
+            let numerator: i64 = numerator.trim().parse().ok()?;
+            let denominator: i64 = denominator.trim().parse().ok()?;
+            if denominator == 0 {
+                return None;
+            }
+            let reduced = gcd(numerator.abs(), denominator.abs()) == 1;
+            Some((numerator as f64 / denominator as f64, reduced))
+        }
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The rows of a matrix value, or of a list of lists of numbers
+fn matrix_rows(value: &Value) -> Option<Vec<Vec<f64>>> {
+    match value {
+        Value::Matrix(rows) => Some(rows.clone()),
+        v => v
+            .as_list()?
+            .iter()
+            .map(|row| row.as_list()?.iter().map(|c| c.as_f64()).collect())
+            .collect(),
+    }
+}
+
+/// The score for the cells of a multiple response part, given by their marks and whether they
+/// are ticked.
+fn multiple_response_score(
+    cells: &[(f64, bool)],
+    method: &MultipleChoiceMarkingMethod,
+    available: f64,
+) -> f64 {
+    // A cell should be ticked if it has positive marks
+    let matched = cells
+        .iter()
+        .filter(|(marks, ticked)| (*marks > 0.0) == *ticked)
+        .count();
+    match method {
+        MultipleChoiceMarkingMethod::SumTickedCells => cells
+            .iter()
+            .filter(|(_, ticked)| *ticked)
+            .map(|(marks, _)| marks)
+            .sum(),
+        MultipleChoiceMarkingMethod::ScorePerMatchedCell => {
+            if cells.is_empty() {
+                0.0
+            } else {
+                available * matched as f64 / cells.len() as f64
+            }
+        }
+        MultipleChoiceMarkingMethod::AllOrNothing => {
+            if matched == cells.len() {
+                available
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+/// Apply the minimum and maximum marks, a maximum of zero means that there is no maximum
+fn clamp_marks(score: f64, min_marks: f64, max_marks: f64) -> f64 {
+    let score = score.max(min_marks);
+    if max_marks > 0.0 {
+        score.min(max_marks)
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn marks(part: serde_json::Value, answer: serde_json::Value) -> MarkingResult {
+        let part: numbas::question::part::QuestionPart = serde_json::from_value(part).unwrap();
+        let answer: StudentAnswer = serde_json::from_value(answer).unwrap();
+        let mut scope = Scope::new();
+        scope.set_variable("a", Value::Integer(3));
+        mark(&part, &answer, &mut Evaluator::new(1), &scope)
+    }
+
+    #[test]
+    fn number_entry() {
+        let part = serde_json::json!({
+            "type": "numberentry", "marks": 2, "minValue": "a/4", "maxValue": "a/4",
+            "allowFractions": true, "mustBeReduced": true, "mustBeReducedPC": 50
+        });
+        assert_eq!(marks(part.clone(), serde_json::json!(0.75)), Ok(2.0));
+        assert_eq!(marks(part.clone(), serde_json::json!("3/4")), Ok(2.0));
+        assert_eq!(marks(part.clone(), serde_json::json!("6/8")), Ok(1.0));
+        assert_eq!(marks(part.clone(), serde_json::json!(1)), Ok(0.0));
+        assert_eq!(marks(part, serde_json::json!("three")), Ok(0.0));
+    }
+
+    #[test]
+    fn pattern_match() {
+        let part = serde_json::json!({
+            "type": "patternmatch", "marks": 1, "answer": "N{a}m(bas)?",
+            "matchMode": "regex", "caseSensitive": true, "partialCredit": 50
+        });
+        assert_eq!(marks(part.clone(), serde_json::json!("N3m")), Ok(1.0));
+        assert_eq!(marks(part.clone(), serde_json::json!("N3mbas")), Ok(1.0));
+        assert_eq!(marks(part.clone(), serde_json::json!("n3M")), Ok(0.5));
+        assert_eq!(marks(part, serde_json::json!("N3mb")), Ok(0.0));
+    }
+
+    #[test]
+    fn choose_multiple() {
+        let part = serde_json::json!({
+            "type": "m_n_2", "marks": 0, "choices": ["a", "b", "c"],
+            "matrix": ["1", "a-4", "1"], "minMarks": 0
+        });
+        assert_eq!(marks(part.clone(), serde_json::json!([0, 2])), Ok(2.0));
+        assert_eq!(marks(part.clone(), serde_json::json!([0, 1])), Ok(0.0));
+        assert!(marks(part, serde_json::json!([3])).is_err());
+    }
+
+    #[test]
+    fn jme() {
+        let part = serde_json::json!({
+            "type": "jme", "marks": 3, "answer": "{a}x^2 + x",
+            "checkingType": "absdiff", "checkingAccuracy": 0.001
+        });
+        assert_eq!(marks(part.clone(), serde_json::json!("x*(3x+1)")), Ok(3.0));
+        assert_eq!(marks(part.clone(), serde_json::json!("3x^2")), Ok(0.0));
+        assert_eq!(marks(part, serde_json::json!("3x^")), Ok(0.0));
+    }
+
+    #[test]
+    fn unsupported() {
+        let part = serde_json::json!({"type": "information", "marks": 0});
+        assert_eq!(
+            marks(part, serde_json::json!(1)),
+            Err(MarkingError::UnsupportedPartType("information"))
+        );
+    }
+}
@@ -4,12 +4,17 @@ pub mod constants;
 pub mod custom_part_type;
 pub mod extension;
 pub mod function;
+pub mod identifiers;
+pub mod marking;
 pub mod navigation;
 pub mod part;
 pub mod preamble;
 pub mod resource;
 pub mod variable;
+pub mod variable_dependencies;
+pub mod variable_sampling;
 pub mod variable_test;
+pub mod variable_types;
 
 use crate::exam::{FileReadError, ParseError, RecursiveTemplatesError};
 use crate::question::custom_part_type::CustomPartTypeDefinitionPath;
@@ -94,9 +99,6 @@ pub struct Question {
 impl ToNumbas<numbas::question::Question> for Question {
     type ToNumbasHelper = String;
     fn to_numbas(&self, locale: &str, name: &String) -> numbas::question::Question {
-        if self.variables.contains_key("e") {
-            panic!("e is not allowed as a variable name"); //TODO is this still the case? // TODO: check this in validation?
-        }
         numbas::question::Question {
             name: name.clone(),
             statement: self.statement.to_numbas(locale, &()),
@@ -205,6 +207,34 @@ impl ToRumbas<Question> for numbas::question::Question {
     }
 }
 
+impl Question {
+    /// Use another name for a custom part type, in the parts and the used custom part types
+    pub fn rename_custom_part_type(&mut self, old_name: &str, new_name: &str) {
+        for part in self.parts.iter_mut() {
+            part.rename_custom_part_type(old_name, new_name);
+        }
+        for cpt in self.custom_part_types.iter_mut() {
+            if cpt.file_name == old_name {
+                cpt.file_name = new_name.to_string();
+            }
+        }
+    }
+    /// All variables of the question: the ungrouped and the grouped ones
+    pub fn all_variables(&self) -> BTreeMap<String, VariableRepresentation> {
+        self.variables
+            .clone()
+            .into_iter()
+            .chain(
+                self.grouped_variables
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|(_group, variable_map)| variable_map.into_iter()),
+            )
+            .collect()
+    }
+}
+
 #[derive(Input, Overwrite, RumbasCheck, Examples, StructDoc)]
 #[input(name = "QuestionFileTypeInput")]
 #[derive(Serialize, Deserialize, Comparable, Debug, Clone, JsonSchema, PartialEq)]
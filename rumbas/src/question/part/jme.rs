@@ -50,6 +50,7 @@ question_part_type! {
         /// The expression for each variable can be written in terms of the other variables, as long as there are no circular dependencies. The values will be evaluated in order, like question variables.
         /// Each variable specified in the expected answer can be overriden
         /// The variable vRange represents the checking range defined for this part: a continuous interval between the checking range start and checking range end.
+        #[rumbas_check(scope = "value_generators_scope")]
         value_generators: Noneable<Vec<JMEValueGenerator>>,
 
         /// *DEPRECATED* String restrictions are an unreliable method of restricting the form of a student’s answer. They are deprecated and retained only for backwards compatibility; use a pattern restriction instead.
@@ -81,6 +82,34 @@ question_part_type! {
     }
 }
 
+impl QuestionPartJME {
+    /// The value generators can use `vRange`, the variables of the answer and the other generated
+    /// variables
+    fn value_generators_scope(&self, locale: &str, scope: &ValidationScope) -> ValidationScope {
+        let mut scope = scope.clone();
+        scope.add_variable("vRange");
+        let answer = self
+            .answer
+            .to_string(locale)
+            .and_then(|a| numbas::jme::EmbracedJMEString::try_from(a).ok())
+            .and_then(|a| a.substitute(|e| Ok::<_, ()>(format!("({})", e))).ok())
+            .and_then(|a| numbas::jme::JMEString::try_from(a).ok());
+        if let Some(ast) = answer.as_ref().and_then(|a| a.ast()) {
+            for name in ast.free_variables() {
+                scope.add_variable(&name);
+            }
+        }
+        if let Noneable::NotNone(generators) = &self.value_generators {
+            for generator in generators.iter() {
+                if let Some(name) = generator.name.get_content(locale) {
+                    scope.add_variable(&name);
+                }
+            }
+        }
+        scope
+    }
+}
+
 impl ToNumbas<numbas::question::part::jme::QuestionPartJME> for QuestionPartJME {
     type ToNumbasHelper = ();
     fn to_numbas(&self, locale: &str, _data: &Self::ToNumbasHelper) -> numbas::question::part::jme::QuestionPartJME {
@@ -159,49 +159,54 @@ impl ToRumbas<MultipleChoiceMatchAnswerData>
     for numbas::question::part::match_answers::QuestionPartMatchAnswersWithChoices
 {
     fn to_rumbas(&self) -> MultipleChoiceMatchAnswerData {
-        if let (
-            numbas::support::primitive::VariableValued::Value(answer_options),
-            numbas::support::primitive::VariableValued::Value(choice_options),
-            numbas::support::primitive::VariableValued::Value(marking_matrix),
-        ) = (
+        match (
             self.answers.clone(),
             self.choices.clone(),
             self.marking_matrix.clone(),
         ) {
-            let items_data = choice_options.into_iter().zip(marking_matrix.into_iter());
-
-            MultipleChoiceMatchAnswerData::ItemBased({
-                let answers: Vec<_> = answer_options.iter().map(|a| a.clone().into()).collect();
-                MultipleChoiceMatchAnswers {
-                    answers: answers.clone(),
-                    items: items_data
-                        .into_iter()
-                        .map(|(statement, marks)| {
-                            MatchAnswersItem {
-                                // TODO: extract to ToRumbas?
-                                statement: statement.into(),
-                                answer_marks: marks
-                                    .into_iter()
-                                    .enumerate()
-                                    .map(|(i, marks)| MatchAnswersItemMarks {
-                                        marks,
-                                        answer: answers.get(i).unwrap().clone(),
-                                    })
-                                    .collect(),
-                            }
-                        })
-                        .collect(),
-                }
-            })
-        } else {
-            MultipleChoiceMatchAnswerData::NumbasLike(Box::new(
+            // Marks for more answers than there are can't be item based
+            (
+                numbas::support::primitive::VariableValued::Value(answer_options),
+                numbas::support::primitive::VariableValued::Value(choice_options),
+                numbas::support::primitive::VariableValued::Value(marking_matrix),
+            ) if marking_matrix
+                .iter()
+                .all(|marks| marks.len() <= answer_options.len()) =>
+            {
+                let items_data = choice_options.into_iter().zip(marking_matrix.into_iter());
+
+                MultipleChoiceMatchAnswerData::ItemBased({
+                    let answers: Vec<_> = answer_options.iter().map(|a| a.clone().into()).collect();
+                    MultipleChoiceMatchAnswers {
+                        answers: answers.clone(),
+                        items: items_data
+                            .into_iter()
+                            .map(|(statement, marks)| {
+                                MatchAnswersItem {
+                                    // TODO: extract to ToRumbas?
+                                    statement: statement.into(),
+                                    answer_marks: marks
+                                        .into_iter()
+                                        .enumerate()
+                                        .map(|(i, marks)| MatchAnswersItemMarks {
+                                            marks,
+                                            answer: answers[i].clone(),
+                                        })
+                                        .collect(),
+                                }
+                            })
+                            .collect(),
+                    }
+                })
+            }
+            _ => MultipleChoiceMatchAnswerData::NumbasLike(Box::new(
                 MultipleChoiceMatchAnswerDataNumbasLike {
                     answers: self.answers.to_rumbas(),
                     choices: self.choices.to_rumbas(),
 
                     marks: self.marking_matrix.to_rumbas(),
                 },
-            ))
+            )),
         }
     }
 }
@@ -61,6 +61,73 @@ impl ToRumbas<QuestionPart> for numbas::question::part::QuestionPart {
     }
 }
 
+/// Apply an expression to the data of a part, whatever its type
+macro_rules! on_part {
+    ($part: expr, $p: ident => $e: expr) => {
+        match $part {
+            QuestionPart::Builtin(b) => match b {
+                QuestionPartBuiltin::JME($p) => $e,
+                QuestionPartBuiltin::GapFill($p) => $e,
+                QuestionPartBuiltin::ChooseOne($p) => $e,
+                QuestionPartBuiltin::ChooseMultiple($p) => $e,
+                QuestionPartBuiltin::MatchAnswersWithItems($p) => $e,
+                QuestionPartBuiltin::NumberEntry($p) => $e,
+                QuestionPartBuiltin::PatternMatch($p) => $e,
+                QuestionPartBuiltin::Information($p) => $e,
+                QuestionPartBuiltin::Extension($p) => $e,
+                QuestionPartBuiltin::Matrix($p) => $e,
+            },
+            QuestionPart::Custom($p) => $e,
+        }
+    };
+}
+
+impl QuestionPart {
+    /// The number of marks to award for answering the part correctly
+    pub fn marks(&self) -> &numbas::support::primitive::Number {
+        on_part!(self, p => &p.marks)
+    }
+
+    pub fn tests(&self) -> &[MarkingTest] {
+        on_part!(self, p => &p.tests[..])
+    }
+
+    /// The steps and gaps of this part, with their path relative to this part
+    pub fn sub_parts(&self) -> Vec<(Vec<String>, &QuestionPart)> {
+        let steps = on_part!(self, p => &p.steps[..]);
+        let gaps = match self {
+            QuestionPart::Builtin(QuestionPartBuiltin::GapFill(p)) => &p.gaps[..],
+            _ => &[],
+        };
+        steps
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| (vec!["steps".to_string(), idx.to_string()], p))
+            .chain(
+                gaps.iter()
+                    .enumerate()
+                    .map(|(idx, p)| (vec!["gaps".to_string(), idx.to_string()], p)),
+            )
+            .collect()
+    }
+
+    /// Use another custom part type name in this part and its steps and gaps
+    pub fn rename_custom_part_type(&mut self, old_name: &str, new_name: &str) {
+        if let QuestionPart::Custom(p) = self {
+            if p.type_name == old_name {
+                p.type_name = new_name.to_string();
+            }
+        }
+        on_part!(self, p => p.steps.iter_mut())
+            .for_each(|step| step.rename_custom_part_type(old_name, new_name));
+        if let QuestionPart::Builtin(QuestionPartBuiltin::GapFill(p)) = self {
+            for gap in p.gaps.iter_mut() {
+                gap.rename_custom_part_type(old_name, new_name);
+            }
+        }
+    }
+}
+
 impl QuestionPartInput {
     pub fn get_steps(&mut self) -> &mut Value<Vec<ValueType<QuestionPartInput>>> {
         match self {
@@ -208,12 +275,30 @@ impl QuestionPartBuiltinInput {
     }
 }
 
-#[derive(Input, Overwrite, RumbasCheck, Examples, StructDoc)]
+#[derive(Input, Overwrite, Examples, StructDoc)]
 #[input(name = "JMENotesInput")]
 #[derive(Debug, Clone, JsonSchema, Deserialize, Serialize, Comparable, PartialEq, Eq, Default)]
 #[serde(transparent)]
 pub struct JMENotes(pub Vec<JMENote>);
 
+impl RumbasCheck for JMENotes {
+    fn check(&self, locale: &str) -> RumbasCheckResult {
+        self.0.check(locale)
+    }
+    /// The notes can use the marking script names and the names of the notes
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let mut scope = scope.clone();
+        scope.add_marking_script_names();
+        for note in self.0.iter() {
+            scope.add_variable(&note.name);
+        }
+        self.0.check_identifiers(locale, &scope)
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        self.0.check_jme_formatting(locale)
+    }
+}
+
 impl ToNumbas<numbas::jme::JMENotesString> for JMENotes {
     type ToNumbasHelper = ();
     fn to_numbas(&self, locale: &str, _data: &Self::ToNumbasHelper) -> numbas::jme::JMENotesString {
@@ -331,7 +416,11 @@ macro_rules! question_part_type {
             // Adaptive marking
             /// Adaptive marking allows you to incorporate the student’s answers to earlier parts when marking their answer to another part. You could use this to allow an “error carried forward” marking scheme, or in more free-form questions where one part has no correct answer - for example, “think of a number and find its square root”. This is achieved by replacing the values of question variables with the student’s answers to other parts. When a variable is replaced, any other variables depending on that one are recalculated using the new value. All other variables keep their original values.
             /// See for more info and a warning https://numbas-editor.readthedocs.io/en/latest/question/parts/reference.html#adaptive-marking
-            pub adaptive_marking: Noneable<AdaptiveMarking>
+            pub adaptive_marking: Noneable<AdaptiveMarking>,
+            #[serde(default)]
+            #[input(skip, default)]
+            /// A (possibly empty) list of answers with the marks that they should be awarded. These are not part of the exam, but are checked by `rumbas test`.
+            pub tests: Vec<crate::question::part::question_part::MarkingTest>
             $(,
             $(
                 $(#[$inner])*
@@ -367,6 +456,17 @@ macro_rules! question_part_type {
     }
 }
 
+#[derive(Input, Overwrite, RumbasCheck, Examples, StructDoc)]
+#[input(name = "MarkingTestInput")]
+#[derive(Serialize, Deserialize, Comparable, Debug, Clone, JsonSchema, PartialEq)]
+/// An answer of a student with the marks that it should be awarded
+pub struct MarkingTest {
+    /// The answer of the student, e.g. a number, an expression, the index of a choice, a list of indices or the rows of a matrix
+    pub answer: numbas::support::student_answer::StudentAnswer,
+    /// The marks that should be awarded for this answer
+    pub marks: numbas::support::primitive::Number,
+}
+
 question_part_type! {
     #[derive(Input, Overwrite, RumbasCheck, Examples, StructDoc)]
     #[input(name = "QuestionPartCustomInput")]
@@ -450,6 +550,7 @@ impl ToRumbas<QuestionPartCustom> for numbas::question::part::QuestionPartCustom
             custom_marking: self.part_data.custom_marking.to_rumbas(),
 
             steps: extract_part_common_steps(&self.part_data),
+            tests: Vec::new(),
 
             type_name: self.r#type.clone(),
             settings: self.settings.to_rumbas(),
@@ -540,7 +641,7 @@ impl ToRumbas<Noneable<CustomMarking>> for numbas::question::part::CustomMarking
     }
 }
 
-#[derive(Input, Overwrite, RumbasCheck, Examples, StructDoc)]
+#[derive(Input, Overwrite, Examples, StructDoc)]
 #[input(name = "VariableReplacementInput")]
 #[derive(Serialize, Deserialize, Comparable, Debug, Clone, JsonSchema, PartialEq, Eq)]
 pub struct VariableReplacement {
@@ -552,6 +653,26 @@ pub struct VariableReplacement {
     must_be_answered: bool,
 }
 
+impl RumbasCheck for VariableReplacement {
+    fn check(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    /// The replaced variable should be a variable of the question
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        match numbas::jme::JMEString::try_from(self.variable.clone()) {
+            Ok(variable) => {
+                let mut result = variable.check_identifiers(locale, scope);
+                result.extend_path("variable".to_string());
+                result
+            }
+            Err(_) => RumbasCheckResult::empty(),
+        }
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+}
+
 impl ToNumbas<numbas::question::part::VariableReplacement> for VariableReplacement {
     type ToNumbasHelper = ();
     fn to_numbas(
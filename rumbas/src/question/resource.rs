@@ -44,11 +44,13 @@ impl ToNumbas<numbas::question::resource::Resource> for ResourcePath {
     }
 }
 
+/// The path of a numbas resource is a path on the machine that created the exam, the resource
+/// is placed in the resources folder of the project under its name
 impl ToRumbas<ResourcePath> for numbas::question::resource::Resource {
     fn to_rumbas(&self) -> ResourcePath {
         ResourcePath {
             resource_name: self.0[0].clone(),
-            resource_path: std::path::Path::new(&self.0[1]).to_path_buf(),
+            resource_path: std::path::Path::new(crate::RESOURCES_FOLDER).join(&self.0[0]),
         }
     }
 }
@@ -153,4 +155,18 @@ test
         });
         assert_eq!(r.to_yaml().unwrap(), serde_yaml::to_string(&rid).unwrap());
     }
+
+    #[test]
+    fn from_numbas() {
+        let r: ResourcePath = numbas::question::resource::Resource([
+            "question-resources/image.png".to_string(),
+            "/srv/numbas/media/question-resources/image.png".to_string(),
+        ])
+        .to_rumbas();
+        assert_eq!(r.resource_name, "question-resources/image.png");
+        assert_eq!(
+            r.resource_path,
+            Path::new("resources/question-resources/image.png")
+        );
+    }
 }
@@ -64,6 +64,15 @@ impl ToRumbas<VariableRepresentation> for numbas::question::variable::Variable {
 }
 
 impl VariableRepresentation {
+    /// The locales that have their own definition, empty if the definition is the same for all
+    /// locales
+    pub fn locales(&self) -> Vec<String> {
+        match self {
+            VariableRepresentation::TranslatableString(l) => l.locales(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn to_variable(&self, locale: &str) -> Variable {
         match self {
             VariableRepresentation::ListOfStrings(l) => Variable::new(
@@ -379,6 +388,21 @@ impl Variable {
             description: String::new().to_rumbas(),
         }
     }
+
+    /// Parse the definition as JME.
+    ///
+    /// Returns None for the string template types, whose definition is plain text.
+    pub fn jme_definition(
+        &self,
+        locale: &str,
+    ) -> Option<Result<JMEString, numbas::jme::parser::ConsumeError>> {
+        match self.template_type {
+            VariableTemplateType::r#String | VariableTemplateType::LongString => None,
+            _ => Some(JMEString::try_from(
+                self.definition.get_content(locale).unwrap_or_default(),
+            )),
+        }
+    }
 }
 
 #[derive(Input, Overwrite, RumbasCheck, Examples, StructDoc)]
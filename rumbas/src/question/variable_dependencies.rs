@@ -0,0 +1,398 @@
+//! The dependencies between the variables of a question.
+
+use crate::question::variable::VariableRepresentation;
+use crate::question::Question;
+use numbas::jme::ast::{Expr, ExprValidationError};
+use numbas::jme::{ContentAreaString, JMEString};
+use rumbas_support::rumbas_check::{RumbasCheckResult, VariableIssue};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// A graph with an edge from each variable to the variables that are used in its definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableDependencyGraph {
+    /// The original name of each variable, by lowercase name
+    names: BTreeMap<String, String>,
+    /// The (lowercase) names used in the definition of each variable, by lowercase name
+    used_names: BTreeMap<String, BTreeSet<String>>,
+    /// The (lowercase) names that are defined without being a variable (e.g. custom constants)
+    known_names: BTreeSet<String>,
+    /// The (lowercase) names that are referenced by each variable without being a dependency,
+    /// by lowercase name
+    referenced_names: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl VariableDependencyGraph {
+    /// Create the graph from the variable names and their parsed definitions.
+    /// Definitions that are plain text or invalid JME have no dependencies.
+    pub fn from_definitions(
+        definitions: impl IntoIterator<Item = (String, Option<Expr>)>,
+        known_names: impl IntoIterator<Item = String>,
+    ) -> Self {
+        let mut names = BTreeMap::new();
+        let mut used_names = BTreeMap::new();
+        for (name, definition) in definitions.into_iter() {
+            let key = name.to_lowercase();
+            used_names.insert(
+                key.clone(),
+                definition.map(|d| d.free_variables()).unwrap_or_default(),
+            );
+            names.insert(key, name);
+        }
+        Self {
+            names,
+            used_names,
+            known_names: known_names.into_iter().map(|n| n.to_lowercase()).collect(),
+            referenced_names: BTreeMap::new(),
+        }
+    }
+
+    /// Add names that are referenced by a variable without being used in its definition, like
+    /// the names in its definition for another locale
+    pub fn add_references(&mut self, name: &str, references: impl IntoIterator<Item = String>) {
+        self.referenced_names
+            .entry(name.to_lowercase())
+            .or_default()
+            .extend(references.into_iter().map(|n| n.to_lowercase()));
+    }
+
+    /// The original name of a variable, given its lowercase name
+    fn name(&self, key: &str) -> String {
+        self.names
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// The variables that are used in the definition of the given variable
+    pub fn dependencies(&self, name: &str) -> Vec<String> {
+        self.used_names
+            .get(&name.to_lowercase())
+            .map(|used| {
+                used.iter()
+                    .filter(|n| self.names.contains_key(*n))
+                    .map(|n| self.name(n))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The names that are used in definitions, but are not defined, by variable
+    pub fn undefined_names(&self) -> BTreeMap<String, Vec<String>> {
+        self.used_names
+            .iter()
+            .map(|(key, used)| {
+                (
+                    self.name(key),
+                    used.iter()
+                        .filter(|n| !self.names.contains_key(*n) && !self.known_names.contains(*n))
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .filter(|(_, undefined)| !undefined.is_empty())
+            .collect()
+    }
+
+    /// The variables that are not used by the definition of another variable
+    pub fn unreferenced_variables(&self) -> Vec<String> {
+        let referenced: BTreeSet<_> = self
+            .used_names
+            .iter()
+            .chain(self.referenced_names.iter())
+            .flat_map(|(key, used)| used.iter().filter(move |n| *n != key))
+            .collect();
+        self.names
+            .iter()
+            .filter(|(key, _)| !referenced.contains(key))
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    /// The groups of variables whose definitions depend on each other.
+    /// Each cycle starts and ends with the same variable.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = Vec::new();
+        let mut finished = BTreeSet::new();
+        for start in self.names.keys() {
+            let mut stack = Vec::new();
+            self.find_cycles(start, &mut stack, &mut finished, &mut cycles);
+        }
+        cycles
+    }
+
+    fn find_cycles(
+        &self,
+        key: &str,
+        stack: &mut Vec<String>,
+        finished: &mut BTreeSet<String>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        if finished.contains(key) {
+            return;
+        }
+        if let Some(position) = stack.iter().position(|n| n == key) {
+            cycles.push(
+                stack[position..]
+                    .iter()
+                    .chain(std::iter::once(&key.to_string()))
+                    .map(|n| self.name(n))
+                    .collect(),
+            );
+            return;
+        }
+        stack.push(key.to_string());
+        for dependency in self.used_names[key].iter() {
+            if self.names.contains_key(dependency) {
+                self.find_cycles(dependency, stack, finished, cycles);
+            }
+        }
+        stack.pop();
+        finished.insert(key.to_string());
+    }
+}
+
+impl Question {
+    /// The dependency graph of the variables of this question
+    pub fn variable_dependency_graph(&self, locale: &str) -> VariableDependencyGraph {
+        let variables = self.all_variables();
+        let mut graph = VariableDependencyGraph::from_definitions(
+            variables.iter().map(|(name, variable)| {
+                let definition = variable
+                    .to_variable(locale)
+                    .jme_definition(locale)
+                    .and_then(|d| d.ok())
+                    .and_then(|d| d.ast().cloned());
+                (name.clone(), definition)
+            }),
+            self.custom_constants.iter().map(|c| c.name.clone()),
+        );
+        for (name, variable) in variables.iter() {
+            graph.add_references(name, variable_references(variable, locale));
+        }
+        graph
+    }
+
+    /// Check for reserved variable names, circular variable definitions, references to undefined
+    /// variables and unused variables.
+    pub fn check_variables(&self, locale: &str) -> RumbasCheckResult {
+        let graph = self.variable_dependency_graph(locale);
+        let mut issues: Vec<(String, VariableIssue)> = Vec::new();
+        if self.variables.contains_key("e") {
+            issues.push(("e".to_string(), VariableIssue::ReservedName));
+        }
+        for cycle in graph.cycles() {
+            issues.push((cycle[0].clone(), VariableIssue::CircularDefinition(cycle)));
+        }
+        for (name, undefined) in graph.undefined_names() {
+            for undefined_name in undefined.into_iter() {
+                issues.push((
+                    name.clone(),
+                    VariableIssue::UndefinedVariable(undefined_name),
+                ));
+            }
+        }
+        for name in self.unused_variables(locale, &graph) {
+            issues.push((name, VariableIssue::UnusedVariable));
+        }
+
+        let mut result = RumbasCheckResult::empty();
+        for (name, issue) in issues.into_iter() {
+            let mut issue_result = RumbasCheckResult::from_variable_issue(issue);
+            issue_result.extend_path(name.clone());
+            if let Some(group) = self.variable_group(&name) {
+                issue_result.extend_path(group);
+                issue_result.extend_path("grouped_variables".to_string());
+            } else {
+                issue_result.extend_path("variables".to_string());
+            }
+            result.union(&issue_result);
+        }
+        result
+    }
+
+    pub(crate) fn variable_group(&self, name: &str) -> Option<String> {
+        self.grouped_variables
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(_, variables)| variables.contains_key(name))
+            .map(|(group, _)| group)
+    }
+
+    /// The variables that are not used by other variables, nor anywhere else in the question
+    fn unused_variables(&self, locale: &str, graph: &VariableDependencyGraph) -> Vec<String> {
+        let candidates = graph.unreferenced_variables();
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        // Without the variables in the scope, each use of a variable is an unknown variable
+        let used: BTreeSet<_> = self
+            .check_usage_identifiers(locale, &self.validation_scope_without_variables())
+            .unknown_identifiers()
+            .into_iter()
+            .filter_map(|unknown| match unknown.error() {
+                ExprValidationError::UnknownVariable(ident) => Some(ident.name().to_lowercase()),
+                _ => None,
+            })
+            .collect();
+        candidates
+            .into_iter()
+            .filter(|name| !used.contains(&name.to_lowercase()))
+            .collect()
+    }
+}
+
+/// The (lowercase) names that are used by the definition of a variable in any locale, also
+/// within the strings of the definition (e.g. `\var{a}` in a list of strings)
+fn variable_references(variable: &VariableRepresentation, locale: &str) -> BTreeSet<String> {
+    let definition = |locale: &str| match variable {
+        VariableRepresentation::TranslatableString(s) => s.to_string(locale),
+        _ => variable.to_variable(locale).definition.get_content(locale),
+    };
+    variable
+        .locales()
+        .iter()
+        .map(|l| &l[..])
+        .chain(std::iter::once(locale))
+        .filter_map(definition)
+        .flat_map(|d| definition_references(&d))
+        .collect()
+}
+
+/// The (lowercase) names that are used by a definition. Definitions that are no valid jme are
+/// read as text with substituted expressions.
+fn definition_references(definition: &str) -> BTreeSet<String> {
+    match JMEString::try_from(definition.to_string()) {
+        Ok(jme) => jme
+            .ast()
+            .map(|ast| {
+                let mut strings = Vec::new();
+                collect_strings(ast, &mut strings);
+                let mut names = ast.free_variables();
+                names.extend(strings.into_iter().flat_map(text_references));
+                names
+            })
+            .unwrap_or_default(),
+        Err(_) => text_references(definition),
+    }
+}
+
+/// The (lowercase) names that are used in the substituted expressions (`{a}` and `\var{a}`) of
+/// a text
+fn text_references(text: &str) -> BTreeSet<String> {
+    ContentAreaString::try_from(text.to_string())
+        .map(|content| {
+            content
+                .asts()
+                .iter()
+                .flat_map(|ast| ast.free_variables())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn collect_strings<'a>(expr: &'a Expr, strings: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Str(s) => strings.push(s),
+        e => e
+            .children()
+            .into_iter()
+            .for_each(|child| collect_strings(child, strings)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use numbas::jme::JMEString;
+
+    fn graph(definitions: Vec<(&str, &str)>) -> VariableDependencyGraph {
+        VariableDependencyGraph::from_definitions(
+            definitions.into_iter().map(|(name, definition)| {
+                (
+                    name.to_string(),
+                    JMEString::try_from(definition.to_string())
+                        .unwrap()
+                        .ast()
+                        .cloned(),
+                )
+            }),
+            vec!["c".to_string()],
+        )
+    }
+
+    #[test]
+    fn dependencies() {
+        let g = graph(vec![
+            ("a", "random(1..b)"),
+            ("b", "2*c"),
+            ("d", "map(x+a, x, [1,2])"),
+        ]);
+        assert_eq!(g.dependencies("a"), vec!["b".to_string()]);
+        assert_eq!(g.dependencies("b"), Vec::<String>::new());
+        assert_eq!(g.dependencies("d"), vec!["a".to_string()]);
+        assert!(g.cycles().is_empty());
+        assert!(g.undefined_names().is_empty());
+        assert_eq!(g.unreferenced_variables(), vec!["d".to_string()]);
+    }
+
+    #[test]
+    fn cycles() {
+        let g = graph(vec![("a", "b+1"), ("b", "c2*a"), ("c2", "c2"), ("d", "a")]);
+        assert_eq!(
+            g.cycles(),
+            vec![
+                vec!["a".to_string(), "b".to_string(), "a".to_string()],
+                vec!["c2".to_string(), "c2".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined() {
+        let g = graph(vec![("a", "b+x"), ("b", "if(y, 1, 2)")]);
+        let expected: BTreeMap<_, _> = vec![
+            ("a".to_string(), vec!["x".to_string()]),
+            ("b".to_string(), vec!["y".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(g.undefined_names(), expected);
+    }
+
+    #[test]
+    fn references() {
+        let mut g = graph(vec![
+            ("a", "1"),
+            ("b", "2"),
+            ("prompts", "reorder(prompts_en, a)"),
+            ("prompts_en", "[]"),
+            ("prompts_nl", "[]"),
+        ]);
+        // The definition of prompts for another locale
+        g.add_references("prompts", vec!["Prompts_NL".to_string()]);
+        assert_eq!(
+            g.unreferenced_variables(),
+            vec!["b".to_string(), "prompts".to_string()]
+        );
+        assert!(g.undefined_names().is_empty());
+    }
+
+    #[test]
+    fn references_in_strings() {
+        let names = |v: Vec<&str>| {
+            v.into_iter()
+                .map(|n| n.to_string())
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(
+            definition_references(r#"["costs $\\var{price_now1}$ euro", "{percent}%"]"#),
+            names(vec!["percent", "price_now1"])
+        );
+        assert_eq!(
+            definition_references("reorder(prompts_en, selection)"),
+            names(vec!["prompts_en", "selection"])
+        );
+    }
+}
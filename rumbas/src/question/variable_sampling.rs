@@ -0,0 +1,282 @@
+//! Generate values for the variables of a question, without using Numbas.
+
+use crate::question::function::FunctionDefinition;
+use crate::question::Question;
+use numbas::jme::ast::Expr;
+use numbas::jme::eval::{EvaluationError, Evaluator, Function, Scope, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableError {
+    /// The definition is not a valid JME expression
+    InvalidDefinition(String),
+    /// The definition (indirectly) depends on the variable itself
+    CircularDefinition(Vec<String>),
+    /// A variable that is used in the definition can't be generated
+    FailingDependency(String),
+    Evaluation(EvaluationError),
+}
+
+impl Display for VariableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InvalidDefinition(e) => write!(f, "Invalid definition: {}", e),
+            Self::CircularDefinition(names) => {
+                write!(f, "Circular definition: {}", names.join(" -> "))
+            }
+            Self::FailingDependency(name) => write!(f, "Depends on failing variable {}", name),
+            Self::Evaluation(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The values of the variables for one seed
+#[derive(Debug, Clone)]
+pub struct VariablesSample {
+    pub seed: u64,
+    /// The values of the variables in the last generated set
+    pub values: BTreeMap<String, Result<Value, VariableError>>,
+    /// The amount of sets of variables that were generated
+    pub runs: usize,
+    /// The result of the variables test for the last generated set.
+    /// None if the question has no variables test.
+    pub condition: Option<Result<bool, EvaluationError>>,
+}
+
+impl VariablesSample {
+    /// Whether a set of variables was found that satisfies the variables test
+    pub fn passed(&self) -> bool {
+        matches!(self.condition, None | Some(Ok(true)))
+    }
+}
+
+enum Definition {
+    Expression(Expr),
+    Text(String),
+    Invalid(String),
+}
+
+/// Generates the variables of a question
+pub struct VariableSampler {
+    /// The definitions by lowercase variable name
+    definitions: BTreeMap<String, (String, Definition)>,
+    functions: HashMap<String, Function>,
+    constants: Vec<(String, Expr)>,
+    condition: Option<Expr>,
+    max_runs: usize,
+}
+
+impl VariableSampler {
+    pub fn new(question: &Question, locale: &str) -> Self {
+        let definitions = question
+            .all_variables()
+            .into_iter()
+            .map(|(name, variable)| {
+                let variable = variable.to_variable(locale);
+                let definition = match variable.jme_definition(locale) {
+                    None => Definition::Text(
+                        variable.definition.get_content(locale).unwrap_or_default(),
+                    ),
+                    Some(Ok(jme)) => match jme.ast() {
+                        Some(expr) => Definition::Expression(expr.clone()),
+                        None => Definition::Invalid("The definition is empty".to_string()),
+                    },
+                    Some(Err(e)) => Definition::Invalid(e.to_string()),
+                };
+                (name.to_lowercase(), (name, definition))
+            })
+            .collect();
+        let functions = question
+            .functions
+            .iter()
+            .filter_map(|(name, function)| match &function.definition {
+                FunctionDefinition::JME(jme) => jme
+                    .definition
+                    .to_string(locale)
+                    .and_then(|s| numbas::jme::JMEString::try_from(s).ok())
+                    .and_then(|s| s.ast().cloned())
+                    .map(|body| {
+                        (
+                            name.clone(),
+                            Function {
+                                parameters: function
+                                    .parameters
+                                    .iter()
+                                    .map(|(n, _)| n.clone())
+                                    .collect(),
+                                body,
+                            },
+                        )
+                    }),
+                FunctionDefinition::Javascript(_) => None,
+            })
+            .collect();
+        let constants = question
+            .custom_constants
+            .iter()
+            .filter_map(|c| c.value.ast().map(|e| (c.name.clone(), e.clone())))
+            .collect();
+        Self {
+            definitions,
+            functions,
+            constants,
+            condition: question.variables_test.condition.ast().cloned(),
+            max_runs: question.variables_test.max_runs.max(1),
+        }
+    }
+
+    /// Generate sets of variables with the given seed until the variables test passes or
+    /// max_runs sets are generated.
+    pub fn sample(&self, seed: u64) -> VariablesSample {
+        let mut evaluator = Evaluator::new(seed);
+        let scope = self.base_scope(&mut evaluator);
+
+        let mut runs = 0;
+        loop {
+            runs += 1;
+            let values = self.generate(&mut evaluator, &scope);
+            let condition = self.condition.as_ref().map(|condition| {
+                let mut condition_scope = scope.child();
+                for (name, value) in values.iter() {
+                    if let Ok(value) = value {
+                        condition_scope.set_variable(name, value.clone());
+                    }
+                }
+                evaluator
+                    .evaluate(condition, &condition_scope)
+                    .and_then(|v| match v {
+                        Value::Boolean(b) => Ok(b),
+                        v => Err(EvaluationError::InvalidArguments(
+                            "variables test".to_string(),
+                            vec![v.type_name()],
+                        )),
+                    })
+            });
+            let done = matches!(condition, None | Some(Ok(true)) | Some(Err(_)));
+            if done || runs >= self.max_runs {
+                return VariablesSample {
+                    seed,
+                    values: values
+                        .into_iter()
+                        .map(|(k, v)| (self.definitions[&k].0.clone(), v))
+                        .collect(),
+                    runs,
+                    condition,
+                };
+            }
+        }
+    }
+
+    /// A scope with the functions and the custom constants of the question
+    fn base_scope(&self, evaluator: &mut Evaluator) -> Scope<'static> {
+        let mut scope = Scope::new();
+        for (name, function) in self.functions.iter() {
+            scope.add_function(name, function.clone());
+        }
+        for (name, expr) in self.constants.iter() {
+            if let Ok(value) = evaluator.evaluate(expr, &scope) {
+                scope.set_variable(name, value);
+            }
+        }
+        scope
+    }
+
+    /// A scope with the functions and the custom constants of the question and the values of
+    /// the variables that could be generated in the given sample.
+    pub fn scope(&self, sample: &VariablesSample) -> Scope<'static> {
+        let mut scope = self.base_scope(&mut Evaluator::new(sample.seed));
+        for (name, value) in sample.values.iter() {
+            if let Ok(value) = value {
+                scope.set_variable(name, value.clone());
+            }
+        }
+        scope
+    }
+
+    /// Generate one set of variables, by lowercase name
+    fn generate(
+        &self,
+        evaluator: &mut Evaluator,
+        scope: &Scope,
+    ) -> BTreeMap<String, Result<Value, VariableError>> {
+        let mut values = BTreeMap::new();
+        for name in self.definitions.keys() {
+            self.generate_variable(name, evaluator, scope, &mut Vec::new(), &mut values);
+        }
+        values
+    }
+
+    fn generate_variable(
+        &self,
+        name: &str,
+        evaluator: &mut Evaluator,
+        scope: &Scope,
+        stack: &mut Vec<String>,
+        values: &mut BTreeMap<String, Result<Value, VariableError>>,
+    ) {
+        if values.contains_key(name) {
+            return;
+        }
+        let expr = match &self.definitions[name].1 {
+            Definition::Text(s) => {
+                values.insert(name.to_string(), Ok(Value::String(s.clone())));
+                return;
+            }
+            Definition::Invalid(e) => {
+                values.insert(
+                    name.to_string(),
+                    Err(VariableError::InvalidDefinition(e.clone())),
+                );
+                return;
+            }
+            Definition::Expression(expr) => expr,
+        };
+        stack.push(name.to_string());
+        let mut variable_scope = scope.child();
+        let mut result = None;
+        for dependency in expr.free_variables() {
+            if !self.definitions.contains_key(&dependency) {
+                continue;
+            }
+            if let Some(position) = stack.iter().position(|n| n == &dependency) {
+                let mut cycle: Vec<_> = stack[position..]
+                    .iter()
+                    .map(|n| self.definitions[n].0.clone())
+                    .collect();
+                cycle.push(self.definitions[&dependency].0.clone());
+                result = Some(Err(VariableError::CircularDefinition(cycle)));
+                break;
+            }
+            self.generate_variable(&dependency, evaluator, scope, stack, values);
+            match &values[&dependency] {
+                Ok(value) => variable_scope.set_variable(&dependency, value.clone()),
+                Err(_) => {
+                    result = Some(Err(VariableError::FailingDependency(
+                        self.definitions[&dependency].0.clone(),
+                    )));
+                    break;
+                }
+            }
+        }
+        stack.pop();
+        let result = result.unwrap_or_else(|| {
+            evaluator
+                .evaluate(expr, &variable_scope)
+                .map_err(VariableError::Evaluation)
+        });
+        values.insert(name.to_string(), result);
+    }
+}
+
+impl Question {
+    /// Generate the variables of this question for the given seeds
+    pub fn sample_variables(
+        &self,
+        locale: &str,
+        seeds: impl Iterator<Item = u64>,
+    ) -> Vec<VariablesSample> {
+        let sampler = VariableSampler::new(self, locale);
+        seeds.map(|seed| sampler.sample(seed)).collect()
+    }
+}
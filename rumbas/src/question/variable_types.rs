@@ -0,0 +1,109 @@
+//! Infer the types of the variables of a question and check the types in its jme expressions.
+
+use crate::question::function::FunctionType;
+use crate::question::variable::VariableTemplateType;
+use crate::question::Question;
+use numbas::jme::ast::Expr;
+use numbas::jme::types::JMEType;
+use rumbas_support::preamble::*;
+use std::collections::BTreeMap;
+
+impl FunctionType {
+    /// The jme type of the values of this type
+    pub fn jme_type(&self) -> JMEType {
+        match self {
+            FunctionType::Boolean => JMEType::Boolean,
+            FunctionType::Decimal => JMEType::Decimal,
+            FunctionType::Dictionary => JMEType::Dictionary,
+            FunctionType::Expression => JMEType::Expression,
+            FunctionType::HTML => JMEType::HTML,
+            FunctionType::Integer => JMEType::Integer,
+            FunctionType::List => JMEType::List,
+            FunctionType::Matrix => JMEType::Matrix,
+            FunctionType::Nothing => JMEType::Nothing,
+            FunctionType::Number => JMEType::Number,
+            FunctionType::Range => JMEType::Range,
+            FunctionType::Rational => JMEType::Rational,
+            FunctionType::Set => JMEType::Set,
+            FunctionType::r#String => JMEType::String,
+            FunctionType::Vector => JMEType::Vector,
+            FunctionType::KeyPair | FunctionType::ExtensionGeogebraApplet => JMEType::Any,
+        }
+    }
+}
+
+impl Question {
+    /// The parsed definitions of the variables, None for plain text and invalid definitions
+    fn variable_definitions(&self, locale: &str) -> BTreeMap<String, (JMEType, Option<Expr>)> {
+        self.all_variables()
+            .into_iter()
+            .map(|(name, variable)| {
+                let variable = variable.to_variable(locale);
+                let definition = variable
+                    .jme_definition(locale)
+                    .and_then(|d| d.ok())
+                    .and_then(|d| d.ast().cloned());
+                let initial_type = match variable.template_type {
+                    VariableTemplateType::r#String | VariableTemplateType::LongString => {
+                        JMEType::String
+                    }
+                    _ => JMEType::Any,
+                };
+                (name, (initial_type, definition))
+            })
+            .collect()
+    }
+
+    /// The validation scope of this question, with the inferred types of the variables and the
+    /// output types of the functions.
+    pub fn typed_validation_scope(&self, locale: &str) -> ValidationScope {
+        let mut scope = self.validation_scope();
+        for (name, function) in self.functions.iter() {
+            scope.set_function_type(name, function.output_type.jme_type());
+        }
+        let definitions = self.variable_definitions(locale);
+        for (name, (initial_type, _)) in definitions.iter() {
+            scope.set_variable_type(name, *initial_type);
+        }
+        // Each round, the types of the variables that depend on a newly typed variable can be
+        // inferred. The amount of rounds is bounded, circular definitions never get a type.
+        for _ in 0..=definitions.len() {
+            let mut changed = false;
+            for (name, (_, definition)) in definitions.iter() {
+                if let Some(definition) = definition {
+                    let inferred = definition.infer_type(&scope);
+                    if inferred != scope.variable_type(name) {
+                        scope.set_variable_type(name, inferred);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        scope
+    }
+
+    /// Check the types in the definitions of the variables
+    pub fn check_variable_types(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        for (name, (_, definition)) in self.variable_definitions(locale) {
+            if let Some(definition) = definition {
+                for error in definition.type_errors(scope) {
+                    let mut error_result = RumbasCheckResult::from_type_error(error);
+                    error_result.extend_path("definition".to_string());
+                    error_result.extend_path(name.clone());
+                    if let Some(group) = self.variable_group(&name) {
+                        error_result.extend_path(group);
+                        error_result.extend_path("grouped_variables".to_string());
+                    } else {
+                        error_result.extend_path("variables".to_string());
+                    }
+                    result.union(&error_result);
+                }
+            }
+        }
+        result
+    }
+}
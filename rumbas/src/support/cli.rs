@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use crate::support::dependency_graph::DependencyKind;
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
 /// The rumbas cli
@@ -49,6 +50,31 @@ pub enum Command {
         /// Don't perform minification on the created js in the exam. Useful if you don't have uglifyjs or want to debug something.
         #[clap(value_parser, long)]
         no_minification: bool,
+        /// Run numbas for all exams, also for the exams whose output is up to date.
+        ///
+        /// By default, numbas is skipped if the output of the same generated exam, theme, locale, options and numbas version already exists.
+        #[clap(value_parser, long)]
+        force: bool,
+        /// The maximal number of exams that are compiled at the same time.
+        ///
+        /// Defaults to the number of cpus.
+        #[clap(value_parser = clap::value_parser!(u16).range(1..), long, short)]
+        jobs: Option<u16>,
+        /// Keep the numbas compilers running and reuse them for the next exams.
+        ///
+        /// This avoids starting python for each exam and locale. At most `jobs` compilers are started.
+        #[clap(value_parser, long)]
+        numbas_workers: bool,
+        /// Only check the exams and write the generated .exam files to this folder, numbas is not run.
+        ///
+        /// The files are written to `<folder>/<locale>/<path of the exam>.exam`. `NUMBAS_FOLDER` and python are not needed, extensions are referred to by their name.
+        #[clap(value_parser, long, value_name = "FOLDER")]
+        exam_only: Option<std::path::PathBuf>,
+        /// The format of the reported errors and warnings.
+        ///
+        /// With json, each diagnostic is printed as one json record on stdout and the log is written to stderr.
+        #[clap(value_enum, long, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
     },
     /// Check a rumbas exam (or question)
     ///
@@ -62,6 +88,67 @@ pub enum Command {
         /// It is possible to specify multiple paths to folder/files.
         #[clap(required = true, value_parser)]
         exam_or_question_paths: Vec<String>,
+        /// The format of the reported errors and warnings.
+        ///
+        /// With json, each diagnostic is printed as one json record on stdout and the log is written to stderr.
+        #[clap(value_enum, long, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+    /// Lint a rumbas exam (or question): report parts and questions that are valid, but probably not what you intended.
+    ///
+    /// The lint rules can be disabled in the `lints` field of the .rumbasrc.yaml file, e.g. `empty_advice: false`.
+    #[clap(arg_required_else_help = true)]
+    Lint {
+        /// The path to the exam or question file to lint.
+        ///
+        /// If a folder within the questions or exams folder is used, all questions/exams in that folder will be linted.
+        ///
+        /// It is possible to specify multiple paths to folder/files.
+        #[clap(required = true, value_parser)]
+        exam_or_question_paths: Vec<String>,
+        /// The format of the reported warnings.
+        ///
+        /// With json, each diagnostic is printed as one json record on stdout and the log is written to stderr.
+        #[clap(value_enum, long, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+    /// Generate the variables of a rumbas question (or the questions of an exam) for multiple seeds, without using numbas.
+    ///
+    /// Shows the generated values and reports how often the variables test passes.
+    #[clap(arg_required_else_help = true)]
+    Variables {
+        /// The path to the exam or question file.
+        ///
+        /// If a folder within the questions or exams folder is used, all questions/exams in that folder will be used.
+        ///
+        /// It is possible to specify multiple paths to folder/files.
+        #[clap(required = true, value_parser)]
+        exam_or_question_paths: Vec<String>,
+        /// The amount of seeds to generate variables for
+        #[clap(value_parser, long, short, default_value_t = 10)]
+        seeds: u64,
+        /// The first seed to use
+        #[clap(value_parser, long, default_value_t = 0)]
+        first_seed: u64,
+    },
+    /// Simulate the marking of the tests of the parts of a rumbas question (or the questions of an exam), without using numbas.
+    ///
+    /// Each part can specify `tests`: answers with the marks that they should be awarded.
+    #[clap(arg_required_else_help = true)]
+    Test {
+        /// The path to the exam or question file.
+        ///
+        /// If a folder within the questions or exams folder is used, all questions/exams in that folder will be tested.
+        ///
+        /// It is possible to specify multiple paths to folder/files.
+        #[clap(required = true, value_parser)]
+        exam_or_question_paths: Vec<String>,
+        /// The amount of seeds to generate variables for
+        #[clap(value_parser, long, short, default_value_t = 1)]
+        seeds: u64,
+        /// The first seed to use
+        #[clap(value_parser, long, default_value_t = 0)]
+        first_seed: u64,
     },
     /// Watch a path
     #[clap(arg_required_else_help = true)]
@@ -72,6 +159,65 @@ pub enum Command {
         #[clap(short)]
         only_check: bool,
     },
+    /// Watch a path and serve the output folder on a local http server
+    ///
+    /// The index page lists all compiled exams per locale. Open exams are reloaded when they are recompiled.
+    #[clap(arg_required_else_help = true)]
+    Serve {
+        /// The path to watch
+        path: String,
+        /// The port of the http server
+        #[clap(value_parser, long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Show the dependency graph of rumbas exams (or questions).
+    ///
+    /// The graph contains the questions, templates, default files, `file:` references, resources, custom part types and themes that the exams and questions use. It is written to stdout.
+    #[clap(arg_required_else_help = true)]
+    Deps {
+        /// The path to the exam or question file.
+        ///
+        /// If a folder within the questions or exams folder is used, all questions/exams in that folder will be used.
+        ///
+        /// It is possible to specify multiple paths to folder/files.
+        #[clap(required = true, value_parser)]
+        exam_or_question_paths: Vec<String>,
+        /// The format of the graph
+        #[clap(value_enum, long, default_value_t = DependencyGraphFormat::Dot)]
+        format: DependencyGraphFormat,
+        /// Only show the files that (indirectly) depend on this file, e.g. to see which exams are affected by a change of a default file.
+        #[clap(value_parser, long, value_name = "FILE")]
+        dependants_of: Vec<String>,
+        /// Only show the files that this file (indirectly) depends on.
+        #[clap(value_parser, long, value_name = "FILE")]
+        dependencies_of: Vec<String>,
+        /// Only show the dependencies of this kind.
+        #[clap(value_enum, long)]
+        kind: Vec<DependencyKind>,
+    },
+    /// List the rumbas exams (and questions) that are affected by the changes since a git revision.
+    ///
+    /// The changes are the differences between the revision and the working tree, untracked files included. An exam or question is affected if it, or a file that it (indirectly) depends on, is changed. The paths of the affected files, relative to the root of the project, are written to stdout.
+    #[clap(arg_required_else_help = true)]
+    Affected {
+        /// The path to the exam or question file.
+        ///
+        /// If a folder within the questions or exams folder is used, all questions/exams in that folder will be used.
+        ///
+        /// It is possible to specify multiple paths to folder/files.
+        #[clap(required = true, value_parser)]
+        exam_or_question_paths: Vec<String>,
+        /// The git revision to compare with, e.g. a commit, branch or tag.
+        #[clap(value_parser, long, value_name = "REV")]
+        since: String,
+        /// Compile the affected exams and questions.
+        #[clap(value_parser, long)]
+        compile: bool,
+    },
+    /// List the files in the questions, resources, custom_part_types, themes and defaults folders that no exam uses, and the templates that no file uses.
+    ///
+    /// The paths of the files, relative to the root of the project, are written to stdout.
+    Unused,
     /// Format a rumbas exam (or question).
     ///
     /// You can pass a path to a folder to format all files in the folder.
@@ -84,19 +230,37 @@ pub enum Command {
         /// It is possible to specify multiple paths to folder/files.
         #[clap(required = true, value_parser)]
         exam_or_question_paths: Vec<String>,
+        /// Also normalize the jme expressions: consistent spacing and only the necessary parentheses.
+        #[clap(value_parser, long)]
+        jme: bool,
+        /// The format of the reported errors and warnings.
+        ///
+        /// With json, each diagnostic is printed as one json record on stdout and the log is written to stderr.
+        #[clap(value_enum, long, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
     },
-    /// Import a numbas .exam file
-    ///    
-    /// Resources have to be manually placed in the resources folder
+    /// Import numbas .exam files
+    ///
+    /// The zip file that is downloaded from the numbas editor or the folder of a compiled exam can
+    /// also be imported, its resources are copied to the resources folder. The resources of a
+    /// .exam file are searched in the resources folder next to it.
     #[clap(arg_required_else_help = true)]
     Import {
-        /// The path to the numbas .exam file
-        #[clap(value_parser)]
-        exam_path: String,
+        /// The path to the numbas .exam file, the zip file or the folder of a compiled exam
+        ///
+        /// If another folder is used, all .exam and zip files and compiled exams in it are imported.
+        ///
+        /// It is possible to specify multiple paths to folders/files.
+        #[clap(required = true, value_parser)]
+        exam_paths: Vec<String>,
         /// Tells rumbas that this is the exam file of a numbas question instead of of a numbas exam.
         #[clap(short)]
         question: bool,
     },
+    /// Start a language server for the yaml files of a rumbas project.
+    ///
+    /// It uses the Language Server Protocol over stdio and provides diagnostics, hover documentation, completion and go to definition.
+    Lsp,
     /// Initialize a rumbas project in this folder
     Init {
         /// Whether the defaults should be of summative nature (instead of formative)
@@ -142,7 +306,54 @@ pub enum Command {
     },
 }
 
+/// The format of the reported errors and warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Log messages for humans
+    Human,
+    /// One json record per diagnostic
+    Json,
+}
+
+/// The format of a dependency graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DependencyGraphFormat {
+    /// The Graphviz DOT format
+    Dot,
+    /// A json object with the files and the dependencies
+    Json,
+}
+
+/// Where the log is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOutput {
+    Stdout,
+    /// Used when stdout is used for machine readable output
+    Stderr,
+}
+
 impl Command {
+    /// The message format of the command
+    pub fn message_format(&self) -> MessageFormat {
+        match self {
+            Self::Compile { message_format, .. }
+            | Self::Check { message_format, .. }
+            | Self::Lint { message_format, .. }
+            | Self::Fmt { message_format, .. } => *message_format,
+            _ => MessageFormat::Human,
+        }
+    }
+    /// Where the command writes its log to
+    pub fn log_output(&self) -> LogOutput {
+        match (self, self.message_format()) {
+            (Self::Lsp, _)
+            | (Self::Deps { .. }, _)
+            | (Self::Affected { .. }, _)
+            | (Self::Unused, _)
+            | (_, MessageFormat::Json) => LogOutput::Stderr,
+            _ => LogOutput::Stdout,
+        }
+    }
     fn can_execute_in_old_version(&self) -> bool {
         matches!(
             self,
@@ -86,6 +86,22 @@ pub fn combine_question_with_default_files(path: RumbasPath, question: &mut Ques
     handle_question!(default_files, question);
 }
 
+/// The paths of the exam default files that apply to the given path
+pub fn exam_default_file_paths(path: RumbasPath) -> Vec<RumbasPath> {
+    <DefaultFile<DefaultExamFileType>>::files(path)
+        .into_iter()
+        .map(|f| f.get_path())
+        .collect()
+}
+
+/// The paths of the question default files that apply to the given path
+pub fn question_default_file_paths(path: RumbasPath) -> Vec<RumbasPath> {
+    <DefaultFile<DefaultQuestionFileType>>::files(path)
+        .into_iter()
+        .map(|f| f.get_path())
+        .collect()
+}
+
 /// Returns a vector of paths to default files for the given path
 fn default_file_paths(path: RumbasPath) -> Vec<RumbasPath> {
     let mut used = HashSet::new(); //Use set to remove duplicates (only happens for the 'defaults' folder in root
@@ -114,6 +130,13 @@ fn default_file_paths(path: RumbasPath) -> Vec<RumbasPath> {
     result
 }
 
+/// The default file with the given name (e.g. `questionpart.jme`) that applies to the given path
+pub fn default_file_path(path: RumbasPath, name: &str) -> Option<RumbasPath> {
+    default_file_paths(path)
+        .into_iter()
+        .find(|p| p.project().file_stem().and_then(|s| s.to_str()) == Some(name))
+}
+
 // Create the needed enum for exams by specifying which files contain which data
 create_default_file_type_enums!(
     DefaultExamFileType: DefaultExamData: exam_defaults_test,
@@ -0,0 +1,351 @@
+//! The graph of the files of a project and the files that they depend on
+
+use rumbas_support::path::RumbasPath;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+/// The way a file depends on another file
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, clap::ValueEnum, serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    /// A question of an exam
+    Question,
+    /// A template that is used by an exam or question
+    Template,
+    /// A default file that applies to an exam or question
+    Default,
+    /// A `file:` reference
+    File,
+    /// A resource of a question
+    Resource,
+    /// A custom part type that is used by a question
+    CustomPartType,
+    /// The custom theme of an exam
+    Theme,
+}
+
+impl std::fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Self::Question => "question",
+            Self::Template => "template",
+            Self::Default => "default",
+            Self::File => "file",
+            Self::Resource => "resource",
+            Self::CustomPartType => "custom_part_type",
+            Self::Theme => "theme",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl DependencyKind {
+    /// The kind of the dependency of the dependant on the dependency, based on the folders they
+    /// are in
+    pub fn of(dependant: &RumbasPath, dependency: &RumbasPath) -> Self {
+        let is_yaml = dependency.extension().and_then(|e| e.to_str()) == Some("yaml");
+        if dependency.in_main_folder(crate::CUSTOM_PART_TYPES_FOLDER) {
+            Self::CustomPartType
+        } else if dependency.in_main_folder(crate::RESOURCES_FOLDER) {
+            Self::Resource
+        } else if dependency.in_main_folder(crate::THEMES_FOLDER) {
+            Self::Theme
+        } else if is_default_file(dependency.project()) {
+            Self::Default
+        } else if is_yaml && dependency.in_main_folder(crate::EXAMS_FOLDER) {
+            Self::Template
+        } else if is_yaml && dependency.in_main_folder(crate::QUESTIONS_FOLDER) {
+            // Questions only depend on other questions by using them as template
+            if dependant.in_main_folder(crate::EXAMS_FOLDER) {
+                Self::Question
+            } else {
+                Self::Template
+            }
+        } else {
+            Self::File
+        }
+    }
+}
+
+/// Default files are placed in a defaults folder, at any depth
+fn is_default_file(path: &Path) -> bool {
+    path.parent()
+        .map(|parent| {
+            parent
+                .components()
+                .any(|c| c == Component::Normal(crate::DEFAULTS_FOLDER.as_ref()))
+        })
+        .unwrap_or(false)
+}
+
+/// The kind of file, based on the folder it is in
+pub fn file_kind(path: &Path) -> &'static str {
+    if is_default_file(path) {
+        "default"
+    } else if path.starts_with(crate::EXAMS_FOLDER) {
+        "exam"
+    } else if path.starts_with(crate::QUESTIONS_FOLDER)
+        && path.extension().and_then(|e| e.to_str()) == Some("yaml")
+    {
+        "question"
+    } else if path.starts_with(crate::RESOURCES_FOLDER) {
+        "resource"
+    } else if path.starts_with(crate::CUSTOM_PART_TYPES_FOLDER) {
+        "custom_part_type"
+    } else if path.starts_with(crate::THEMES_FOLDER) {
+        "theme"
+    } else {
+        "file"
+    }
+}
+
+/// The files of a project (by their path in the project) with the files that they directly
+/// depend on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    dependencies: BTreeMap<PathBuf, BTreeMap<PathBuf, DependencyKind>>,
+}
+
+impl DependencyGraph {
+    pub fn add_file(&mut self, file: PathBuf) {
+        self.dependencies.entry(file).or_default();
+    }
+    pub fn add_dependency(&mut self, file: PathBuf, dependency: PathBuf, kind: DependencyKind) {
+        self.add_file(dependency.clone());
+        self.dependencies
+            .entry(file)
+            .or_default()
+            .insert(dependency, kind);
+    }
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.dependencies.keys()
+    }
+    pub fn contains(&self, file: &Path) -> bool {
+        self.dependencies.contains_key(file)
+    }
+    /// The files that the file directly depends on
+    pub fn dependencies(&self, file: &Path) -> BTreeMap<PathBuf, DependencyKind> {
+        self.dependencies.get(file).cloned().unwrap_or_default()
+    }
+    /// The files that directly depend on the file
+    pub fn dependants(&self, file: &Path) -> BTreeMap<PathBuf, DependencyKind> {
+        self.dependencies
+            .iter()
+            .filter_map(|(dependant, dependencies)| {
+                dependencies
+                    .get(file)
+                    .map(|kind| (dependant.clone(), *kind))
+            })
+            .collect()
+    }
+    /// The given files and all files that (indirectly) depend on them
+    pub fn transitive_dependants(&self, files: &[PathBuf]) -> BTreeSet<PathBuf> {
+        let mut dependants: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+        for (dependant, dependencies) in self.dependencies.iter() {
+            for dependency in dependencies.keys() {
+                dependants
+                    .entry(dependency.as_path())
+                    .or_default()
+                    .push(dependant.as_path());
+            }
+        }
+        reachable(files, |file| {
+            dependants.get(file).cloned().unwrap_or_default()
+        })
+    }
+    /// The given files and all files that they (indirectly) depend on
+    pub fn transitive_dependencies(&self, files: &[PathBuf]) -> BTreeSet<PathBuf> {
+        reachable(files, |file| {
+            self.dependencies
+                .get(file)
+                .map(|d| d.keys().map(|p| p.as_path()).collect())
+                .unwrap_or_default()
+        })
+    }
+    /// The graph with only the given files
+    pub fn restrict(&self, files: &BTreeSet<PathBuf>) -> Self {
+        Self {
+            dependencies: self
+                .dependencies
+                .iter()
+                .filter(|(file, _)| files.contains(*file))
+                .map(|(file, dependencies)| {
+                    (
+                        file.clone(),
+                        dependencies
+                            .iter()
+                            .filter(|(dependency, _)| files.contains(*dependency))
+                            .map(|(dependency, kind)| (dependency.clone(), *kind))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+    /// The graph with only the dependencies of the given kinds (and the files of these
+    /// dependencies)
+    pub fn with_kinds(&self, kinds: &[DependencyKind]) -> Self {
+        let mut graph = Self::default();
+        for (file, dependencies) in self.dependencies.iter() {
+            for (dependency, kind) in dependencies.iter() {
+                if kinds.contains(kind) {
+                    graph.add_dependency(file.clone(), dependency.clone(), *kind);
+                }
+            }
+        }
+        graph
+    }
+    pub fn to_json(&self) -> serde_json::Value {
+        let files: Vec<_> = self
+            .files()
+            .map(|file| {
+                serde_json::json!({
+                    "path": display_path(file),
+                    "kind": file_kind(file),
+                })
+            })
+            .collect();
+        let dependencies: Vec<_> = self
+            .dependencies
+            .iter()
+            .flat_map(|(file, dependencies)| {
+                dependencies.iter().map(move |(dependency, kind)| {
+                    serde_json::json!({
+                        "from": display_path(file),
+                        "to": display_path(dependency),
+                        "kind": kind,
+                    })
+                })
+            })
+            .collect();
+        serde_json::json!({ "files": files, "dependencies": dependencies })
+    }
+    /// The graph in the Graphviz DOT format. Exams are boxes and default files are grey.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n    rankdir=LR;\n");
+        for file in self.files() {
+            let attributes = match file_kind(file) {
+                "exam" => " [shape=box]",
+                "default" => " [style=filled, fillcolor=lightgrey]",
+                _ => "",
+            };
+            dot.push_str(&format!(
+                "    {}{};\n",
+                dot_id(&display_path(file)),
+                attributes
+            ));
+        }
+        for (file, dependencies) in self.dependencies.iter() {
+            for (dependency, kind) in dependencies.iter() {
+                dot.push_str(&format!(
+                    "    {} -> {} [label={}];\n",
+                    dot_id(&display_path(file)),
+                    dot_id(&display_path(dependency)),
+                    dot_id(&kind.to_string())
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn reachable<'a>(files: &[PathBuf], next: impl Fn(&Path) -> Vec<&'a Path>) -> BTreeSet<PathBuf> {
+    let mut found: BTreeSet<PathBuf> = files.iter().cloned().collect();
+    let mut todo: Vec<PathBuf> = files.to_vec();
+    while let Some(file) = todo.pop() {
+        for other in next(&file) {
+            if found.insert(other.to_path_buf()) {
+                todo.push(other.to_path_buf());
+            }
+        }
+    }
+    found
+}
+
+/// The path with forward slashes, also on windows
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph() -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        graph.add_dependency(
+            "exams/a.yaml".into(),
+            "questions/q.yaml".into(),
+            DependencyKind::Question,
+        );
+        graph.add_dependency(
+            "exams/b.yaml".into(),
+            "questions/r.yaml".into(),
+            DependencyKind::Question,
+        );
+        graph.add_dependency(
+            "questions/q.yaml".into(),
+            "defaults/question.yaml".into(),
+            DependencyKind::Default,
+        );
+        graph.add_dependency(
+            "questions/q.yaml".into(),
+            "resources/image.png".into(),
+            DependencyKind::Resource,
+        );
+        graph
+    }
+
+    #[test]
+    fn transitive() {
+        let graph = graph();
+        assert_eq!(
+            graph.transitive_dependants(&["defaults/question.yaml".into()]),
+            vec![
+                "defaults/question.yaml".into(),
+                "exams/a.yaml".into(),
+                "questions/q.yaml".into()
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(
+            graph.transitive_dependencies(&["exams/b.yaml".into()]),
+            vec!["exams/b.yaml".into(), "questions/r.yaml".into()]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            graph.dependants(Path::new("resources/image.png")),
+            vec![("questions/q.yaml".into(), DependencyKind::Resource)]
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn filters_and_dot() {
+        let graph = graph()
+            .restrict(&graph().transitive_dependencies(&["exams/a.yaml".into()]))
+            .with_kinds(&[DependencyKind::Question, DependencyKind::Default]);
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph dependencies {
+    rankdir=LR;
+    "defaults/question.yaml" [style=filled, fillcolor=lightgrey];
+    "exams/a.yaml" [shape=box];
+    "questions/q.yaml";
+    "exams/a.yaml" -> "questions/q.yaml" [label="question"];
+    "questions/q.yaml" -> "defaults/question.yaml" [label="default"];
+}
+"#
+        );
+    }
+}
@@ -76,6 +76,47 @@ impl DependencyManager {
         }
     }
 
+    /// The file is moved: the new file depends on the same files. The files that depend on the
+    /// previous path keep depending on it, they now refer to a missing file.
+    pub fn rename(&self, previous: &RumbasPath, new: &RumbasPath) {
+        let map = self
+            .depended_on_by
+            .read()
+            .expect("Can read depended_on_by map");
+        let mut renamed = false;
+        for (dependency, dependants) in map.iter() {
+            let mut dependants = dependants
+                .lock()
+                .expect("unlock loaded depended_on_by mutex");
+            if dependants.remove(previous) {
+                // The new file doesn't depend on the previous one
+                if dependency != previous {
+                    dependants.insert(new.clone());
+                }
+                renamed = true;
+            }
+        }
+        std::mem::drop(map);
+        if renamed {
+            log::debug!("Renamed {} to {}.", previous.display(), new.display());
+            self.add_dependencies(new.clone(), HashSet::new());
+        }
+    }
+
+    /// The file is removed, so it no longer depends on other files
+    pub fn remove_dependant(&self, path: &RumbasPath) {
+        let map = self
+            .depended_on_by
+            .read()
+            .expect("Can read depended_on_by map");
+        for dependants in map.values() {
+            dependants
+                .lock()
+                .expect("unlock loaded depended_on_by mutex")
+                .remove(path);
+        }
+    }
+
     pub fn log_debug(&self) {
         let map = self
             .depended_on_by
@@ -84,3 +125,44 @@ impl DependencyManager {
         log::debug!("{:?}", map);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn rename_and_remove() {
+        let root = Path::new("/project");
+        let path = |p: &str| RumbasPath::test_make(Path::new(p), root);
+        let dependencies = DependencyManager::default();
+        dependencies.add_dependencies(
+            path("exams/exam.yaml"),
+            vec![path("questions/a.yaml")].into_iter().collect(),
+        );
+        dependencies.add_dependencies(
+            path("questions/a.yaml"),
+            vec![path("defaults/question.yaml")].into_iter().collect(),
+        );
+
+        dependencies.rename(&path("questions/a.yaml"), &path("questions/b.yaml"));
+        // The exam still refers to the previous path
+        assert_eq!(
+            dependencies.get_dependants(path("questions/a.yaml")),
+            vec![path("exams/exam.yaml")].into_iter().collect()
+        );
+        assert_eq!(
+            dependencies.get_dependants(path("defaults/question.yaml")),
+            vec![path("questions/b.yaml")].into_iter().collect()
+        );
+        assert_eq!(
+            dependencies.get_dependants(path("questions/b.yaml")),
+            vec![path("questions/b.yaml")].into_iter().collect()
+        );
+
+        dependencies.remove_dependant(&path("exams/exam.yaml"));
+        assert!(dependencies
+            .get_dependants(path("questions/a.yaml"))
+            .is_empty());
+    }
+}
@@ -262,6 +262,33 @@ impl FileManager {
             map.remove(&file);
         }
     }
+    /// Forget the entries of the folder, e.g. because a file is added or removed
+    pub fn delete_folder(&self, folder: &RumbasPath) {
+        let mut map = self.dir_cache.write().expect("Can write dir_cache map");
+        if map.remove(folder.absolute()).is_some() {
+            log::debug!("Deleting {} from the dir_cache.", folder.display());
+        }
+    }
+    /// Use the given content for the file instead of the content on disk (e.g. the unsaved
+    /// content of an editor). The localized version of the file is read from disk again.
+    pub fn set_content(&self, file_path: RumbasPath, content: String) {
+        log::debug!(
+            "Setting the content of {} in the cache.",
+            file_path.display()
+        );
+        let mut map = self.cache.write().expect("Can write cache map");
+        map.remove(&FileToLoad {
+            file_path: file_path.clone(),
+            locale_dependant: true,
+        });
+        map.insert(
+            FileToLoad {
+                file_path: file_path.clone(),
+                locale_dependant: false,
+            },
+            Mutex::new(LoadedFile::Normal(LoadedNormalFile { content, file_path })),
+        );
+    }
 }
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone)]
@@ -822,6 +849,16 @@ macro_rules! create_from_string_type {
                 previous_result.extend_path(self.file_name.clone());
                 previous_result
             }
+            fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+                let mut previous_result = self.data.check_identifiers(locale, scope);
+                previous_result.extend_path(self.file_name.clone());
+                previous_result
+            }
+            fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+                let mut previous_result = self.data.check_jme_formatting(locale);
+                previous_result.extend_path(self.file_name.clone());
+                previous_result
+            }
         }
 
         impl Overwrite<$ti> for $ti {
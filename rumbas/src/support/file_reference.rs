@@ -116,6 +116,22 @@ macro_rules! file_type {
                         None => RumbasCheckResult::from_missing_translation(Some(locale.to_string())),
                     }
                 }
+                fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+                    let conversion_res: Option<Result<$subtype, _>> =
+                        self.get_content(locale).map(|c| c.try_into());
+                    match conversion_res {
+                        Some(Ok(s)) => s.check_identifiers(locale, scope),
+                        _ => RumbasCheckResult::empty(), // Reported by check
+                    }
+                }
+                fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+                    let conversion_res: Option<Result<$subtype, _>> =
+                        self.get_content(locale).map(|c| c.try_into());
+                    match conversion_res {
+                        Some(Ok(s)) => s.check_jme_formatting(locale),
+                        _ => RumbasCheckResult::empty(), // Reported by check
+                    }
+                }
             }
             impl Overwrite< [<$type Input>]> for  [<$type Input>] {
                 fn overwrite(&mut self, _other: &[<$type Input>]) {}
@@ -1,5 +1,6 @@
 use comparable::Comparable;
 use regex::Regex;
+use rumbas_support::rumbas_check::{RumbasCheck, RumbasCheckResult, ValidationScope};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use structdoc::StructDoc;
@@ -33,6 +34,18 @@ impl std::convert::From<InputString> for String {
     }
 }
 
+impl RumbasCheck for InputString {
+    fn check(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_identifiers(&self, _locale: &str, _scope: &ValidationScope) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
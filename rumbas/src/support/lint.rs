@@ -0,0 +1,317 @@
+//! Lints: warnings about exams and questions that are valid, but probably not what the author
+//! intended. They are reported by `rumbas lint` and don't make the checks fail.
+
+use crate::exam::Exam;
+use crate::question::part::multiple_choice::MultipleChoiceAnswerData;
+use crate::question::part::question_part::{QuestionPart, QuestionPartBuiltin};
+use crate::question::Question;
+use crate::support::noneable::Noneable;
+use crate::support::rc::RC;
+use crate::support::translatable::JMETranslatableString;
+use crate::support::variable_valued::VariableValued;
+use numbas::support::primitive::Number;
+use rumbas_support::diagnostic::{Diagnostic, DiagnosticKind};
+use serde::{Deserialize, Serialize};
+
+/// The lint rules, they can be disabled in the `lints` field of the rc file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// A part that awards no marks, information and gapfill parts are not reported
+    ZeroMarks,
+    /// A question without advice
+    EmptyAdvice,
+    /// A diagnostic topic name of a question that is not a topic of the diagnostic exam
+    UnusedDiagnosticTopicName,
+    /// A choose one part where no answer has positive marks
+    ChooseOneWithoutPositiveMarks,
+    /// A jme part that uses the deprecated string restrictions (e.g. `max_length`)
+    DeprecatedStringRestriction,
+}
+
+impl LintRule {
+    pub fn name(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .expect("A lint rule to be serialized as a string")
+    }
+}
+
+impl std::fmt::Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LintWarning {
+    pub rule: LintRule,
+    /// The keys and indices of the yaml path of the value, like the paths of the check results
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+impl LintWarning {
+    fn new(rule: LintRule, path: &[String], message: String) -> Self {
+        Self {
+            rule,
+            path: path.to_vec(),
+            message,
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::warning(DiagnosticKind::Lint, self.message.clone())
+            .with_rule(self.rule.name())
+            .with_path(self.path.clone())
+    }
+}
+
+impl std::fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} [{}]",
+            self.path.join("."),
+            self.message,
+            self.rule
+        )
+    }
+}
+
+fn child(path: &[String], keys: &[&str]) -> Vec<String> {
+    let mut path = path.to_vec();
+    path.extend(keys.iter().map(|k| k.to_string()));
+    path
+}
+
+/// The lint warnings of all questions of the exam, for the rules that are enabled in the rc
+pub fn lint_exam(exam: &Exam, locale: &str, rc: &RC) -> Vec<LintWarning> {
+    let topics: Option<Vec<String>> = match exam {
+        Exam::Diagnostic(d) => Some(
+            d.diagnostic
+                .topics
+                .iter()
+                .filter_map(|t| t.name.to_string(locale))
+                .collect(),
+        ),
+        Exam::Normal(_) => None,
+    };
+    let mut warnings = Vec::new();
+    for (group_idx, group) in exam.question_groups().iter().enumerate() {
+        for (question_idx, question) in group.questions.iter().enumerate() {
+            // The same path as the check results: the name of the question follows its index
+            let path = vec![
+                "question_groups".to_string(),
+                group_idx.to_string(),
+                "questions".to_string(),
+                question_idx.to_string(),
+                question.question_path.clone().unwrap_or_default(),
+            ];
+            lint_question(
+                &question.data,
+                locale,
+                topics.as_deref(),
+                &path,
+                &mut warnings,
+            );
+        }
+    }
+    warnings.retain(|w| rc.lint_enabled(w.rule));
+    warnings
+}
+
+/// Lint a question, `topics` are the topics of the diagnostic exam (if it is one)
+fn lint_question(
+    question: &Question,
+    locale: &str,
+    topics: Option<&[String]>,
+    path: &[String],
+    warnings: &mut Vec<LintWarning>,
+) {
+    if let Some(advice) = question.advice.to_string(locale) {
+        if advice.trim().is_empty() {
+            warnings.push(LintWarning::new(
+                LintRule::EmptyAdvice,
+                &child(path, &["advice"]),
+                "The advice is empty, consider adding a worked solution".to_string(),
+            ));
+        }
+    }
+    if let Some(topics) = topics {
+        for (idx, topic_name) in question.diagnostic_topic_names.iter().enumerate() {
+            if let Some(name) = topic_name.to_string(locale) {
+                if !topics.contains(&name) {
+                    warnings.push(LintWarning::new(
+                        LintRule::UnusedDiagnosticTopicName,
+                        &child(path, &["diagnostic_topic_names", &idx.to_string()]),
+                        format!("No topic of the diagnostic exam is named {}", name),
+                    ));
+                }
+            }
+        }
+    }
+    for (idx, part) in question.parts.iter().enumerate() {
+        lint_part(
+            part,
+            locale,
+            &child(path, &["parts", &idx.to_string()]),
+            warnings,
+        );
+    }
+}
+
+/// Lint a part and its steps and gaps
+fn lint_part(part: &QuestionPart, locale: &str, path: &[String], warnings: &mut Vec<LintWarning>) {
+    let builtin = match part {
+        QuestionPart::Builtin(b) => Some(b),
+        QuestionPart::Custom(_) => None,
+    };
+    // The marks of gapfill parts are the sum of the marks of their gaps
+    let without_marks = matches!(
+        builtin,
+        Some(QuestionPartBuiltin::Information(_)) | Some(QuestionPartBuiltin::GapFill(_))
+    );
+    if !without_marks && is_zero(part.marks()) {
+        warnings.push(LintWarning::new(
+            LintRule::ZeroMarks,
+            &child(path, &["marks"]),
+            "The part awards no marks".to_string(),
+        ));
+    }
+    match builtin {
+        Some(QuestionPartBuiltin::ChooseOne(p)) => {
+            let marks: Vec<&JMETranslatableString> = match &p.answer_data {
+                MultipleChoiceAnswerData::ItemBased(answers) => {
+                    answers.iter().map(|a| &a.marks).collect()
+                }
+                MultipleChoiceAnswerData::NumbasLike(data) => match &data.marks {
+                    VariableValued::Value(marks) => marks.iter().collect(),
+                    VariableValued::Variable(_) => Vec::new(),
+                },
+            };
+            // Marks that are jme expressions can't be judged without the variables
+            let values: Option<Vec<f64>> = marks
+                .iter()
+                .map(|m| m.to_string(locale).and_then(|m| m.trim().parse().ok()))
+                .collect();
+            if let Some(values) = values {
+                if !values.is_empty() && values.iter().all(|v| *v <= 0.0) {
+                    warnings.push(LintWarning::new(
+                        LintRule::ChooseOneWithoutPositiveMarks,
+                        &child(path, &["answer_data"]),
+                        "No answer has positive marks, the student can't answer correctly"
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+        Some(QuestionPartBuiltin::JME(p)) => {
+            let restrictions = [
+                ("max_length", matches!(p.max_length, Noneable::NotNone(_))),
+                ("min_length", matches!(p.min_length, Noneable::NotNone(_))),
+                ("must_have", matches!(p.must_have, Noneable::NotNone(_))),
+                (
+                    "may_not_have",
+                    matches!(p.may_not_have, Noneable::NotNone(_)),
+                ),
+            ];
+            for (name, used) in restrictions {
+                if used {
+                    warnings.push(LintWarning::new(
+                        LintRule::DeprecatedStringRestriction,
+                        &child(path, &[name]),
+                        format!(
+                            "The {} restriction is deprecated, use must_match_pattern instead",
+                            name
+                        ),
+                    ));
+                }
+            }
+        }
+        _ => (),
+    }
+    for (sub_path, sub_part) in part.sub_parts() {
+        let mut path = path.to_vec();
+        path.extend(sub_path);
+        lint_part(sub_part, locale, &path, warnings);
+    }
+}
+
+fn is_zero(number: &Number) -> bool {
+    match number {
+        Number::Integer(i) => *i == 0,
+        Number::Float(f) => *f == 0.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::support::to_rumbas::ToRumbas;
+
+    fn lint(part: serde_json::Value) -> Vec<(LintRule, String)> {
+        let part: numbas::question::part::QuestionPart = serde_json::from_value(part).unwrap();
+        let part: QuestionPart = part.to_rumbas();
+        let mut warnings = Vec::new();
+        lint_part(
+            &part,
+            "en",
+            &["parts".to_string(), "0".to_string()],
+            &mut warnings,
+        );
+        warnings
+            .into_iter()
+            .map(|w| (w.rule, w.path.join(".")))
+            .collect()
+    }
+
+    #[test]
+    fn parts() {
+        let part = serde_json::json!({
+            "type": "1_n_2", "marks": 0, "choices": ["a", "b"], "matrix": [0, "-1"],
+            "steps": [{"type": "information", "marks": 0}]
+        });
+        assert_eq!(
+            lint(part),
+            vec![
+                (LintRule::ZeroMarks, "parts.0.marks".to_string()),
+                (
+                    LintRule::ChooseOneWithoutPositiveMarks,
+                    "parts.0.answer_data".to_string()
+                ),
+            ]
+        );
+
+        let part = serde_json::json!({
+            "type": "1_n_2", "marks": 1, "choices": ["a", "b"], "matrix": ["m", 0]
+        });
+        assert_eq!(lint(part), vec![]);
+
+        let part = serde_json::json!({
+            "type": "gapfill", "marks": 0,
+            "gaps": [{"type": "jme", "marks": 1, "answer": "x",
+                "checkingType": "absdiff", "checkingAccuracy": 0.001,
+                "maxlength": {"length": 5, "partialCredit": 0, "message": "shorter"}}]
+        });
+        assert_eq!(
+            lint(part),
+            vec![(
+                LintRule::DeprecatedStringRestriction,
+                "parts.0.gaps.0.max_length".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn config() {
+        let rc: RC = serde_yaml::from_str("version: 0.8.0\nlints:\n  empty_advice: false").unwrap();
+        assert!(!rc.lint_enabled(LintRule::EmptyAdvice));
+        assert!(rc.lint_enabled(LintRule::ZeroMarks));
+        assert_eq!(
+            LintRule::UnusedDiagnosticTopicName.name(),
+            "unused_diagnostic_topic_name"
+        );
+    }
+}
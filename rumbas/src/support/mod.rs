@@ -6,13 +6,18 @@ pub mod to_numbas;
 pub mod to_rumbas;
 pub mod cli;
 pub mod default;
+pub mod dependency_graph;
 pub mod dependency_manager;
 pub mod file_manager;
 pub mod file_reference;
 pub mod input_string;
+pub mod lint;
 pub mod noneable;
+pub mod numbas_error;
 pub mod rc;
 pub mod sanitize;
+pub mod schema_lookup;
+pub mod source_location;
 pub mod template;
 pub mod translatable;
 pub mod variable_valued;
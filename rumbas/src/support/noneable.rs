@@ -134,6 +134,18 @@ impl<T: RumbasCheck> RumbasCheck for Noneable<T> {
             _ => RumbasCheckResult::empty(),
         }
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        match self {
+            Noneable::NotNone(val) => val.check_identifiers(locale, scope),
+            _ => RumbasCheckResult::empty(),
+        }
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        match self {
+            Noneable::NotNone(val) => val.check_jme_formatting(locale),
+            _ => RumbasCheckResult::empty(),
+        }
+    }
 }
 
 impl<S, T: ToNumbas<S> + RumbasCheck> ToNumbas<Option<S>> for Noneable<T> {
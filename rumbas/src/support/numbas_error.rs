@@ -0,0 +1,409 @@
+//! The errors that numbas writes to stderr when it fails to compile an exam.
+//!
+//! The python tracebacks and messages are parsed and the question, part or resource that they
+//! are about is looked up in the exam, so they can be reported at their place in the rumbas files.
+
+use rumbas_support::diagnostic::{Diagnostic, DiagnosticKind};
+
+lazy_static! {
+    /// The path of a part within a question, like `p0g1` for the second gap of the first part
+    static ref PART_PATH: regex::Regex = regex::Regex::new(r"\bp\d+(?:[gs]\d+)*\b").unwrap();
+    static ref PART_PATH_SEGMENT: regex::Regex = regex::Regex::new(r"([pgs])(\d+)").unwrap();
+}
+
+const TRACEBACK_START: &str = "Traceback (most recent call last):";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumbasError {
+    /// The python exception, e.g. `FileNotFoundError`
+    pub exception: Option<String>,
+    pub message: String,
+    /// The python file and line where the exception was raised
+    pub origin: Option<(String, usize)>,
+    /// The name of the question
+    pub question: Option<String>,
+    /// The numbas path of the part within the question, e.g. `p0g1`
+    pub part: Option<String>,
+    /// The name or path of the resource
+    pub resource: Option<String>,
+    /// The yaml path of the question, part or resource in the exam, like the paths of the check
+    /// results
+    pub path: Option<Vec<String>>,
+}
+
+impl NumbasError {
+    /// An error with only a message, e.g. for a failure before numbas is run
+    pub fn new(message: String) -> Self {
+        Self {
+            exception: None,
+            message,
+            origin: None,
+            question: None,
+            part: None,
+            resource: None,
+            path: None,
+        }
+    }
+
+    fn from_message(exception: Option<String>, message: String) -> Self {
+        let quoted = quoted_strings(&message);
+        let question = message
+            .to_lowercase()
+            .match_indices("question ")
+            .find_map(|(idx, _)| quoted_at(&message[idx + "question ".len()..]));
+        let resource = message
+            .find("No such file or directory: ")
+            .and_then(|_| quoted.last().cloned());
+        let part = PART_PATH.find(&message).map(|m| m.as_str().to_string());
+        Self {
+            exception,
+            question,
+            part,
+            resource,
+            ..Self::new(message)
+        }
+    }
+
+    /// Find the question, part or resource in the exam and set the path.
+    /// `question_paths` are the names of the question files per question group.
+    pub fn locate(mut self, exam: &numbas::exam::Exam, question_paths: &[Vec<String>]) -> Self {
+        let quoted = quoted_strings(&self.message);
+        let questions: Vec<_> = exam
+            .question_groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group_idx, group)| {
+                group
+                    .questions
+                    .iter()
+                    .enumerate()
+                    .map(move |(question_idx, question)| (group_idx, question_idx, question))
+            })
+            .collect();
+        if self.question.is_none() {
+            self.question = quoted
+                .iter()
+                .find(|q| {
+                    questions
+                        .iter()
+                        .any(|(_, _, question)| &question.name == *q)
+                })
+                .cloned();
+        }
+        let mut position = None;
+        for (group_idx, question_idx, question) in questions.iter() {
+            let resource_idx = question.resources.iter().position(|resource| {
+                let [name, path] = &resource.0;
+                self.resource
+                    .iter()
+                    .chain(quoted.iter())
+                    .any(|r| r == name || r == path || r.ends_with(&format!("/{}", name)))
+            });
+            if let Some(resource_idx) = resource_idx {
+                self.resource = Some(question.resources[resource_idx].0[0].clone());
+                self.question = Some(question.name.clone());
+                position = Some((*group_idx, *question_idx, Some(resource_idx)));
+                break;
+            }
+            if self.question.as_ref() == Some(&question.name) {
+                position = Some((*group_idx, *question_idx, None));
+                break;
+            }
+        }
+        // A part of the only question, e.g. of a question preview
+        if position.is_none() && self.part.is_some() && questions.len() == 1 {
+            position = Some((questions[0].0, questions[0].1, None));
+        }
+        if let Some((group_idx, question_idx, resource_idx)) = position {
+            let mut path = vec![
+                "question_groups".to_string(),
+                group_idx.to_string(),
+                "questions".to_string(),
+                question_idx.to_string(),
+                question_paths
+                    .get(group_idx)
+                    .and_then(|g| g.get(question_idx))
+                    .cloned()
+                    .unwrap_or_default(),
+            ];
+            match (resource_idx, &self.part) {
+                (Some(resource_idx), _) => {
+                    path.extend(["resources".to_string(), resource_idx.to_string()])
+                }
+                (None, Some(part)) => path.extend(part_path(part)),
+                (None, None) => (),
+            }
+            self.path = Some(path);
+        }
+        self
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(DiagnosticKind::CompilationFailed, self.to_string());
+        match &self.path {
+            Some(path) => diagnostic.with_path(path.clone()),
+            None => diagnostic,
+        }
+    }
+}
+
+impl std::fmt::Display for NumbasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.exception {
+            Some(exception) if self.message.is_empty() => write!(f, "{}", exception)?,
+            Some(exception) => write!(f, "{}: {}", exception, self.message)?,
+            None => write!(f, "{}", self.message)?,
+        }
+        // Only the question, part and resource that are not named in the message
+        let mut context = Vec::new();
+        let unnamed = |value: &String| !self.message.contains(&value[..]);
+        if let Some(question) = self.question.as_ref().filter(|q| unnamed(q)) {
+            context.push(format!("question \"{}\"", question));
+        }
+        if let Some(part) = self.part.as_ref().filter(|p| unnamed(p)) {
+            context.push(format!("part {}", part));
+        }
+        if let Some(resource) = self.resource.as_ref().filter(|r| unnamed(r)) {
+            context.push(format!("resource {}", resource));
+        }
+        if let Some((file, line)) = &self.origin {
+            context.push(format!("raised at {}:{}", file, line));
+        }
+        if !context.is_empty() {
+            write!(f, " ({})", context.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the stderr of numbas into errors: one per python traceback and one per block of other
+/// lines. Messages that are repeated in a traceback are only reported once.
+pub fn parse_numbas_errors(stderr: &str) -> Vec<NumbasError> {
+    let lines: Vec<&str> = stderr.lines().collect();
+    let mut tracebacks = Vec::new();
+    let mut messages: Vec<Vec<&str>> = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx];
+        if line.starts_with(TRACEBACK_START) {
+            idx += 1;
+            let mut origin = None;
+            while idx < lines.len() && (lines[idx].starts_with(' ') || lines[idx].is_empty()) {
+                origin = parse_frame(lines[idx].trim()).or(origin);
+                idx += 1;
+            }
+            let mut exception_lines = Vec::new();
+            while idx < lines.len()
+                && !lines[idx].trim().is_empty()
+                && !lines[idx].starts_with(TRACEBACK_START)
+            {
+                exception_lines.push(lines[idx]);
+                idx += 1;
+            }
+            let mut error = parse_exception(&exception_lines.join("\n"));
+            error.origin = origin;
+            tracebacks.push(error);
+        } else {
+            if line.trim().is_empty() || is_chaining_message(line) {
+                messages.push(Vec::new());
+            } else {
+                match messages.last_mut() {
+                    Some(message) => message.push(line.trim()),
+                    None => messages.push(vec![line.trim()]),
+                }
+            }
+            idx += 1;
+        }
+    }
+    let mut errors: Vec<NumbasError> = messages
+        .into_iter()
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| lines.join("\n"))
+        .filter(|message| {
+            !tracebacks
+                .iter()
+                .any(|t| &t.message == message || t.to_string().starts_with(&message[..]))
+        })
+        .map(|message| NumbasError::from_message(None, message))
+        .collect();
+    errors.extend(tracebacks);
+    errors
+}
+
+/// The python file and line of a traceback frame like `File "numbas.py", line 5, in compile`
+fn parse_frame(line: &str) -> Option<(String, usize)> {
+    let rest = line.strip_prefix("File \"")?;
+    let (file, rest) = rest.split_once('"')?;
+    let line_number = rest
+        .strip_prefix(", line ")?
+        .split(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some((file.to_string(), line_number))
+}
+
+/// The last line(s) of a traceback, like `KeyError: 'name'`
+fn parse_exception(text: &str) -> NumbasError {
+    let is_name = |name: &str| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+    };
+    match text.split_once(':') {
+        Some((name, message)) if is_name(name) => {
+            NumbasError::from_message(Some(name.to_string()), message.trim().to_string())
+        }
+        _ if is_name(text) => NumbasError::from_message(Some(text.to_string()), String::new()),
+        _ => NumbasError::from_message(None, text.to_string()),
+    }
+}
+
+fn is_chaining_message(line: &str) -> bool {
+    line.starts_with("During handling of the above exception")
+        || line.starts_with("The above exception was the direct cause")
+}
+
+/// The strings between single or double quotes, an apostrophe within a word (like in `Couldn't`)
+/// doesn't start a quoted string
+fn quoted_strings(text: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let rest = &text[start..];
+        let quote_start = rest.char_indices().find(|(idx, c)| {
+            (*c == '\'' || *c == '"')
+                && !rest[..*idx]
+                    .chars()
+                    .last()
+                    .map(|p| p.is_alphanumeric())
+                    .unwrap_or(false)
+        });
+        match quote_start.and_then(|(idx, _)| quoted_at(&rest[idx..]).map(|s| (idx, s))) {
+            Some((idx, s)) => {
+                start += idx + s.len() + 2;
+                strings.push(s);
+            }
+            None => match quote_start {
+                Some((idx, _)) => start += idx + 1,
+                None => break,
+            },
+        }
+    }
+    strings
+}
+
+/// The quoted string at the start of the text
+fn quoted_at(text: &str) -> Option<String> {
+    let quote = text.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let end = text[1..].find(quote)?;
+    Some(text[1..end + 1].to_string())
+}
+
+/// The yaml path of a numbas part path, e.g. `p0g1` is `parts.0.gaps.1`
+fn part_path(part: &str) -> Vec<String> {
+    PART_PATH_SEGMENT
+        .captures_iter(part)
+        .flat_map(|c| {
+            let key = match &c[1] {
+                "p" => "parts",
+                "g" => "gaps",
+                _ => "steps",
+            };
+            [key.to_string(), c[2].to_string()]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn traceback() {
+        let stderr = "[Errno 2] No such file or directory: '/project/resources/plot.png'
+Traceback (most recent call last):
+  File \"/numbas/bin/numbas.py\", line 418, in run
+    compiler.compile()
+  File \"/numbas/bin/numbas.py\", line 120, in collect_files
+    shutil.copyfile(src, dst)
+FileNotFoundError: [Errno 2] No such file or directory: '/project/resources/plot.png'
+";
+        let errors = parse_numbas_errors(stderr);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].exception, Some("FileNotFoundError".to_string()));
+        assert_eq!(
+            errors[0].origin,
+            Some(("/numbas/bin/numbas.py".to_string(), 120))
+        );
+        assert_eq!(
+            errors[0].resource,
+            Some("/project/resources/plot.png".to_string())
+        );
+
+        assert_eq!(
+            quoted_strings("Couldn't find theme 'dark' or \"light\""),
+            vec!["dark", "light"]
+        );
+
+        let errors = parse_numbas_errors("Error in question \"Sums\": invalid part p1g0s2\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].exception, None);
+        assert_eq!(errors[0].question, Some("Sums".to_string()));
+        assert_eq!(errors[0].part, Some("p1g0s2".to_string()));
+        assert_eq!(
+            part_path("p1g0s2"),
+            vec!["parts", "1", "gaps", "0", "steps", "2"]
+        );
+    }
+
+    #[test]
+    fn locate() {
+        let exam: numbas::exam::Exam = serde_json::from_value(serde_json::json!({
+            "name": "Exam", "duration": 0, "percentPass": 0, "showQuestionGroupNames": false,
+            "showstudentname": true, "shuffleQuestionGroups": false, "navigation":
+            {"allowregen": true, "navigatemode": "menu", "allowsteps": true,
+             "showfrontpage": true, "preventleave": true, "startpassword": "",
+             "onleave": {"action": "none", "message": ""}},
+            "timing": {"allowPause": true, "timeout": {"action": "none", "message": ""},
+             "timedwarning": {"action": "none", "message": ""}},
+            "feedback": {"showactualmark": true, "showtotalmark": true, "showanswerstate": true,
+             "allowrevealanswer": true, "advicethreshold": 0, "intro": "", "feedbackmessages": []},
+            "question_groups": [{"name": "", "pickingStrategy": "all-ordered", "questions": [
+                {"name": "First", "statement": "", "advice": "", "parts": [], "variables": {},
+                 "variablesTest": {"condition": "", "maxRuns": 1}, "functions": {},
+                 "ungrouped_variables": [], "variable_groups": [], "rulesets": {},
+                 "preamble": {"js": "", "css": ""}, "navigation": {"allowregen": true,
+                 "showfrontpage": true, "preventleave": false},
+                 "resources": [["plot.png", "/project/resources/plot.png"]]},
+                {"name": "Second", "statement": "", "advice": "", "parts": [], "variables": {},
+                 "variablesTest": {"condition": "", "maxRuns": 1}, "functions": {},
+                 "ungrouped_variables": [], "variable_groups": [], "rulesets": {},
+                 "preamble": {"js": "", "css": ""}, "navigation": {"allowregen": true,
+                 "showfrontpage": true, "preventleave": false}}
+            ]}]
+        }))
+        .unwrap();
+        let question_paths = vec![vec!["first".to_string(), "second".to_string()]];
+
+        let error = NumbasError::from_message(
+            None,
+            "No such file or directory: '/project/resources/plot.png'".to_string(),
+        )
+        .locate(&exam, &question_paths);
+        assert_eq!(error.question, Some("First".to_string()));
+        assert_eq!(
+            error.path.unwrap().join("."),
+            "question_groups.0.questions.0.first.resources.0"
+        );
+
+        let error = NumbasError::from_message(None, "'Second' in p0s1".to_string())
+            .locate(&exam, &question_paths);
+        assert_eq!(
+            error.path.unwrap().join("."),
+            "question_groups.0.questions.1.second.parts.0.steps.1"
+        );
+    }
+}
@@ -1,19 +1,25 @@
+use crate::support::lint::LintRule;
 use crate::RC_FILE_NAME;
 use rumbas_support::path::RumbasPath;
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// "Run commands" that specify how this rumbas repo should be executed
 pub struct RC {
     version: Version,
+    /// Enable or disable lint rules, all rules are enabled by default
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    lints: BTreeMap<LintRule, bool>,
 }
 
 impl Default for RC {
     fn default() -> Self {
         Self {
             version: Version::new(0, 4, 0),
+            lints: BTreeMap::new(),
         }
     }
 }
@@ -33,6 +39,10 @@ impl RC {
     pub fn version(&self) -> Version {
         self.version.clone()
     }
+    /// Whether the lint rule is enabled for this repo
+    pub fn lint_enabled(&self, rule: LintRule) -> bool {
+        self.lints.get(&rule).copied().unwrap_or(true)
+    }
     pub fn from_path(r: &RumbasPath) -> Result<RC, serde_yaml::Error> {
         read(r.root())
     }
@@ -0,0 +1,333 @@
+//! Look up the documentation, the field names and the enum variants of the value at a yaml path,
+//! using the json schema of the rumbas types.
+
+use crate::exam::ExamFileType;
+use crate::question::custom_part_type::CustomPartTypeDefinition;
+use crate::question::QuestionFileType;
+use rumbas_support::path::RumbasPath;
+use schemars::schema_for;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+lazy_static! {
+    static ref EXAM_SCHEMA: SchemaLookup = SchemaLookup::new(schema_for!(ExamFileType));
+    static ref QUESTION_SCHEMA: SchemaLookup = SchemaLookup::new(schema_for!(QuestionFileType));
+    static ref CUSTOM_PART_TYPE_SCHEMA: SchemaLookup =
+        SchemaLookup::new(schema_for!(CustomPartTypeDefinition));
+}
+
+/// The maximal depth of references that is followed, the schemas are recursive
+const MAX_DEPTH: usize = 32;
+
+/// Finds the value of the `type` key of the mapping at a path, used to choose between the
+/// variants of tagged enums
+pub type TypeFinder<'a> = dyn Fn(&[String]) -> Option<String> + 'a;
+
+/// A field of a mapping, with its documentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaField {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+pub struct SchemaLookup {
+    root: Value,
+}
+
+impl SchemaLookup {
+    pub fn new(root: schemars::schema::RootSchema) -> Self {
+        Self {
+            root: serde_json::to_value(root).expect("The schema to be serializable"),
+        }
+    }
+
+    /// The lookup for the kind of file at the path (exam, question or custom part type)
+    pub fn for_file(path: &RumbasPath) -> Option<&'static Self> {
+        if path.in_main_folder(crate::EXAMS_FOLDER) {
+            Some(&EXAM_SCHEMA)
+        } else if path.in_main_folder(crate::QUESTIONS_FOLDER) {
+            Some(&QUESTION_SCHEMA)
+        } else if path.in_main_folder(crate::CUSTOM_PART_TYPES_FOLDER) {
+            Some(&CUSTOM_PART_TYPE_SCHEMA)
+        } else {
+            None
+        }
+    }
+
+    /// The documentation of the value at the path
+    pub fn description(&self, path: &[String], type_of: &TypeFinder) -> Option<String> {
+        self.schemas_at(path, type_of)
+            .into_iter()
+            .find_map(|schema| {
+                description(schema).or_else(|| {
+                    self.alternatives(schema, 0)
+                        .into_iter()
+                        .flatten()
+                        .find_map(description)
+                })
+            })
+    }
+
+    /// The fields that can be used in the mapping at the path
+    pub fn fields(&self, path: &[String], type_of: &TypeFinder) -> Vec<SchemaField> {
+        let mut fields = BTreeMap::new();
+        for alternative in self.object_alternatives(path, type_of) {
+            for part in alternative {
+                if let Some(Value::Object(properties)) = part.get("properties") {
+                    for (name, schema) in properties {
+                        let entry = fields.entry(name.clone()).or_insert(None);
+                        if entry.is_none() {
+                            *entry = description(schema);
+                        }
+                    }
+                }
+            }
+        }
+        fields
+            .into_iter()
+            .map(|(name, description)| SchemaField { name, description })
+            .collect()
+    }
+
+    /// The string values (e.g. enum variants) that can be used for the value at the path
+    pub fn variants(&self, path: &[String], type_of: &TypeFinder) -> Vec<String> {
+        // The current value of the mapping's `type` should not restrict its own variants
+        let parent = &path[..path.len().saturating_sub(1)];
+        let type_of = |p: &[String]| {
+            if p == parent {
+                None
+            } else {
+                type_of(p)
+            }
+        };
+        let mut variants = Vec::new();
+        for schema in self.schemas_at(path, &type_of) {
+            for part in self.alternatives(schema, 0).into_iter().flatten() {
+                let values = part
+                    .get("enum")
+                    .and_then(|e| e.as_array())
+                    .into_iter()
+                    .flatten()
+                    .chain(part.get("const"));
+                for value in values.filter_map(|v| v.as_str()) {
+                    if !variants.iter().any(|v| v == value) {
+                        variants.push(value.to_string());
+                    }
+                }
+            }
+        }
+        variants
+    }
+
+    /// The schemas of the value at the path, without following their references
+    fn schemas_at(&self, path: &[String], type_of: &TypeFinder) -> Vec<&Value> {
+        let mut current = vec![&self.root];
+        for (idx, segment) in path.iter().enumerate() {
+            let alternatives = self.filter_on_type(
+                current
+                    .into_iter()
+                    .flat_map(|schema| self.alternatives(schema, 0))
+                    .collect(),
+                type_of(&path[..idx]),
+            );
+            current = alternatives
+                .into_iter()
+                .filter_map(|alternative| child(&alternative, segment))
+                .collect();
+            if current.is_empty() {
+                break;
+            }
+        }
+        current
+    }
+
+    /// The alternatives of the mapping at the path that match its `type`
+    fn object_alternatives(&self, path: &[String], type_of: &TypeFinder) -> Vec<Vec<&Value>> {
+        self.filter_on_type(
+            self.schemas_at(path, type_of)
+                .into_iter()
+                .flat_map(|schema| self.alternatives(schema, 0))
+                .collect(),
+            type_of(path),
+        )
+    }
+
+    /// Keep the alternatives whose `type` field allows the given type. If none does, the
+    /// alternatives without a fixed type are kept (e.g. custom part types).
+    fn filter_on_type<'a>(
+        &'a self,
+        alternatives: Vec<Vec<&'a Value>>,
+        r#type: Option<String>,
+    ) -> Vec<Vec<&'a Value>> {
+        let r#type = match r#type {
+            Some(t) => t,
+            None => return alternatives,
+        };
+        let allowed_types = |alternative: &Vec<&'a Value>| -> Option<Vec<String>> {
+            let type_schema = alternative
+                .iter()
+                .find_map(|part| part.get("properties").and_then(|p| p.get("type")))?;
+            let variants: Vec<String> = self
+                .alternatives(type_schema, 0)
+                .into_iter()
+                .flatten()
+                .filter_map(|part| part.get("enum").and_then(|e| e.as_array()))
+                .flatten()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect();
+            if variants.is_empty() {
+                None
+            } else {
+                Some(variants)
+            }
+        };
+        let matching: Vec<_> = alternatives
+            .iter()
+            .filter(|a| {
+                allowed_types(a)
+                    .map(|v| v.contains(&r#type))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+        if !matching.is_empty() {
+            return matching;
+        }
+        let untyped: Vec<_> = alternatives
+            .iter()
+            .filter(|a| allowed_types(a).is_none())
+            .cloned()
+            .collect();
+        if !untyped.is_empty() {
+            untyped
+        } else {
+            alternatives
+        }
+    }
+
+    /// The alternatives that a schema allows. Each alternative consists of the schemas that
+    /// apply together (`allOf`).
+    fn alternatives<'a>(&'a self, schema: &'a Value, depth: usize) -> Vec<Vec<&'a Value>> {
+        if depth > MAX_DEPTH {
+            return Vec::new();
+        }
+        if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+            return match self.definition(reference) {
+                Some(definition) => self.alternatives(definition, depth + 1),
+                None => Vec::new(),
+            };
+        }
+        let mut alternatives = vec![vec![schema]];
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for member in all {
+                let member_alternatives = self.alternatives(member, depth + 1);
+                alternatives = combine(alternatives, member_alternatives);
+            }
+        }
+        let mut choices = Vec::new();
+        for key in ["anyOf", "oneOf"] {
+            if let Some(Value::Array(members)) = schema.get(key) {
+                choices.extend(members.iter());
+            }
+        }
+        for key in ["then", "else"] {
+            if let Some(member) = schema.get(key) {
+                choices.push(member);
+            }
+        }
+        if choices.is_empty() {
+            return alternatives;
+        }
+        let choice_alternatives = choices
+            .into_iter()
+            .flat_map(|member| self.alternatives(member, depth + 1))
+            .collect();
+        combine(alternatives, choice_alternatives)
+    }
+
+    fn definition(&self, reference: &str) -> Option<&Value> {
+        let name = reference.strip_prefix("#/definitions/")?;
+        self.root.get("definitions").and_then(|d| d.get(name))
+    }
+}
+
+/// All combinations of an alternative of `a` with an alternative of `b`
+fn combine<'a>(a: Vec<Vec<&'a Value>>, b: Vec<Vec<&'a Value>>) -> Vec<Vec<&'a Value>> {
+    a.iter()
+        .flat_map(|x| {
+            b.iter().map(move |y| {
+                let mut combined = x.clone();
+                combined.extend(y.iter());
+                combined
+            })
+        })
+        .collect()
+}
+
+/// The schema of the value of a key (or list item) within an alternative
+fn child<'a>(alternative: &[&'a Value], segment: &str) -> Option<&'a Value> {
+    alternative.iter().find_map(|part| {
+        if let Some(property) = part.get("properties").and_then(|p| p.get(segment)) {
+            return Some(property);
+        }
+        if let Ok(index) = segment.parse::<usize>() {
+            match part.get("items") {
+                Some(Value::Array(items)) => return items.get(index),
+                Some(items @ Value::Object(_)) => return Some(items),
+                _ => (),
+            }
+        }
+        part.get("additionalProperties").filter(|a| a.is_object())
+    })
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema
+        .get("description")
+        .and_then(|d| d.as_str())
+        .map(|d| d.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path(p: &str) -> Vec<String> {
+        p.split('.').map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn question_schema() {
+        let lookup = SchemaLookup::new(schema_for!(QuestionFileType));
+        let part_type = |p: &[String]| {
+            if p == &path("parts.0")[..] {
+                Some("number_entry".to_string())
+            } else {
+                None
+            }
+        };
+        assert!(lookup
+            .description(&path("statement"), &part_type)
+            .unwrap()
+            .contains("statement"));
+        let fields: Vec<_> = lookup
+            .fields(&[], &part_type)
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert!(fields.contains(&"parts".to_string()));
+        assert!(fields.contains(&"template".to_string()));
+
+        let part_fields: Vec<_> = lookup
+            .fields(&path("parts.0"), &part_type)
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert!(part_fields.contains(&"answer".to_string()));
+        assert!(!part_fields.contains(&"gaps".to_string()));
+
+        let part_types = lookup.variants(&path("parts.1.type"), &|_| None);
+        assert!(part_types.contains(&"jme".to_string()));
+        assert!(part_types.contains(&"gapfill".to_string()));
+    }
+}
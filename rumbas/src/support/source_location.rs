@@ -0,0 +1,638 @@
+//! Find the line and column where a value of an exam or question is written.
+//!
+//! The check results only know the logical path of a value (e.g. `parts.0.marks`). This path is
+//! followed through the files that make up the exam: the exam file, the question files, the
+//! template files and the default files.
+
+use crate::support::default::default_file_path;
+use crate::support::file_manager::{ExamFileToRead, QuestionFileToRead, CACHE};
+use crate::support::template::TEMPLATE_PREFIX;
+use rumbas_support::diagnostic::{Diagnostic, DiagnosticLocation};
+use rumbas_support::input::{FileToLoad, LoadedFile};
+use rumbas_support::path::RumbasPath;
+use std::collections::BTreeMap;
+
+/// A position in a source file, lines and columns start at 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: RumbasPath,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+impl From<SourceLocation> for DiagnosticLocation {
+    fn from(location: SourceLocation) -> Self {
+        Self {
+            file: location.file.display().to_string(),
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+/// The positions of the keys and list items of a yaml file, by their path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    positions: BTreeMap<Vec<String>, (usize, usize)>,
+    /// The (unquoted) scalar values that are written on the same line as their key
+    values: BTreeMap<Vec<String>, String>,
+}
+
+/// A key or list item that can still get children
+struct Node {
+    indent: usize,
+    path: Vec<String>,
+    is_item: bool,
+    next_index: usize,
+}
+
+/// The lines that are part of a multi-line value
+enum Skip {
+    /// A block scalar (`|` or `>`): the lines that are indented more than its key
+    Block(usize),
+    /// A quoted string that is not closed yet
+    Quoted(char),
+    /// A flow collection (`[` or `{`) that is not closed yet, with its depth
+    Flow(usize),
+}
+
+impl SourceMap {
+    /// Index the block style yaml. Values within flow collections and multi-line strings are
+    /// located at their key.
+    pub fn from_yaml(content: &str) -> Self {
+        let mut map = SourceMap::default();
+        let mut stack = vec![Node {
+            indent: 0,
+            path: Vec::new(),
+            is_item: false,
+            next_index: 0,
+        }];
+        let mut skip: Option<Skip> = None;
+        for (line_idx, line) in content.lines().enumerate() {
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            let trimmed = line.trim();
+            match skip {
+                Some(Skip::Block(block_indent)) => {
+                    if trimmed.is_empty() || indent > block_indent {
+                        continue;
+                    }
+                    skip = None;
+                }
+                Some(Skip::Quoted(quote)) => {
+                    if closing_quote(line, quote).is_some() {
+                        skip = None;
+                    }
+                    continue;
+                }
+                Some(Skip::Flow(depth)) => {
+                    let depth = flow_depth(line, depth);
+                    skip = if depth == 0 {
+                        None
+                    } else {
+                        Some(Skip::Flow(depth))
+                    };
+                    continue;
+                }
+                None => (),
+            }
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+                continue;
+            }
+            skip = map.index_node(&mut stack, line, indent, line_idx + 1);
+        }
+        map
+    }
+
+    /// Index the key or list item that starts at the given column of the line
+    fn index_node(
+        &mut self,
+        stack: &mut Vec<Node>,
+        line: &str,
+        column: usize,
+        line_nb: usize,
+    ) -> Option<Skip> {
+        let rest = &line[column..];
+        if rest == "-" || rest.starts_with("- ") {
+            // The items of a list can be written at the same indentation as its key
+            while stack.len() > 1 && {
+                let top = stack.last().unwrap();
+                top.indent > column || (top.indent == column && top.is_item)
+            } {
+                stack.pop();
+            }
+            let parent = stack.last_mut().unwrap();
+            let mut path = parent.path.clone();
+            path.push(parent.next_index.to_string());
+            parent.next_index += 1;
+            self.positions.insert(path.clone(), (line_nb, column + 1));
+            stack.push(Node {
+                indent: column,
+                path: path.clone(),
+                is_item: true,
+                next_index: 0,
+            });
+            let item = &rest[1..];
+            let item_column = column + 1 + (item.len() - item.trim_start().len());
+            if item.trim().is_empty() {
+                None
+            } else if let Some(skip) = self.index_key(stack, line, item_column, line_nb) {
+                skip
+            } else {
+                self.index_value(path, item.trim(), column)
+            }
+        } else {
+            self.index_key(stack, line, column, line_nb).flatten()
+        }
+    }
+
+    /// Index the key that starts at the given column, returns None if there is no key
+    fn index_key(
+        &mut self,
+        stack: &mut Vec<Node>,
+        line: &str,
+        column: usize,
+        line_nb: usize,
+    ) -> Option<Option<Skip>> {
+        let rest = &line[column..];
+        if rest == "-" || rest.starts_with("- ") {
+            return Some(self.index_node(stack, line, column, line_nb));
+        }
+        let (key, value) = split_key(rest)?;
+        while stack.len() > 1 && stack.last().unwrap().indent >= column {
+            stack.pop();
+        }
+        let mut path = stack.last().unwrap().path.clone();
+        path.push(key);
+        self.positions.insert(path.clone(), (line_nb, column + 1));
+        stack.push(Node {
+            indent: column,
+            path: path.clone(),
+            is_item: false,
+            next_index: 0,
+        });
+        Some(self.index_value(path, value, column))
+    }
+
+    /// Remember a scalar value, or start skipping the lines of a multi-line value
+    fn index_value(&mut self, path: Vec<String>, value: &str, column: usize) -> Option<Skip> {
+        let value = strip_comment(value);
+        match value.chars().next() {
+            None => None,
+            Some('|') | Some('>') => Some(Skip::Block(column)),
+            Some(quote @ '"') | Some(quote @ '\'') => match closing_quote(&value[1..], quote) {
+                Some(end) => {
+                    self.values.insert(path, value[1..end + 1].to_string());
+                    None
+                }
+                None => Some(Skip::Quoted(quote)),
+            },
+            Some('[') | Some('{') => match flow_depth(value, 0) {
+                0 => None,
+                depth => Some(Skip::Flow(depth)),
+            },
+            _ => {
+                self.values.insert(path, value.to_string());
+                None
+            }
+        }
+    }
+
+    /// The line and column of the value at the given path
+    pub fn get(&self, path: &[String]) -> Option<(usize, usize)> {
+        self.positions.get(path).copied()
+    }
+
+    /// The scalar value at the given path, if it is written on the line of its key
+    pub fn value(&self, path: &[String]) -> Option<&str> {
+        self.values.get(path).map(|s| &s[..])
+    }
+
+    /// The length of the longest prefix of the path that is written in the file, with its
+    /// position
+    pub fn deepest(&self, path: &[String]) -> Option<(usize, (usize, usize))> {
+        (1..=path.len())
+            .rev()
+            .find_map(|len| self.get(&path[..len]).map(|position| (len, position)))
+    }
+
+    /// The path of the deepest key or list item that starts on the line, at or before the column
+    pub fn path_at(&self, line: usize, column: usize) -> Option<Vec<String>> {
+        self.positions
+            .iter()
+            .filter(|(_, (l, c))| *l == line && *c <= column)
+            .max_by_key(|(_, (_, c))| *c)
+            .map(|(path, _)| path.clone())
+    }
+
+    /// The path of the mapping (or list item) that a key at the given line and column belongs
+    /// to: the last key or list item before it that is indented less
+    pub fn parent_at(&self, line: usize, column: usize) -> Vec<String> {
+        self.positions
+            .iter()
+            .filter(|(_, (l, c))| *l <= line && *c < column)
+            .max_by_key(|(_, position)| **position)
+            .map(|(path, _)| path.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Split a `key: value` line, the key can be quoted
+fn split_key(s: &str) -> Option<(String, &str)> {
+    let (key, after) = match s.chars().next()? {
+        quote @ '"' | quote @ '\'' => {
+            let end = closing_quote(&s[1..], quote)? + 1;
+            (s[1..end].to_string(), s[end + 1..].trim_start())
+        }
+        '[' | '{' | '|' | '>' | '#' | '&' | '*' | '!' => return None,
+        _ => {
+            let end = s
+                .char_indices()
+                .find(|(i, c)| *c == ':' && matches!(s[i + 1..].chars().next(), None | Some(' ')))
+                .map(|(i, _)| i)?;
+            (s[..end].trim_end().to_string(), &s[end..])
+        }
+    };
+    let value = after.strip_prefix(':')?;
+    if !value.is_empty() && !value.starts_with(' ') {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// The index of the quote that closes a string, `s` starts after the opening quote
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' && !escaped {
+            escaped = true;
+            continue;
+        }
+        if c == quote && !escaped {
+            // A quote is escaped by doubling it in single quoted strings
+            if quote == '\'' && chars.peek().map(|(_, n)| *n) == Some('\'') {
+                chars.next();
+                continue;
+            }
+            return Some(i);
+        }
+        escaped = false;
+    }
+    None
+}
+
+/// The nesting depth of the flow collections after the line, starting at the given depth
+fn flow_depth(line: &str, mut depth: usize) -> usize {
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '[') | (None, '{') => depth += 1,
+            (None, ']') | (None, '}') => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    depth
+}
+
+fn strip_comment(value: &str) -> &str {
+    match value.find(" #") {
+        Some(i) if !value.starts_with('"') && !value.starts_with('\'') => value[..i].trim_end(),
+        _ => value,
+    }
+}
+
+/// A file whose top level values can be inserted into a template
+struct TemplateValues {
+    file: RumbasPath,
+    map: SourceMap,
+    /// The path of the template data within the file
+    prefix: Vec<String>,
+}
+
+impl TemplateValues {
+    /// The location of the value at the path within the value of the template key, or of the
+    /// deepest part of it that is written
+    fn locate(&self, key: &str, rest: &[String]) -> Option<SourceLocation> {
+        let mut path = self.prefix.clone();
+        path.push(key.to_string());
+        let key_len = path.len();
+        path.extend(rest.iter().cloned());
+        self.map
+            .deepest(&path)
+            .filter(|(len, _)| *len >= key_len)
+            .map(|(_, (line, column))| SourceLocation {
+                file: self.file.clone(),
+                line,
+                column,
+            })
+    }
+    /// The scalar value of the template key
+    fn value(&self, key: &str) -> Option<&str> {
+        let mut path = self.prefix.clone();
+        path.push(key.to_string());
+        self.map.value(&path)
+    }
+}
+
+fn read_source_map(file: &RumbasPath) -> Option<SourceMap> {
+    match CACHE.read_file(FileToLoad {
+        file_path: file.clone(),
+        locale_dependant: false,
+    }) {
+        Some(LoadedFile::Normal(n)) => Some(SourceMap::from_yaml(&n.content)),
+        _ => None,
+    }
+}
+
+/// The source location of the value at the given path of the exam (or question) in the main
+/// file. Values that are not written anywhere are located at the place where they should be
+/// written: their deepest parent that is written.
+pub fn locate(main_file: &RumbasPath, path: &[String]) -> Option<SourceLocation> {
+    // question_groups.<i>.questions.<j>.<question path>.<path within the question>
+    let question_position = path
+        .windows(2)
+        .position(|w| w[0] == "questions" && w[1].parse::<usize>().is_ok())
+        .filter(|p| path.len() > p + 2);
+    if main_file.in_main_folder(crate::QUESTIONS_FOLDER) {
+        let start = question_position? + 3;
+        return locate_in_file(main_file, &path[start..], Vec::new(), FileKind::Question);
+    }
+    let exam_map = read_source_map(main_file)?;
+    if let Some(position) = question_position {
+        let question_prefix = &path[..position + 2];
+        let question_name = path[position + 2].clone();
+        if exam_map.get(question_prefix).is_some() {
+            let question_file: RumbasPath =
+                QuestionFileToRead::with_file_name(question_name, main_file).into();
+            let mut template_values = Vec::new();
+            let mut template_prefix = question_prefix.to_vec();
+            template_prefix.push("template".to_string());
+            if exam_map.get(&template_prefix).is_some() {
+                template_values.push(TemplateValues {
+                    file: main_file.clone(),
+                    map: exam_map.clone(),
+                    prefix: question_prefix.to_vec(),
+                });
+            }
+            if let Some(location) = locate_in_file(
+                &question_file,
+                &path[position + 3..],
+                template_values,
+                FileKind::Question,
+            ) {
+                return Some(location);
+            }
+        }
+    }
+    locate_in_file(main_file, path, Vec::new(), FileKind::Exam)
+}
+
+/// Set the file and the source location of a diagnostic about the exam (or question) in the
+/// main file
+pub fn locate_diagnostic(main_file: &RumbasPath, diagnostic: Diagnostic) -> Diagnostic {
+    let location = if diagnostic.segments().is_empty() {
+        None
+    } else {
+        locate(main_file, diagnostic.segments()).map(|location| location.into())
+    };
+    diagnostic
+        .with_file(main_file.display().to_string())
+        .with_location(location)
+}
+
+/// A locator for the check results of the exam (or question) in the main file
+pub fn locator(main_file: &RumbasPath) -> impl Fn(&[String]) -> Option<String> + '_ {
+    move |path| locate(main_file, path).map(|location| location.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Exam,
+    Question,
+}
+
+fn locate_in_file(
+    file: &RumbasPath,
+    path: &[String],
+    mut template_values: Vec<TemplateValues>,
+    kind: FileKind,
+) -> Option<SourceLocation> {
+    let map = read_source_map(file)?;
+    let at = |(line, column): (usize, usize)| SourceLocation {
+        file: file.clone(),
+        line,
+        column,
+    };
+    let key = |k: &str| k.split('.').map(|s| s.to_string()).collect::<Vec<_>>();
+    if map.value(&key("type")) == Some("template") {
+        // The template can be a template key itself, filled in by a file that uses this one
+        let parent = map
+            .value(&key("template"))
+            .map(|p| p.to_string())
+            .or_else(|| {
+                let template_key = map.value(&key("template.template_key"))?;
+                template_values
+                    .iter()
+                    .rev()
+                    .find_map(|t| t.value(template_key))
+                    .or_else(|| map.value(&key("template.default_value")))
+                    .map(|p| p.to_string())
+            });
+        if let Some(parent) = parent {
+            let parent_file: RumbasPath = match kind {
+                FileKind::Question => QuestionFileToRead::with_file_name(parent, file).into(),
+                FileKind::Exam => ExamFileToRead::with_file_name(parent, file).into(),
+            };
+            template_values.push(TemplateValues {
+                file: file.clone(),
+                map: map.clone(),
+                prefix: Vec::new(),
+            });
+            if let Some(location) = locate_in_file(&parent_file, path, template_values, kind) {
+                return Some(location);
+            }
+        }
+        return map.get(&key("template")).map(at);
+    }
+    let deepest = map.deepest(path);
+    if let Some((len, _)) = deepest {
+        // The value (or one of its parents) can be a template key that is filled in by a file
+        // that uses this one
+        let template_key = map
+            .value(&path[..len])
+            .and_then(|v| v.strip_prefix(TEMPLATE_PREFIX))
+            .and_then(|v| v.strip_prefix(':'));
+        if let Some(template_key) = template_key {
+            if let Some(location) = template_values
+                .iter()
+                .rev()
+                .find_map(|t| t.locate(template_key, &path[len..]))
+            {
+                return Some(location);
+            }
+        }
+    }
+    match deepest {
+        Some((len, position)) if len == path.len() => Some(at(position)),
+        deepest => locate_in_default_file(file, &map, path, kind)
+            .or_else(|| Some(at(deepest.map(|(_, position)| position).unwrap_or((1, 1))))),
+    }
+}
+
+/// The location of a value that is not written in the file, but in a default file
+fn locate_in_default_file(
+    file: &RumbasPath,
+    map: &SourceMap,
+    path: &[String],
+    kind: FileKind,
+) -> Option<SourceLocation> {
+    let part_type = |part_path: &[String]| {
+        let mut type_path = part_path.to_vec();
+        type_path.push("type".to_string());
+        map.value(&type_path).map(|t| t.to_string())
+    };
+    // The default file and the path of the value within that file
+    let candidates: Vec<(String, &[String])> = match (kind, path) {
+        (FileKind::Question, [parts, _, gaps, _, rest @ ..])
+            if parts == "parts" && gaps == "gaps" =>
+        {
+            part_type(&path[..4])
+                .map(|t| vec![(format!("questionpart.gapfill.gap.{}", t), rest)])
+                .unwrap_or_default()
+        }
+        (FileKind::Question, [parts, _, rest @ ..]) if parts == "parts" => part_type(&path[..2])
+            .map(|t| vec![(format!("questionpart.{}", t), rest)])
+            .unwrap_or_default(),
+        (FileKind::Question, _) => vec![("question".to_string(), path)],
+        (FileKind::Exam, [navigation, rest @ ..]) if navigation == "navigation" => vec![
+            ("navigation".to_string(), rest),
+            ("navigation.menu".to_string(), rest),
+            ("navigation.diagnostic".to_string(), rest),
+        ],
+        (FileKind::Exam, [first, rest @ ..])
+            if ["locales", "timing", "feedback", "numbas_settings"].contains(&&first[..]) =>
+        {
+            vec![(first.clone(), rest)]
+        }
+        (FileKind::Exam, _) => Vec::new(),
+    };
+    candidates
+        .into_iter()
+        .filter(|(_, p)| !p.is_empty())
+        .find_map(|(name, default_path)| {
+            let default_file = default_file_path(file.clone(), &name)?;
+            let default_map = read_source_map(&default_file)?;
+            default_map
+                .get(default_path)
+                .map(|(line, column)| SourceLocation {
+                    file: default_file,
+                    line,
+                    column,
+                })
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path(p: &str) -> Vec<String> {
+        p.split('.').map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn source_map() {
+        let map = SourceMap::from_yaml(
+            r#"---
+name: "a: name" # comment
+statement: |
+  text: that is no key
+  - nor a list item
+parts:
+  - type: jme
+    marks: 1
+    answer: x^2
+  -
+    type: number_entry
+    'quoted key': [1,
+      2]
+variables:
+- a
+- b: 2
+  c: "multi
+    line: string"
+after: true
+"#,
+        );
+        assert_eq!(map.get(&path("name")), Some((2, 1)));
+        assert_eq!(map.value(&path("name")), Some("a: name"));
+        assert_eq!(map.get(&path("statement")), Some((3, 1)));
+        assert_eq!(map.get(&path("parts.0")), Some((7, 3)));
+        assert_eq!(map.get(&path("parts.0.type")), Some((7, 5)));
+        assert_eq!(map.value(&path("parts.0.type")), Some("jme"));
+        assert_eq!(map.get(&path("parts.0.answer")), Some((9, 5)));
+        assert_eq!(map.get(&path("parts.1")), Some((10, 3)));
+        assert_eq!(map.get(&path("parts.1.type")), Some((11, 5)));
+        assert_eq!(map.get(&path("parts.1.quoted key")), Some((12, 5)));
+        assert_eq!(map.get(&path("variables.0")), Some((15, 1)));
+        assert_eq!(map.get(&path("variables.1.b")), Some((16, 3)));
+        assert_eq!(map.get(&path("variables.1.c")), Some((17, 3)));
+        assert_eq!(map.get(&path("after")), Some((19, 1)));
+        assert_eq!(map.get(&path("text")), None);
+        assert_eq!(map.get(&path("statement.0")), None);
+        assert_eq!(map.deepest(&path("parts.1.marks")), Some((2, (10, 3))));
+
+        assert_eq!(map.path_at(8, 7), Some(path("parts.0.marks")));
+        assert_eq!(map.path_at(7, 3), Some(path("parts.0")));
+        assert_eq!(map.path_at(4, 3), None);
+        assert_eq!(map.parent_at(9, 5), path("parts.0"));
+        assert_eq!(map.parent_at(10, 5), path("parts.1"));
+        assert_eq!(map.parent_at(16, 3), path("variables.1"));
+        assert_eq!(map.parent_at(20, 1), Vec::<String>::new());
+    }
+
+    #[test]
+    fn locate_in_template_question() {
+        let folder = tempfile::tempdir().unwrap();
+        let root = folder.path();
+        let files = [
+            (
+                "exams/exam.yaml",
+                "---\ntype: normal\nquestion_groups:\n  - questions:\n      - template: question.yaml\n        base_template: templates/base.yaml\n",
+            ),
+            (
+                "questions/question.yaml",
+                "---\ntype: template\ntemplate:\n  template_key: base_template\n  default_value: templates/other.yaml\nvariables:\n  a: 1\n  b: a + 1\n",
+            ),
+            (
+                "questions/templates/base.yaml",
+                "---\ntype: normal\nvariables: \"template:variables\"\n",
+            ),
+        ];
+        for (name, content) in files.iter() {
+            let file = root.join(name);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+        let exam = RumbasPath::create(&root.join("exams/exam.yaml"), root).unwrap();
+        let location = locate(
+            &exam,
+            &path("question_groups.0.questions.0.question.variables.b"),
+        )
+        .unwrap();
+        assert_eq!(
+            location.file.project(),
+            std::path::Path::new("questions/question.yaml")
+        );
+        assert_eq!((location.line, location.column), (8, 3));
+    }
+}
@@ -64,6 +64,12 @@ impl RumbasCheck for TemplateFile {
     fn check(&self, _locale: &str) -> RumbasCheckResult {
         RumbasCheckResult::empty()
     }
+    fn check_identifiers(&self, _locale: &str, _scope: &ValidationScope) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
 }
 
 impl Input for TemplateFileInput {
@@ -156,6 +162,12 @@ impl RumbasCheck for MyYamlValue {
     fn check(&self, _locale: &str) -> RumbasCheckResult {
         RumbasCheckResult::empty()
     }
+    fn check_identifiers(&self, _locale: &str, _scope: &ValidationScope) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
 }
 
 impl Input for MyYamlValue {
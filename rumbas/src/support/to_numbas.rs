@@ -7,6 +7,7 @@ pub trait ToNumbas<NumbasType>: Clone + RumbasCheck {
     fn to_numbas_safe(&self, locale: &str, data: &Self::ToNumbasHelper) -> NumbasResult<NumbasType> {
         let check = self.check(locale);
         if check.is_empty() {
+            check.log_warnings();
             Ok(self.to_numbas(locale, data))
         } else {
             Err(check)
@@ -24,6 +25,7 @@ impl_to_numbas!(numbas::jme::JMEString);
 impl_to_numbas!(numbas::jme::EmbracedJMEString);
 impl_to_numbas!(numbas::jme::ContentAreaString);
 impl_to_numbas!(numbas::support::primitive::Number);
+impl_to_numbas!(numbas::support::student_answer::StudentAnswer);
 
 impl<S, O: ToNumbas<S>> ToNumbas<Vec<S>> for Vec<O> {
     type ToNumbasHelper= O::ToNumbasHelper;
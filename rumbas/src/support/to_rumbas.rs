@@ -13,8 +13,41 @@ pub trait ToRumbas<RumbasType>: Clone {
     fn to_rumbas(&self) -> RumbasType;
 }
 
+/// The reasons why a numbas exam or question can't be converted to rumbas data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// A diagnostic exam without diagnostic settings
+    MissingDiagnostic,
+    /// A percentage setting of a custom part type whose default value is not a number
+    InvalidPercentage {
+        custom_part_type: String,
+        setting: String,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingDiagnostic => {
+                write!(f, "The diagnostic exam has no diagnostic settings")
+            }
+            Self::InvalidPercentage {
+                custom_part_type,
+                setting,
+                value,
+            } => write!(
+                f,
+                "The default value {:?} of the percentage setting {} of custom part type {} is not a number",
+                value, setting, custom_part_type
+            ),
+        }
+    }
+}
+
 impl_to_rumbas!(String, bool, f64, usize, [f64; 2]);
 impl_to_rumbas!(numbas::support::primitive::Number);
+impl_to_rumbas!(numbas::support::student_answer::StudentAnswer);
 impl_to_rumbas!(numbas::jme::JMEString);
 impl_to_rumbas!(numbas::jme::EmbracedJMEString);
 impl_to_rumbas!(numbas::jme::ContentAreaString);
@@ -169,6 +202,7 @@ macro_rules! create_question_part {
                 adaptive_marking: part_data.adaptive_marking.to_rumbas(),
                 custom_marking: part_data.custom_marking.to_rumbas(),
                 steps: extract_part_common_steps(&part_data),
+                tests: Vec::new(),
                 $(
                     $field$(: $val)?
                 ),*
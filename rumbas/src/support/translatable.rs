@@ -231,6 +231,13 @@ impl TranslationContent {
             Self::Locales(m) => m.get(locale),
         }
     }
+    /// The locales that have their own content, empty if the content is the same for all locales
+    pub fn locales(&self) -> Vec<String> {
+        match self {
+            Self::Content(_) => Vec::new(),
+            Self::Locales(m) => m.keys().cloned().collect(),
+        }
+    }
 }
 
 mod helpers {
@@ -457,6 +464,9 @@ impl Translation {
             .get(locale)
             .and_then(|s| substitute(&s.get_content(locale), locale, self))
     }
+    pub fn locales(&self) -> Vec<String> {
+        self.content.locales()
+    }
 }
 
 macro_rules! translatable_type {
@@ -499,6 +509,22 @@ macro_rules! translatable_type {
                         None => RumbasCheckResult::from_missing_translation(Some(locale.to_owned())),
                     }
                 }
+                fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+                    let conversion_res: Option<Result<$subtype, _>> =
+                        self.to_string(locale).map(|c| c.try_into());
+                    match conversion_res {
+                        Some(Ok(s)) => s.check_identifiers(locale, scope),
+                        _ => RumbasCheckResult::empty(), // Reported by check
+                    }
+                }
+                fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+                    let conversion_res: Option<Result<$subtype, _>> =
+                        self.to_string(locale).map(|c| c.try_into());
+                    match conversion_res {
+                        Some(Ok(s)) => s.check_jme_formatting(locale),
+                        _ => RumbasCheckResult::empty(), // Reported by check
+                    }
+                }
             }
 
             impl ToNumbas<$subtype> for $type {
@@ -556,6 +582,9 @@ macro_rules! translatable_type {
                 pub fn to_string(&self, locale: &str) -> Option<String> {
                     self.0.to_string(locale)
                 }
+                pub fn locales(&self) -> Vec<String> {
+                    self.0.locales()
+                }
             }
 
             impl Examples for [<$type Input>] {
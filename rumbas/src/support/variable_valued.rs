@@ -82,6 +82,18 @@ impl<T: RumbasCheck> RumbasCheck for VariableValued<T> {
             VariableValued::Value(v) => v.check(locale),
         }
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        match self {
+            VariableValued::Variable(s) => s.check_identifiers(locale, scope),
+            VariableValued::Value(v) => v.check_identifiers(locale, scope),
+        }
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        match self {
+            VariableValued::Variable(s) => s.check_jme_formatting(locale),
+            VariableValued::Value(v) => v.check_jme_formatting(locale),
+        }
+    }
 }
 
 impl<T: Input> Input for VariableValued<T> {
@@ -254,6 +266,18 @@ impl<T: RumbasCheck> RumbasCheck for ReverseVariableValued<T> {
             Self::Value(v) => v.check(locale),
         }
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        match self {
+            Self::Variable(s) => s.check_identifiers(locale, scope),
+            Self::Value(v) => v.check_identifiers(locale, scope),
+        }
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        match self {
+            Self::Variable(s) => s.check_jme_formatting(locale),
+            Self::Value(v) => v.check_jme_formatting(locale),
+        }
+    }
 }
 
 impl<T: Input> Input for ReverseVariableValued<T> {
@@ -0,0 +1,114 @@
+//! Machine readable records of the errors and warnings that rumbas reports.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// The yaml of a file can't be parsed
+    ParseError,
+    /// The exam (or question) doesn't specify any locale
+    LocalesNotSet,
+    MissingField,
+    MissingTemplateKey,
+    InvalidField,
+    /// Any other error while reading the input
+    InputError,
+    MissingTranslation,
+    InvalidJme,
+    VariableIssue,
+    UnknownIdentifier,
+    TypeError,
+    /// Numbas failed to compile the exam
+    CompilationFailed,
+    /// The file can't be formatted
+    FormatFailed,
+    /// A warning of `rumbas lint`, the rule is set
+    Lint,
+}
+
+/// The position of a value in a yaml file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// One error or warning, serialized as one json record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// The exam or question file that was processed
+    pub file: Option<String>,
+    /// The yaml path of the value, the keys and indices joined by dots
+    pub path: Option<String>,
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// The name of the lint rule, for lint warnings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    pub locale: Option<String>,
+    pub message: String,
+    /// The location of the value in the yaml files
+    pub location: Option<DiagnosticLocation>,
+    #[serde(skip)]
+    segments: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, kind: DiagnosticKind, message: String) -> Self {
+        Self {
+            file: None,
+            path: None,
+            severity,
+            kind,
+            rule: None,
+            locale: None,
+            message,
+            location: None,
+            segments: Vec::new(),
+        }
+    }
+    pub fn error(kind: DiagnosticKind, message: String) -> Self {
+        Self::new(Severity::Error, kind, message)
+    }
+    pub fn warning(kind: DiagnosticKind, message: String) -> Self {
+        Self::new(Severity::Warning, kind, message)
+    }
+    pub fn with_path(mut self, segments: Vec<String>) -> Self {
+        self.path = Some(segments.join("."));
+        self.segments = segments;
+        self
+    }
+    pub fn with_rule(mut self, rule: String) -> Self {
+        self.rule = Some(rule);
+        self
+    }
+    pub fn with_file(mut self, file: String) -> Self {
+        self.file = Some(file);
+        self
+    }
+    pub fn with_locale(mut self, locale: String) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+    pub fn with_location(mut self, location: Option<DiagnosticLocation>) -> Self {
+        self.location = location;
+        self
+    }
+    /// The keys and indices of the yaml path, from the root to the value
+    pub fn segments(&self) -> &[String] {
+        &self.segments[..]
+    }
+    /// The diagnostic as a single line of json
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("A diagnostic to be serializable")
+    }
+}
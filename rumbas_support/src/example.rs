@@ -535,3 +535,12 @@ mod box_test {
 
 impl_examples!(numbas::jme::JMEString: vec!["x^5".to_string().try_into().unwrap()]);
 impl_examples!(numbas::support::primitive::Number: vec![1usize.into(), 1.5.into()]);
+impl_examples!(numbas::support::student_answer::StudentAnswer: vec![
+    numbas::support::student_answer::StudentAnswer::Single(
+        numbas::support::student_answer::AnswerValue::Integer(1)
+    ),
+    numbas::support::student_answer::StudentAnswer::List(vec![
+        numbas::support::student_answer::AnswerValue::Integer(0),
+        numbas::support::student_answer::AnswerValue::Integer(2)
+    ])
+]);
@@ -1,4 +1,6 @@
+use crate::diagnostic::{Diagnostic, DiagnosticKind};
 use crate::path::RumbasPath;
+use crate::rumbas_check::{located, SourceLocator};
 use crate::value::Value;
 use crate::value::ValueType;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -400,6 +402,7 @@ impl_input!(numbas::support::answer_style::AnswerStyle);
 impl_input!(numbas::question::function::FunctionType);
 impl_input!(numbas::question::custom_part_type::CustomPartTypeSetting);
 impl_input!(numbas::support::primitive::Number);
+impl_input!(numbas::support::student_answer::StudentAnswer);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InputCheckResult {
@@ -506,7 +509,46 @@ impl InputCheckResult {
 }
 
 impl InputCheckResult {
+    /// The errors as machine readable diagnostics
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let missing_template_keys = self.missing_template_keys.iter().map(|e| {
+            Diagnostic::error(
+                DiagnosticKind::MissingTemplateKey,
+                format!("missing template key {}", e.key),
+            )
+            .with_path(e.path.segments())
+        });
+        let missing_values = self.missing_values.iter().map(|e| {
+            Diagnostic::error(DiagnosticKind::MissingField, "missing field".to_string())
+                .with_path(e.path.segments())
+        });
+        let invalid_yaml_values = self.invalid_yaml_values.iter().map(|e| {
+            Diagnostic::error(
+                DiagnosticKind::InvalidField,
+                e.error
+                    .clone()
+                    .unwrap_or_else(|| "invalid value".to_string()),
+            )
+            .with_path(e.path.segments())
+        });
+        let error_messages = self
+            .error_messages
+            .iter()
+            .map(|e| Diagnostic::error(DiagnosticKind::InputError, e.clone()));
+        missing_template_keys
+            .chain(missing_values)
+            .chain(invalid_yaml_values)
+            .chain(error_messages)
+            .collect()
+    }
+
     pub fn log(&self, path: &RumbasPath) {
+        self.log_located(path, &|_| None)
+    }
+
+    /// Log the errors, prefixed with the source location of the value that they are about (if
+    /// the locator finds it).
+    pub fn log_located(&self, path: &RumbasPath, locate: &SourceLocator) {
         log::error!("Error when processing {}.", path.display());
         if !self.missing_template_keys.is_empty() {
             log::error!(
@@ -514,19 +556,34 @@ impl InputCheckResult {
                 self.missing_template_keys.len()
             );
             for (idx, error) in self.missing_template_keys.iter().enumerate() {
-                log::error!("{}\t{}", idx + 1, error.to_string());
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
             }
         }
         if !self.missing_values.is_empty() {
             log::error!("Found {} missing fields:", self.missing_values.len());
             for (idx, error) in self.missing_values.iter().enumerate() {
-                log::error!("{}\t{}", idx + 1, error.to_string());
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
             }
         }
         if !self.invalid_yaml_values.is_empty() {
             log::error!("Found {} invalid fields:", self.invalid_yaml_values.len());
             for (idx, error) in self.invalid_yaml_values.iter().enumerate() {
-                log::error!("{}\t{}", idx + 1, error.to_string());
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
             }
         }
         if !self.error_messages.is_empty() {
@@ -557,6 +614,14 @@ impl InputCheckPath {
     pub fn add(&mut self, s: String) {
         self.parts.insert(0, s)
     }
+    /// The keys and indices of the path, from the root to the value
+    pub fn segments(&self) -> Vec<String> {
+        self.parts
+            .iter()
+            .cloned()
+            .chain(self.last_part.clone())
+            .collect()
+    }
 }
 
 impl std::fmt::Display for InputCheckPath {
@@ -579,6 +644,12 @@ pub struct InputCheckMissingData {
     path: InputCheckPath,
 }
 
+impl InputCheckMissingData {
+    pub fn path(&self) -> &InputCheckPath {
+        &self.path
+    }
+}
+
 impl std::fmt::Display for InputCheckMissingData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.path)
@@ -591,6 +662,12 @@ pub struct InputCheckMissingTemplateData {
     path: InputCheckPath,
 }
 
+impl InputCheckMissingTemplateData {
+    pub fn path(&self) -> &InputCheckPath {
+        &self.path
+    }
+}
+
 impl std::fmt::Display for InputCheckMissingTemplateData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{} at {}", self.key, self.path)
@@ -604,6 +681,12 @@ pub struct InputCheckInvalidYamlData {
     error: Option<String>,
 }
 
+impl InputCheckInvalidYamlData {
+    pub fn path(&self) -> &InputCheckPath {
+        &self.path
+    }
+}
+
 impl std::fmt::Display for InputCheckInvalidYamlData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let p = self.path.to_string();
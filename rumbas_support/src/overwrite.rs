@@ -89,3 +89,4 @@ impl_overwrite!(numbas::support::answer_style::AnswerStyle);
 impl_overwrite!(numbas::question::function::FunctionType);
 impl_overwrite!(numbas::question::custom_part_type::CustomPartTypeSetting);
 impl_overwrite!(numbas::support::primitive::Number);
+impl_overwrite!(numbas::support::student_answer::StudentAnswer);
@@ -1,8 +1,16 @@
+use crate::diagnostic::{Diagnostic, DiagnosticKind, Severity};
+pub use numbas::jme::ast::ValidationScope;
+use numbas::jme::ast::{Expr, ExprValidationError};
+use numbas::jme::types::TypeError;
 use std::collections::{BTreeMap, HashMap};
 
 pub trait RumbasCheck {
     /// Check the read rumbas data
     fn check(&self, locale: &str) -> RumbasCheckResult;
+    /// Check that the jme expressions only use names that are defined in the scope
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult;
+    /// Check that the jme strings are written in their canonical form
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult;
 }
 
 impl<O: RumbasCheck> RumbasCheck for Vec<O> {
@@ -15,6 +23,24 @@ impl<O: RumbasCheck> RumbasCheck for Vec<O> {
         }
         result
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        for (i, item) in self.iter().enumerate() {
+            let mut previous_result = item.check_identifiers(locale, scope);
+            previous_result.extend_path(i.to_string());
+            result.union(&previous_result)
+        }
+        result
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        for (i, item) in self.iter().enumerate() {
+            let mut previous_result = item.check_jme_formatting(locale);
+            previous_result.extend_path(i.to_string());
+            result.union(&previous_result)
+        }
+        result
+    }
 }
 
 impl<T: RumbasCheck> RumbasCheck for HashMap<String, T> {
@@ -28,6 +54,26 @@ impl<T: RumbasCheck> RumbasCheck for HashMap<String, T> {
         }
         result
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        // Key is not displayable, so show an index, just to differentiate
+        for (i, (_key, item)) in self.iter().enumerate() {
+            let mut previous_result = item.check_identifiers(locale, scope);
+            previous_result.extend_path(i.to_string());
+            result.union(&previous_result)
+        }
+        result
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        // Key is not displayable, so show an index, just to differentiate
+        for (i, (_key, item)) in self.iter().enumerate() {
+            let mut previous_result = item.check_jme_formatting(locale);
+            previous_result.extend_path(i.to_string());
+            result.union(&previous_result)
+        }
+        result
+    }
 }
 
 impl<T: RumbasCheck> RumbasCheck for BTreeMap<String, T> {
@@ -41,12 +87,38 @@ impl<T: RumbasCheck> RumbasCheck for BTreeMap<String, T> {
         }
         result
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        // Key is not displayable, so show an index, just to differentiate
+        for (i, (_key, item)) in self.iter().enumerate() {
+            let mut previous_result = item.check_identifiers(locale, scope);
+            previous_result.extend_path(i.to_string());
+            result.union(&previous_result)
+        }
+        result
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        // Key is not displayable, so show an index, just to differentiate
+        for (i, (_key, item)) in self.iter().enumerate() {
+            let mut previous_result = item.check_jme_formatting(locale);
+            previous_result.extend_path(i.to_string());
+            result.union(&previous_result)
+        }
+        result
+    }
 }
 
 impl<O: RumbasCheck> RumbasCheck for Box<O> {
     fn check(&self, locale: &str) -> RumbasCheckResult {
         (**self).check(locale)
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        (**self).check_identifiers(locale, scope)
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        (**self).check_jme_formatting(locale)
+    }
 }
 
 impl<A: RumbasCheck, B: RumbasCheck> RumbasCheck for (A, B) {
@@ -62,6 +134,30 @@ impl<A: RumbasCheck, B: RumbasCheck> RumbasCheck for (A, B) {
         result.union(&previous_result);
         result
     }
+    fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        let i = 0;
+        let mut previous_result = self.0.check_identifiers(locale, scope);
+        previous_result.extend_path(i.to_string());
+        result.union(&previous_result);
+        let i = 1;
+        let mut previous_result = self.1.check_identifiers(locale, scope);
+        previous_result.extend_path(i.to_string());
+        result.union(&previous_result);
+        result
+    }
+    fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+        let mut result = RumbasCheckResult::empty();
+        let i = 0;
+        let mut previous_result = self.0.check_jme_formatting(locale);
+        previous_result.extend_path(i.to_string());
+        result.union(&previous_result);
+        let i = 1;
+        let mut previous_result = self.1.check_jme_formatting(locale);
+        previous_result.extend_path(i.to_string());
+        result.union(&previous_result);
+        result
+    }
 }
 
 macro_rules! impl_rumbas_check {
@@ -71,6 +167,12 @@ macro_rules! impl_rumbas_check {
             fn check(&self, _locale: &str) -> RumbasCheckResult {
                 RumbasCheckResult::empty()
             }
+            fn check_identifiers(&self, _locale: &str, _scope: &ValidationScope) -> RumbasCheckResult {
+                RumbasCheckResult::empty()
+            }
+            fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+                RumbasCheckResult::empty()
+            }
         }
         )*
     };
@@ -84,15 +186,98 @@ impl_rumbas_check!(bool);
 
 impl_rumbas_check!(std::path::PathBuf);
 
-impl_rumbas_check!(numbas::jme::ContentAreaString);
-impl_rumbas_check!(numbas::jme::EmbracedJMEString);
-impl_rumbas_check!(numbas::jme::JMEString);
+/// Check the identifiers and the types of the expressions of a jme string in the given scope
+fn check_expressions<'a>(
+    expressions: impl Iterator<Item = &'a Expr>,
+    scope: &ValidationScope,
+) -> RumbasCheckResult {
+    let mut result = RumbasCheckResult::empty();
+    for expression in expressions {
+        for error in expression.validate_in_scope(scope) {
+            // Unknown functions are not reported, marking scripts and extensions define functions
+            // that are not builtin.
+            if let ExprValidationError::UnknownVariable(_) = error {
+                result.union(&RumbasCheckResult::from_validation_error(&error));
+            }
+        }
+        for error in expression.type_errors(scope) {
+            result.union(&RumbasCheckResult::from_type_error(error));
+        }
+    }
+    result
+}
+
+/// Check whether a jme string is equal to its canonical form
+fn check_formatting(original: String, formatted: String) -> RumbasCheckResult {
+    if original == formatted {
+        RumbasCheckResult::empty()
+    } else {
+        RumbasCheckResult::from_unformatted_jme(original, formatted)
+    }
+}
+
+impl RumbasCheck for numbas::jme::JMEString {
+    fn check(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_identifiers(&self, _locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        check_expressions(self.ast().into_iter(), scope)
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        check_formatting(self.to_string(), self.formatted().to_string())
+    }
+}
+
+impl RumbasCheck for numbas::jme::EmbracedJMEString {
+    fn check(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_identifiers(&self, _locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        check_expressions(self.asts().iter(), scope)
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        check_formatting(self.to_string(), self.formatted().to_string())
+    }
+}
+
+impl RumbasCheck for numbas::jme::ContentAreaString {
+    fn check(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    fn check_identifiers(&self, _locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        check_expressions(self.asts().iter(), scope)
+    }
+    /// Content areas are html, they are not formatted
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+}
+
+impl RumbasCheck for numbas::jme::JMENotesString {
+    fn check(&self, _locale: &str) -> RumbasCheckResult {
+        RumbasCheckResult::empty()
+    }
+    /// The marking script names and the names of the notes are added to the scope
+    fn check_identifiers(&self, _locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+        let notes = self.notes.clone().unwrap_or_default();
+        let mut scope = scope.clone();
+        scope.add_marking_script_names();
+        for note in notes.iter() {
+            scope.add_variable(note.name.name());
+        }
+        check_expressions(notes.iter().map(|n| &n.expression), &scope)
+    }
+    fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+        check_formatting(self.to_string(), self.formatted().to_string())
+    }
+}
 impl_rumbas_check!(numbas::question::part::match_answers::MatchAnswersWithChoicesLayout);
 impl_rumbas_check!(numbas::question::part::match_answers::MatchAnswersWithChoicesDisplayType);
 impl_rumbas_check!(numbas::question::part::match_answers::MultipleChoiceWarningType);
 impl_rumbas_check!(numbas::question::part::pattern_match::PatternMatchMode);
 impl_rumbas_check!(numbas::support::answer_style::AnswerStyle);
 impl_rumbas_check!(numbas::support::primitive::Number);
+impl_rumbas_check!(numbas::support::student_answer::StudentAnswer);
 impl_rumbas_check!(numbas::question::function::FunctionType);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -100,6 +285,10 @@ pub struct RumbasCheckResult {
     // When adding a field, do also add it to is_empty
     missing_translations: Vec<RumbasCheckMissingData>,
     invalid_jme_strings: Vec<RumbasCheckInvalidJMEStringData>,
+    variable_issues: Vec<RumbasCheckVariableIssueData>,
+    unknown_identifiers: Vec<RumbasCheckUnknownIdentifierData>,
+    type_errors: Vec<RumbasCheckTypeErrorData>,
+    unformatted_jme: Vec<RumbasCheckUnformattedJMEData>,
 }
 
 impl RumbasCheckResult {
@@ -109,6 +298,10 @@ impl RumbasCheckResult {
                 path: RumbasCheckPath::with_last(os),
             }],
             invalid_jme_strings: vec![],
+            variable_issues: vec![],
+            unknown_identifiers: vec![],
+            type_errors: vec![],
+            unformatted_jme: vec![],
         }
     }
 
@@ -119,16 +312,85 @@ impl RumbasCheckResult {
                 path: RumbasCheckPath::without_last(),
                 error: e.clone(),
             }],
+            variable_issues: vec![],
+            unknown_identifiers: vec![],
+            type_errors: vec![],
+            unformatted_jme: vec![],
+        }
+    }
+
+    pub fn from_variable_issue(issue: VariableIssue) -> RumbasCheckResult {
+        RumbasCheckResult {
+            missing_translations: vec![],
+            invalid_jme_strings: vec![],
+            variable_issues: vec![RumbasCheckVariableIssueData {
+                path: RumbasCheckPath::without_last(),
+                issue,
+            }],
+            unknown_identifiers: vec![],
+            type_errors: vec![],
+            unformatted_jme: vec![],
+        }
+    }
+
+    pub fn from_validation_error(e: &ExprValidationError) -> RumbasCheckResult {
+        RumbasCheckResult {
+            missing_translations: vec![],
+            invalid_jme_strings: vec![],
+            variable_issues: vec![],
+            unknown_identifiers: vec![RumbasCheckUnknownIdentifierData {
+                path: RumbasCheckPath::without_last(),
+                error: e.clone(),
+            }],
+            type_errors: vec![],
+            unformatted_jme: vec![],
+        }
+    }
+
+    pub fn from_type_error(error: TypeError) -> RumbasCheckResult {
+        RumbasCheckResult {
+            missing_translations: vec![],
+            invalid_jme_strings: vec![],
+            variable_issues: vec![],
+            unknown_identifiers: vec![],
+            type_errors: vec![RumbasCheckTypeErrorData {
+                path: RumbasCheckPath::without_last(),
+                error,
+            }],
+            unformatted_jme: vec![],
+        }
+    }
+
+    pub fn from_unformatted_jme(original: String, formatted: String) -> RumbasCheckResult {
+        RumbasCheckResult {
+            missing_translations: vec![],
+            invalid_jme_strings: vec![],
+            variable_issues: vec![],
+            unknown_identifiers: vec![],
+            type_errors: vec![],
+            unformatted_jme: vec![RumbasCheckUnformattedJMEData {
+                path: RumbasCheckPath::without_last(),
+                original,
+                formatted,
+            }],
         }
     }
     pub fn empty() -> RumbasCheckResult {
         RumbasCheckResult {
             missing_translations: vec![],
             invalid_jme_strings: vec![],
+            variable_issues: vec![],
+            unknown_identifiers: vec![],
+            type_errors: vec![],
+            unformatted_jme: vec![],
         }
     }
+    /// Whether there are no errors. Warnings are not taken into account.
     pub fn is_empty(&self) -> bool {
-        self.missing_translations.len() == 0 && self.invalid_jme_strings.len() == 0
+        self.missing_translations.len() == 0
+            && self.invalid_jme_strings.len() == 0
+            && self.variable_issues.iter().all(|i| i.issue.is_warning())
+            && self.type_errors.len() == 0
     }
     pub fn extend_path(&mut self, s: String) {
         for missing_value in self.missing_translations.iter_mut() {
@@ -137,12 +399,29 @@ impl RumbasCheckResult {
         for invalid_value in self.invalid_jme_strings.iter_mut() {
             invalid_value.path.add(s.clone());
         }
+        for issue in self.variable_issues.iter_mut() {
+            issue.path.add(s.clone());
+        }
+        for unknown_identifier in self.unknown_identifiers.iter_mut() {
+            unknown_identifier.path.add(s.clone());
+        }
+        for type_error in self.type_errors.iter_mut() {
+            type_error.path.add(s.clone());
+        }
+        for unformatted in self.unformatted_jme.iter_mut() {
+            unformatted.path.add(s.clone());
+        }
     }
     pub fn union(&mut self, other: &Self) {
         self.missing_translations
             .extend(other.missing_translations.clone());
         self.invalid_jme_strings
             .extend(other.invalid_jme_strings.clone());
+        self.variable_issues.extend(other.variable_issues.clone());
+        self.unknown_identifiers
+            .extend(other.unknown_identifiers.clone());
+        self.type_errors.extend(other.type_errors.clone());
+        self.unformatted_jme.extend(other.unformatted_jme.clone());
     }
     pub fn missing_translations(&self) -> Vec<RumbasCheckMissingData> {
         self.missing_translations.clone()
@@ -150,16 +429,93 @@ impl RumbasCheckResult {
     pub fn invalid_jme_fields(&self) -> Vec<RumbasCheckInvalidJMEStringData> {
         self.invalid_jme_strings.clone()
     }
+    pub fn variable_issues(&self) -> Vec<RumbasCheckVariableIssueData> {
+        self.variable_issues
+            .iter()
+            .filter(|i| !i.issue.is_warning())
+            .cloned()
+            .collect()
+    }
+    pub fn variable_warnings(&self) -> Vec<RumbasCheckVariableIssueData> {
+        self.variable_issues
+            .iter()
+            .filter(|i| i.issue.is_warning())
+            .cloned()
+            .collect()
+    }
+    /// Unknown identifiers are warnings, the scope does not know all names (e.g. the notes of
+    /// the builtin marking algorithms).
+    pub fn unknown_identifiers(&self) -> Vec<RumbasCheckUnknownIdentifierData> {
+        self.unknown_identifiers.clone()
+    }
+    pub fn type_errors(&self) -> Vec<RumbasCheckTypeErrorData> {
+        self.type_errors.clone()
+    }
+    /// The jme strings that are not written in their canonical form, these are no errors
+    pub fn unformatted_jme(&self) -> Vec<RumbasCheckUnformattedJMEData> {
+        self.unformatted_jme.clone()
+    }
 }
 
 impl RumbasCheckResult {
+    /// The errors and warnings as machine readable diagnostics
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let missing_translations = self.missing_translations.iter().map(|e| {
+            Diagnostic::error(
+                DiagnosticKind::MissingTranslation,
+                "missing translation".to_string(),
+            )
+            .with_path(e.path.segments())
+        });
+        let invalid_jme_strings = self.invalid_jme_strings.iter().map(|e| {
+            Diagnostic::error(DiagnosticKind::InvalidJme, e.error.to_string())
+                .with_path(e.path.segments())
+        });
+        let variable_issues = self.variable_issues.iter().map(|e| {
+            let severity = if e.issue.is_warning() {
+                Severity::Warning
+            } else {
+                Severity::Error
+            };
+            Diagnostic::new(severity, DiagnosticKind::VariableIssue, e.issue.to_string())
+                .with_path(e.path.segments())
+        });
+        let unknown_identifiers = self.unknown_identifiers.iter().map(|e| {
+            Diagnostic::warning(DiagnosticKind::UnknownIdentifier, e.error.to_string())
+                .with_path(e.path.segments())
+        });
+        let type_errors = self.type_errors.iter().map(|e| {
+            Diagnostic::error(DiagnosticKind::TypeError, e.error.to_string())
+                .with_path(e.path.segments())
+        });
+        missing_translations
+            .chain(invalid_jme_strings)
+            .chain(variable_issues)
+            .chain(unknown_identifiers)
+            .chain(type_errors)
+            .collect()
+    }
+
     pub fn log(&self) {
+        self.log_located(&|_| None)
+    }
+
+    /// Log the errors and warnings, prefixed with the source location of the value that they
+    /// are about (if the locator finds it).
+    pub fn log_located(&self, locate: &SourceLocator) {
         let missing_translations = self.missing_translations();
         let invalid_jme_fields = self.invalid_jme_fields();
+        let variable_issues = self.variable_issues();
+        let type_errors = self.type_errors();
         if !missing_translations.is_empty() {
             log::error!("Found {} missing translations:", missing_translations.len());
             for (idx, error) in missing_translations.iter().enumerate() {
-                log::error!("{}\t{}", idx + 1, error.to_string());
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
             }
         }
         if !invalid_jme_fields.is_empty() {
@@ -168,12 +524,90 @@ impl RumbasCheckResult {
                 invalid_jme_fields.len()
             );
             for (idx, error) in invalid_jme_fields.iter().enumerate() {
-                log::error!("{}\t{}", idx + 1, error.to_string());
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
+            }
+        }
+        if !variable_issues.is_empty() {
+            log::error!("Found {} problems with variables:", variable_issues.len());
+            for (idx, error) in variable_issues.iter().enumerate() {
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
+            }
+        }
+        if !type_errors.is_empty() {
+            log::error!(
+                "Found {} type errors in jme expressions:",
+                type_errors.len()
+            );
+            for (idx, error) in type_errors.iter().enumerate() {
+                log::error!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &error.path().segments()),
+                    error
+                );
+            }
+        }
+        self.log_warnings_located(locate);
+    }
+
+    pub fn log_warnings(&self) {
+        self.log_warnings_located(&|_| None)
+    }
+
+    pub fn log_warnings_located(&self, locate: &SourceLocator) {
+        let variable_warnings = self.variable_warnings();
+        if !variable_warnings.is_empty() {
+            log::warn!(
+                "Found {} warnings about variables:",
+                variable_warnings.len()
+            );
+            for (idx, warning) in variable_warnings.iter().enumerate() {
+                log::warn!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &warning.path().segments()),
+                    warning
+                );
+            }
+        }
+        let unknown_identifiers = self.unknown_identifiers();
+        if !unknown_identifiers.is_empty() {
+            log::warn!(
+                "Found {} unknown identifiers in jme expressions:",
+                unknown_identifiers.len()
+            );
+            for (idx, warning) in unknown_identifiers.iter().enumerate() {
+                log::warn!(
+                    "{}\t{}{}",
+                    idx + 1,
+                    located(locate, &warning.path().segments()),
+                    warning
+                );
             }
         }
     }
 }
 
+/// Finds the source location (e.g. `file:line:column`) of the value at the given path
+pub type SourceLocator<'a> = dyn Fn(&[String]) -> Option<String> + 'a;
+
+/// The prefix for a log message about the value at the given path
+pub fn located(locate: &SourceLocator, path: &[String]) -> String {
+    locate(path)
+        .map(|location| format!("{}: ", location))
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RumbasCheckPath {
     parts: Vec<String>,
@@ -193,6 +627,14 @@ impl RumbasCheckPath {
     pub fn add(&mut self, s: String) {
         self.parts.insert(0, s)
     }
+    /// The keys and indices of the path, from the root to the value
+    pub fn segments(&self) -> Vec<String> {
+        self.parts
+            .iter()
+            .cloned()
+            .chain(self.last_part.clone())
+            .collect()
+    }
 }
 
 impl std::fmt::Display for RumbasCheckPath {
@@ -215,6 +657,12 @@ pub struct RumbasCheckMissingData {
     path: RumbasCheckPath,
 }
 
+impl RumbasCheckMissingData {
+    pub fn path(&self) -> &RumbasCheckPath {
+        &self.path
+    }
+}
+
 impl std::fmt::Display for RumbasCheckMissingData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.path)
@@ -227,9 +675,232 @@ pub struct RumbasCheckInvalidJMEStringData {
     error: numbas::jme::parser::ConsumeError,
 }
 
+impl RumbasCheckInvalidJMEStringData {
+    pub fn path(&self) -> &RumbasCheckPath {
+        &self.path
+    }
+}
+
 impl std::fmt::Display for RumbasCheckInvalidJMEStringData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let p = self.path.to_string();
         write!(f, "{}\n With error:\n{}", p, self.error)
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableIssue {
+    /// The definitions of these variables depend on each other
+    CircularDefinition(Vec<String>),
+    /// The definition uses a variable that is not defined
+    UndefinedVariable(String),
+    /// The variable is not used anywhere in the question
+    UnusedVariable,
+    /// The name can't be used for a variable
+    ReservedName,
+}
+
+impl VariableIssue {
+    /// Whether the issue doesn't prevent the question from working
+    pub fn is_warning(&self) -> bool {
+        matches!(self, VariableIssue::UnusedVariable)
+    }
+}
+
+impl std::fmt::Display for VariableIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VariableIssue::CircularDefinition(names) => {
+                write!(f, "circular definition: {}", names.join(" -> "))
+            }
+            VariableIssue::UndefinedVariable(name) => {
+                write!(f, "uses the undefined variable {}", name)
+            }
+            VariableIssue::UnusedVariable => write!(f, "is not used"),
+            VariableIssue::ReservedName => write!(f, "is not allowed as a variable name"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RumbasCheckVariableIssueData {
+    path: RumbasCheckPath,
+    issue: VariableIssue,
+}
+
+impl RumbasCheckVariableIssueData {
+    pub fn path(&self) -> &RumbasCheckPath {
+        &self.path
+    }
+    pub fn issue(&self) -> &VariableIssue {
+        &self.issue
+    }
+}
+
+impl std::fmt::Display for RumbasCheckVariableIssueData {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.path, self.issue)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RumbasCheckUnknownIdentifierData {
+    path: RumbasCheckPath,
+    error: ExprValidationError,
+}
+
+impl RumbasCheckUnknownIdentifierData {
+    pub fn path(&self) -> &RumbasCheckPath {
+        &self.path
+    }
+    pub fn error(&self) -> &ExprValidationError {
+        &self.error
+    }
+}
+
+impl std::fmt::Display for RumbasCheckUnknownIdentifierData {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} uses an {}", self.path, self.error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RumbasCheckTypeErrorData {
+    path: RumbasCheckPath,
+    error: TypeError,
+}
+
+impl RumbasCheckTypeErrorData {
+    pub fn path(&self) -> &RumbasCheckPath {
+        &self.path
+    }
+    pub fn error(&self) -> &TypeError {
+        &self.error
+    }
+}
+
+impl std::fmt::Display for RumbasCheckTypeErrorData {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RumbasCheckUnformattedJMEData {
+    path: RumbasCheckPath,
+    original: String,
+    formatted: String,
+}
+
+impl RumbasCheckUnformattedJMEData {
+    pub fn path(&self) -> &RumbasCheckPath {
+        &self.path
+    }
+    pub fn original(&self) -> &str {
+        &self.original[..]
+    }
+    pub fn formatted(&self) -> &str {
+        &self.formatted[..]
+    }
+}
+
+impl std::fmt::Display for RumbasCheckUnformattedJMEData {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} should be written as {}", self.path, self.formatted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_identifiers() {
+        let mut scope = ValidationScope::new();
+        scope.add_variable("a");
+
+        let content_area = numbas::jme::ContentAreaString::try_from(
+            "<p>{a} $\\var{b}$ $\\simplify{{a}x}$</p>".to_string(),
+        )
+        .unwrap();
+        let result = content_area.check_identifiers("en", &scope);
+        assert!(result.is_empty());
+        assert_eq!(
+            result
+                .unknown_identifiers()
+                .iter()
+                .map(|i| i.error().to_string())
+                .collect::<Vec<_>>(),
+            vec!["unknown variable b".to_string()]
+        );
+
+        let notes = numbas::jme::JMENotesString::try_from(
+            "mark: correctif(studentAnswer = a)\n\nfeedback: mark + c".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            notes
+                .check_identifiers("en", &scope)
+                .unknown_identifiers()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn check_types() {
+        let mut scope = ValidationScope::new();
+        scope.add_variable("a");
+        scope.set_variable_type("a", numbas::jme::types::JMEType::Integer);
+
+        let jme = numbas::jme::JMEString::try_from("len(a) + len([a])".to_string()).unwrap();
+        let mut result = jme.check_identifiers("en", &scope);
+        result.extend_path("answer".to_string());
+        assert!(!result.is_empty());
+        assert_eq!(
+            result
+                .type_errors()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>(),
+            vec!["answer: len can't be called with (integer), expected len(string) -> integer or len(list) -> integer or len(dict) -> integer".to_string()]
+        );
+    }
+
+    #[test]
+    fn check_jme_formatting() {
+        let jme = numbas::jme::JMEString::try_from("(a+b)*  c".to_string()).unwrap();
+        let result = jme.check_jme_formatting("en");
+        assert!(result.is_empty());
+        let unformatted = result.unformatted_jme();
+        assert_eq!(unformatted.len(), 1);
+        assert_eq!(unformatted[0].original(), "(a+b)*  c");
+        assert_eq!(unformatted[0].formatted(), "(a + b) * c");
+
+        let jme = numbas::jme::JMEString::try_from("a + b * c".to_string()).unwrap();
+        assert!(jme.check_jme_formatting("en").unformatted_jme().is_empty());
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut result = RumbasCheckResult::from_missing_translation(Some("nl".to_string()));
+        result.extend_path("statement".to_string());
+        let mut unused = RumbasCheckResult::from_variable_issue(VariableIssue::UnusedVariable);
+        unused.extend_path("a".to_string());
+        unused.extend_path("variables".to_string());
+        result.union(&unused);
+
+        let diagnostics = result.diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::MissingTranslation);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].path, Some("statement.nl".to_string()));
+        assert_eq!(
+            diagnostics[1]
+                .clone()
+                .with_locale("en".to_string())
+                .to_json(),
+            r#"{"file":null,"path":"variables.a","severity":"warning","kind":"variable_issue","locale":"en","message":"is not used","location":null}"#
+        );
+    }
+}
@@ -45,6 +45,10 @@ pub struct ExamplesFieldReceiver {
 
     #[darling(default)]
     pub skip: bool,
+
+    /// The field can be left out, handled by the Input derive
+    #[darling(default)]
+    pub default: bool,
 }
 
 #[derive(Debug, FromVariant)]
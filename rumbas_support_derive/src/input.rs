@@ -107,6 +107,20 @@ fn handle_attributes(
     (tokens_main, tokens_second)
 }
 
+/// Whether the attributes contain the given flag in an `input` attribute, e.g. `#[input(default)]`
+fn has_input_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("input")
+            && match attr.parse_meta() {
+                Ok(syn::Meta::List(meta)) => meta.nested.iter().any(|m| match m {
+                    syn::NestedMeta::Meta(syn::Meta::Path(p)) => p.is_ident(flag),
+                    _ => false,
+                }),
+                _ => false,
+            }
+    })
+}
+
 fn is_serde_from(a: &syn::Attribute) -> bool {
     if a.path.is_ident("serde") {
         match a.parse_meta() {
@@ -279,7 +293,7 @@ fn input_handle_struct_struct(
         .map(|f| {
             f.attrs
                 .iter()
-                .filter(|a| !a.path.is_ident("input"))
+                .filter(|a| !a.path.is_ident("input") && !a.path.is_ident("rumbas_check"))
                 .map(|a| quote!(#a))
                 .collect::<Vec<_>>()
         })
@@ -305,6 +319,23 @@ fn input_handle_struct_struct(
         })
         .collect::<Vec<_>>();
 
+    // Fields with #[input(default)] can be left out, they get their default value
+    let field_is_default = fields
+        .iter()
+        .map(|f| has_input_flag(&f.attrs, "default"))
+        .collect::<Vec<_>>();
+    let field_attributes = field_attributes
+        .into_iter()
+        .zip(field_is_default.iter())
+        .map(|(a, default)| {
+            if *default {
+                quote!(#a #[serde(skip_serializing_if = "Value::is_none")])
+            } else {
+                a
+            }
+        })
+        .collect::<Vec<_>>();
+
     let enum_input_ident = syn::Ident::new(&format!("{}Enum", input_ident)[..], input_ident.span());
 
     let input_attributes_input = &input_attributes.0;
@@ -349,14 +380,59 @@ fn input_handle_struct_struct(
     let from_normal_lines = field_names
         .iter()
         .zip(field_is_flattened.iter())
-        .map(|(f, flattened)| {
+        .zip(field_is_default.iter())
+        .zip(fields.iter())
+        .map(|(((f, flattened), default), field)| {
             if *flattened {
                 quote!(Input::from_normal(normal.#f))
+            } else if *default {
+                let ty = &field.ty;
+                quote! {
+                    if normal.#f == <#ty as Default>::default() {
+                        Value::default()
+                    } else {
+                        Value::Normal(Input::from_normal(normal.#f))
+                    }
+                }
             } else {
                 quote!(Value::Normal(Input::from_normal(normal.#f)))
             }
         })
         .collect::<Vec<_>>();
+    let to_normal_lines = field_names
+        .iter()
+        .zip(field_is_default.iter())
+        .map(|(f, default)| {
+            if *default {
+                quote! {
+                    if self.#f.is_none() {
+                        Default::default()
+                    } else {
+                        self.#f.to_normal()
+                    }
+                }
+            } else {
+                quote!(self.#f.to_normal())
+            }
+        })
+        .collect::<Vec<_>>();
+    let find_missing_lines = field_names
+        .iter()
+        .zip(field_is_default.iter())
+        .map(|(f, default)| {
+            if *default {
+                quote! {
+                    if self.#f.is_none() {
+                        InputCheckResult::empty()
+                    } else {
+                        self.#f.find_missing()
+                    }
+                }
+            } else {
+                quote!(self.#f.find_missing())
+            }
+        })
+        .collect::<Vec<_>>();
 
     tokens.extend(quote! {
         #[automatically_derived]
@@ -364,7 +440,7 @@ fn input_handle_struct_struct(
             type Normal = #ident #ty;
             fn to_normal(&self) -> <Self as Input>::Normal {
                 Self::Normal {
-                    #(#field_names: self.#field_names.to_normal()),*
+                    #(#field_names: #to_normal_lines),*
                 }
             }
             fn from_normal(normal: <Self as Input>::Normal) -> Self {
@@ -375,7 +451,7 @@ fn input_handle_struct_struct(
             fn find_missing(&self) -> InputCheckResult {
                 let mut result = InputCheckResult::empty();
                 #(
-                    let mut previous_result = self.#field_names.find_missing();
+                    let mut previous_result = #find_missing_lines;
                     previous_result.extend_path(stringify!(#field_names).to_string());
                     result.union(&previous_result);
                 )*
@@ -37,7 +37,7 @@ pub fn derive_overwrite(input: TokenStream) -> TokenStream {
     quote!(#overwrite).into()
 }
 
-#[proc_macro_derive(RumbasCheck)]
+#[proc_macro_derive(RumbasCheck, attributes(rumbas_check))]
 pub fn derive_rumbas_check(input: TokenStream) -> TokenStream {
     let derive_input = parse_macro_input!(input as syn::DeriveInput);
 
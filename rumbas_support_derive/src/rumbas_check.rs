@@ -26,6 +26,12 @@ fn rumbas_check_handle_unit_struct(
                 fn check(&self, _locale: &str) -> RumbasCheckResult {
                     RumbasCheckResult::empty()
                 }
+                fn check_identifiers(&self, _locale: &str, _scope: &ValidationScope) -> RumbasCheckResult {
+                    RumbasCheckResult::empty()
+                }
+                fn check_jme_formatting(&self, _locale: &str) -> RumbasCheckResult {
+                    RumbasCheckResult::empty()
+                }
             }
     });
 }
@@ -58,6 +64,24 @@ fn rumbas_check_handle_tuple_struct(
                     )*
                     result
                 }
+                fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+                    let mut result = RumbasCheckResult::empty();
+                    #(
+                        let mut previous_result = self.#field_indexes.check_identifiers(locale, scope);
+                        previous_result.extend_path(stringify!(#field_indexes).to_string());
+                        result.union(&previous_result);
+                    )*
+                    result
+                }
+                fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+                    let mut result = RumbasCheckResult::empty();
+                    #(
+                        let mut previous_result = self.#field_indexes.check_jme_formatting(locale);
+                        previous_result.extend_path(stringify!(#field_indexes).to_string());
+                        result.union(&previous_result);
+                    )*
+                    result
+                }
             }
     });
 }
@@ -73,6 +97,13 @@ fn rumbas_check_handle_struct_struct(
         .iter()
         .map(|f| f.ident.as_ref().map(|v| quote!(#v)).unwrap())
         .collect::<Vec<_>>();
+    let field_scopes = fields
+        .iter()
+        .map(|f| match scope_method(&f.attrs) {
+            Some(method) => quote!(&self.#method(locale, scope)),
+            None => quote!(scope),
+        })
+        .collect::<Vec<_>>();
 
     tokens.extend(quote! {
         #[automatically_derived]
@@ -86,10 +117,48 @@ fn rumbas_check_handle_struct_struct(
                 )*
                 result
             }
+            fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+                let mut result = RumbasCheckResult::empty();
+                #(
+                    let mut previous_result = self.#field_names.check_identifiers(locale, #field_scopes);
+                    previous_result.extend_path(stringify!(#field_names).to_string());
+                    result.union(&previous_result);
+                )*
+                result
+            }
+            fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+                let mut result = RumbasCheckResult::empty();
+                #(
+                    let mut previous_result = self.#field_names.check_jme_formatting(locale);
+                    previous_result.extend_path(stringify!(#field_names).to_string());
+                    result.union(&previous_result);
+                )*
+                result
+            }
         }
     });
 }
 
+/// The method that creates the scope of a field, e.g. `#[rumbas_check(scope = "answer_scope")]`
+/// checks the identifiers of the field with `self.answer_scope(locale, scope)`
+fn scope_method(attrs: &[syn::Attribute]) -> Option<syn::Ident> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("rumbas_check"))
+        .find_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::List(meta)) => meta.nested.iter().find_map(|m| match m {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("scope") => {
+                    match &nv.lit {
+                        syn::Lit::Str(s) => Some(syn::Ident::new(&s.value(), s.span())),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }),
+            _ => None,
+        })
+}
+
 fn rumbas_check_handle_struct(
     fields: &ast::Fields<InputFieldReceiver>,
     ident: &syn::Ident,
@@ -103,9 +172,11 @@ fn rumbas_check_handle_struct(
     }
 }
 
+/// Create the match arms for the variants of an enum, where `check` is the call that checks a field
 fn rumbas_check_handle_enum_check_variants(
     v: &[InputVariantReceiver],
     ident: &syn::Ident,
+    check: &proc_macro2::TokenStream,
 ) -> Vec<proc_macro2::TokenStream> {
     v.iter()
         .map(|variant| {
@@ -139,15 +210,23 @@ fn rumbas_check_handle_enum_check_variants(
                             quote!(#i)
                         })
                         .collect::<Vec<_>>();
-                    quote! {
-                        #ident::#variant_ident(#(#items),*) => {
-                            let mut result = RumbasCheckResult::empty();
-                            #(
-                                let mut previous_result = #items.check(locale);
-                                previous_result.extend_path(stringify!(#numbers).to_string());
-                                result.union(&previous_result);
-                            )*
-                            result
+                    if items.len() == 1 {
+                        // The value of a newtype variant is not nested in the yaml (the enum is
+                        // tagged or untagged), so its index is not part of the path
+                        quote! {
+                            #ident::#variant_ident(elem0) => elem0.#check
+                        }
+                    } else {
+                        quote! {
+                            #ident::#variant_ident(#(#items),*) => {
+                                let mut result = RumbasCheckResult::empty();
+                                #(
+                                    let mut previous_result = #items.#check;
+                                    previous_result.extend_path(stringify!(#numbers).to_string());
+                                    result.union(&previous_result);
+                                )*
+                                result
+                            }
                         }
                     }
                 }
@@ -162,7 +241,7 @@ fn rumbas_check_handle_enum_check_variants(
                         #ident::#variant_ident { #(#items),* } => {
                             let mut result = RumbasCheckResult::empty();
                             #(
-                                let mut previous_result = #items.check(locale);
+                                let mut previous_result = #items.#check;
                                 previous_result.extend_path(stringify!(#items).to_string());
                                 result.union(&previous_result);
                             )*
@@ -183,7 +262,14 @@ fn rumbas_check_handle_enum(
 ) {
     let (imp, ty, wher) = generics.split_for_impl();
 
-    let check_variants = rumbas_check_handle_enum_check_variants(v, ident);
+    let check_variants = rumbas_check_handle_enum_check_variants(v, ident, &quote!(check(locale)));
+    let check_identifiers_variants = rumbas_check_handle_enum_check_variants(
+        v,
+        ident,
+        &quote!(check_identifiers(locale, scope)),
+    );
+    let check_jme_formatting_variants =
+        rumbas_check_handle_enum_check_variants(v, ident, &quote!(check_jme_formatting(locale)));
 
     tokens.extend(quote! {
         #[automatically_derived]
@@ -193,6 +279,16 @@ fn rumbas_check_handle_enum(
                     #(#check_variants),*
                 }
             }
+            fn check_identifiers(&self, locale: &str, scope: &ValidationScope) -> RumbasCheckResult {
+                match self {
+                    #(#check_identifiers_variants),*
+                }
+            }
+            fn check_jme_formatting(&self, locale: &str) -> RumbasCheckResult {
+                match self {
+                    #(#check_jme_formatting_variants),*
+                }
+            }
         }
     });
 }
@@ -44,6 +44,16 @@ pub struct TestFromAndInto {
     field2: String,
 }
 
+#[derive(Input, RumbasCheck, Examples)]
+#[input(name = "TestDefaultInput")]
+#[derive(Clone, Debug, Deserialize, Serialize, Comparable, PartialEq)]
+pub struct TestDefault {
+    field1: bool,
+    #[serde(default)]
+    #[input(default)]
+    field2: Vec<f64>,
+}
+
 impl std::convert::From<String> for TestFromAndIntoInput {
     fn from(s: String) -> TestFromAndIntoInput {
         Self {
@@ -111,6 +121,46 @@ field1: true
 
         assert!(ok.is_ok());
     }
+    #[test]
+    fn default_field_can_be_left_out() {
+        let input: TestDefaultInput = serde_yaml::from_str(
+            r"---
+field1: true
+",
+        )
+        .unwrap();
+        assert!(input.find_missing().is_empty());
+        assert_eq!(
+            input.to_normal(),
+            TestDefault {
+                field1: true,
+                field2: Vec::new(),
+            }
+        );
+
+        let missing: TestDefaultInput = serde_yaml::from_str(
+            r"---
+field2: [1.5]
+",
+        )
+        .unwrap();
+        assert!(!missing.find_missing().is_empty());
+    }
+
+    #[test]
+    fn default_field_is_not_serialized() {
+        let input = TestDefaultInput::from_normal(TestDefault {
+            field1: true,
+            field2: Vec::new(),
+        });
+        assert_eq!(serde_yaml::to_string(&input).unwrap(), "---\nfield1: true\n");
+        let input = TestDefaultInput::from_normal(TestDefault {
+            field1: true,
+            field2: vec![1.5],
+        });
+        assert!(serde_yaml::to_string(&input).unwrap().contains("field2"));
+    }
+
     #[test]
     fn examples() {
         TestInput::examples();